@@ -0,0 +1,66 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use fang::lexer::Tokenizer;
+use fang::parser::{parse_source, Parser};
+
+/// Generate a program of `count` functions exercising every kind of
+/// token, with comments and strings in between.
+fn generate_program(count: usize) -> String {
+    let mut text = String::new();
+
+    for index in 0..count {
+        text.push_str(&format!("\
+// Function number {index}.
+func function_{index}(value_a: int, value_b: int) -> int {{
+    let sum = value_a + value_b * {index};
+    let text = \"Hello, world! {index}\";
+    /* Compare, then combine. */
+    let equal = sum == (value_a - 47) / 3;
+    return function_{index}(sum, equal != text);
+}}
+
+let result_{index} = function_{index}(1, 2);
+"));
+    }
+
+    text
+}
+
+fn lexer(c: &mut Criterion) {
+    let text = generate_program(2000);
+    let mut group = c.benchmark_group("lexer");
+
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("scan", |b| b.iter(|| {
+        let mut tokenizer = Tokenizer::new();
+
+        tokenizer.scan(black_box(&text)).unwrap();
+
+        tokenizer.extract()
+    }));
+    group.finish();
+}
+
+fn parser(c: &mut Criterion) {
+    let text = generate_program(2000);
+    let mut group = c.benchmark_group("parser");
+
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("parse_program", |b| b.iter_batched(
+        || {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(&text).unwrap();
+
+            tokenizer.extract()
+        },
+        |stream| Parser::new(stream).parse_program().unwrap(),
+        BatchSize::SmallInput,
+    ));
+    group.bench_function("parse_source", |b| b.iter(|| {
+        parse_source(black_box(&text)).unwrap()
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, lexer, parser);
+criterion_main!(benches);
@@ -0,0 +1,14 @@
+#![no_main]
+
+use fang::lexer::Tokenizer;
+use fang::parser::Parser;
+use libfuzzer_sys::fuzz_target;
+
+/* Any text that scans must parse into a program or diagnostics. */
+fuzz_target!(|text: &str| {
+    let mut tokenizer = Tokenizer::new();
+
+    if tokenizer.scan(text).is_ok() {
+        let _ = Parser::new(tokenizer.extract()).parse_program();
+    }
+});
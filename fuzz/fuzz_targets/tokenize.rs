@@ -0,0 +1,14 @@
+#![no_main]
+
+use fang::lexer::Tokenizer;
+use libfuzzer_sys::fuzz_target;
+
+/* Any bytes must scan into tokens or diagnostics, read as a whole or
+ * from a reader, which also sees invalid UTF-8. */
+fuzz_target!(|data: &[u8]| {
+    let _ = Tokenizer::new().scan_reader(data);
+
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = Tokenizer::new().scan(text);
+    }
+});
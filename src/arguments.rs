@@ -0,0 +1,145 @@
+use crate::constant::literal_value;
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::interpreter::type_admits;
+use crate::parser::{Expression, Parameter, Program, Statement};
+use crate::printer::format_type;
+use crate::resolver::{resolve, SymbolKind};
+use crate::span::Span;
+use crate::visit::{walk_program, walk_statement, Visitor};
+use std::collections::HashMap;
+
+/// Functions of a program by name, with their parameters and the span
+/// of their name. Methods are left out, being called on a value.
+struct Functions<'ast> {
+    definitions: HashMap<&'ast str, Vec<(&'ast [Parameter], Span)>>,
+}
+
+impl<'ast> Visitor<'ast> for Functions<'ast> {
+    fn visit_statement(&mut self, program: &'ast Program, statement: &'ast Statement) {
+        match statement {
+            Statement::FunctionDefinition {
+                callee_name,
+                parameters,
+                span,
+                ..
+            } => self.definitions.entry(callee_name).or_default().push((parameters, *span)),
+            Statement::Implementation { .. } => return,
+            _ => {},
+        }
+
+        walk_statement(self, program, statement);
+    }
+}
+
+/// Check the arguments of every call of a function the program
+/// defines: that the named ones name one of its parameters, that no
+/// parameter is given twice, counting the positional arguments before
+/// them, and that the literals given to parameters with a type are
+/// of that type.
+///
+/// Functions defined more than once are overloaded, so which one a
+/// call picks is only known at run time, as is the function a variable
+/// holds. Those calls are checked when they're made.
+pub fn check_arguments(program: &Program) -> Result<(), Diagnostics> {
+    let index = resolve(program);
+    let mut functions = Functions {
+        definitions: HashMap::new(),
+    };
+    let mut diagnostics = Diagnostics::new();
+
+    walk_program(&mut functions, program);
+
+    for expression in program.expressions.iter() {
+        let (name, arguments, span) = match expression {
+            Expression::FunctionCall {
+                callee_name,
+                arguments,
+                span,
+            } => (callee_name, arguments, span),
+            _ => continue,
+        };
+        let symbol = match index.symbol_at(span.start) {
+            Some((id, _)) if index.symbol(id).kind == SymbolKind::Function => index.symbol(id),
+            _ => continue,
+        };
+        let parameters = match functions.definitions.get(name.as_str()).map(Vec::as_slice) {
+            Some([(parameters, definition)]) if *definition == symbol.span => parameters,
+            _ => continue,
+        };
+        let mut given = vec![false; parameters.len()];
+
+        for (position, argument) in arguments.iter().enumerate() {
+            let slot = match &argument.name {
+                Some(name) => match parameters.iter().position(|parameter| &parameter.name == name) {
+                    Some(slot) => slot,
+                    None => {
+                        diagnostics.push(Diagnostic::error(format!(
+                            "Function \"{}\" has no parameter \"{}\"!", symbol.name, name))
+                            .with_span(argument.span));
+                        continue;
+                    },
+                },
+                None if position < given.len() => position,
+                None => continue,
+            };
+
+            if given[slot] {
+                diagnostics.push(Diagnostic::error(format!(
+                    "Parameter \"{}\" is given more than once!", parameters[slot].name))
+                    .with_span(argument.span));
+            }
+
+            given[slot] = true;
+
+            /* Only literals have a type known before running. */
+            if let (Some(r#type), Some(value)) = (&parameters[slot].r#type, literal_value(program.expression(argument.value))) {
+                if !type_admits(r#type, &value) {
+                    diagnostics.push(Diagnostic::error(format!(
+                        "Parameter \"{}\" of \"{}\" should be {} but is given {}!",
+                        parameters[slot].name, symbol.name, format_type(&program.expressions, r#type), value.type_name()))
+                        .with_span(argument.span));
+                }
+            }
+        }
+    }
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_source;
+    use super::*;
+
+    #[test]
+    fn named_arguments() {
+        let check = |source: &str| check_arguments(&parse_source(source).unwrap()).map_err(|diagnostics| {
+            diagnostics.iter().map(|diagnostic| (diagnostic.message.to_owned(), diagnostic.span)).collect::<Vec<_>>()
+        });
+        let span = "func span(from, to) { return to - from; }\n";
+
+        assert_eq!(check(&format!("{}span(to = 2, from = 1); span(0, to = 1);", span)), Ok(()));
+        assert_eq!(check(&format!("{}span(1, size = 3, to = 2, to = 4);", span)), Err(vec![
+            (String::from("Function \"span\" has no parameter \"size\"!"), Some(Span::new(50, 54))),
+            (String::from("Parameter \"to\" is given more than once!"), Some(Span::new(68, 70))),
+        ]));
+        assert_eq!(check(&format!("{}span(1, 2, from = 3);", span)), Err(vec![
+            (String::from("Parameter \"from\" is given more than once!"), Some(Span::new(53, 57))),
+        ]));
+
+        assert_eq!(check("func f(x: int, y: string?) { print(x); }\nf(\"s\", nil); f(y = 2, x = nil); f(1, y = \"y\");"), Err(vec![
+            (String::from("Parameter \"x\" of \"f\" should be int but is given string!"), Some(Span::new(43, 46))),
+            (String::from("Parameter \"y\" of \"f\" should be string? but is given int!"), Some(Span::new(56, 57))),
+            (String::from("Parameter \"x\" of \"f\" should be int but is given nil!"), Some(Span::new(63, 64))),
+        ]));
+
+        /* Overloads, function values and methods are left to run time. */
+        assert_eq!(check(&format!("{}func span(a) {{ return a; }} span(size = 1);", span)), Ok(()));
+        assert_eq!(check(&format!("{}let f = span; f(size = 1);", span)), Ok(()));
+        assert_eq!(check("impl int { func add(self, other) { return self + other; } } 1.add(size = 1);"), Ok(()));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::parser::{ParseOptions, Program};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Version of the cache layout and of the artifacts stored in it.
+///
+/// Bump this whenever the AST changes shape, so entries written by
+/// an older compiler are never read back.
+const CACHE_VERSION: u32 = 27;
+
+/// Entry of the cache as written to disk, with the version of the
+/// format of its program.
+#[derive(Serialize, Deserialize)]
+struct Entry<P> {
+
+    /// [`CACHE_VERSION`] of the compiler that wrote the entry, which
+    /// must match that of the one reading it.
+    version: u32,
+    program: P,
+}
+
+/// On-disk cache of parsed files, keyed by a hash of their content
+/// and of the options they were parsed with.
+///
+/// Entries live at `<dir>/v<CACHE_VERSION>/ast/<hash>.json`. Since the
+/// key is derived from the source text, an edited file simply misses
+/// the cache and no explicit invalidation is needed. The cache is best
+/// effort: unreadable or corrupt entries count as misses and failing
+/// to store an entry is not an error.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+/// 64-bit FNV-1a hash, stable across compiler builds and platforms.
+fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Cache {
+        Cache {
+            dir: dir.into(),
+        }
+    }
+
+    fn entry_path(&self, source: &str, options: ParseOptions) -> PathBuf {
+
+        /* The width of a tab only changes how diagnostics are located. */
+        let key = format!("{:?}\n{}", ParseOptions { tab_width: 1, ..options }, source);
+
+        self.dir
+            .join(format!("v{}", CACHE_VERSION))
+            .join("ast")
+            .join(format!("{:016x}.json", content_hash(key.as_bytes())))
+    }
+
+    /// Program previously stored for exactly this source text, parsed
+    /// with `options`.
+    pub fn load(&self, source: &str, options: ParseOptions) -> Option<Program> {
+        let text = fs::read_to_string(self.entry_path(source, options)).ok()?;
+        let entry: Entry<Program> = serde_json::from_str(&text).ok()?;
+
+        (entry.version == CACHE_VERSION).then_some(entry.program)
+    }
+
+    pub fn store(&self, source: &str, options: ParseOptions, program: &Program) {
+        let path = self.entry_path(source, options);
+        let entry = Entry {
+            version: CACHE_VERSION,
+            program,
+        };
+        let text = match serde_json::to_string(&entry) {
+            Ok(text) => text,
+            Err(_) => return,
+        };
+
+        if let Some(parent) = path.parent() {
+            if fs::create_dir_all(parent).is_err() {
+                return;
+            }
+        }
+
+        let _ = fs::write(path, text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_str;
+    use crate::parser::Edition;
+    use super::*;
+
+    #[test]
+    fn load_stored_program() {
+        let dir = std::env::temp_dir().join(format!("fang-cache-test-{}", std::process::id()));
+        let cache = Cache::new(&dir);
+        let source = "let value = 17;";
+        let options = ParseOptions::default();
+        let program = compile_str(source).unwrap().program().clone();
+
+        assert_eq!(cache.load(source, options), None);
+        cache.store(source, options, &program);
+        assert_eq!(cache.load(source, options), Some(program));
+        assert_eq!(cache.load("let value = 18;", options), None);
+        assert_eq!(cache.load(source, ParseOptions {
+            edition: Edition::E2026,
+            ..options
+        }), None);
+
+        /* Entries of another version of the format are misses, as are
+         * programs stored without one. */
+        let path = cache.entry_path(source, options);
+        let stored = fs::read_to_string(&path).unwrap();
+
+        fs::write(&path, stored.replace(&format!("\"version\":{}", CACHE_VERSION), "\"version\":0")).unwrap();
+        assert_eq!(cache.load(source, options), None);
+        fs::write(&path, serde_json::to_string(&Program::default()).unwrap()).unwrap();
+        assert_eq!(cache.load(source, options), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use std::cell::Cell;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Source of the time the builtins `now_millis` and `sleep_millis`
+/// read and pass, which a host sets with
+/// [`Env::set_clock`](crate::interpreter::Env::set_clock).
+pub trait Clock {
+
+    /// Milliseconds since the Unix epoch.
+    fn now_millis(&self) -> u64;
+
+    /// Let `millis` milliseconds pass.
+    fn sleep_millis(&self, millis: u64);
+}
+
+/// Time of the system, the default.
+pub struct SystemClock {
+
+    /// Longest a single sleep lasts, longer ones being cut short.
+    max_sleep: Option<u64>,
+}
+
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        SystemClock::new()
+    }
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {
+            max_sleep: None,
+        }
+    }
+
+    /// Clock of the system whose sleeps last at most `max_sleep`
+    /// milliseconds, keeping a program from stalling its host.
+    pub fn with_max_sleep(max_sleep: u64) -> SystemClock {
+        SystemClock {
+            max_sleep: Some(max_sleep),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64)
+    }
+
+    fn sleep_millis(&self, millis: u64) {
+        thread::sleep(Duration::from_millis(self.max_sleep.map_or(millis, |max| millis.min(max))));
+    }
+}
+
+/// Clock whose time only passes when the program sleeps, which returns
+/// at once, so the program runs the same whenever it runs.
+pub struct VirtualClock {
+    now: Cell<u64>,
+}
+
+impl VirtualClock {
+
+    /// Virtual clock starting at `start` milliseconds since the Unix
+    /// epoch.
+    pub fn new(start: u64) -> VirtualClock {
+        VirtualClock {
+            now: Cell::new(start),
+        }
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now_millis(&self) -> u64 {
+        self.now.get()
+    }
+
+    fn sleep_millis(&self, millis: u64) {
+        self.now.set(self.now.get().saturating_add(millis));
+    }
+}
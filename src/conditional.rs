@@ -0,0 +1,117 @@
+use crate::parser::{Attribute, Expression, Program, Statement};
+use crate::span::Span;
+use crate::visit::{fold_program, fold_statements, walk_statement_mut, Folder};
+
+/// Keep the blocks of conditional compilation chosen by `symbols`,
+/// splicing their statements into the enclosing ones, and drop the
+/// others, so later passes never see a branch not taken.
+///
+/// The expressions of the dropped blocks stay in the arena, but no
+/// statement refers to them anymore. The attributes of statements move
+/// to [`Program::scoped_attributes`] likewise.
+pub fn configure(program: &mut Program, symbols: &[String]) {
+    let mut configurer = Configurer {
+        symbols,
+        scoped_attributes: Vec::new(),
+    };
+
+    fold_program(&mut configurer, program);
+    program.scoped_attributes.extend(configurer.scoped_attributes);
+}
+
+struct Configurer<'a> {
+    symbols: &'a [String],
+    scoped_attributes: Vec<(Span, Vec<Attribute>)>,
+}
+
+impl Folder for Configurer<'_> {
+    fn fold_statement(&mut self, expressions: &mut Vec<Expression>, mut statement: Statement) -> Vec<Statement> {
+        match statement {
+            Statement::Conditional {
+                symbol,
+                statements,
+                otherwise,
+                ..
+            } => {
+                let kept = if self.symbols.contains(&symbol) {
+                    statements
+                } else {
+                    otherwise
+                };
+
+                fold_statements(self, expressions, kept)
+            },
+            Statement::Attributed {
+                attributes,
+                statement,
+                span,
+            } => {
+                self.scoped_attributes.push((span, attributes));
+                self.fold_statement(expressions, *statement)
+            },
+            _ => {
+                walk_statement_mut(self, expressions, &mut statement);
+
+                vec![statement]
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{Env, Interpreter, Value};
+    use crate::parser::parse_source;
+    use crate::printer::format_program;
+    use super::*;
+
+    macro_rules! configure_source {
+        ($text:expr, [$($symbol:expr),*]) => {{
+            let mut program = parse_source($text).unwrap();
+            let symbols: Vec<String> = vec![$(String::from($symbol)),*];
+
+            configure(&mut program, &symbols);
+            format_program(&program)
+        }};
+    }
+
+    #[test]
+    fn branches() {
+        let text = "#if DEBUG { let level = 2; } #else { let level = 0; } func log() { #if TRACE { print(level); } }";
+
+        assert_eq!(configure_source!(text, []), "\
+let level = 0;
+
+func log() {}
+");
+        assert_eq!(configure_source!(text, ["DEBUG", "TRACE"]), "\
+let level = 2;
+
+func log() {
+    print(level);
+}
+");
+        assert_eq!(configure_source!("#if A { #if B { b(); } #else { a(); } }", ["A"]), "a();\n");
+    }
+
+    #[test]
+    fn kept_definitions_are_visible() {
+        let mut env = Env::with_builtins();
+        let mut program = parse_source("#if DEBUG { let level = 2; } level;").unwrap();
+
+        configure(&mut program, &[String::from("DEBUG")]);
+
+        assert_eq!(Interpreter::new(&mut env).run(&program), Ok(Value::Int(2)));
+    }
+
+    #[test]
+    fn attributes_move_to_the_program() {
+        let mut program = parse_source("#[allow(warnings)] func f() { #[deny(unreachable_arms)] a; }").unwrap();
+
+        configure(&mut program, &[]);
+
+        assert_eq!(format_program(&program), "func f() {\n    a;\n}\n");
+        assert_eq!(program.scoped_attributes.iter().map(|(span, _)| *span).collect::<Vec<_>>(),
+                   [Span::new(19, 60), Span::new(56, 58)]);
+    }
+}
@@ -0,0 +1,661 @@
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::interpreter::{
+    cast, element_slot, eval_binary_operation, eval_unary_operation, index_value, map_insert, range_value, slice_bounds,
+    type_admits, Value,
+};
+use crate::parser::{BinaryOperator, ExprId, Expression, Program, Statement, Type};
+use crate::printer::format_type;
+use crate::resolver::{resolve, SymbolKind};
+use crate::span::Span;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Definition of a constant, with the state of its evaluation.
+struct Constant<'a> {
+    value: ExprId,
+    span: Span,
+    state: State,
+
+    /// Name of the constant, kept for the order of the results.
+    name: &'a str,
+}
+
+enum State {
+    Pending,
+
+    /// Being evaluated, so reaching it again means it depends on
+    /// itself.
+    Evaluating,
+    Done(Value),
+    Failed,
+}
+
+struct Evaluator<'a> {
+    program: &'a Program,
+    constants: Vec<Constant<'a>>,
+    indices: HashMap<&'a str, usize>,
+    diagnostics: Diagnostics,
+}
+
+/// Evaluate the constants defined at the top level of the program, in
+/// the order they're defined.
+///
+/// Constants may refer to each other regardless of their order, but
+/// not to themselves. Their values may only use literals, operators,
+/// casts and other constants.
+pub fn evaluate_constants(program: &Program) -> Result<Vec<(String, Value)>, Diagnostics> {
+    let evaluator = Evaluator::new(program);
+
+    if !evaluator.diagnostics.is_empty() {
+        return Err(evaluator.diagnostics);
+    }
+
+    Ok(evaluator.constants.into_iter()
+        .map(|constant| match constant.state {
+            State::Done(value) => (constant.name.to_owned(), value),
+            _ => unreachable!("every constant is evaluated without errors"),
+        })
+        .collect())
+}
+
+/// Check that the constants of the program evaluate, that nothing
+/// assigns to them, and that the sizes of array types are constants.
+///
+/// Indexing an array of known length with a constant out of its bounds
+/// is reported too, as are constants, and variables starting with a
+/// literal, whose type doesn't admit their value.
+pub fn check_constants(program: &Program) -> Result<(), Diagnostics> {
+    let mut evaluator = Evaluator::new(program);
+    let mut lengths: HashMap<usize, usize> = HashMap::new();
+    let index = resolve(program);
+
+    evaluator.check_statements(&program.statements, &mut lengths);
+
+    for expression in program.expressions.iter() {
+        match expression {
+            Expression::BinaryOperation {
+                operator: BinaryOperator::Assign,
+                operand_left,
+                ..
+            } => {
+                if let Expression::Identifier {
+                    name,
+                    span,
+                } = program.expression(*operand_left) {
+                    if index.symbol_at(span.start).is_some_and(|(id, _)| index.symbol(id).kind == SymbolKind::Constant) {
+                        evaluator.diagnostics.push(Diagnostic::error(format!("Cannot assign to constant \"{}\"!", name))
+                            .with_span(*span));
+                    }
+                }
+            },
+            Expression::Cast {
+                r#type,
+                span,
+                ..
+            } => {
+                evaluator.check_type(r#type, *span);
+            },
+            Expression::Index {
+                target,
+                index: position,
+                span,
+            } => {
+                let length = match program.expression(*target) {
+                    Expression::Array {
+                        elements,
+                    } => Some(elements.len()),
+                    Expression::Identifier {
+                        name,
+                        span,
+                    } => match index.symbol_at(span.start).map(|(id, _)| index.symbol(id)) {
+                        Some(symbol) if symbol.kind == SymbolKind::Constant => evaluator.constant_length(name),
+                        Some(symbol) => lengths.get(&symbol.span.start).copied(),
+                        None => None,
+                    },
+                    _ => None,
+                };
+
+                if let Some(length) = length {
+                    evaluator.check_index(*position, length, *span);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    if !evaluator.diagnostics.is_empty() {
+        return Err(evaluator.diagnostics);
+    }
+
+    Ok(())
+}
+
+/// Value of `expression` if it's a literal, whose type is known before
+/// the program runs.
+pub fn literal_value(expression: &Expression) -> Option<Value> {
+    match expression {
+        Expression::Number(num) => Some(Value::Int(*num)),
+        Expression::String(str) => Some(Value::String(str.to_owned())),
+        Expression::Nil => Some(Value::Nil),
+        Expression::Unit => Some(Value::Unit),
+        _ => None,
+    }
+}
+
+impl<'a> Evaluator<'a> {
+
+    /// Evaluate every constant of the program.
+    fn new(program: &'a Program) -> Self {
+        let mut evaluator = Evaluator {
+            program,
+            constants: Vec::new(),
+            indices: HashMap::new(),
+            diagnostics: Diagnostics::new(),
+        };
+
+        for statement in &program.statements {
+        match statement {
+            Statement::ConstantDefinition {
+                identifier,
+                value,
+                span,
+                ..
+            } => {
+                if evaluator.indices.contains_key(identifier.as_str()) {
+                    evaluator.diagnostics.push(Diagnostic::error(
+                        format!("Constant \"{}\" is already defined!", identifier)).with_span(*span));
+                    continue;
+                }
+
+                evaluator.indices.insert(identifier, evaluator.constants.len());
+                evaluator.constants.push(Constant {
+                    value: *value,
+                    span: *span,
+                    state: State::Pending,
+                    name: identifier,
+                });
+            },
+                Statement::FunctionDefinition {
+                    statements,
+                    ..
+                } |
+                Statement::Block {
+                    statements,
+                } |
+                Statement::For {
+                    statements,
+                    ..
+                } |
+                Statement::Implementation {
+                    functions: statements,
+                    ..
+                } => evaluator.reject_nested(statements),
+                _ => {},
+            }
+        }
+
+        for index in 0..evaluator.constants.len() {
+            evaluator.constant(index);
+        }
+
+        evaluator
+    }
+
+    /// Check the types written in `statements`, recording the length
+    /// of the variables with an array type by the start of their
+    /// definition.
+    fn check_statements(&mut self, statements: &[Statement], lengths: &mut HashMap<usize, usize>) {
+        for statement in statements {
+            match statement {
+                Statement::VariableDefinition {
+                    identifier,
+                    r#type: Some(r#type),
+                    value,
+                    span,
+                } => {
+                    if let Some(length) = self.check_type(r#type, *span) {
+                        lengths.insert(span.start, length);
+                    }
+
+                    /* Only literals have a type known before running. */
+                    let value = match value.and_then(|value| literal_value(self.program.expression(value))) {
+                        Some(value) => value,
+                        None => continue,
+                    };
+
+                    self.check_value("Variable", identifier, r#type, &value, *span);
+                },
+                Statement::ConstantDefinition {
+                    identifier,
+                    r#type: Some(r#type),
+                    span,
+                    ..
+                } => {
+                    self.check_type(r#type, *span);
+
+                    let value = match self.indices.get(identifier.as_str()).map(|&index| &self.constants[index]) {
+                        Some(Constant {
+                            span: defined,
+                            state: State::Done(value),
+                            ..
+                        }) if defined == span => value.clone(),
+                        _ => continue,
+                    };
+
+                    self.check_value("Constant", identifier, r#type, &value, *span);
+                },
+                Statement::FunctionDefinition {
+                    parameters,
+                    return_type,
+                    statements,
+                    span,
+                    ..
+                } => {
+                    for parameter in parameters {
+                        if let Some(r#type) = &parameter.r#type {
+                            self.check_type(r#type, parameter.span);
+                        }
+                    }
+
+                    if let Some(return_type) = return_type {
+                        self.check_type(return_type, *span);
+                    }
+
+                    self.check_statements(statements, lengths);
+                },
+                Statement::Enum {
+                    variants,
+                    ..
+                } => {
+                    for variant in variants {
+                        for field in &variant.fields {
+                            self.check_type(field, variant.span);
+                        }
+                    }
+                },
+                Statement::Block {
+                    statements,
+                } |
+                Statement::For {
+                    statements,
+                    ..
+                } |
+                Statement::Implementation {
+                    functions: statements,
+                    ..
+                } => self.check_statements(statements, lengths),
+                _ => {},
+            }
+        }
+    }
+
+    /// Check that the sizes in the type written at `span` are
+    /// constants, returning its length if it's an array type.
+    fn check_type(&mut self, r#type: &Type, span: Span) -> Option<usize> {
+        match r#type {
+            Type::Named(_) |
+            Type::Unit => None,
+            Type::Nullable(r#type) |
+            Type::Slice(r#type) => {
+                self.check_type(r#type, span);
+
+                None
+            },
+            Type::Map {
+                key,
+                value,
+            } => {
+                self.check_type(key, span);
+                self.check_type(value, span);
+
+                None
+            },
+            Type::Array {
+                element,
+                size,
+            } => {
+                self.check_type(element, span);
+
+                match self.expression(*size, span)? {
+                    Value::Int(size) if size >= 0 => Some(size as usize),
+                    _ => {
+                        self.fail(Diagnostic::error("Array size must be a non-negative int!").with_span(span));
+
+                        None
+                    },
+                }
+            },
+        }
+    }
+
+    /// Report the `kind` named `name` defined at `span` if its type
+    /// `r#type` doesn't admit `value`, the value it starts with.
+    fn check_value(&mut self, kind: &str, name: &str, r#type: &Type, value: &Value, span: Span) {
+        if !type_admits(r#type, value) {
+            self.diagnostics.push(Diagnostic::error(
+                format!("{} \"{}\" should be {} but is given {}!",
+                        kind, name, format_type(&self.program.expressions, r#type), value.type_name()))
+                .with_span(span));
+        }
+    }
+
+    /// Length of the constant `name` if it holds an array.
+    fn constant_length(&self, name: &str) -> Option<usize> {
+        match &self.constants[*self.indices.get(name)?].state {
+            State::Done(Value::Array(elements)) => Some(elements.len()),
+            _ => None,
+        }
+    }
+
+    /// Report the index or slice at `span` if it's made of constants
+    /// out of the bounds of an array of `length`.
+    fn check_index(&mut self, index: ExprId, length: usize, span: Span) {
+        let diagnostics = std::mem::take(&mut self.diagnostics);
+        let result = match &self.expression(index, span) {
+            Some(Value::Range(range)) => Some(slice_bounds(range, length, "an array").map(|_| ())),
+            Some(index) => Some(element_slot(index, length, "an array").map(|_| ())),
+            None => None,
+        };
+
+        /* Indices that aren't constants are checked at run time. */
+        self.diagnostics = diagnostics;
+
+        if let Some(Err(err)) = result {
+            self.diagnostics.push(Diagnostic::error(err.message).with_span(span));
+        }
+    }
+
+    /// Report the constants defined below the top level.
+    fn reject_nested(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::ConstantDefinition {
+                    span,
+                    ..
+                } => self.diagnostics.push(Diagnostic::error("Constants must be defined at the top level!")
+                    .with_span(*span)),
+                Statement::FunctionDefinition {
+                    statements,
+                    ..
+                } |
+                Statement::Block {
+                    statements,
+                } |
+                Statement::For {
+                    statements,
+                    ..
+                } |
+                Statement::Implementation {
+                    functions: statements,
+                    ..
+                } => self.reject_nested(statements),
+                _ => {},
+            }
+        }
+    }
+
+    /// Value of the constant, evaluating it on first use. `None` if it
+    /// failed, which is reported once.
+    fn constant(&mut self, index: usize) -> Option<Value> {
+        let constant = &mut self.constants[index];
+        let (value, span, name) = (constant.value, constant.span, constant.name);
+
+        match &constant.state {
+            State::Pending => constant.state = State::Evaluating,
+            State::Evaluating => {
+                constant.state = State::Failed;
+
+                return self.fail(Diagnostic::error(
+                    format!("Constant \"{}\" depends on itself!", name)).with_span(span));
+            },
+            State::Done(value) => return Some(value.clone()),
+            State::Failed => return None,
+        }
+
+        let result: Option<Value> = self.expression(value, span);
+
+        let constant = &mut self.constants[index];
+
+        /* A cycle through this constant may have failed it already. */
+        constant.state = match (&result, &constant.state) {
+            (Some(value), State::Evaluating) => State::Done(value.clone()),
+            _ => State::Failed,
+        };
+
+        match constant.state {
+            State::Done(_) => result,
+            _ => None,
+        }
+    }
+
+    fn fail(&mut self, diagnostic: Diagnostic) -> Option<Value> {
+        self.diagnostics.push(diagnostic);
+
+        None
+    }
+
+    /// Evaluate the expression if there's one, `None` if it failed.
+    fn optional_expression(&mut self, expression: Option<ExprId>, span: Span) -> Option<Option<Value>> {
+        match expression {
+            Some(expression) => Some(Some(self.expression(expression, span)?)),
+            None => Some(None),
+        }
+    }
+
+    /// Evaluate the expression in the value of the constant defined at
+    /// `span`, `None` if it failed.
+    fn expression(&mut self, expression: ExprId, span: Span) -> Option<Value> {
+        let value = match self.program.expression(expression) {
+            Expression::Number(num) => Value::Int(*num),
+            Expression::String(str) => Value::String(str.to_owned()),
+            Expression::Nil => Value::Nil,
+            Expression::Unit => Value::Unit,
+            Expression::Identifier {
+                name,
+                span,
+            } => match self.indices.get(name.as_str()) {
+                Some(&index) => self.constant(index)?,
+                None => return self.fail(Diagnostic::error(format!("\"{}\" is not a constant!", name))
+                    .with_span(*span)),
+            },
+            Expression::UnaryOperation {
+                operator,
+                operand,
+            } => {
+                let operand = self.expression(*operand, span)?;
+
+                match eval_unary_operation(operator, operand) {
+                    Ok(value) => value,
+                    Err(err) => return self.fail(Diagnostic::error(err.message).with_span(span)),
+                }
+            },
+            Expression::BinaryOperation {
+                operator: BinaryOperator::Assign,
+                ..
+            } => return self.fail(Diagnostic::error("Assignments are not allowed in constants!").with_span(span)),
+            Expression::BinaryOperation {
+                operator,
+                operand_left,
+                operand_right,
+                ..
+            } => {
+                let left = self.expression(*operand_left, span)?;
+                let right = self.expression(*operand_right, span)?;
+
+                match eval_binary_operation(operator, left, right) {
+                    Ok(value) => value,
+                    Err(err) => return self.fail(Diagnostic::error(err.message).with_span(span)),
+                }
+            },
+            Expression::Cast {
+                operand,
+                r#type,
+                span: cast_span,
+            } => {
+                let operand = self.expression(*operand, span)?;
+
+                match cast(operand, r#type, &self.program.expressions) {
+                    Ok(value) => value,
+                    Err(err) => return self.fail(Diagnostic::error(err.message).with_span(*cast_span)),
+                }
+            },
+            Expression::Array {
+                elements,
+            } => {
+                let mut values: Vec<Value> = Vec::new();
+
+                for element in elements {
+                    values.push(self.expression(*element, span)?);
+                }
+
+                Value::Array(Rc::new(values))
+            },
+            Expression::Map {
+                entries,
+            } => {
+                let mut map: Vec<(Value, Value)> = Vec::new();
+
+                for (key, value) in entries {
+                    let key = self.expression(*key, span)?;
+                    let value = self.expression(*value, span)?;
+
+                    if let Err(err) = map_insert(&mut map, key, value) {
+                        return self.fail(Diagnostic::error(err.message).with_span(span));
+                    }
+                }
+
+                Value::Map(Rc::new(map))
+            },
+            Expression::Index {
+                target,
+                index,
+                span,
+            } => {
+                let array = self.expression(*target, *span)?;
+                let index = self.expression(*index, *span)?;
+
+                match index_value(&array, &index) {
+                    Ok(value) => value,
+                    Err(err) => return self.fail(Diagnostic::error(err.message).with_span(*span)),
+                }
+            },
+            Expression::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let start = self.optional_expression(*start, span)?;
+                let end = self.optional_expression(*end, span)?;
+
+                match range_value(start, end, *inclusive) {
+                    Ok(value) => value,
+                    Err(err) => return self.fail(Diagnostic::error(err.message).with_span(span)),
+                }
+            },
+            Expression::FunctionCall {
+                span,
+                ..
+            } |
+            Expression::MethodCall {
+                span,
+                ..
+            } => return self.fail(Diagnostic::error("Calls are not allowed in constants!").with_span(*span)),
+            Expression::Try {
+                span,
+                ..
+            } => return self.fail(Diagnostic::error("\"?\" is not allowed in constants!").with_span(*span)),
+            Expression::Match {
+                span,
+                ..
+            } => return self.fail(Diagnostic::error("\"match\" is not allowed in constants!").with_span(*span)),
+        };
+
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_source;
+    use super::*;
+
+    macro_rules! evaluate_source {
+        ($text:expr) => {{
+            let text = $text;
+
+            evaluate_constants(&parse_source(text).unwrap())
+                .map_err(|diagnostics| diagnostics.render("main.fang", text))
+        }};
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(evaluate_source!("const AREA = WIDTH * 4; const WIDTH = 2 + -1; const NAME = \"n\" + AREA as string;"), Ok(vec![
+            (String::from("AREA"), Value::Int(4)),
+            (String::from("WIDTH"), Value::Int(1)),
+            (String::from("NAME"), Value::String(String::from("n4"))),
+        ]));
+    }
+
+    #[test]
+    fn assignments() {
+        let text = "const A = 1; A = 2; func f(A) { A = 3; } { let A = 4; A = 5; }";
+
+        assert_eq!(check_constants(&parse_source(text).unwrap()).unwrap_err().render("main.fang", text),
+                   "main.fang:1:14: error: Cannot assign to constant \"A\"!\n");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(evaluate_source!("const A = B; const B = A + 1; const C = A;"),
+                   Err(String::from("main.fang:1:7: error: Constant \"A\" depends on itself!\n")));
+        assert_eq!(evaluate_source!("const BIG = 9223372036854775807 + 1;\nlet a = 1;\nconst B = a;\n"), Err(String::from("\
+main.fang:1:7: error: Integer overflow!
+main.fang:3:11: error: \"a\" is not a constant!
+")));
+        assert_eq!(evaluate_source!("const A = f(); { const B = 1; }"), Err(String::from("\
+main.fang:1:24: error: Constants must be defined at the top level!
+main.fang:1:11: error: Calls are not allowed in constants!
+")));
+    }
+
+    #[test]
+    fn annotations() {
+        let text = "\
+const A: int = 1 + 1;
+const B: string? = 2;
+let a: int? = nil;
+let b: int = nil;
+let c: [int] = \"c\";
+let d: string = a;
+";
+
+        assert_eq!(check_constants(&parse_source(text).unwrap()).unwrap_err().render("main.fang", text), "\
+main.fang:2:7: error: Constant \"B\" should be string? but is given int!
+main.fang:4:5: error: Variable \"b\" should be int but is given nil!
+main.fang:5:5: error: Variable \"c\" should be [int] but is given string!
+");
+    }
+
+    #[test]
+    fn arrays() {
+        let text = "\
+const SIZES = [2, -1];
+let a: [int; SIZES[0]];
+let b: [int; SIZES[1]];
+let c: [int; a[0]];
+a[1] = a[2] + a[SIZES[0] - 2] + [1, 2][5] + SIZES[2] + a[1..3][0];
+";
+
+        assert_eq!(evaluate_source!("const A = [1, 2][1] + [3, 4, 5][1..][..1][0] + {\"a\": 1, \"b\": 2}[\"b\"];"),
+                   Ok(vec![(String::from("A"), Value::Int(8))]));
+        assert_eq!(check_constants(&parse_source(text).unwrap()).unwrap_err().render("main.fang", text), "\
+main.fang:3:5: error: Array size must be a non-negative int!
+main.fang:4:14: error: \"a\" is not a constant!
+main.fang:5:9: error: Index 2 is out of bounds for an array of length 2!
+main.fang:5:39: error: Index 5 is out of bounds for an array of length 2!
+main.fang:5:50: error: Index 2 is out of bounds for an array of length 2!
+main.fang:5:57: error: Range 1..3 is out of bounds for an array of length 2!
+");
+    }
+}
@@ -0,0 +1,178 @@
+use crate::interpreter::{statement_span, Hooks, RuntimeError, State};
+use crate::parser::{Program, Statement};
+use crate::span::SourceMap;
+use crate::visit::{walk_program, walk_statement, Visitor};
+use std::collections::{BTreeMap, HashMap};
+
+/// Lines of one file that hold statements, with how many times each ran.
+#[derive(PartialEq, Debug, Clone)]
+pub struct FileCoverage {
+    pub name: String,
+
+    /// Times the statements starting on each line ran, counting the
+    /// one that ran most if there are several.
+    pub lines: BTreeMap<usize, usize>,
+}
+
+impl FileCoverage {
+
+    /// Number of lines whose statements ran.
+    pub fn hit(&self) -> usize {
+        self.lines.values().filter(|hits| **hits > 0).count()
+    }
+}
+
+/// Hooks counting how many times each statement of a program runs.
+pub struct Coverage {
+
+    /// Times each statement ran, keyed by the start of its span.
+    hits: HashMap<usize, usize>,
+}
+
+/// Pass finding the statements of a program the interpreter tells
+/// hooks about.
+struct Statements {
+    starts: Vec<usize>,
+}
+
+impl<'ast> Visitor<'ast> for Statements {
+    fn visit_statement(&mut self, program: &'ast Program, statement: &'ast Statement) {
+        if let Some(span) = statement_span(&program.expressions, statement) {
+            self.starts.push(span.start);
+        }
+
+        walk_statement(self, program, statement);
+    }
+}
+
+impl Coverage {
+
+    /// Coverage of `program`, none of whose statements ran yet.
+    pub fn new(program: &Program) -> Coverage {
+        let mut statements = Statements {
+            starts: Vec::new(),
+        };
+
+        walk_program(&mut statements, program);
+
+        Coverage {
+            hits: statements.starts.into_iter().map(|start| (start, 0)).collect(),
+        }
+    }
+
+    /// Coverage of each file of `sources` holding statements, in the
+    /// order of the files.
+    pub fn files(&self, sources: &SourceMap) -> Vec<FileCoverage> {
+        let mut files: Vec<FileCoverage> = sources.files().iter()
+            .map(|file| FileCoverage {
+                name: file.name.to_owned(),
+                lines: BTreeMap::new(),
+            })
+            .collect();
+
+        for (start, hits) in &self.hits {
+            let (file, offset) = match sources.find(*start) {
+                Some(found) => found,
+                None => continue,
+            };
+            let index = files.iter().position(|coverage| coverage.name == file.name).unwrap();
+            let (line, _) = file.location(offset);
+            let count = files[index].lines.entry(line).or_default();
+
+            *count = (*count).max(*hits);
+        }
+
+        files.retain(|file| !file.lines.is_empty());
+        files
+    }
+
+    /// Share of the lines with statements that ran in each file.
+    pub fn summary(&self, sources: &SourceMap) -> String {
+        let mut text = String::new();
+
+        for file in self.files(sources) {
+            let total = file.lines.len();
+
+            text.push_str(&format!("{}: {}/{} lines covered ({:.1}%)\n",
+                                   file.name, file.hit(), total, file.hit() as f64 * 100.0 / total as f64));
+        }
+
+        text
+    }
+
+    /// Report in the lcov tracefile format that coverage tools read.
+    pub fn lcov(&self, sources: &SourceMap) -> String {
+        let mut text = String::new();
+
+        for file in self.files(sources) {
+            text.push_str(&format!("SF:{}\n", file.name));
+
+            for (line, hits) in &file.lines {
+                text.push_str(&format!("DA:{},{}\n", line, hits));
+            }
+
+            text.push_str(&format!("LH:{}\nLF:{}\nend_of_record\n", file.hit(), file.lines.len()));
+        }
+
+        text
+    }
+}
+
+impl Hooks for Coverage {
+    fn before_statement(&mut self, state: &State) -> Result<(), RuntimeError> {
+        /* Only statements of the program count, not ones added to run
+         * it, like the call a test runs in. */
+        if let Some(hits) = self.hits.get_mut(&state.span().start) {
+            *hits += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_str;
+    use crate::interpreter::{Env, Interpreter};
+    use crate::span::SourceFile;
+    use super::*;
+
+    #[test]
+    fn lines_and_lcov() {
+        let source = "\
+func unused(value) {
+    return value + 1;
+}
+func double(value) {
+    return value * 2;
+}
+let a = double(1); let b = 2;
+for item in 0..3 {
+    a = double(a);
+}
+";
+        let program = compile_str(source).unwrap();
+        let mut sources = SourceMap::new();
+        let mut coverage = Coverage::new(program.program());
+
+        sources.add(SourceFile::new("main.fang", source));
+        Interpreter::new(&mut Env::new()).with_hooks(&mut coverage).run(program.program()).unwrap();
+
+        assert_eq!(coverage.files(&sources), [FileCoverage {
+            name: String::from("main.fang"),
+            lines: BTreeMap::from([(2, 0), (5, 4), (7, 1), (8, 1), (9, 3)]),
+        }]);
+        assert_eq!(coverage.summary(&sources), "main.fang: 4/5 lines covered (80.0%)\n");
+        assert_eq!(coverage.lcov(&sources), "\
+SF:main.fang
+DA:2,0
+DA:5,4
+DA:7,1
+DA:8,1
+DA:9,3
+LH:4
+LF:5
+end_of_record
+");
+    }
+}
@@ -0,0 +1,263 @@
+use crate::interpreter::{Hooks, RuntimeError, State, Value};
+use crate::span::SourceMap;
+use std::io::{BufRead, Write};
+
+/// Message of the error aborting a program the user quit debugging.
+pub const QUIT_MESSAGE: &str = "Debugging stopped!";
+
+const HELP: &str = "\
+break [FILE:]LINE   pause before running the line, `b` for short
+step                run the next statement, entering calls, `s`
+next                run the next statement, stepping over calls, `n`
+continue            run until a breakpoint, `c`
+locals              print the variables in scope
+print NAME          print a variable, `p`
+backtrace           print the active calls, `bt`
+quit                stop the program, `q`
+";
+
+/// Line a breakpoint pauses the program before.
+struct Breakpoint {
+
+    /// End of the path of the file, any file if `None`.
+    file: Option<String>,
+    line: usize,
+}
+
+/// When the debugger pauses the program next.
+enum Mode {
+
+    /// Before the next statement.
+    Step,
+
+    /// Before the next statement run by a call at most this deep.
+    Next(usize),
+
+    /// At the next breakpoint.
+    Continue,
+}
+
+/// Hooks pausing a program at breakpoints and before steps to read
+/// commands from `input`, reporting to `output`.
+///
+/// The program is paused before its first statement, so breakpoints
+/// can be set before it starts.
+pub struct Debugger<'s, R, W> {
+    sources: &'s SourceMap,
+    input: R,
+    output: W,
+    breakpoints: Vec<Breakpoint>,
+    mode: Mode,
+
+    /// Whether the user quit, the error stopping the program then being
+    /// no failure of it.
+    quit: bool,
+}
+
+impl<'s, R: BufRead, W: Write> Debugger<'s, R, W> {
+
+    /// Debugger of a program compiled from the files of `sources`.
+    pub fn new(sources: &'s SourceMap, input: R, output: W) -> Debugger<'s, R, W> {
+        Debugger {
+            sources,
+            input,
+            output,
+            breakpoints: Vec::new(),
+            mode: Mode::Step,
+            quit: false,
+        }
+    }
+
+    /// Whether the user quit debugging, which stops the program with
+    /// an error.
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Error stopping the program as the user quits.
+    fn quit(&mut self) -> RuntimeError {
+        self.quit = true;
+
+        RuntimeError::new(QUIT_MESSAGE)
+    }
+
+    /// Whether the program should pause before the statement `state`
+    /// is about to run.
+    fn should_pause(&self, state: &State) -> bool {
+        match self.mode {
+            Mode::Step => true,
+            Mode::Next(depth) if state.depth() <= depth => true,
+            _ => self.breakpoints.iter().any(|breakpoint| self.is_at(breakpoint, state)),
+        }
+    }
+
+    fn is_at(&self, breakpoint: &Breakpoint, state: &State) -> bool {
+        match self.sources.location(state.span().start) {
+            Some((file_name, line, _)) => line == breakpoint.line && breakpoint.file.as_ref()
+                .is_none_or(|file| file_name.ends_with(file.as_str())),
+            None => false,
+        }
+    }
+
+    /// Text telling where the byte `offset` of the sources is, with the
+    /// line it's on.
+    fn describe(&self, offset: usize) -> String {
+        match self.sources.find(offset) {
+            Some((file, offset)) => {
+                let (line, _) = file.location(offset);
+                let text = file.text.lines().nth(line - 1).unwrap_or("");
+
+                format!("{}:{}: {}", file.name, line, text.trim())
+            },
+            None => String::from("<unknown>"),
+        }
+    }
+
+    /// Run the command `line`, returning whether the program resumes.
+    fn execute(&mut self, line: &str, state: &State) -> Result<bool, RuntimeError> {
+        let mut words = line.split_whitespace();
+
+        match (words.next(), words.next()) {
+            (None, _) => {},
+            (Some("s" | "step"), None) => self.mode = Mode::Step,
+            (Some("n" | "next"), None) => self.mode = Mode::Next(state.depth()),
+            (Some("c" | "continue"), None) => self.mode = Mode::Continue,
+            (Some("q" | "quit"), None) => return Err(self.quit()),
+            (Some("b" | "break"), Some(location)) => {
+                let (file, line) = match location.rsplit_once(':') {
+                    Some((file, line)) => (Some(file.to_owned()), line),
+                    None => (None, location),
+                };
+
+                match line.parse() {
+                    Ok(line) if line > 0 => {
+                        self.breakpoints.push(Breakpoint {
+                            file,
+                            line,
+                        });
+                        self.print(format!("Breakpoint {} at line {}.", self.breakpoints.len(), line));
+                    },
+                    _ => self.print(format!("\"{}\" is not a line number.", line)),
+                }
+
+                return Ok(false);
+            },
+            (Some("locals"), None) => {
+                for (name, value) in state.locals() {
+                    if !matches!(value, Value::Function(_)) {
+                        self.print(format!("{} = {}", name, value));
+                    }
+                }
+
+                return Ok(false);
+            },
+            (Some("p" | "print"), Some(name)) => {
+                match state.locals().into_iter().find(|(local, _)| *local == name) {
+                    Some((_, value)) => self.print(format!("{} = {}", name, value)),
+                    None => self.print(format!("No variable \"{}\" in scope.", name)),
+                }
+
+                return Ok(false);
+            },
+            (Some("bt" | "backtrace"), None) => {
+                let mut offset = state.span().start;
+
+                for (index, frame) in state.backtrace().iter().enumerate() {
+                    self.print(format!("#{} in \"{}\" at {}", index, frame.function, self.describe(offset)));
+                    offset = frame.span.start;
+                }
+
+                self.print(format!("#{} at {}", state.depth(), self.describe(offset)));
+
+                return Ok(false);
+            },
+            (Some("h" | "help"), None) => {
+                self.print(HELP.trim_end());
+
+                return Ok(false);
+            },
+            (Some(command), _) => {
+                self.print(format!("Unknown command \"{}\", try \"help\".", command));
+
+                return Ok(false);
+            },
+        }
+
+        Ok(!line.trim().is_empty())
+    }
+
+    fn print(&mut self, text: impl AsRef<str>) {
+        writeln!(self.output, "{}", text.as_ref()).unwrap();
+    }
+}
+
+impl<R: BufRead, W: Write> Hooks for Debugger<'_, R, W> {
+    fn before_statement(&mut self, state: &State) -> Result<(), RuntimeError> {
+        if !self.should_pause(state) {
+            return Ok(());
+        }
+
+        self.print(self.describe(state.span().start));
+
+        loop {
+            let mut line = String::new();
+
+            write!(self.output, "(debug) ").unwrap();
+            self.output.flush().unwrap();
+
+            /* Closing the input quits like `quit` does. */
+            if self.input.read_line(&mut line).unwrap_or(0) == 0 {
+                return Err(self.quit());
+            }
+
+            if self.execute(&line, state)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_str;
+    use crate::interpreter::{Env, Interpreter};
+    use crate::span::SourceFile;
+    use super::*;
+
+    #[test]
+    fn breakpoints_and_steps() {
+        let source = "\
+func area(width, height) {
+    let size = width * height;
+    return size;
+}
+let a = area(2, 3);
+let b = area(4, 5);
+";
+        let program = compile_str(source).unwrap();
+        let mut sources = SourceMap::new();
+        let mut env = Env::new();
+        let mut output: Vec<u8> = Vec::new();
+        let input = "break 3\ncontinue\nlocals\nbacktrace\nnext\nprint a\nquit\n";
+
+        sources.add(SourceFile::new("main.fang", source));
+
+        let mut debugger = Debugger::new(&sources, input.as_bytes(), &mut output);
+        let result = Interpreter::new(&mut env).with_hooks(&mut debugger).run(program.program());
+
+        assert_eq!(result.map_err(|err| err.message), Err(String::from(QUIT_MESSAGE)));
+        assert!(debugger.has_quit());
+        assert_eq!(String::from_utf8(output).unwrap(), "\
+main.fang:5: let a = area(2, 3);
+(debug) Breakpoint 1 at line 3.
+(debug) main.fang:3: return size;
+(debug) height = 3
+size = 6
+width = 2
+(debug) #0 in \"area\" at main.fang:3: return size;
+#1 at main.fang:5: let a = area(2, 3);
+(debug) main.fang:6: let b = area(4, 5);
+(debug) a = 6
+(debug) ");
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use crate::lint::LintRegistry;
+use crate::parser::Program;
+use crate::span::{SourceFile, SourceMap, Span};
+use serde::{Serialize, Deserialize};
+
+/// Name standing for every lint when setting lint levels.
+pub const WARNINGS: &str = "warnings";
+
+/// How serious a diagnostic is.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Severity {
+
+    /// Problem preventing the program from compiling.
+    Error,
+
+    /// Likely mistake that doesn't prevent the program from compiling.
+    Warning,
+}
+
+impl Severity {
+
+    /// Label of the severity in rendered diagnostics.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A message reported to the user about the compiled source.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+
+    /// Source code the diagnostic points at, if any.
+    pub span: Option<Span>,
+
+    /// Suggestion on how to fix the problem, if any.
+    pub help: Option<String>,
+
+    /// Lint that reported the diagnostic, if any.
+    pub lint: Option<&'static str>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            span: None,
+            help: None,
+            lint: None,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(message)
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Diagnostic {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Diagnostic {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_lint(mut self, lint: &'static str) -> Diagnostic {
+        self.lint = Some(lint);
+        self
+    }
+
+    /// Format the diagnostic as `file:line:column: error: message`,
+    /// or with `warning` in place of `error` for a warning,
+    /// where `source` is the text of the file named `file_name`,
+    /// followed by a `help: ...` line at the same location if the
+    /// diagnostic has a suggestion.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        self.render_in(&SourceFile::new(file_name, source))
+    }
+
+    /// Format the diagnostic like [`Diagnostic::render`], its span
+    /// being within the text of `file`.
+    pub fn render_in(&self, file: &SourceFile) -> String {
+        let location = match self.span {
+            Some(span) => {
+                let (line, column) = file.location(span.start);
+
+                format!("{}:{}:{}", file.name, line, column)
+            },
+            None => file.name.clone(),
+        };
+
+        match &self.help {
+            Some(help) => format!("{}: {}: {}\n{}: help: {}", location, self.severity.label(), self.message, location, help),
+            None => format!("{}: {}: {}", location, self.severity.label(), self.message),
+        }
+    }
+
+    /// Format the diagnostic like [`Diagnostic::render`], its span
+    /// being an offset among the files of `map`.
+    pub fn render_in_map(&self, map: &SourceMap) -> String {
+        match (self.span.and_then(|span| map.find(span.start).map(|found| (span, found))), map.files()) {
+            (Some((span, (file, start))), _) => {
+                let mut local = self.clone();
+
+                local.span = Some(Span::new(start, start + span.end - span.start));
+                local.render_in(file)
+            },
+            (None, [file]) => self.render_in(file),
+            (None, _) => self.to_string(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity.label(), self.message)?;
+
+        if let Some(help) = &self.help {
+            write!(f, "\nhelp: {}", help)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// All diagnostics collected while compiling a program.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics {
+            items: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.items.push(diagnostic);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Whether any of the diagnostics is an error.
+    pub fn has_errors(&self) -> bool {
+        self.items.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.items.iter()
+    }
+
+    pub fn extend(&mut self, diagnostics: Diagnostics) {
+        self.items.extend(diagnostics.items);
+    }
+
+    /// Render every diagnostic against the file it was reported in,
+    /// one per line.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        self.render_in(&SourceFile::new(file_name, source))
+    }
+
+    /// Format every diagnostic like [`Diagnostics::render`], their
+    /// spans being within the text of `file`.
+    pub fn render_in(&self, file: &SourceFile) -> String {
+        let mut text = String::new();
+
+        for diagnostic in &self.items {
+            text.push_str(&diagnostic.render_in(file));
+            text.push('\n');
+        }
+
+        text
+    }
+
+    /// Format every diagnostic like [`Diagnostic::render_in_map`], one
+    /// per line.
+    pub fn render_in_map(&self, map: &SourceMap) -> String {
+        let mut text = String::new();
+
+        for diagnostic in &self.items {
+            text.push_str(&diagnostic.render_in_map(map));
+            text.push('\n');
+        }
+
+        text
+    }
+}
+
+/// What to do with the warnings of a lint.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+
+    /// Drop them.
+    Allow,
+
+    /// Report them as warnings.
+    Warn,
+
+    /// Report them as errors, so the program doesn't compile.
+    Deny,
+}
+
+impl Level {
+
+    /// Level an attribute `#[name(lint)]` sets, if `name` is one.
+    pub fn from_attribute(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Level of each lint, as the manifest and the command line set them.
+///
+/// A lint's own level comes first, then the level of [`WARNINGS`]
+/// applies to the lints left to warn, as `-D warnings` does for rustc.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct LintLevels {
+    levels: HashMap<String, Level>,
+}
+
+impl LintLevels {
+    pub fn new() -> LintLevels {
+        LintLevels {
+            levels: HashMap::new(),
+        }
+    }
+
+    /// Set the level of `lint` of `registry`, or of every lint for
+    /// [`WARNINGS`], overriding what was set before.
+    pub fn set(&mut self, registry: &LintRegistry, lint: &str, level: Level) -> Result<(), String> {
+        if !registry.knows(lint) {
+            return Err(format!("Unknown lint \"{}\", lints are {}.", lint, registry.names().join(", ")));
+        }
+
+        self.levels.insert(lint.to_owned(), level);
+
+        Ok(())
+    }
+
+    /// Level the warnings of `lint` are reported at.
+    pub fn level(&self, lint: &str) -> Level {
+        match self.levels.get(lint) {
+            Some(Level::Warn) | None => self.levels.get(WARNINGS).copied().unwrap_or(Level::Warn),
+            Some(level) => *level,
+        }
+    }
+
+    /// Drop the allowed warnings of `diagnostics` and turn the denied
+    /// ones into errors, the attributes of `program` around a warning
+    /// coming before the levels set here.
+    pub fn apply(&self, program: &Program, diagnostics: Diagnostics) -> Diagnostics {
+        let mut applied = Diagnostics::new();
+
+        for mut diagnostic in diagnostics.items {
+            if diagnostic.severity == Severity::Warning {
+                let lint = diagnostic.lint.unwrap_or(WARNINGS);
+                let level = program.lint_level(lint, diagnostic.span).unwrap_or_else(|| self.level(lint));
+
+                match level {
+                    Level::Allow => continue,
+                    Level::Warn => {},
+                    Level::Deny => diagnostic.severity = Severity::Error,
+                }
+            }
+
+            applied.push(diagnostic);
+        }
+
+        applied
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(diagnostic: Diagnostic) -> Diagnostics {
+        Diagnostics {
+            items: vec![diagnostic],
+        }
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for diagnostic in &self.items {
+            writeln!(f, "{}", diagnostic)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lint_levels() {
+        let warning = || Diagnostic::warning("Unreachable arm!").with_lint("unreachable_arms");
+        let severities = |levels: &LintLevels| -> Vec<Severity> {
+            let mut diagnostics = Diagnostics::from(warning());
+
+            diagnostics.push(Diagnostic::warning("Other warning!"));
+            diagnostics.push(Diagnostic::error("Error!"));
+            levels.apply(&Program::default(), diagnostics).iter().map(|diagnostic| diagnostic.severity).collect()
+        };
+        let registry = LintRegistry::with_builtins();
+        let mut levels = LintLevels::new();
+
+        assert_eq!(severities(&levels), [Severity::Warning, Severity::Warning, Severity::Error]);
+
+        levels.set(&registry, WARNINGS, Level::Deny).unwrap();
+        assert_eq!(severities(&levels), [Severity::Error, Severity::Error, Severity::Error]);
+
+        /* A lint's own level comes before that of every warning. */
+        levels.set(&registry, "unreachable_arms", Level::Allow).unwrap();
+        assert_eq!(severities(&levels), [Severity::Error, Severity::Error]);
+
+        levels.set(&registry, WARNINGS, Level::Allow).unwrap();
+        levels.set(&registry, "unreachable_arms", Level::Deny).unwrap();
+        assert_eq!(severities(&levels), [Severity::Error, Severity::Error]);
+
+        assert!(levels.set(&registry, "unused_lint", Level::Deny).is_err());
+    }
+}
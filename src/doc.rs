@@ -0,0 +1,173 @@
+use crate::lexer::doc_comment_before;
+use crate::parser::{Program, Statement};
+use crate::printer::{format_signature, format_variant};
+use crate::span::Span;
+
+/// Append the entry of a definition named `name` to `page`: its
+/// declaration, then its doc comment in `source` if it has one.
+fn document(page: &mut String, source: &str, name: &str, signature: &str, offset: usize) {
+    page.push_str(&format!("### `{}`\n\n```fang\n{}\n```\n\n", name, signature));
+
+    if let Some(doc) = doc_comment_before(source, offset) {
+        page.push_str(&doc);
+        page.push_str("\n\n");
+    }
+}
+
+/// Titles of the sections of a module page, in order.
+const SECTIONS: [&str; 3] = ["Constants", "Enums", "Functions"];
+
+/// Name of the constant, enum or function `statement` defines, with
+/// the title of the section documenting it and the span of the name.
+fn definition(statement: &Statement) -> Option<(&str, &'static str, Span)> {
+    match statement {
+        Statement::ConstantDefinition {
+            identifier,
+            span,
+            ..
+        } => Some((identifier, "Constants", *span)),
+        Statement::Enum {
+            name,
+            span,
+            ..
+        } => Some((name, "Enums", *span)),
+        Statement::FunctionDefinition {
+            callee_name,
+            span,
+            ..
+        } => Some((callee_name, "Functions", *span)),
+        _ => None,
+    }
+}
+
+/// Markdown page documenting the constants, enums and functions the
+/// module `name` defines at its top level, in source order.
+///
+/// `program` is the module compiled from `source`, with its spans
+/// within it, so the doc comment above each definition is found.
+pub fn document_module(name: &str, source: &str, program: &Program) -> String {
+    let mut page = format!("# {}\n\n", name);
+
+    for section in SECTIONS {
+        let mut entries = String::new();
+
+        for statement in &program.statements {
+            let (name, span) = match definition(statement) {
+                Some((name, title, span)) if title == section => (name, span),
+                _ => continue,
+            };
+
+            document(&mut entries, source, name, &format_signature(&program.expressions, statement).unwrap(), span.start);
+
+            if let Statement::Enum { variants, .. } = statement {
+                for variant in variants {
+                    entries.push_str(&format!("- `{}`\n", format_variant(&program.expressions, variant)));
+                }
+
+                entries.push('\n');
+            }
+        }
+
+        if !entries.is_empty() {
+            page.push_str(&format!("## {}\n\n{}", section, entries));
+        }
+    }
+
+    page.truncate(page.trim_end().len());
+    page.push('\n');
+    page
+}
+
+/// Markdown page linking to the pages of `modules`.
+pub fn document_index(modules: &[String]) -> String {
+    let mut page = String::from("# Modules\n\n");
+
+    for module in modules {
+        page.push_str(&format!("- [{}]({}.md)\n", module, module));
+    }
+
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_str;
+    use super::*;
+
+    #[test]
+    fn module_pages() {
+        let source = "\
+/// Largest value of a digit.
+const MAX = 9;
+let count = 0;
+
+/// Kind of a token.
+enum Token {
+    Digit(int),
+    End,
+}
+
+/// Sum of `a` and `b`.
+///
+/// Never fails.
+func add(a: int, b: int) -> int {
+    return a + b;
+}
+func log(text) {
+    print(text);
+}
+";
+        let program = compile_str(source).unwrap();
+
+        assert_eq!(document_module("math", source, program.program()), "\
+# math
+
+## Constants
+
+### `MAX`
+
+```fang
+const MAX
+```
+
+Largest value of a digit.
+
+## Enums
+
+### `Token`
+
+```fang
+enum Token
+```
+
+Kind of a token.
+
+- `Digit(int)`
+- `End`
+
+## Functions
+
+### `add`
+
+```fang
+func add(a: int, b: int) -> int
+```
+
+Sum of `a` and `b`.
+
+Never fails.
+
+### `log`
+
+```fang
+func log(text)
+```
+");
+        assert_eq!(document_index(&[String::from("main"), String::from("math")]), "\
+# Modules
+
+- [main](main.md)
+- [math](math.md)
+");
+    }
+}
@@ -0,0 +1,112 @@
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+
+/// Byte order mark some editors start UTF-8 files with.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Text of a source file from its `bytes`, without the byte order mark
+/// it may start with.
+///
+/// Files are UTF-8 unless a byte order mark says they're UTF-16, in
+/// which case they're transcoded. Bytes invalid in the encoding are
+/// read as U+FFFD if `lossy`, and are otherwise reported along with the
+/// text read lossily, for the error to be rendered against.
+pub fn decode(mut bytes: Vec<u8>, lossy: bool) -> Result<String, (Diagnostic, String)> {
+    match bytes.get(..2) {
+        Some([0xFF, 0xFE]) => return decode_utf16(&bytes[2..], u16::from_le_bytes, lossy),
+        Some([0xFE, 0xFF]) => return decode_utf16(&bytes[2..], u16::from_be_bytes, lossy),
+        _ => {},
+    }
+
+    if bytes.starts_with(UTF8_BOM) {
+        bytes.drain(..UTF8_BOM.len());
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(err) => {
+            let offset = err.utf8_error().valid_up_to();
+            let text = String::from_utf8_lossy(err.as_bytes()).into_owned();
+
+            if lossy {
+                Ok(text)
+            } else {
+                Err((invalid("Invalid UTF-8!", offset), text))
+            }
+        },
+    }
+}
+
+/// Text of the UTF-16 `bytes` following a byte order mark, whose code
+/// units are read by `unit`.
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16, lossy: bool) -> Result<String, (Diagnostic, String)> {
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut offset: Option<usize> = None;
+    let pairs = bytes.chunks_exact(2);
+    let odd = !pairs.remainder().is_empty();
+
+    for char in char::decode_utf16(pairs.map(|pair| unit([pair[0], pair[1]]))) {
+        match char {
+            Ok(char) => text.push(char),
+            Err(_) => {
+                offset.get_or_insert(text.len());
+                text.push(char::REPLACEMENT_CHARACTER);
+            },
+        }
+    }
+
+    /* A last byte left over is half a code unit. */
+    if odd {
+        offset.get_or_insert(text.len());
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+
+    match offset {
+        Some(offset) if !lossy => Err((invalid("Invalid UTF-16!", offset), text)),
+        _ => Ok(text),
+    }
+}
+
+/// Error about the bytes read as the U+FFFD at `offset` of the text.
+fn invalid(message: &str, offset: usize) -> Diagnostic {
+    Diagnostic::error(message)
+        .with_span(Span::new(offset, offset + char::REPLACEMENT_CHARACTER.len_utf8()))
+        .with_help("source files are UTF-8, or UTF-16 starting with a byte order mark")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_order_marks() {
+        let utf16 = |bom: [u8; 2], unit: fn(u16) -> [u8; 2], text: &str| -> Vec<u8> {
+            bom.into_iter().chain(text.encode_utf16().flat_map(unit)).collect()
+        };
+
+        assert_eq!(decode(b"\xEF\xBB\xBFlet a = 1;".to_vec(), false), Ok(String::from("let a = 1;")));
+        assert_eq!(decode(utf16([0xFF, 0xFE], u16::to_le_bytes, "let caf\u{e9};"), false),
+                   Ok(String::from("let caf\u{e9};")));
+        assert_eq!(decode(utf16([0xFE, 0xFF], u16::to_be_bytes, "let a = \"\u{1F600}\";"), false),
+                   Ok(String::from("let a = \"\u{1F600}\";")));
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        let (diagnostic, text) = decode(b"let a = 1;\nb\xFF;".to_vec(), false).unwrap_err();
+
+        assert_eq!(diagnostic.render("main.fang", &text), "\
+main.fang:2:2: error: Invalid UTF-8!
+main.fang:2:2: help: source files are UTF-8, or UTF-16 starting with a byte order mark");
+        assert_eq!(decode(b"b\xFF;".to_vec(), true), Ok(String::from("b\u{FFFD};")));
+
+        /* An unpaired surrogate and a last odd byte. */
+        for bytes in [b"\xFF\xFEa\0\x00\xD8;\0".to_vec(), b"\xFF\xFEa\0;".to_vec()] {
+            let (diagnostic, _) = decode(bytes.clone(), false).unwrap_err();
+
+            assert_eq!(diagnostic.message, "Invalid UTF-16!");
+            assert_eq!(diagnostic.span, Some(Span::new(1, 4)));
+            assert!(decode(bytes, true).unwrap().starts_with("a\u{FFFD}"));
+        }
+    }
+}
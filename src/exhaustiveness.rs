@@ -0,0 +1,532 @@
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::parser::{Expression, MatchArm, Pattern, Program, Statement, Variant};
+use crate::span::Span;
+use crate::visit::{walk_program, walk_statement, Visitor};
+use std::collections::HashMap;
+
+/// Pattern reduced to what matters for coverage: a constructor with
+/// the patterns of its fields, or anything else.
+#[derive(PartialEq, Debug, Clone)]
+enum Pat {
+
+    /// Pattern matching any value, like `_` or a binding.
+    Wild,
+    Constructor(Constructor, Vec<Pat>),
+}
+
+/// Way of building a value a pattern can tell apart from the others.
+#[derive(PartialEq, Debug, Clone)]
+enum Constructor {
+    Variant(String),
+
+    /// Array of the given length.
+    Array(usize),
+    Number(isize),
+    String(String),
+    Nil,
+}
+
+/// Enum declared in the program.
+struct Enum<'a> {
+    name: &'a str,
+    variants: &'a [Variant],
+}
+
+/// Rows of patterns, one per arm, each matched against the same
+/// values column by column.
+type Matrix = Vec<Vec<Pat>>;
+
+/// Check every `match` of the program: an error for a match over an
+/// enum that leaves variants uncovered, naming them, and a warning for
+/// each arm the arms before it already cover. A destructuring `let`
+/// is an error if its pattern leaves variants uncovered too.
+///
+/// Only enums declared in the program are known, the frontend passing
+/// the program of every file of a compilation unit, so an enum any of
+/// them declares is checked in all of them.
+pub fn check_matches(program: &Program) -> Diagnostics {
+    let mut checker = Checker {
+        enums: HashMap::new(),
+        destructurings: Vec::new(),
+        diagnostics: Diagnostics::new(),
+    };
+
+    walk_program(&mut checker, program);
+
+    for (pattern, span) in std::mem::take(&mut checker.destructurings) {
+        checker.check_destructuring(pattern, span);
+    }
+
+    for expression in program.expressions.iter() {
+        if let Expression::Match {
+            arms,
+            span,
+            ..
+        } = expression {
+            checker.check_match(arms, *span);
+        }
+    }
+
+    checker.diagnostics
+}
+
+struct Checker<'a> {
+
+    /// Enums by the names of their variants, which enums declared in
+    /// different scopes may share.
+    enums: HashMap<&'a str, Vec<Enum<'a>>>,
+
+    /// Patterns of the destructuring `let`s, with their spans.
+    destructurings: Vec<(&'a Pattern, Span)>,
+    diagnostics: Diagnostics,
+}
+
+/// Gathers the enums and destructuring `let`s of the program.
+impl<'a> Visitor<'a> for Checker<'a> {
+    fn visit_statement(&mut self, program: &'a Program, statement: &'a Statement) {
+        match statement {
+            Statement::Enum {
+                name,
+                variants,
+                ..
+            } => {
+                for variant in variants {
+                    self.enums.entry(&variant.name).or_default().push(Enum {
+                        name,
+                        variants,
+                    });
+                }
+            },
+            Statement::Destructuring {
+                pattern,
+                span,
+                ..
+            } => self.destructurings.push((pattern, *span)),
+            _ => {},
+        }
+
+        walk_statement(self, program, statement);
+    }
+}
+
+impl<'a> Checker<'a> {
+
+    /// Numbers of fields of the variants named `name`, none if no enum
+    /// declares one.
+    fn arities(&self, name: &str) -> Vec<usize> {
+        self.enums.get(name).into_iter().flatten().map(|r#enum| {
+            r#enum.variants.iter().find(|variant| variant.name == name).unwrap().fields.len()
+        }).collect()
+    }
+
+    /// Enum declaring a variant of each name of `constructors`, which
+    /// the other constructors are left out of.
+    fn enum_of<'c>(&self, constructors: impl IntoIterator<Item = &'c Constructor>) -> Option<&Enum<'a>> {
+        let names: Vec<&str> = constructors.into_iter()
+            .filter_map(|constructor| match constructor {
+                Constructor::Variant(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        self.enums.get(*names.first()?)?.iter().find(|r#enum| {
+            names.iter().all(|name| r#enum.variants.iter().any(|variant| variant.name == *name))
+        })
+    }
+
+    fn check_match(&mut self, arms: &[MatchArm], span: Span) {
+        let mut matrix: Matrix = Vec::new();
+
+        for arm in arms {
+            let pat = match self.lower(&arm.pattern) {
+                Ok(pat) => pat,
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    return;
+                },
+            };
+
+            if !self.useful(&matrix, std::slice::from_ref(&pat)) {
+                self.diagnostics.push(Diagnostic::warning("Unreachable arm, the arms before it cover every value it matches!")
+                    .with_span(arm.span)
+                    .with_lint("unreachable_arms"));
+            }
+
+            matrix.push(vec![pat]);
+        }
+
+        if let Some((name, missing)) = self.missing(&matrix) {
+            self.diagnostics.push(Diagnostic::error(
+                format!("Match over \"{}\" doesn't cover every variant!", name))
+                .with_span(span)
+                .with_help(format!("Add arms for {}, or a \"_\" arm.", missing.join(", "))));
+        }
+    }
+
+    fn check_destructuring(&mut self, pattern: &Pattern, span: Span) {
+        let matrix: Matrix = match self.lower(pattern) {
+
+            /* Only another variant of an enum is known to fail the
+             * pattern before run time. */
+            Ok(pat) => vec![vec![variants_only(pat)]],
+            Err(diagnostic) => {
+                self.diagnostics.push(diagnostic);
+                return;
+            },
+        };
+
+        if let Some((name, missing)) = self.missing(&matrix) {
+            self.diagnostics.push(Diagnostic::error(
+                format!("Pattern doesn't match every value of \"{}\"!", name))
+                .with_span(span)
+                .with_help(format!("Use \"match\" to handle {} too.", missing.join(", "))));
+        }
+    }
+
+    /// Name of the enum the rows of `matrix` match over, with the
+    /// variants they leave uncovered, `None` if they cover them all.
+    ///
+    /// Only a match over an enum is known to need a catch-all, so rows
+    /// over other values cover them all.
+    fn missing(&self, matrix: &Matrix) -> Option<(&'a str, Vec<String>)> {
+        let r#enum = self.enum_of(matrix.iter().filter_map(|row| match &row[0] {
+            Pat::Constructor(constructor, _) => Some(constructor),
+            Pat::Wild => None,
+        }))?;
+        let mut missing: Vec<String> = Vec::new();
+
+        for variant in r#enum.variants {
+            let constructor = Constructor::Variant(variant.name.to_owned());
+            let arity = variant.fields.len();
+
+            if let Some(fields) = self.witness(&specialize(matrix, &constructor, arity), arity) {
+                missing.push(format_pat(&Pat::Constructor(constructor, fields)));
+            }
+        }
+
+        if missing.is_empty() {
+            None
+        } else {
+            Some((r#enum.name, missing))
+        }
+    }
+
+    /// Pattern as far as coverage goes. A name is the variant it names
+    /// if that has no fields, or else matches anything.
+    fn lower(&self, pattern: &Pattern) -> Result<Pat, Diagnostic> {
+        let pat = match pattern {
+            Pattern::Wildcard => Pat::Wild,
+            Pattern::Identifier {
+                name,
+                ..
+            } => match self.arities(name).contains(&0) {
+                true => Pat::Constructor(Constructor::Variant(name.to_owned()), Vec::new()),
+                false => Pat::Wild,
+            },
+            Pattern::Variant {
+                name,
+                fields,
+                span,
+            } => {
+                let mut pats: Vec<Pat> = Vec::new();
+                let arities = self.arities(name);
+
+                if !arities.is_empty() && !arities.contains(&fields.len()) {
+                    return Err(Diagnostic::error(
+                        format!("Variant \"{}\" has {} fields but the pattern has {}!",
+                                name, arities[0], fields.len())).with_span(*span));
+                }
+
+                for field in fields {
+                    pats.push(self.lower(field)?);
+                }
+
+                Pat::Constructor(Constructor::Variant(name.to_owned()), pats)
+            },
+            Pattern::Array(elements) => {
+                let mut pats: Vec<Pat> = Vec::new();
+
+                for element in elements {
+                    pats.push(self.lower(element)?);
+                }
+
+                Pat::Constructor(Constructor::Array(elements.len()), pats)
+            },
+            Pattern::Number(num) => Pat::Constructor(Constructor::Number(*num), Vec::new()),
+            Pattern::String(str) => Pat::Constructor(Constructor::String(str.to_owned()), Vec::new()),
+            Pattern::Nil => Pat::Constructor(Constructor::Nil, Vec::new()),
+        };
+
+        Ok(pat)
+    }
+
+    /// Constructors heading the rows of `matrix`, and whether they are
+    /// every variant of their enum, so no value escapes them.
+    fn head_constructors(&self, matrix: &Matrix) -> (Vec<(Constructor, usize)>, bool) {
+        let mut constructors: Vec<(Constructor, usize)> = Vec::new();
+
+        for row in matrix {
+            if let Pat::Constructor(constructor, fields) = &row[0] {
+                if !constructors.iter().any(|(other, _)| other == constructor) {
+                    constructors.push((constructor.clone(), fields.len()));
+                }
+            }
+        }
+
+        let complete = match self.enum_of(constructors.iter().map(|(constructor, _)| constructor)) {
+            Some(r#enum) => r#enum.variants.iter().all(|variant| constructors.iter().any(|(constructor, _)|
+                *constructor == Constructor::Variant(variant.name.to_owned()))),
+            None => false,
+        };
+
+        (constructors, complete)
+    }
+
+    /// Whether some values match the row `pats` but no row of
+    /// `matrix`.
+    fn useful(&self, matrix: &Matrix, pats: &[Pat]) -> bool {
+        let (head, rest) = match pats.split_first() {
+            Some(split) => split,
+            None => return matrix.is_empty(),
+        };
+
+        match head {
+            Pat::Constructor(constructor, fields) => {
+                let mut row = fields.clone();
+
+                row.extend_from_slice(rest);
+
+                self.useful(&specialize(matrix, constructor, fields.len()), &row)
+            },
+            Pat::Wild => {
+                let (constructors, complete) = self.head_constructors(matrix);
+
+                if !complete {
+                    return self.useful(&default_rows(matrix), rest);
+                }
+
+                constructors.iter().any(|(constructor, arity)| {
+                    let mut row = vec![Pat::Wild; *arity];
+
+                    row.extend_from_slice(rest);
+
+                    self.useful(&specialize(matrix, constructor, *arity), &row)
+                })
+            },
+        }
+    }
+
+    /// Row of `width` patterns matching values that no row of `matrix`
+    /// matches, `None` if the rows cover every value.
+    fn witness(&self, matrix: &Matrix, width: usize) -> Option<Vec<Pat>> {
+        if width == 0 {
+            return if matrix.is_empty() { Some(Vec::new()) } else { None };
+        }
+
+        let (constructors, complete) = self.head_constructors(matrix);
+
+        if complete {
+            for (constructor, arity) in constructors {
+                if let Some(mut pats) = self.witness(&specialize(matrix, &constructor, arity), arity + width - 1) {
+                    let rest = pats.split_off(arity);
+
+                    pats = vec![Pat::Constructor(constructor, pats)];
+                    pats.extend(rest);
+
+                    return Some(pats);
+                }
+            }
+
+            return None;
+        }
+
+        let mut pats = self.witness(&default_rows(matrix), width - 1)?;
+        let head = match self.enum_of(constructors.iter().map(|(constructor, _)| constructor)) {
+
+            /* Name a variant none of the rows starts with. */
+            Some(r#enum) => r#enum.variants.iter()
+                .find(|variant| !constructors.iter().any(|(constructor, _)|
+                    *constructor == Constructor::Variant(variant.name.to_owned())))
+                .map(|variant| Pat::Constructor(
+                    Constructor::Variant(variant.name.to_owned()),
+                    vec![Pat::Wild; variant.fields.len()]))
+                .unwrap_or(Pat::Wild),
+            None => Pat::Wild,
+        };
+
+        pats.insert(0, head);
+
+        Some(pats)
+    }
+}
+
+/// Rows of `matrix` that match a value built by `constructor`, with
+/// the patterns of its `arity` fields in place of their first pattern.
+fn specialize(matrix: &Matrix, constructor: &Constructor, arity: usize) -> Matrix {
+    let mut rows: Matrix = Vec::new();
+
+    for row in matrix {
+        let mut specialized = match &row[0] {
+            Pat::Constructor(other, fields) if other == constructor => fields.clone(),
+            Pat::Constructor(..) => continue,
+            Pat::Wild => vec![Pat::Wild; arity],
+        };
+
+        specialized.extend_from_slice(&row[1..]);
+        rows.push(specialized);
+    }
+
+    rows
+}
+
+/// Rows of `matrix` starting with a pattern matching anything, without
+/// it.
+fn default_rows(matrix: &Matrix) -> Matrix {
+    matrix.iter()
+        .filter(|row| row[0] == Pat::Wild)
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+/// `pat` keeping only the variant heading it and those nested in its
+/// fields, everything else matching anything.
+fn variants_only(pat: Pat) -> Pat {
+    match pat {
+        Pat::Constructor(Constructor::Variant(name), fields) =>
+            Pat::Constructor(Constructor::Variant(name), fields.into_iter().map(variants_only).collect()),
+        _ => Pat::Wild,
+    }
+}
+
+fn format_pat(pat: &Pat) -> String {
+    match pat {
+        Pat::Wild => String::from("_"),
+        Pat::Constructor(Constructor::Variant(name), fields) if fields.is_empty() => name.to_owned(),
+        Pat::Constructor(Constructor::Variant(name), fields) => {
+            let fields: Vec<String> = fields.iter().map(format_pat).collect();
+
+            format!("{}({})", name, fields.join(", "))
+        },
+        Pat::Constructor(Constructor::Array(_), elements) => {
+            let elements: Vec<String> = elements.iter().map(format_pat).collect();
+
+            format!("[{}]", elements.join(", "))
+        },
+        Pat::Constructor(Constructor::Number(num), _) => num.to_string(),
+        Pat::Constructor(Constructor::String(str), _) => format!("\"{}\"", str),
+        Pat::Constructor(Constructor::Nil, _) => String::from("nil"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conditional::configure;
+    use crate::diagnostic::LintLevels;
+    use crate::parser::parse_source;
+    use super::*;
+
+    macro_rules! check_source {
+        ($text:expr) => {{
+            let text = $text;
+            let mut program = parse_source(text).unwrap();
+
+            configure(&mut program, &[]);
+            LintLevels::new().apply(&program, check_matches(&program)).render("main.fang", text)
+        }};
+    }
+
+    #[test]
+    fn missing_variants() {
+        assert_eq!(check_source!("
+enum Shape { Circle(int), Rect(int, int), Empty }
+a = match s { Circle(_) => 1, Rect(0, h) => h };
+b = match s { Circle(r) => r, Rect(w, h) => w, Empty => 0 };
+c = match s { Empty => 0, _ => 1 };
+"), "\
+main.fang:3:5: error: Match over \"Shape\" doesn't cover every variant!
+main.fang:3:5: help: Add arms for Rect(_, _), Empty, or a \"_\" arm.
+");
+        assert_eq!(check_source!("
+enum Option { Some(Tree), None }
+enum Tree { Leaf, Node(Option, int) }
+a = match t { Leaf => 0, Node(None, _) => 1, Node(Some(Leaf), 2) => 2 };
+"), "\
+main.fang:4:5: error: Match over \"Tree\" doesn't cover every variant!
+main.fang:4:5: help: Add arms for Node(Some(Node(_, _)), _), or a \"_\" arm.
+");
+    }
+
+    #[test]
+    fn shared_variant_names() {
+        let text = "enum E { A, B } enum F { A, C } let x = B; match x { A => 1, B => 2 };";
+
+        assert_eq!(parse_source(text).unwrap_err().iter().next().unwrap().message, "Variant \"A\" is already defined!");
+
+        /* Enums of different scopes may share a variant, the arms telling
+         * which one a match is over. */
+        assert_eq!(check_source!("
+enum E { A, B }
+func f(x) {
+    enum F { A, C }
+    return match x { A => 1, C => 2 };
+}
+a = match x { A => 1, B => 2 };
+b = match x { B => 1 };
+"), "\
+main.fang:8:5: error: Match over \"E\" doesn't cover every variant!
+main.fang:8:5: help: Add arms for A, or a \"_\" arm.
+");
+    }
+
+    #[test]
+    fn unreachable_arms() {
+        assert_eq!(check_source!("
+enum Bit { Zero, One }
+a = match b { Zero => 0, _ => 1, One => 2 };
+b = match n { 1 => 0, x => x, 2 => 1 };
+c = match p { Pair(Zero, _) => 0, Pair(_, One) => 1, Pair(Zero, One) => 2 };
+d = match b { One(1) => 0 };
+"), "\
+main.fang:3:34: warning: Unreachable arm, the arms before it cover every value it matches!
+main.fang:4:31: warning: Unreachable arm, the arms before it cover every value it matches!
+main.fang:5:54: warning: Unreachable arm, the arms before it cover every value it matches!
+main.fang:6:15: error: Variant \"One\" has 0 fields but the pattern has 1!
+");
+        assert_eq!(check_source!("#![allow(unreachable_arms)]
+enum Bit { Zero, One }
+a = match b { Zero => 0, _ => 1, One => 2 };
+d = match b { One => 0 };
+"), "\
+main.fang:4:5: error: Match over \"Bit\" doesn't cover every variant!
+main.fang:4:5: help: Add arms for Zero, or a \"_\" arm.
+");
+
+        /* The innermost attribute naming the lint sets its level. */
+        assert_eq!(check_source!("#![deny(warnings)]
+#[allow(unreachable_arms)]
+func f(b) {
+    a = match b { 0 => 0, _ => 1, 1 => 2 };
+    #[warn(unreachable_arms)]
+    c = match b { _ => 1, 1 => 2 };
+}
+d = match b { _ => 1, 1 => 2 };
+"), "\
+main.fang:6:27: warning: Unreachable arm, the arms before it cover every value it matches!
+main.fang:8:23: error: Unreachable arm, the arms before it cover every value it matches!
+");
+    }
+
+    #[test]
+    fn destructuring() {
+        assert_eq!(check_source!("
+enum Shape { Circle(int), Rect(int, int), Empty }
+enum Pair { Pair(Shape, int) }
+let Pair(s, n) = p;
+let [Circle(r), _] = shapes;
+let Pair(Rect(w, _), _) = p;
+"), "\
+main.fang:6:5: error: Pattern doesn't match every value of \"Pair\"!
+main.fang:6:5: help: Use \"match\" to handle Pair(Circle(_), _) too.
+");
+    }
+}
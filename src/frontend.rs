@@ -1,49 +1,885 @@
-use crate::lexer::Tokenizer;
-use crate::parser::Parser;
+use crate::arguments::check_arguments;
+use crate::cache::Cache;
+use crate::conditional::configure;
+use crate::constant::check_constants;
+use crate::diagnostic::{Diagnostic, Diagnostics, Level, LintLevels};
+use crate::encoding::decode;
+use crate::exhaustiveness::check_matches;
+use crate::macros::expand_macros;
+use crate::lexer::{Stream, Tokenizer};
+use crate::lint::LintRegistry;
+use crate::parser::{parse_source_with, Attribute, ExprId, ParseOptions, Pattern, Program, Statement, Type};
+use crate::plugin::Plugin;
+use crate::span::{FileId, SourceFile, SourceMap, Span};
+use crate::template::check_formats;
+use crate::visit::{walk_expression, walk_pattern, walk_program, walk_statement, walk_type, Visitor};
 use std::fs::File;
 use std::io::Read;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Stack a thread sets aside for each level code nests, with room for
+/// the passes after parsing.
+const STACK_PER_LEVEL: usize = 16 * 1024;
+
+/// Stack of a thread however shallow code nests.
+const MIN_STACK_SIZE: usize = 2 * 1024 * 1024;
 
 pub struct Frontend {
 
+    /// Cache of files parsed by earlier runs.
+    cache: Option<Cache>,
+    options: ParseOptions,
+
+    /// Symbols of conditional compilation, `None` keeping the program
+    /// as written, with every block and macro in place, as formatting
+    /// does.
+    symbols: Option<Vec<String>>,
+
+    /// Files processed so far, the spans of the programs returned
+    /// being offsets among all of them.
+    sources: SourceMap,
+    lint_levels: LintLevels,
+    plugins: Vec<Box<dyn Plugin>>,
+}
+
+/// Time each phase of the frontend took, parsing being summed over the
+/// files, which are parsed in parallel.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Timings {
+
+    /// Reading and decoding the files.
+    pub read: Duration,
+
+    /// Lexing and parsing them, or loading them from the cache.
+    pub parse: Duration,
+
+    /// Configuring the program of all files, expanding its macros and
+    /// checking it.
+    pub check: Duration,
+}
+
+/// What compiling files produced.
+#[derive(Debug)]
+pub struct CompilationResult {
+
+    /// Program compiled from all files, `None` if any has errors.
+    pub program: Option<Program>,
+
+    /// Diagnostics of each file that reported any, warnings included,
+    /// in the order of the files. Their spans are within the text of
+    /// their file.
+    pub diagnostics: Vec<(FileId, Diagnostics)>,
+    pub timings: Timings,
+}
+
+impl CompilationResult {
+
+    /// Format the diagnostics like [`Diagnostics::render`], each
+    /// against its file in `sources`.
+    pub fn render(&self, sources: &SourceMap) -> String {
+        self.diagnostics.iter()
+            .map(|(id, diagnostics)| diagnostics.render_in(sources.file(*id)))
+            .collect()
+    }
+}
+
+/// What parsing one file produced.
+struct Parsed {
+    program: Option<Program>,
+
+    /// How deep the tree of the program nests.
+    depth: usize,
+    diagnostics: Diagnostics,
+    timings: Timings,
+}
+
+/// Finds how deep the tree of a program nests, counting statements,
+/// expressions, patterns and types alike.
+#[derive(Default)]
+struct Depth {
+    current: usize,
+    max: usize,
+}
+
+impl Depth {
+    fn of(program: &Program) -> usize {
+        let mut depth = Depth::default();
+
+        walk_program(&mut depth, program);
+
+        depth.max
+    }
+
+    fn enter(&mut self, walk: impl FnOnce(&mut Self)) {
+        self.current += 1;
+        self.max = self.max.max(self.current);
+        walk(self);
+        self.current -= 1;
+    }
+}
+
+impl<'ast> Visitor<'ast> for Depth {
+    fn visit_statement(&mut self, program: &'ast Program, statement: &'ast Statement) {
+        self.enter(|depth| walk_statement(depth, program, statement));
+    }
+
+    fn visit_expression(&mut self, program: &'ast Program, id: ExprId) {
+        self.enter(|depth| walk_expression(depth, program, id));
+    }
+
+    fn visit_pattern(&mut self, program: &'ast Program, pattern: &'ast Pattern) {
+        self.enter(|depth| walk_pattern(depth, program, pattern));
+    }
+
+    fn visit_type(&mut self, program: &'ast Program, r#type: &'ast Type) {
+        self.enter(|depth| walk_type(depth, program, r#type));
+    }
+}
+
+/// Program of a compilation unit, appending the programs of its files
+/// in order, each moved to the base of its file in `sources`.
+///
+/// The lint attributes heading a file set levels within that file only,
+/// so when there are several files they become attributes scoped to the
+/// text of their file, ranking below those of its statements.
+pub fn merge_programs(sources: &SourceMap, programs: Vec<(FileId, Program)>) -> Program {
+    let several = programs.len() > 1;
+    let mut merged = Program::default();
+
+    for (id, mut program) in programs {
+        let file = sources.file(id);
+
+        program.shift_spans(file.base);
+
+        if several {
+            let (lints, others): (Vec<Attribute>, Vec<Attribute>) = program.attributes.into_iter()
+                .partition(|attribute| Level::from_attribute(&attribute.name).is_some());
+
+            program.attributes = others;
+
+            if !lints.is_empty() {
+                merged.scoped_attributes.push((Span::new(file.base, file.base + file.text.len()), lints));
+            }
+        }
+
+        merged.append(program);
+    }
+
+    merged
+}
+
+/// Drop the blocks of conditional compilation not chosen for `symbols`
+/// and expand the macros, then run the checks of every compilation
+/// over `program`, returning its warnings at `lint_levels`.
+///
+/// Without symbols, the program is checked as written, as formatting
+/// needs it. On failure, the diagnostics may hold warnings besides the
+/// errors.
+pub fn check_program(
+    program: &mut Program,
+    symbols: Option<&[String]>,
+    registry: &LintRegistry,
+    lint_levels: &LintLevels
+) -> Result<Diagnostics, Diagnostics> {
+    if let Some(symbols) = symbols {
+        configure(program, symbols);
+        expand_macros(program)?;
+    }
+
+    registry.check_attributes(program)?;
+    check_constants(program)?;
+    check_formats(program)?;
+    check_arguments(program)?;
+
+    let diagnostics = lint_levels.apply(program, check_matches(program));
+
+    if diagnostics.has_errors() {
+        return Err(diagnostics);
+    }
+
+    Ok(diagnostics)
+}
+
+/// Diagnostics of the compilation unit of `files`, whose spans are
+/// offsets among the files of `sources`, split by file in the order of
+/// `files`, with their spans moved within the text of their file.
+///
+/// Those without a span are reported with the first file.
+pub fn split_diagnostics(sources: &SourceMap, files: &[FileId], diagnostics: &Diagnostics) -> Vec<(FileId, Diagnostics)> {
+    let mut split: Vec<(FileId, Diagnostics)> = files.iter().map(|id| (*id, Diagnostics::new())).collect();
+
+    for diagnostic in diagnostics.iter() {
+        let mut diagnostic = diagnostic.clone();
+        let index = diagnostic.span.and_then(|span| files.iter().position(|id| {
+            let file = sources.file(*id);
+
+            file.base <= span.start && span.start <= file.base + file.text.len()
+        }));
+
+        match (index, diagnostic.span) {
+            (Some(index), Some(span)) => {
+                let base = sources.file(files[index]).base;
+
+                diagnostic.span = Some(Span::new(span.start - base, span.end.saturating_sub(base)));
+                split[index].1.push(diagnostic);
+            },
+            _ => if let Some((_, first)) = split.first_mut() {
+                first.push(diagnostic);
+            },
+        }
+    }
+
+    split.retain(|(_, diagnostics)| !diagnostics.is_empty());
+    split
+}
+
+impl Default for Frontend {
+    fn default() -> Frontend {
+        Frontend::new()
+    }
 }
 
 impl Frontend {
     pub fn new() -> Frontend {
         Frontend {
+            cache: None,
+            options: ParseOptions::default(),
+            symbols: Some(Vec::new()),
+            sources: SourceMap::new(),
+            lint_levels: LintLevels::new(),
+            plugins: Vec::new(),
+        }
+    }
 
+    /// Frontend reusing the parse results stored in `cache` for
+    /// files whose content didn't change.
+    pub fn with_cache(cache: Cache) -> Frontend {
+        Frontend {
+            cache: Some(cache),
+            options: ParseOptions::default(),
+            symbols: Some(Vec::new()),
+            sources: SourceMap::new(),
+            lint_levels: LintLevels::new(),
+            plugins: Vec::new(),
         }
     }
 
-    fn process_string(&self, str: &str) {
-        let mut tokenizer = Tokenizer::new();
+    /// Files processed so far, locating the spans of the programs
+    /// returned.
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
 
-        tokenizer.scan(str);
+    /// Parse every file with `options` from now on.
+    pub fn set_options(&mut self, options: ParseOptions) {
+        self.options = options;
+    }
 
-        let stream = tokenizer.extract();
+    /// Keep the blocks of conditional compilation chosen by `symbols`
+    /// and expand the macros from now on, or with `None` leave the
+    /// program as written.
+    pub fn set_symbols(&mut self, symbols: Option<Vec<String>>) {
+        self.symbols = symbols;
+    }
 
-        dbg!(&stream);
+    /// Report the warnings of each lint at the level `levels` sets.
+    pub fn set_lint_levels(&mut self, levels: LintLevels) {
+        self.lint_levels = levels;
+    }
+
+    /// Run the passes of `plugin` from now on, after those of the
+    /// plugins added before it.
+    pub fn add_plugin(&mut self, plugin: Box<dyn Plugin>) {
+        self.plugins.push(plugin);
+    }
+
+    /// Registry of the built-in lints and of those of the plugins.
+    pub fn lint_registry(&self) -> LintRegistry {
+        let mut registry = LintRegistry::with_builtins();
+
+        for plugin in &self.plugins {
+            plugin.register_lints(&mut registry);
+        }
+
+        registry
+    }
+
+    /// Artifact in the format named `format` for `program`, from the
+    /// first plugin emitting that format.
+    pub fn emit(&self, format: &str, program: &Program) -> Option<String> {
+        self.plugins.iter().find_map(|plugin| plugin.emit(format, program))
+    }
+
+    fn tokenize_string<'src>(&self, str: &'src str) -> Result<Stream<'src>, Diagnostics> {
+        let mut tokenizer = Tokenizer::new();
+
+        tokenizer.scan(str)?;
+
+        Ok(tokenizer.extract())
+    }
 
-        let mut parser = Parser::new(stream);
+    /// Check the program like [`check_program`] with the symbols and
+    /// lints of the frontend, then transform it with the plugins,
+    /// returning it with its warnings.
+    fn check_program(&self, mut program: Program) -> Result<(Program, Diagnostics), Diagnostics> {
+        let diagnostics = check_program(&mut program, self.symbols.as_deref(), &self.lint_registry(), &self.lint_levels)?;
 
-        let program = parser.parse_program();
+        if self.symbols.is_some() {
+            for plugin in &self.plugins {
+                plugin.transform(&mut program)?;
+            }
+        }
 
-        dbg!(&program);
+        Ok((program, diagnostics))
     }
 
-    pub fn process_file(&self, path: &String) {
+    /// Read a file as text, or the diagnostic of why it couldn't be
+    /// read along with whatever text was read, for the diagnostic to be
+    /// rendered against.
+    fn read_source(&self, path: &String) -> Result<String, (Diagnostic, String)> {
+        let mut buf: Vec<u8> = Vec::new();
         let mut file = match File::open(path) {
             Ok(file) => file,
-            Err(err) => {
-                eprintln!("Failed to open \"{}\": {}", path, err);
-                return;
+            Err(err) => return Err((Diagnostic::error(format!("Failed to open: {}", err)), String::new())),
+        };
+
+        if let Err(err) = file.read_to_end(&mut buf) {
+            return Err((Diagnostic::error(format!("Failed to read: {}", err)), String::new()));
+        }
+
+        decode(buf, self.options.lossy_utf8)
+    }
+
+    /// Source file named `name` holding `text`, locating spans with
+    /// the width of a tab the options give.
+    pub fn source_file(&self, name: &str, text: impl Into<String>) -> SourceFile {
+        SourceFile::new(name, text).with_tab_width(self.options.tab_width)
+    }
+
+    /// Read a file as text, or describe why it couldn't be read.
+    pub fn read_file(&self, path: &String) -> Result<String, String> {
+        self.read_source(path).map_err(|(diagnostic, text)| Diagnostics::from(diagnostic).render_in(&self.source_file(path, text)))
+    }
+
+    /// Lex a single file, or on failure return its diagnostics
+    /// rendered against the file's path.
+    pub fn tokenize_file(&self, path: &String) -> Result<Stream<'static>, String> {
+        let str = self.read_file(path)?;
+
+        match self.tokenize_string(&str) {
+            Ok(stream) => Ok(stream.into_owned()),
+            Err(diagnostics) => Err(diagnostics.render_in(&self.source_file(path, str))),
+        }
+    }
+
+    /// Read a file into the source map, along with the diagnostic of
+    /// why it couldn't be read, if so. The map then holds whatever text
+    /// was read.
+    fn add_file(&mut self, path: &String) -> (FileId, Option<Diagnostic>) {
+        let (text, failure) = match self.read_source(path) {
+            Ok(text) => (text, None),
+            Err((diagnostic, text)) => (text, Some(diagnostic)),
+        };
+        let id = self.sources.add(self.source_file(path, text));
+
+        (id, failure)
+    }
+
+    /// Lex and parse a single file of the source map, its spans and
+    /// those of its diagnostics being within the file's text.
+    fn load_file(&self, id: FileId) -> Parsed {
+        let str = &self.sources.file(id).text;
+        let start = Instant::now();
+
+        /* The cache holds programs as parsed, so they serve any symbols,
+         * and the checks run again on every load. */
+        let program: Result<Program, Diagnostics> = match self.cache.as_ref().and_then(|cache| cache.load(str, self.options)) {
+            Some(program) => Ok(program),
+            None => parse_source_with(str, self.options).inspect(|program| {
+                if let Some(cache) = &self.cache {
+                    cache.store(str, self.options, program);
+                }
+            }),
+        };
+
+        let timings = Timings {
+            parse: start.elapsed(),
+            ..Timings::default()
+        };
+
+        match program {
+            Ok(program) => Parsed {
+                depth: Depth::of(&program),
+                program: Some(program),
+                diagnostics: Diagnostics::new(),
+                timings,
+            },
+            Err(diagnostics) => Parsed {
+                program: None,
+                depth: 0,
+                diagnostics,
+                timings,
+            },
+        }
+    }
+
+    /// Lex, parse and check a single file.
+    pub fn process_file(&mut self, path: &String) -> CompilationResult {
+        self.process_files(std::slice::from_ref(path))
+    }
+
+    /// Lex, parse and check `text` as the file named `name`, which
+    /// needn't exist.
+    pub fn process_string(&mut self, name: &str, text: &str) -> CompilationResult {
+        let id = self.sources.add(self.source_file(name, text));
+
+        self.process(vec![(id, None)], Duration::ZERO)
+    }
+
+    /// Stack of a thread deep enough for code nesting `depth` levels.
+    fn stack_size(depth: usize) -> usize {
+        depth.saturating_mul(STACK_PER_LEVEL).max(MIN_STACK_SIZE)
+    }
+
+    /// Lex and parse all files read in parallel, returning the results
+    /// in the order of `files`.
+    fn load_files(&self, files: &[(FileId, Option<Diagnostic>)]) -> Vec<Parsed> {
+        let workers = thread::available_parallelism()
+            .map_or(1, |count| count.get())
+            .min(files.len());
+        let stack_size = Frontend::stack_size(self.options.nesting_limit);
+        let mut results: Vec<Option<Parsed>>;
+
+        results = files.iter().map(|_| None).collect();
+
+        thread::scope(|scope| {
+            let mut handles = Vec::new();
+
+            /* Worker `n` takes every `workers`-th file from the `n`-th. */
+            for worker in 0..workers {
+                handles.push(thread::Builder::new().stack_size(stack_size).spawn_scoped(scope, move || {
+                    files.iter()
+                        .enumerate()
+                        .skip(worker)
+                        .step_by(workers)
+                        .map(|(index, (id, failure))| (index, match failure {
+                            Some(diagnostic) => Parsed {
+                                program: None,
+                                depth: 0,
+                                diagnostics: Diagnostics::from(diagnostic.clone()),
+                                timings: Timings::default(),
+                            },
+                            None => self.load_file(*id),
+                        }))
+                        .collect::<Vec<_>>()
+                }).unwrap());
+            }
+
+            for handle in handles {
+                for (index, result) in handle.join().unwrap() {
+                    results[index] = Some(result);
+                }
+            }
+        });
+
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Lex and parse all files as one compilation unit, whose
+    /// statements follow the order of `paths`.
+    ///
+    /// Files are read one after the other into the source map, then
+    /// processed in parallel. Every file is processed even if another
+    /// one fails, and diagnostics are listed file by file in the order
+    /// of `paths`, so the result is the same from run to run.
+    pub fn process_files(&mut self, paths: &[String]) -> CompilationResult {
+        let start = Instant::now();
+
+        let files: Vec<(FileId, Option<Diagnostic>)> = paths.iter().map(|path| self.add_file(path)).collect();
+
+        self.process(files, start.elapsed())
+    }
+
+    /// Compile the files of the source map as one compilation unit,
+    /// those with a diagnostic failing with it, `read` being the time
+    /// it took to read them.
+    ///
+    /// The files are parsed on their own, then their programs are
+    /// merged and checked together, so a macro, constant or enum of one
+    /// file serves the others. The unit is only checked once every file
+    /// parses, so errors of the checks don't bury those of the syntax.
+    fn process(&self, files: Vec<(FileId, Option<Diagnostic>)>, read: Duration) -> CompilationResult {
+        let ids: Vec<FileId> = files.iter().map(|(id, _)| *id).collect();
+        let mut programs: Vec<(FileId, Program)> = Vec::new();
+        let mut depth = 0;
+        let mut result = CompilationResult {
+            program: None,
+            diagnostics: Vec::new(),
+            timings: Timings {
+                read,
+                ..Timings::default()
             },
         };
-        let mut buf: Vec<u8> = Vec::new();
 
-        file.read_to_end(&mut buf).unwrap();
+        for (id, parsed) in ids.iter().zip(self.load_files(&files)) {
+            if let Some(program) = parsed.program {
+                programs.push((*id, program));
+            }
+
+            if !parsed.diagnostics.is_empty() {
+                result.diagnostics.push((*id, parsed.diagnostics));
+            }
+
+            result.timings.parse += parsed.timings.parse;
+            depth = depth.max(parsed.depth);
+        }
+
+        if programs.len() < ids.len() {
+            return result;
+        }
+
+        let program = merge_programs(&self.sources, programs);
+        let start = Instant::now();
+
+        /* The checks recurse as deep as the tree nests, which the
+         * parser kept within the limit. */
+        let checked = thread::scope(|scope| {
+            thread::Builder::new()
+                .stack_size(Frontend::stack_size(depth))
+                .spawn_scoped(scope, || self.check_program(program))
+                .unwrap()
+                .join()
+                .unwrap()
+        });
+
+        result.timings.check = start.elapsed();
+        result.diagnostics = match checked {
+            Ok((program, warnings)) => {
+                result.program = Some(program);
+                split_diagnostics(&self.sources, &ids, &warnings)
+            },
+            Err(diagnostics) => split_diagnostics(&self.sources, &ids, &diagnostics),
+        };
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::WARNINGS;
+    use crate::lint::Lint;
+    use crate::parser::{parse_source, ExprId, Expression, Statement, Type};
+    use crate::printer::format_program;
+    use std::sync::Arc;
+    use super::*;
+
+    #[test]
+    fn files_keep_their_order() {
+        let dir = std::env::temp_dir().join(format!("fang-frontend-test-{}", std::process::id()));
+        let mut paths: Vec<String> = Vec::new();
+        let mut frontend = Frontend::new();
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for index in 0..16 {
+            let path = dir.join(format!("file_{}.fang", index));
+
+            std::fs::write(&path, format!("let var_{} = {};", index, index)).unwrap();
+            paths.push(path.to_string_lossy().into_owned());
+        }
+
+        let program: Program = frontend.process_files(&paths).program.unwrap();
+
+        /* Spans are offsets among all files, each after the previous
+         * one. */
+        for (index, statement) in program.statements.iter().enumerate() {
+            let base = frontend.sources().files()[index].base;
+            let span = Span::new(base + 4, base + 4 + format!("var_{}", index).len());
+
+            assert_eq!(statement, &Statement::VariableDefinition {
+                identifier: format!("var_{}", index),
+                r#type: None,
+                value: Some(ExprId(index as u32)),
+                span,
+            });
+            assert_eq!(frontend.sources().location(span.start), Some((paths[index].as_str(), 1, 5)));
+            assert_eq!(program.expression(ExprId(index as u32)), &Expression::Number(index as isize));
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Compile `sources` as the files of one unit, then remove them.
+    fn compile_unit(name: &str, sources: &[&str]) -> (Frontend, CompilationResult) {
+        let dir = std::env::temp_dir().join(format!("fang-{}-test-{}", name, std::process::id()));
+        let mut paths: Vec<String> = Vec::new();
+        let mut frontend = Frontend::new();
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for (index, source) in sources.iter().enumerate() {
+            let path = dir.join(format!("m{}.fang", index + 1));
+
+            std::fs::write(&path, source).unwrap();
+            paths.push(path.to_string_lossy().into_owned());
+        }
+
+        let result: CompilationResult = frontend.process_files(&paths);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        (frontend, result)
+    }
+
+    #[test]
+    fn macros_serve_every_file() {
+        let (_, result) = compile_unit("macros", &["macro twice(x) { x; x; }", "twice(print(1));"]);
+
+        assert_eq!(format_program(&result.program.unwrap()), "print(1);\nprint(1);\n");
+    }
+
+    #[test]
+    fn constants_serve_every_file() {
+        let (_, defined) = compile_unit("constants", &["const A = 1;", "const B = A + 1;"]);
+        let (frontend, variable) = compile_unit("variables", &["let A = 1;", "const B = A + 1;"]);
+
+        assert!(defined.program.is_some());
+        assert!(variable.program.is_none());
+        assert!(variable.render(frontend.sources()).contains("m2.fang:1:11: error: \"A\" is not a constant!"));
+    }
+
+    #[test]
+    fn enums_serve_every_file() {
+        let (frontend, result) = compile_unit("enums", &[
+            "enum Shape { Circle(int), Square(int) }",
+            "match Circle(1) { Circle(r) => r };",
+        ]);
+
+        assert!(result.program.is_none());
+        assert!(result.render(frontend.sources())
+            .contains("m2.fang:1:1: error: Match over \"Shape\" doesn't cover every variant!"));
+    }
+
+    #[test]
+    fn lint_attributes_stay_in_their_file() {
+        let (frontend, result) = compile_unit("lint-attributes", &[
+            "#![allow(unreachable_arms)]\nmatch 1 { _ => 0, 1 => 1 };",
+            "match 2 { _ => 0, 2 => 1 };",
+        ]);
+
+        assert!(result.program.is_some());
+        assert_eq!(result.diagnostics.len(), 1);
+        assert!(result.render(frontend.sources()).contains("m2.fang:1:19: warning: Unreachable arm"));
+    }
+
+    #[test]
+    fn invalid_files_are_reported() {
+        let dir = std::env::temp_dir().join(format!("fang-invalid-test-{}", std::process::id()));
+        let path = dir.join("main.fang").to_string_lossy().into_owned();
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, b"let a = 1;\xff").unwrap();
+
+        let mut frontend = Frontend::new();
+        let result = frontend.process_file(&path);
+
+        assert!(result.program.is_none());
+        assert!(result.render(frontend.sources())
+            .starts_with(&format!("{}:1:11: error: Invalid UTF-8!\n", path)));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn results_hold_the_diagnostics() {
+        let mut frontend = Frontend::new();
+        let warned = frontend.process_string("warned.fang", "match 1 { _ => 0, 1 => 1 };");
+        let failed = frontend.process_string("failed.fang", "let a = ;");
+
+        assert!(warned.program.is_some());
+        assert_eq!(warned.diagnostics.len(), 1);
+        assert!(warned.render(frontend.sources()).starts_with("warned.fang:1:19: warning: Unreachable arm"));
+
+        assert!(failed.program.is_none());
+        assert!(failed.render(frontend.sources()).starts_with("failed.fang:1:9: error: "));
+    }
+
+    #[test]
+    fn tabs_reach_tab_stops() {
+        let source = "func f(a) {\n\treturn a +;\n}";
+        let mut frontend = Frontend::new();
+        let narrow = frontend.process_string("narrow.fang", source);
+
+        frontend.set_options(ParseOptions {
+            tab_width: 4,
+            ..ParseOptions::default()
+        });
+
+        let wide = frontend.process_string("wide.fang", source);
+
+        assert!(narrow.render(frontend.sources()).starts_with("narrow.fang:2:12: error: "));
+        assert!(wide.render(frontend.sources()).starts_with("wide.fang:2:15: error: "));
+    }
+
+    #[test]
+    fn denied_warnings_fail() {
+        let mut frontend = Frontend::new();
+        let mut levels = LintLevels::new();
+
+        levels.set(&frontend.lint_registry(), WARNINGS, Level::Deny).unwrap();
+        frontend.set_lint_levels(levels);
+
+        let result = frontend.process_string("main.fang", "match 1 { _ => 0, 1 => 1 };");
+
+        assert!(result.program.is_none());
+        assert!(result.render(frontend.sources()).starts_with("main.fang:1:19: error: Unreachable arm"));
+    }
+
+    #[test]
+    fn plugins_add_passes() {
+        struct Doubler;
+        struct Numbers;
+
+        impl Lint for Numbers {
+            fn name(&self) -> &'static str {
+                "numbers"
+            }
+
+            fn description(&self) -> &'static str {
+                "Programs hold no numbers."
+            }
+
+            fn check(&self, program: &Program, warnings: &mut Diagnostics) {
+                if program.expressions.iter().any(|expression| matches!(expression, Expression::Number(_))) {
+                    warnings.push(Diagnostic::warning("Number!"));
+                }
+            }
+        }
+
+        impl Plugin for Doubler {
+            fn name(&self) -> &str {
+                "doubler"
+            }
+
+            fn register_lints(&self, registry: &mut LintRegistry) {
+                registry.register(Box::new(Numbers));
+            }
+
+            fn transform(&self, program: &mut Program) -> Result<(), Diagnostics> {
+                for expression in Arc::make_mut(&mut program.expressions) {
+                    if let Expression::Number(number) = expression {
+                        *number *= 2;
+                    }
+                }
+
+                Ok(())
+            }
+
+            fn emit(&self, format: &str, program: &Program) -> Option<String> {
+                (format == "count").then(|| program.statements.len().to_string())
+            }
+        }
+
+        let mut frontend = Frontend::new();
+
+        frontend.add_plugin(Box::new(Doubler));
+
+        let program = frontend.process_string("main.fang", "let a = 21; a;").program.unwrap();
+
+        assert_eq!(format_program(&program), "let a = 42;\na;\n");
+        assert_eq!(frontend.emit("count", &program), Some(String::from("2")));
+        assert_eq!(frontend.emit("ir", &program), None);
+        assert_eq!(frontend.lint_registry().check(&program).iter().next().unwrap().lint, Some("numbers"));
+
+        /* The plugin's lints are set like the built-in ones. */
+        let mut levels = LintLevels::new();
+
+        assert!(levels.set(&frontend.lint_registry(), "numbers", Level::Deny).is_ok());
+        assert!(frontend.process_string("lints.fang", "#![allow(numbers)]\n1;").program.is_some());
+        assert!(Frontend::new().process_string("lints.fang", "#![allow(numbers)]\n1;").program.is_none());
+    }
+
+    #[test]
+    fn builds_are_reproducible() {
+        let dir = std::env::temp_dir().join(format!("fang-reproducible-test-{}", std::process::id()));
+        let sources = [
+            "enum Shape { Circle(int), Square(int) }\n",
+            "func area(shape: Shape) -> int {\n    match shape { Circle(r) => 3 * r * r, Square(s) => s * s }\n}\n",
+            "macro twice(x) { x; x; }\n#if DEBUG { twice(print(area(Square(2)))); } #else { let total = area(Circle(1)); }\n",
+        ];
+        let mut paths: Vec<String> = Vec::new();
+        let mut artifacts: Vec<(String, String)> = Vec::new();
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for (index, source) in sources.iter().enumerate() {
+            let path = dir.join(format!("file_{}.fang", index));
+
+            std::fs::write(&path, source).unwrap();
+            paths.push(path.to_string_lossy().into_owned());
+        }
+
+        /* Without a cache, then with a cold and a warm one. */
+        for cache in [None, Some(dir.join("cache")), Some(dir.join("cache"))] {
+            let mut frontend = match cache {
+                Some(cache) => Frontend::with_cache(Cache::new(cache)),
+                None => Frontend::new(),
+            };
+
+            frontend.set_symbols(Some(vec![String::from("DEBUG")]));
+
+            let program = frontend.process_files(&paths).program.unwrap();
+
+            artifacts.push((format!("{:#?}", program), format_program(&program)));
+        }
+
+        assert!(artifacts.iter().all(|artifact| *artifact == artifacts[0]));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cached_programs_serve_any_symbols() {
+        let dir = std::env::temp_dir().join(format!("fang-symbols-test-{}", std::process::id()));
+        let path = dir.join("main.fang").to_string_lossy().into_owned();
+        let mut frontend = Frontend::with_cache(Cache::new(dir.join("cache")));
+        let mut identifiers: Vec<String> = Vec::new();
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "#if DEBUG { let debug = 1; } #else { let release = 0; }").unwrap();
+
+        for symbols in [vec![String::from("DEBUG")], vec![]] {
+            frontend.set_symbols(Some(symbols));
+
+            if let [Statement::VariableDefinition {
+                identifier,
+                ..
+            }] = frontend.process_file(&path).program.unwrap().statements.as_slice() {
+                identifiers.push(identifier.clone());
+            }
+        }
+
+        assert_eq!(identifiers, vec!["debug", "release"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn let_and_var_define_variables() {
+        let frontend = Frontend::new();
+        let with_let = frontend.check_program(parse_source("let a: int = 1; { let b = a; }").unwrap()).unwrap().0;
+        let with_var = frontend.check_program(parse_source("var a: int = 1; { var b = a; }").unwrap()).unwrap().0;
 
-        let str = String::from_utf8(buf).unwrap();
+        assert_eq!(with_let, with_var);
+        assert!(matches!(&with_var.statements[0], Statement::VariableDefinition {
+            identifier,
+            r#type: Some(r#type),
+            ..
+        } if identifier == "a" && *r#type == Type::Named(String::from("int"))));
 
-        self.process_string(&str);
+        /* Both are keywords, neither names a variable. */
+        let diagnostics: Diagnostics = parse_source("let var = 1;").unwrap_err();
+        assert_eq!(diagnostics.iter().next().unwrap().span, Some(Span::new(4, 7)));
     }
 }
@@ -0,0 +1,715 @@
+/// Names of variables, functions and types the generated programs use.
+const NAMES: [&str; 8] = ["a", "b", "value", "count", "item", "Some", "None", "total"];
+
+/// Names of types, besides the enums a program defines.
+const TYPE_NAMES: [&str; 4] = ["int", "string", "bool", "Shape"];
+
+/// Text between two tokens, from a space to comments.
+const SEPARATORS: [&str; 7] = [" ", " ", "  ", "\n", "\n\n    ", " /* comment */ ", " // comment\n"];
+
+const INFIX_OPERATORS: [&str; 7] = ["=", "==", "!=", "+", "-", "*", "/"];
+
+/// Source of pseudo-random numbers, a xorshift generator, so programs
+/// come out the same for the same seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Number below `bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    /// Whether something happening once in `odds` times happens.
+    fn one_in(&mut self, odds: usize) -> bool {
+        self.below(odds) == 0
+    }
+
+    fn choose<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+/// Writer of a random program, token by token.
+struct Generator {
+    rng: Rng,
+    text: String,
+
+    /// How many more levels statements and expressions may nest.
+    depth: usize,
+
+    /// Number of functions, enums, methods and macros so far, keeping
+    /// their names apart.
+    definitions: usize,
+
+    /// Whether a `{` as the next token would start a block, like at
+    /// the start of a statement or after `..`, so it can't start a map.
+    block_start: bool,
+}
+
+/// Random program valid by the grammar of Fang, the same for the same
+/// `seed`, for testing passes against programs nobody wrote.
+///
+/// Tokens are separated by random spacing and comments, and operands
+/// are parenthesized whenever they're compound, so the program parses
+/// the same however tightly its operators bind.
+pub fn generate_program(seed: u64) -> String {
+    let mut generator = Generator {
+        rng: Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1),
+        text: String::new(),
+        depth: 4,
+        definitions: 0,
+        block_start: false,
+    };
+
+    if generator.rng.one_in(3) {
+        generator.attribute();
+    }
+
+    for _ in 0..generator.rng.below(12) + 1 {
+        generator.statement(false);
+    }
+
+    generator.text
+}
+
+impl Generator {
+    fn token(&mut self, token: &str) {
+        let separator = self.rng.choose(&SEPARATORS);
+
+        self.text.push_str(separator);
+        self.text.push_str(token);
+        self.block_start = false;
+    }
+
+    /// Write one of `tokens`.
+    fn one_of(&mut self, tokens: &[&str]) {
+        let token = self.rng.choose(tokens);
+
+        self.token(token);
+    }
+
+    fn name(&mut self) {
+        self.one_of(&NAMES);
+    }
+
+    /// Write the name of a new function, enum, trait or macro.
+    fn definition(&mut self, prefix: &str) -> String {
+        self.definitions += 1;
+
+        let name = format!("{}{}", prefix, self.definitions);
+
+        self.token(&name);
+
+        name
+    }
+
+    /// Separate up to `most` items `item` writes by commas, with or
+    /// without one after the last.
+    fn list(&mut self, most: usize, mut item: impl FnMut(&mut Generator)) {
+        let count = self.rng.below(most);
+
+        for index in 0..count {
+            if index > 0 {
+                self.token(",");
+            }
+
+            item(self);
+        }
+
+        if count > 0 && self.rng.one_in(3) {
+            self.token(",");
+        }
+    }
+
+    /// Write what `write` writes one level deeper, or nothing at all
+    /// if it's too deep already.
+    fn nested(&mut self, write: impl FnOnce(&mut Generator)) -> bool {
+        if self.depth == 0 {
+            return false;
+        }
+
+        self.depth -= 1;
+        write(self);
+        self.depth += 1;
+
+        true
+    }
+
+    fn attribute(&mut self) {
+        self.token("#!");
+        self.token("[");
+
+        match self.rng.below(3) {
+            0 => {
+                self.token("allow");
+                self.token("(");
+                self.token("unreachable_arms");
+                self.token(")");
+            },
+            1 => self.token("no_prelude"),
+            _ => self.token("no_std_runtime"),
+        }
+
+        self.token("]");
+    }
+
+    /// Write a statement, which may be the implicit return of its
+    /// function if `tail` is set.
+    fn statement(&mut self, tail: bool) {
+        if tail {
+            self.block_start = true;
+            self.expression();
+            return;
+        }
+
+        let written = self.rng.below(14) >= 6 || self.nested(|generator| match generator.rng.below(8) {
+            0 => generator.function(),
+            1 => generator.block(),
+            2 => generator.r#for(),
+            3 => generator.implementation(),
+            4 => generator.r#trait(),
+            5 => generator.r#enum(),
+            6 => generator.r#macro(),
+            _ => generator.conditional(),
+        });
+
+        if !written || self.rng.one_in(3) {
+            self.simple_statement();
+        }
+    }
+
+    /// Write a statement that holds no statements.
+    fn simple_statement(&mut self) {
+        match self.rng.below(6) {
+            0 => {
+                self.one_of(&["let", "var"]);
+                self.name();
+
+                if self.rng.one_in(2) {
+                    self.token(":");
+                    self.r#type();
+                }
+
+                if self.rng.one_in(4) {
+                    self.token(";");
+                    return;
+                }
+
+                self.token("=");
+                self.expression();
+            },
+            1 => {
+                self.token("let");
+
+                if self.rng.one_in(2) {
+                    self.token("[");
+                    self.list(3, Generator::pattern);
+                    self.token("]");
+                } else {
+                    self.name();
+                    self.token("(");
+                    self.list(3, Generator::pattern);
+                    self.token(")");
+                }
+
+                self.token("=");
+                self.expression();
+            },
+            2 => {
+                self.token("const");
+                self.name();
+
+                if self.rng.one_in(2) {
+                    self.token(":");
+                    self.r#type();
+                }
+
+                self.token("=");
+                self.expression();
+            },
+            3 => {
+                self.token("return");
+                self.expression();
+            },
+            _ => {
+                self.block_start = true;
+                self.expression();
+            },
+        }
+
+        self.token(";");
+    }
+
+    /// Write `{`, the statements and `}` of a body, the last one left
+    /// without `;` to return its value if `tail` is set.
+    fn body(&mut self, tail: bool) {
+        let count = self.rng.below(4);
+
+        self.token("{");
+
+        for index in 0..count {
+            self.statement(tail && index == count - 1);
+        }
+
+        self.token("}");
+    }
+
+    fn parameters(&mut self, method: bool) {
+        let parameter = |generator: &mut Generator| {
+            generator.name();
+
+            if generator.rng.one_in(2) {
+                generator.token(":");
+                generator.r#type();
+            }
+        };
+
+        self.token("(");
+
+        if !method {
+            self.list(3, parameter);
+        } else {
+            self.token("self");
+
+            if self.rng.one_in(2) {
+                self.token(",");
+                self.list(3, parameter);
+            }
+        }
+
+        self.token(")");
+
+        if self.rng.one_in(2) {
+            self.token("->");
+            self.r#type();
+        }
+    }
+
+    fn function(&mut self) {
+        self.token("func");
+        self.definition("function");
+        self.parameters(false);
+        self.function_body();
+    }
+
+    fn block(&mut self) {
+        self.body(false);
+    }
+
+    fn r#for(&mut self) {
+        self.token("for");
+        self.name();
+        self.token("in");
+        self.expression();
+        self.body(false);
+    }
+
+    fn implementation(&mut self) {
+        let methods = self.rng.below(3);
+
+        self.token("impl");
+        self.one_of(&TYPE_NAMES);
+        self.token("{");
+
+        for _ in 0..methods {
+            self.token("func");
+            self.definition("method");
+            self.parameters(true);
+            self.function_body();
+        }
+
+        self.token("}");
+    }
+
+    /// Write a trait, and maybe an implementation of it after it,
+    /// whose methods repeat the signatures of the trait.
+    fn r#trait(&mut self) {
+        let mut signatures: Vec<String> = Vec::new();
+
+        self.token("trait");
+        let name: String = self.definition("Trait");
+        self.token("{");
+
+        for _ in 0..self.rng.below(3) {
+            let start = self.text.len();
+
+            self.token("func");
+            self.definition("method");
+            self.parameters(true);
+            signatures.push(self.text[start..].to_string());
+            self.token(";");
+        }
+
+        self.token("}");
+
+        if self.rng.one_in(2) {
+            self.token("impl");
+            self.token(&name);
+            self.token("for");
+            self.one_of(&TYPE_NAMES);
+            self.token("{");
+
+            for signature in signatures {
+                self.text.push_str(&signature);
+                self.function_body();
+            }
+
+            self.token("}");
+        }
+    }
+
+    /// Write the body of a function, which may end in an implicit
+    /// return.
+    fn function_body(&mut self) {
+        let tail = self.rng.one_in(2);
+
+        self.body(tail);
+    }
+
+    fn r#enum(&mut self) {
+        let mut variants = 0;
+
+        self.token("enum");
+        let name: String = self.definition("Enum");
+        self.token("{");
+
+        /* Variants share a namespace across enums, so each is named
+         * after its enum. */
+        self.list(4, |generator| {
+            variants += 1;
+            generator.token(&format!("{}Variant{}", name, variants));
+
+            if generator.rng.one_in(2) {
+                generator.token("(");
+                generator.list(3, Generator::r#type);
+                generator.token(")");
+            }
+        });
+        self.token("}");
+    }
+
+    fn r#macro(&mut self) {
+        self.token("macro");
+        self.definition("macro");
+        self.token("(");
+        self.list(3, Generator::name);
+        self.token(")");
+        self.body(false);
+    }
+
+    fn conditional(&mut self) {
+        self.token("#if");
+        self.one_of(&["DEBUG", "TEST"]);
+        self.body(false);
+
+        if self.rng.one_in(2) {
+            self.token("#else");
+            self.body(false);
+        }
+    }
+
+    fn r#type(&mut self) {
+        let nested = self.rng.one_in(2) && self.nested(|generator| match generator.rng.below(3) {
+            0 => {
+                generator.token("[");
+                generator.r#type();
+
+                if generator.rng.one_in(2) {
+                    generator.token(";");
+                    generator.expression();
+                }
+
+                generator.token("]");
+            },
+            1 => {
+                generator.token("map");
+                generator.token("[");
+                generator.r#type();
+                generator.token(",");
+                generator.r#type();
+                generator.token("]");
+            },
+            _ => {
+                generator.token("(");
+                generator.token(")");
+            },
+        });
+
+        if !nested {
+            self.one_of(&TYPE_NAMES);
+        }
+
+        if self.rng.one_in(4) {
+            self.token("?");
+        }
+    }
+
+    fn pattern(&mut self) {
+        let nested = self.rng.one_in(3) && self.nested(|generator| {
+            if generator.rng.one_in(2) {
+                generator.token("[");
+                generator.list(3, Generator::pattern);
+                generator.token("]");
+            } else {
+                generator.name();
+                generator.token("(");
+                generator.list(3, Generator::pattern);
+                generator.token(")");
+            }
+        });
+
+        if nested {
+            return;
+        }
+
+        match self.rng.below(6) {
+            0 => self.token("_"),
+            1 => self.name(),
+            2 => {
+                self.token("-");
+                self.number();
+            },
+            3 => self.string(),
+            4 => self.token("nil"),
+            _ => self.number(),
+        }
+    }
+
+    fn number(&mut self) {
+        let number = self.rng.below(1000).to_string();
+
+        self.token(&number);
+    }
+
+    /// Write a string of printable characters and line breaks, which
+    /// is all strings may hold.
+    fn string(&mut self) {
+        let mut string = String::from("\"");
+
+        for _ in 0..self.rng.below(8) {
+            match self.rng.below(100) {
+                0..=4 => string.push('\n'),
+                5..=9 => string.push(' '),
+                code => match (b'!' + code as u8 - 10) as char {
+                    '"' => string.push('\''),
+                    char => string.push(char),
+                },
+            }
+        }
+
+        string.push('"');
+        self.token(&string);
+    }
+
+    /// Write any expression.
+    fn expression(&mut self) {
+        if self.rng.one_in(3) || !self.nested(|generator| generator.compound()) {
+            self.factor();
+        }
+    }
+
+    /// Write an expression able to stand as the operand of any
+    /// operator, in parentheses unless it's a factor.
+    fn term(&mut self) {
+        if self.rng.one_in(2) || self.depth == 0 {
+            self.factor();
+            return;
+        }
+
+        self.token("(");
+
+        if self.rng.one_in(8) {
+            self.factor();
+        } else {
+            self.nested(|generator| generator.compound());
+        }
+
+        self.token(")");
+    }
+
+    /// Write an expression made of operators.
+    fn compound(&mut self) {
+        match self.rng.below(8) {
+            0 => {
+                self.token("-");
+                self.term();
+            },
+            1 => {
+                self.term();
+                self.token("as");
+                self.r#type();
+            },
+            /* Ranges can't be operands of ranges, even in parentheses. */
+            2 => match self.rng.below(4) {
+                0 => {
+                    self.token("..");
+                    self.block_start = true;
+                    self.factor();
+                },
+                1 => {
+                    self.factor();
+                    self.token("..=");
+                    self.factor();
+                },
+                _ => {
+                    self.factor();
+                    self.token("..");
+
+                    if self.rng.one_in(2) {
+                        self.block_start = true;
+                        self.factor();
+                    }
+                },
+            },
+            3 => {
+                self.term();
+                self.token(".");
+                self.name();
+                self.arguments();
+            },
+            4 => {
+                self.term();
+                self.token("[");
+                self.expression();
+                self.token("]");
+            },
+            5 => {
+                self.term();
+                self.token("?");
+            },
+            _ => {
+                let operator = self.rng.choose(&INFIX_OPERATORS);
+
+                self.term();
+                self.token(operator);
+                self.term();
+            },
+        }
+    }
+
+    /// Write the arguments of a call, the named ones after the others.
+    fn arguments(&mut self) {
+        let named = self.rng.below(4);
+        let mut index = 0;
+
+        self.token("(");
+        self.list(4, |generator| {
+            if index >= named {
+                generator.name();
+                generator.token("=");
+                generator.expression();
+            } else {
+                generator.term();
+            }
+
+            index += 1;
+        });
+        self.token(")");
+    }
+
+    /// Write an expression that's no operation.
+    fn factor(&mut self) {
+        let block_start = self.block_start;
+        let nested = self.rng.one_in(3) && self.nested(|generator| match generator.rng.below(4) {
+            0 => {
+                generator.name();
+                generator.arguments();
+            },
+            1 => {
+                generator.token("[");
+                generator.list(4, Generator::expression);
+                generator.token("]");
+            },
+            2 => {
+                if block_start {
+                    generator.token("(");
+                }
+
+                generator.token("{");
+                generator.list(3, |generator| {
+                    generator.term();
+                    generator.token(":");
+                    generator.expression();
+                });
+                generator.token("}");
+
+                if block_start {
+                    generator.token(")");
+                }
+            },
+            _ => {
+                generator.token("match");
+                generator.expression();
+                generator.token("{");
+                generator.list(4, |generator| {
+                    generator.pattern();
+                    generator.token("=>");
+                    generator.expression();
+                });
+                generator.token("}");
+            },
+        });
+
+        if nested {
+            return;
+        }
+
+        match self.rng.below(6) {
+            0 => self.number(),
+            1 => self.string(),
+            2 => self.token("nil"),
+            3 => {
+                self.token("(");
+                self.token(")");
+            },
+            _ => self.name(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_source, Program};
+    use crate::printer::format_program;
+    use super::*;
+
+    /// Dump of the program with every span left out, since formatting
+    /// moves the code around.
+    fn without_spans(program: &Program) -> String {
+        let mut dump = format!("{:?}", program);
+
+        while let Some(start) = dump.find("Span { start: ") {
+            let end = start + dump[start..].find('}').unwrap() + 1;
+
+            dump.replace_range(start..end, "Span");
+        }
+
+        dump
+    }
+
+    #[test]
+    fn round_trip() {
+        for seed in 0..300 {
+            let text = generate_program(seed);
+            let program = match parse_source(&text) {
+                Ok(program) => program,
+                Err(diagnostics) => panic!("{}\n{}", text, diagnostics.render("generated.fang", &text)),
+            };
+            let formatted = format_program(&program);
+            let reparsed = match parse_source(&formatted) {
+                Ok(program) => program,
+                Err(diagnostics) => panic!("{}\n{}", formatted, diagnostics.render("formatted.fang", &formatted)),
+            };
+
+            assert_eq!(without_spans(&reparsed), without_spans(&program), "{:?} formatted as {:?}", text, formatted);
+            assert_eq!(format_program(&reparsed), formatted, "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::lexer::{Token, Tokenizer};
+use crate::span::Span;
+
+/// Classes of source ranges an editor highlights differently.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TokenClass {
+    Keyword,
+    Identifier,
+
+    /// Name of a function in its definition or in a call.
+    Function,
+    Number,
+    String,
+    Comment,
+    Operator,
+}
+
+/// Classified range of source code.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Highlight {
+    pub span: Span,
+    pub class: TokenClass,
+}
+
+fn token_class(token: &Token, previous: Option<&Token>, next: Option<&Token>) -> Option<TokenClass> {
+    let class = match token {
+        Token::Let |
+        Token::Variable |
+        Token::Constant |
+        Token::Function |
+        Token::Return |
+        Token::Implementation |
+        Token::Trait |
+        Token::For |
+        Token::In |
+        Token::Enum |
+        Token::Match |
+        Token::Macro |
+        Token::Nil |
+        Token::As |
+        Token::Hash => TokenClass::Keyword,
+
+        /* The name of a directive, like `if` in `#if`. */
+        Token::Identifier(_) if previous == Some(&Token::Hash) => TokenClass::Keyword,
+        Token::Identifier(_) => {
+            if previous == Some(&Token::Function) ||
+               next == Some(&Token::LeftRoundBracket) {
+                TokenClass::Function
+            } else {
+                TokenClass::Identifier
+            }
+        },
+        Token::Number(_) => TokenClass::Number,
+        Token::String(_) => TokenClass::String,
+        Token::Assign |
+        Token::QuestionMark |
+        Token::DotDot |
+        Token::DotDotEqual |
+        Token::ReturnTypeIndicator |
+        Token::MatchArmIndicator |
+        Token::Equal |
+        Token::NotEqual |
+        Token::Add |
+        Token::Minus |
+        Token::Times |
+        Token::Divide => TokenClass::Operator,
+        _ => return None,
+    };
+
+    Some(class)
+}
+
+/// Classify the ranges of `source` worth highlighting, in source order.
+///
+/// Punctuation like brackets and semicolons is left out. Invalid bytes
+/// are skipped, so a program being edited is highlighted as well as
+/// it can be.
+pub fn classify(source: &str) -> Vec<Highlight> {
+    let mut tokenizer = Tokenizer::new();
+    let mut highlights: Vec<Highlight> = Vec::new();
+    let mut tokens: Vec<(Token, Span)> = Vec::new();
+    let mut stream;
+
+    let _ = tokenizer.scan(source);
+
+    for span in tokenizer.comments() {
+        highlights.push(Highlight {
+            span: *span,
+            class: TokenClass::Comment,
+        });
+    }
+
+    stream = tokenizer.extract();
+
+    while stream.peek().is_some() {
+        let span = stream.span();
+
+        tokens.push((stream.consume().unwrap(), span));
+    }
+
+    for (index, (token, span)) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|index| &tokens[index].0);
+        let next = tokens.get(index + 1).map(|(token, _)| token);
+
+        if let Some(class) = token_class(token, previous, next) {
+            highlights.push(Highlight {
+                span: *span,
+                class,
+            });
+        }
+    }
+
+    highlights.sort_by_key(|highlight| highlight.span.start);
+
+    highlights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classes() {
+        let source = "// Sum.\nfunc add(a) { return a + 1; }\nlet text = add(\"x\"); /* done */";
+        let classes: Vec<(&str, TokenClass)> = classify(source).iter()
+            .map(|highlight| (&source[highlight.span.start..highlight.span.end], highlight.class))
+            .collect();
+
+        assert_eq!(classes, vec![
+            ("// Sum.", TokenClass::Comment),
+            ("func", TokenClass::Keyword),
+            ("add", TokenClass::Function),
+            ("a", TokenClass::Identifier),
+            ("return", TokenClass::Keyword),
+            ("a", TokenClass::Identifier),
+            ("+", TokenClass::Operator),
+            ("1", TokenClass::Number),
+            ("let", TokenClass::Keyword),
+            ("text", TokenClass::Identifier),
+            ("=", TokenClass::Operator),
+            ("add", TokenClass::Function),
+            ("\"x\"", TokenClass::String),
+            ("/* done */", TokenClass::Comment),
+        ]);
+    }
+}
@@ -0,0 +1,3379 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fmt::{Debug, Display};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::rc::Rc;
+use std::sync::Arc;
+use crate::parser::{Argument, BinaryOperator, ExprId, Expression, Parameter, Pattern, Program, Statement, Type, UnaryOperator, Variant};
+use crate::printer::{format_pattern, format_type};
+use crate::random::Rng;
+use crate::clock::{Clock, SystemClock};
+use crate::constant::evaluate_constants;
+use crate::span::{SourceFile, SourceMap, Span};
+use crate::template::format_values;
+
+/// Maximum nesting of function calls before the interpreter gives up,
+/// so runaway recursion reports an error instead of overflowing the
+/// host's stack. Unoptimized builds take about 8 KiB of stack per
+/// call, this keeps within the 2 MiB of a spawned thread.
+const MAX_CALL_DEPTH: usize = 200;
+
+/// Values manipulated by a running program.
+#[derive(Clone)]
+pub enum Value {
+
+    /// Value of statements and calls that produce nothing.
+    Unit,
+
+    /// Integer like `47`.
+    Int(isize),
+
+    /// Result of comparisons like `a == b`.
+    Bool(bool),
+
+    /// String like `"Alex Chen"`.
+    String(String),
+
+    /// Missing value written `nil`, admitted by types like `int?`.
+    Nil,
+
+    /// Successful result made by `ok(value)`.
+    Ok(Box<Value>),
+
+    /// Failed result made by `err(error)`, which `?` returns from the
+    /// enclosing function.
+    Err(Box<Value>),
+
+    /// Array like `[1, 2, 3]`, copied on write so it behaves like a
+    /// value.
+    Array(Rc<Vec<Value>>),
+
+    /// Elements `start..end` of an array, viewed by slicing like
+    /// `xs[1..3]`.
+    Slice {
+        elements: Rc<Vec<Value>>,
+        start: usize,
+        end: usize,
+    },
+
+    /// Map like `{"alex": 30}`, keeping its entries in the order their
+    /// keys were inserted and copied on write like arrays.
+    Map(Rc<Vec<(Value, Value)>>),
+
+    /// Range of ints like `0..n`.
+    Range(Box<Range>),
+
+    /// Variant of an enum like `Circle(3)`.
+    Enum(Rc<EnumValue>),
+
+    /// Function defined in Fang code or provided by the host.
+    Function(Rc<Function>),
+}
+
+impl Value {
+
+    /// Name of the value's type, as used in error messages.
+    pub fn type_name(&self) -> &str {
+        match self {
+            Value::Unit => "()",
+            Value::Int(_) => "int",
+            Value::Bool(_) => "bool",
+            Value::String(_) => "string",
+            Value::Nil => "nil",
+            Value::Ok(_) |
+            Value::Err(_) => "result",
+            Value::Array(_) => "array",
+            Value::Slice { .. } => "slice",
+            Value::Map(_) => "map",
+            Value::Range(_) => "range",
+            Value::Enum(value) => &value.enum_name,
+            Value::Function(_) => "function",
+        }
+    }
+
+    /// Elements of an array, or those a slice views.
+    pub fn elements(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(elements) => Some(elements),
+            Value::Slice {
+                elements,
+                start,
+                end,
+            } => Some(&elements[*start..*end]),
+            _ => None,
+        }
+    }
+
+    /// Move into `pending` the values this one alone holds, leaving
+    /// those shared with other values in place.
+    fn take_children(&mut self, pending: &mut Vec<Value>) {
+        match self {
+            Value::Ok(value) |
+            Value::Err(value) if !value.is_scalar() => pending.push(std::mem::replace(value, Value::Unit)),
+            Value::Array(elements) |
+            Value::Slice {
+                elements,
+                ..
+            } => if let Some(elements) = Rc::get_mut(elements) {
+                pending.extend(elements.drain(..).filter(|value| !value.is_scalar()));
+            },
+            Value::Map(entries) => if let Some(entries) = Rc::get_mut(entries) {
+                for (key, value) in entries.drain(..) {
+                    pending.extend([key, value].into_iter().filter(|value| !value.is_scalar()));
+                }
+            },
+            Value::Enum(value) => if let Some(value) = Rc::get_mut(value) {
+                pending.extend(value.fields.drain(..).filter(|value| !value.is_scalar()));
+            },
+            _ => {},
+        }
+    }
+
+    /// Value held by `ok(value)` or `err(value)`, moved out of the
+    /// result.
+    pub fn take_result(&mut self) -> Option<Value> {
+        match self {
+            Value::Ok(value) |
+            Value::Err(value) => Some(std::mem::replace(value, Value::Unit)),
+            _ => None,
+        }
+    }
+
+    /// Whether the value holds no other value.
+    fn is_scalar(&self) -> bool {
+        matches!(self, Value::Unit | Value::Int(_) | Value::Bool(_) | Value::String(_) | Value::Nil |
+            Value::Range(_) | Value::Function(_))
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Unit => write!(f, "Unit"),
+            Value::Int(num) => write!(f, "Int({})", num),
+            Value::Bool(bool) => write!(f, "Bool({})", bool),
+            Value::String(str) => write!(f, "String({:?})", str),
+            Value::Nil => write!(f, "Nil"),
+            Value::Ok(value) => write!(f, "Ok({:?})", value),
+            Value::Err(error) => write!(f, "Err({:?})", error),
+            Value::Array(elements) => write!(f, "Array({:?})", elements),
+            Value::Slice { .. } => write!(f, "Slice({:?})", self.elements().unwrap()),
+            Value::Map(entries) => write!(f, "Map({:?})", entries),
+            Value::Range(range) => write!(f, "Range({})", range),
+            Value::Enum(value) => write!(f, "Enum({})", value),
+            Value::Function(function) => write!(f, "Function({})", function.name),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_pieces(f, vec![Piece::Value(self)])
+    }
+}
+
+/// What's left to write of a value, the next piece being last.
+enum Piece<'a> {
+    Text(&'static str),
+    Value(&'a Value),
+    Enum(&'a EnumValue),
+}
+
+/// Write `pending` from the last piece to the first, expanding the
+/// values that hold others in place rather than recursing into them,
+/// for values as deep as a program builds them.
+fn write_pieces(f: &mut std::fmt::Formatter<'_>, mut pending: Vec<Piece>) -> std::fmt::Result {
+    while let Some(piece) = pending.pop() {
+        let mut pieces: Vec<Piece> = Vec::new();
+
+        match piece {
+            Piece::Text(text) => f.write_str(text)?,
+            Piece::Value(Value::Unit) => write!(f, "()")?,
+            Piece::Value(Value::Int(num)) => write!(f, "{}", num)?,
+            Piece::Value(Value::Bool(bool)) => write!(f, "{}", bool)?,
+            Piece::Value(Value::String(str)) => write!(f, "{}", str)?,
+            Piece::Value(Value::Nil) => write!(f, "nil")?,
+            Piece::Value(Value::Ok(value)) => pieces = vec![Piece::Text("ok("), Piece::Value(value), Piece::Text(")")],
+            Piece::Value(Value::Err(error)) => pieces = vec![Piece::Text("err("), Piece::Value(error), Piece::Text(")")],
+            Piece::Value(value @ (Value::Array(_) | Value::Slice { .. })) => {
+                pieces.push(Piece::Text("["));
+
+                for (index, element) in value.elements().unwrap().iter().enumerate() {
+                    if index > 0 {
+                        pieces.push(Piece::Text(", "));
+                    }
+
+                    pieces.push(Piece::Value(element));
+                }
+
+                pieces.push(Piece::Text("]"));
+            },
+            Piece::Value(Value::Map(entries)) => {
+                pieces.push(Piece::Text("{"));
+
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        pieces.push(Piece::Text(", "));
+                    }
+
+                    pieces.extend([Piece::Value(key), Piece::Text(": "), Piece::Value(value)]);
+                }
+
+                pieces.push(Piece::Text("}"));
+            },
+            Piece::Value(Value::Range(range)) => write!(f, "{}", range)?,
+            Piece::Value(Value::Enum(value)) => pieces.push(Piece::Enum(value)),
+            Piece::Enum(value) => {
+                write!(f, "{}", value.variant)?;
+
+                if !value.fields.is_empty() {
+                    pieces.push(Piece::Text("("));
+
+                    for (index, field) in value.fields.iter().enumerate() {
+                        if index > 0 {
+                            pieces.push(Piece::Text(", "));
+                        }
+
+                        pieces.push(Piece::Value(field));
+                    }
+
+                    pieces.push(Piece::Text(")"));
+                }
+            },
+            Piece::Value(Value::Function(function)) => write!(f, "<func {}>", function.name)?,
+        }
+
+        pending.extend(pieces.into_iter().rev());
+    }
+
+    Ok(())
+}
+
+/* Values are compared with a stack of pairs left to compare, like they
+ * are written, rather than by recursing into them. */
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        let mut pending: Vec<(&Value, &Value)> = vec![(self, other)];
+
+        while let Some(pair) = pending.pop() {
+            let equal = match pair {
+                (Value::Unit, Value::Unit) |
+                (Value::Nil, Value::Nil) => true,
+                (Value::Int(left), Value::Int(right)) => left == right,
+                (Value::Bool(left), Value::Bool(right)) => left == right,
+                (Value::String(left), Value::String(right)) => left == right,
+                (Value::Ok(left), Value::Ok(right)) |
+                (Value::Err(left), Value::Err(right)) => {
+                    pending.push((left, right));
+                    true
+                },
+                (left @ Value::Array(_), right @ Value::Array(_)) |
+                (left @ Value::Slice { .. }, right @ Value::Slice { .. }) => {
+                    let (left, right) = (left.elements().unwrap(), right.elements().unwrap());
+
+                    pending.extend(left.iter().zip(right));
+                    left.len() == right.len()
+                },
+                (Value::Range(left), Value::Range(right)) => left == right,
+                (Value::Enum(left), Value::Enum(right)) => {
+                    pending.extend(left.fields.iter().zip(&right.fields));
+                    left.enum_name == right.enum_name && left.variant == right.variant
+                        && left.fields.len() == right.fields.len()
+                },
+                /* Keys are ints, bools or strings, so finding one doesn't
+                 * recurse. */
+                (Value::Map(left), Value::Map(right)) => left.len() == right.len()
+                    && left.iter().all(|(key, value)| match map_get(right, key) {
+                        Some(found) => {
+                            pending.push((value, found));
+                            true
+                        },
+                        None => false,
+                    }),
+                (Value::Function(left), Value::Function(right)) =>
+                    Rc::ptr_eq(left, right),
+                _ => false,
+            };
+
+            if !equal {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/* Values may nest as deep as a program builds them, like a list of a
+ * million `Cons`, so they're dropped with a stack of their own rather
+ * than by recursing once per level. */
+impl Drop for Value {
+    fn drop(&mut self) {
+        let mut pending: Vec<Value> = Vec::new();
+
+        self.take_children(&mut pending);
+
+        while let Some(mut value) = pending.pop() {
+            value.take_children(&mut pending);
+        }
+    }
+}
+
+/// Range of ints like `0..n`, or `0..=n` if it includes its end.
+/// Either bound may be missing, like in `xs[1..]`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Range {
+    pub start: Option<isize>,
+    pub end: Option<isize>,
+    pub inclusive: bool,
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(start) = self.start {
+            write!(f, "{}", start)?;
+        }
+
+        write!(f, "{}", if self.inclusive { "..=" } else { ".." })?;
+
+        if let Some(end) = self.end {
+            write!(f, "{}", end)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Value of an enum, one of its variants holding the values of the
+/// variant's fields.
+#[derive(PartialEq, Debug)]
+pub struct EnumValue {
+    pub enum_name: String,
+    pub variant: String,
+    pub fields: Vec<Value>,
+}
+
+impl Display for EnumValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_pieces(f, vec![Piece::Enum(self)])
+    }
+}
+
+/// Signature of functions provided by the host application.
+pub type NativeFunction = dyn Fn(&[Value]) -> Result<Value, RuntimeError>;
+
+/// Function callable from Fang code.
+pub struct Function {
+    pub name: String,
+    pub body: FunctionBody,
+}
+
+pub enum FunctionBody {
+
+    /// Function defined by a `func` statement.
+    Defined {
+        parameters: Vec<Parameter>,
+        return_type: Option<Type>,
+        statements: Vec<Statement>,
+
+        /// Arena of the program defining the function.
+        expressions: Arc<Vec<Expression>>,
+    },
+
+    /// Function implemented in Rust by the host application.
+    Native(Box<NativeFunction>),
+
+    /// Functions defined with the same name in the same statement
+    /// list, told apart by the arguments of each call.
+    Overloaded(Vec<Rc<Function>>),
+}
+
+/// Error aborting the execution of a program.
+#[derive(PartialEq, Debug, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+
+    /// Source code that raised the error, if known.
+    pub span: Option<Span>,
+
+    /// Calls of Fang functions the error unwound, innermost first.
+    pub trace: Vec<TraceFrame>,
+}
+
+/// Call of a Fang function active when a runtime error occurred.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TraceFrame {
+    pub function: String,
+
+    /// Span of the call.
+    pub span: Span,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>) -> RuntimeError {
+        RuntimeError {
+            message: message.into(),
+            span: None,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Point the error at `span`, unless it already points somewhere.
+    pub fn with_span(mut self, span: Span) -> RuntimeError {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    /// Format the error with its location and call stack, where
+    /// `source` is the text of the file named `file_name`.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let mut map = SourceMap::new();
+
+        map.add(SourceFile::new(file_name, source));
+
+        self.render_in(&map)
+    }
+
+    /// Format the error like [`RuntimeError::render`], for a program
+    /// compiled from the files of `map` whose spans are offsets among
+    /// all of them.
+    pub fn render_in(&self, map: &SourceMap) -> String {
+        let mut text = match (self.span.and_then(|span| map.location(span.start)), map.files()) {
+            (Some((file_name, line, column)), _) => format!("{}:{}:{}: {}\n", file_name, line, column, self),
+            (None, [file]) => format!("{}: {}\n", file.name, self),
+            (None, _) => format!("{}\n", self),
+        };
+
+        for frame in &self.trace {
+            if let Some((file_name, line, column)) = map.location(frame.span.start) {
+                text.push_str(&format!("    in \"{}\" called at {}:{}:{}\n",
+                                       frame.function, file_name, line, column));
+            }
+        }
+
+        text
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "runtime error: {}", self.message)
+    }
+}
+
+/// Variables of one block, `None` when defined but not initialized yet.
+type Scope = HashMap<String, Option<Value>>;
+
+/// Names of the builtin functions, the prelude the resolver puts in
+/// scope of every program that doesn't opt out with `#![no_prelude]`.
+pub const PRELUDE: [&str; 21] = [
+    "print", "int", "string", "assert", "panic", "ok", "err", "len", "keys", "values", "format", "printf",
+    "read_file", "write_file", "append_file", "args", "env", "set_env", "now_millis", "sleep_millis",
+    "rand_int",
+];
+
+/// Environment a program is evaluated in.
+///
+/// The outermost scope holds the global variables. It outlives a
+/// single evaluation, so the host can seed inputs before running a
+/// program and read its results afterwards.
+pub struct Env {
+    scopes: Vec<Scope>,
+}
+
+impl Default for Env {
+    fn default() -> Env {
+        Env::new()
+    }
+}
+
+impl Env {
+    pub fn new() -> Env {
+        Env {
+            scopes: vec![Scope::new()],
+        }
+    }
+
+    /// Environment providing the builtin functions of the prelude,
+    /// like `print`.
+    pub fn with_builtins() -> Env {
+        let mut env = Env::new();
+
+        env.define_native("print", builtin_print);
+        env.define_native("int", builtin_int);
+        env.define_native("string", builtin_string);
+        env.define_native("assert", builtin_assert);
+        env.define_native("panic", builtin_panic);
+        env.define_native("ok", builtin_ok);
+        env.define_native("err", builtin_err);
+        env.define_native("len", builtin_len);
+        env.define_native("keys", builtin_keys);
+        env.define_native("values", builtin_values);
+        env.define_native("format", builtin_format);
+        env.define_native("printf", builtin_printf);
+        env.define_native("read_file", builtin_read_file);
+        env.define_native("write_file", builtin_write_file);
+        env.define_native("append_file", builtin_append_file);
+        env.set_args(Vec::new());
+        env.set_variables(HashMap::new(), true);
+        env.set_clock(SystemClock::new());
+        env.set_rng(Rng::from_time());
+
+        env
+    }
+
+    /// Define the builtin `rand_int` to draw its numbers from `rng`,
+    /// which a host seeds for the program to run the same every time.
+    pub fn set_rng(&mut self, rng: Rng) {
+        self.define_native("rand_int", move |values| match values {
+            [Value::Int(low), Value::Int(high)] => rng.int_in(*low, *high)
+                .map(Value::Int)
+                .ok_or_else(|| RuntimeError::new(format!("Cannot draw a number from the empty range {}..{}!", low, high))),
+            _ => Err(RuntimeError::new("\"rand_int\" expects the low and high bounds of a range!")),
+        });
+    }
+
+    /// Define the builtins `now_millis` and `sleep_millis` to read and
+    /// pass the time of `clock`, which can cap how long a program
+    /// sleeps or make its time virtual.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        let clock = Rc::new(clock);
+        let sleeping = Rc::clone(&clock);
+
+        self.define_native("now_millis", move |values| match values {
+            [] => Ok(Value::Int(clock.now_millis() as isize)),
+            _ => Err(RuntimeError::new("\"now_millis\" expects no values!")),
+        });
+        self.define_native("sleep_millis", move |values| match values {
+            [Value::Int(millis)] if *millis >= 0 => {
+                sleeping.sleep_millis(*millis as u64);
+
+                Ok(Value::Unit)
+            },
+            _ => Err(RuntimeError::new("\"sleep_millis\" expects a number of milliseconds that isn't negative!")),
+        });
+    }
+
+    /// Make the builtin `name` fail whenever it's called, for a host
+    /// running code it doesn't trust to keep it from the process, like
+    /// its environment variables with `env` and `set_env`, or the
+    /// files with `read_file`, `write_file` and `append_file`.
+    pub fn deny(&mut self, name: &str) {
+        let message = format!("\"{}\" is denied in this environment!", name);
+
+        self.define_native(name, move |_| Err(RuntimeError::new(message.to_owned())));
+    }
+
+    /// Define the builtin `args` to return `arguments`, the arguments
+    /// the program was started with.
+    pub fn set_args(&mut self, arguments: Vec<String>) {
+        let arguments = Rc::new(arguments.into_iter().map(Value::String).collect());
+
+        self.define_native("args", move |values| match values {
+            [] => Ok(Value::Array(Rc::clone(&arguments))),
+            _ => Err(RuntimeError::new("\"args\" expects no values!")),
+        });
+    }
+
+    /// Define the builtins `env` and `set_env` over environment
+    /// variables of their own, starting as `variables`, with `inherit`
+    /// falling back on those of the process for the others.
+    ///
+    /// `set_env` never changes the environment of the process, which
+    /// other threads may be reading, so a variable it sets is only
+    /// seen by the program.
+    pub fn set_variables(&mut self, variables: HashMap<String, String>, inherit: bool) {
+        let variables = Rc::new(RefCell::new(variables));
+        let setting = Rc::clone(&variables);
+
+        self.define_native("env", move |values| match values {
+            [Value::String(name)] => Ok(match variables.borrow().get(name) {
+                Some(value) => Value::String(value.to_owned()),
+                None => match env::var_os(name) {
+                    Some(value) if inherit => Value::String(value.to_string_lossy().into_owned()),
+                    _ => Value::Nil,
+                },
+            }),
+            _ => Err(RuntimeError::new("\"env\" expects a name!")),
+        });
+        self.define_native("set_env", move |values| match values {
+            [Value::String(name), Value::String(value)] => {
+                if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+                    return Err(RuntimeError::new(format!("Cannot set the environment variable \"{}\"!", name)));
+                }
+
+                setting.borrow_mut().insert(name.to_owned(), value.to_owned());
+
+                Ok(Value::Unit)
+            },
+            _ => Err(RuntimeError::new("\"set_env\" expects a name and a string!")),
+        });
+    }
+
+    /// Define or overwrite the global variable `name`.
+    pub fn set(&mut self, name: &str, value: Value) {
+        self.scopes[0].insert(name.to_owned(), Some(value));
+    }
+
+    /// Get the value of the global variable `name`.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self.scopes[0].get(name) {
+            Some(Some(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Expose a Rust function to Fang code as the global `name`.
+    pub fn define_native<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, RuntimeError> + 'static
+    {
+        let function = Function {
+            name: name.to_owned(),
+            body: FunctionBody::Native(Box::new(function)),
+        };
+
+        self.set(name, Value::Function(Rc::new(function)));
+    }
+}
+
+/// Print all arguments separated by spaces, then a newline.
+fn builtin_print(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let mut line = String::new();
+
+    for (index, argument) in arguments.iter().enumerate() {
+        if index > 0 {
+            line.push(' ');
+        }
+
+        line.push_str(&argument.to_string());
+    }
+
+    println!("{}", line);
+
+    Ok(Value::Unit)
+}
+
+/// Fail with the optional message unless the condition holds.
+fn builtin_assert(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::Bool(true)] |
+        [Value::Bool(true), Value::String(_)] => Ok(Value::Unit),
+        [Value::Bool(false)] => Err(RuntimeError::new("Assertion failed!")),
+        [Value::Bool(false), Value::String(message)] => Err(RuntimeError::new(
+            format!("Assertion failed: {}", message))),
+        _ => Err(RuntimeError::new("\"assert\" expects a bool and an optional string!")),
+    }
+}
+
+/// Convert the value to an int like `value as int` does.
+fn builtin_int(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [value] => cast(value.clone(), &Type::Named(String::from("int")), &[]),
+        _ => Err(RuntimeError::new("\"int\" expects one value!")),
+    }
+}
+
+/// Convert the value to a string like `value as string` does.
+fn builtin_string(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [value] => cast(value.clone(), &Type::Named(String::from("string")), &[]),
+        _ => Err(RuntimeError::new("\"string\" expects one value!")),
+    }
+}
+
+/// Abort the program with the message.
+fn builtin_panic(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::String(message)] => Err(RuntimeError::new(
+            format!("Panicked: {}", message))),
+        _ => Err(RuntimeError::new("\"panic\" expects a string!")),
+    }
+}
+
+/// Wrap the value in a successful result.
+fn builtin_ok(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [value] => Ok(Value::Ok(Box::new(value.clone()))),
+        _ => Err(RuntimeError::new("\"ok\" expects one value!")),
+    }
+}
+
+/// Wrap the error in a failed result.
+fn builtin_err(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [error] => Ok(Value::Err(Box::new(error.clone()))),
+        _ => Err(RuntimeError::new("\"err\" expects one value!")),
+    }
+}
+
+/// Number of characters in a string, elements in an array or slice, or
+/// entries in a map.
+fn builtin_len(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let length = match arguments {
+        [Value::String(str)] => str.chars().count(),
+        [Value::Map(entries)] => entries.len(),
+        [value] if value.elements().is_some() => value.elements().unwrap().len(),
+        _ => return Err(RuntimeError::new("\"len\" expects a string, array, slice or map!")),
+    };
+
+    Ok(Value::Int(length as isize))
+}
+
+/// Array of the keys of a map, in insertion order.
+fn builtin_keys(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::Map(entries)] => Ok(Value::Array(Rc::new(
+            entries.iter().map(|(key, _)| key.clone()).collect()))),
+        _ => Err(RuntimeError::new("\"keys\" expects a map!")),
+    }
+}
+
+/// Array of the values of a map, in the insertion order of their keys.
+fn builtin_values(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::Map(entries)] => Ok(Value::Array(Rc::new(
+            entries.iter().map(|(_, value)| value.clone()).collect()))),
+        _ => Err(RuntimeError::new("\"values\" expects a map!")),
+    }
+}
+
+/// Format string filled in with the values after it, one for each of
+/// its placeholders.
+fn builtin_format(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::String(template), values @ ..] => format_values(template, values)
+            .map(Value::String)
+            .map_err(RuntimeError::new),
+        _ => Err(RuntimeError::new("\"format\" expects a format string and its values!")),
+    }
+}
+
+/// Print the format string filled in like `format` does, then a
+/// newline.
+fn builtin_printf(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::String(template), values @ ..] => {
+            println!("{}", format_values(template, values).map_err(RuntimeError::new)?);
+
+            Ok(Value::Unit)
+        },
+        _ => Err(RuntimeError::new("\"printf\" expects a format string and its values!")),
+    }
+}
+
+/// Text of the file at the path.
+fn builtin_read_file(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::String(path)] => fs::read_to_string(path)
+            .map(Value::String)
+            .map_err(|err| RuntimeError::new(format!("Failed to read \"{}\": {}", path, err))),
+        _ => Err(RuntimeError::new("\"read_file\" expects a path!")),
+    }
+}
+
+/// Write the string to the file at the path, replacing its text or
+/// creating it.
+fn builtin_write_file(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::String(path), Value::String(data)] => fs::write(path, data)
+            .map(|_| Value::Unit)
+            .map_err(|err| RuntimeError::new(format!("Failed to write \"{}\": {}", path, err))),
+        _ => Err(RuntimeError::new("\"write_file\" expects a path and a string!")),
+    }
+}
+
+/// Write the string at the end of the file at the path, creating it if
+/// it doesn't exist.
+fn builtin_append_file(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    match arguments {
+        [Value::String(path), Value::String(data)] => OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(data.as_bytes()))
+            .map(|_| Value::Unit)
+            .map_err(|err| RuntimeError::new(format!("Failed to append to \"{}\": {}", path, err))),
+        _ => Err(RuntimeError::new("\"append_file\" expects a path and a string!")),
+    }
+}
+
+/// Callbacks an [`Interpreter`] makes as it runs a program, letting the
+/// host watch the program or pause it, as a debugger does, trace it,
+/// or bound its time and memory.
+///
+/// Every callback does nothing by default. An error one returns aborts
+/// the program where it was called.
+pub trait Hooks {
+
+    /// Called before running each statement that has a location,
+    /// which declarations like `func` and `enum` don't.
+    fn before_statement(&mut self, state: &State) -> Result<(), RuntimeError> {
+        let _ = state;
+
+        Ok(())
+    }
+
+    /// Called when the Fang function `name` is called from `span`,
+    /// before its body runs, including in place of a function making a
+    /// tail call.
+    fn enter_function(&mut self, name: &str, span: Span) -> Result<(), RuntimeError> {
+        let _ = (name, span);
+
+        Ok(())
+    }
+
+    /// Called when the body of the Fang function `name` stops running,
+    /// whether it returned, made a tail call or failed.
+    fn exit_function(&mut self, name: &str) {
+        let _ = name;
+    }
+
+    /// Called after the program builds an array or map, which hosts may
+    /// count the elements of to bound its memory.
+    fn allocate(&mut self, value: &Value) -> Result<(), RuntimeError> {
+        let _ = value;
+
+        Ok(())
+    }
+}
+
+/// Running program as [`Hooks`] see it.
+pub struct State<'i> {
+    span: Span,
+
+    /// Scopes of the running function, or of the top level.
+    scopes: &'i [Scope],
+    calls: &'i [(Rc<Function>, Span)],
+}
+
+impl State<'_> {
+
+    /// Span locating the statement about to run.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Number of function calls active, zero at the top level.
+    pub fn depth(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Initialized variables of the running function, or the global
+    /// ones at the top level, sorted by name. Inner scopes shadow outer
+    /// ones.
+    pub fn locals(&self) -> Vec<(&str, &Value)> {
+        let mut locals: HashMap<&str, &Value> = HashMap::new();
+        let mut sorted: Vec<(&str, &Value)>;
+
+        for scope in self.scopes {
+            for (name, value) in scope {
+                match value {
+                    Some(value) => locals.insert(name, value),
+                    None => locals.remove(name.as_str()),
+                };
+            }
+        }
+
+        sorted = locals.into_iter().collect();
+        sorted.sort_by_key(|(name, _)| *name);
+        sorted
+    }
+
+    /// Active calls of Fang functions, innermost first, like the trace
+    /// of a [`RuntimeError`].
+    pub fn backtrace(&self) -> Vec<TraceFrame> {
+        self.calls.iter()
+            .rev()
+            .map(|(function, span)| TraceFrame {
+                function: function.name.to_owned(),
+                span: *span,
+            })
+            .collect()
+    }
+}
+
+/// How control leaves a statement.
+enum Flow {
+
+    /// Fall through to the next statement, carrying the
+    /// statement's value.
+    Normal(Value),
+
+    /// Unwind to the enclosing function with the returned value.
+    Return(Value),
+
+    /// Unwind to the enclosing function, which returns what calling
+    /// `function` returns, running it in place of the enclosing one.
+    TailCall {
+        function: Rc<Function>,
+        arguments: Vec<Value>,
+        span: Span,
+    },
+}
+
+/// Tree-walking interpreter executing a parsed program.
+pub struct Interpreter<'a> {
+    env: &'a mut Env,
+
+    /// Scopes of every active function call, innermost last.
+    frames: Vec<Vec<Scope>>,
+
+    /// Arena of the code being executed.
+    expressions: Arc<Vec<Expression>>,
+
+    /// Failed result a `?` is returning, unwinding like an error to
+    /// the innermost function call.
+    propagating: Option<Value>,
+
+    /// Functions of the active calls along with the span of each call,
+    /// innermost last.
+    calls: Vec<(Rc<Function>, Span)>,
+
+    /// Callbacks the host watches the execution with, called in the
+    /// order they were given.
+    hooks: Vec<&'a mut dyn Hooks>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(env: &'a mut Env) -> Interpreter<'a> {
+        Interpreter {
+            env,
+            frames: Vec::new(),
+            expressions: Arc::new(Vec::new()),
+            propagating: None,
+            calls: Vec::new(),
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Interpreter calling `hooks` as the program runs, after the
+    /// hooks given before.
+    pub fn with_hooks(mut self, hooks: &'a mut dyn Hooks) -> Interpreter<'a> {
+        self.hooks.push(hooks);
+        self
+    }
+
+    /// Run the program, returning the value of its last statement
+    /// or of a top-level `return`.
+    pub fn run(&mut self, program: &Program) -> Result<Value, RuntimeError> {
+        let scope_count = self.env.scopes.len();
+
+        self.expressions = Arc::clone(&program.expressions);
+
+        /* Constants are known before the program starts. */
+        match evaluate_constants(program) {
+            Ok(constants) => for (name, value) in constants {
+                self.define(&name, Some(value));
+            },
+            Err(diagnostics) => {
+                let diagnostic = diagnostics.iter().next().unwrap();
+                let err = RuntimeError::new(diagnostic.message.to_owned());
+
+                return Err(match diagnostic.span {
+                    Some(span) => err.with_span(span),
+                    None => err,
+                });
+            },
+        }
+
+        let result = self.exec_statements(&program.statements);
+
+        /* Drop block scopes left behind by an error. */
+        self.env.scopes.truncate(scope_count);
+        self.frames.clear();
+        self.calls.clear();
+
+        /* A `?` outside any function returns from the program. */
+        if let Some(value) = self.propagating.take() {
+            return Ok(value);
+        }
+
+        match result? {
+            Flow::Normal(value) |
+            Flow::Return(value) => Ok(value),
+            Flow::TailCall { .. } => unreachable!("only function bodies make tail calls"),
+        }
+    }
+
+    fn scopes(&mut self) -> &mut Vec<Scope> {
+        match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => &mut self.env.scopes,
+        }
+    }
+
+    /// Find the slot of variable `name`, looking through the current
+    /// scopes and then the globals.
+    fn slot(&mut self, name: &str) -> Option<&mut Option<Value>> {
+        if let Some(frame) = self.frames.last_mut() {
+            if let Some(index) = frame.iter().rposition(|scope| scope.contains_key(name)) {
+                return frame[index].get_mut(name);
+            }
+
+            return self.env.scopes[0].get_mut(name);
+        }
+
+        let scopes: &mut Vec<Scope> = &mut self.env.scopes;
+        let index: usize = scopes.iter().rposition(|scope| scope.contains_key(name))?;
+
+        scopes[index].get_mut(name)
+    }
+
+    fn define(&mut self, name: &str, value: Option<Value>) {
+        let scopes = self.scopes();
+
+        scopes.last_mut().unwrap().insert(name.to_owned(), value);
+    }
+
+    fn lookup(&mut self, name: &str) -> Result<Value, RuntimeError> {
+        match self.slot(name) {
+            Some(Some(value)) => Ok(value.clone()),
+            Some(None) => Err(RuntimeError::new(
+                format!("Variable \"{}\" is used before being initialized!", name))),
+            None => Err(RuntimeError::new(
+                format!("Undefined variable \"{}\"!", name))),
+        }
+    }
+
+    fn assign(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+        match self.slot(name) {
+            Some(slot) => {
+                *slot = Some(value);
+
+                Ok(())
+            },
+            None => Err(RuntimeError::new(
+                format!("Undefined variable \"{}\"!", name))),
+        }
+    }
+
+    /// Add the function defined by `definition` to the hoisted
+    /// `functions`, as an overload if the name is taken.
+    fn hoist(
+        &self,
+        functions: &mut Vec<(String, Vec<Rc<Function>>)>,
+        name: String,
+        definition: &Statement
+    ) {
+        let (parameters, return_type, statements) = match definition {
+            Statement::FunctionDefinition {
+                parameters,
+                return_type,
+                statements,
+                ..
+            } => (parameters, return_type, statements),
+            _ => return,
+        };
+        let function = Rc::new(Function {
+            name: name.clone(),
+            body: FunctionBody::Defined {
+                parameters: parameters.clone(),
+                return_type: return_type.clone(),
+                statements: statements.clone(),
+                expressions: Arc::clone(&self.expressions),
+            },
+        });
+
+        match functions.iter_mut().find(|(other, _)| *other == name) {
+            Some((_, overloads)) => overloads.push(function),
+            None => functions.push((name, vec![function])),
+        }
+    }
+
+    /// Value the name of `variant` of the enum `enum_name` is bound to:
+    /// the variant itself if it has no fields, or else a function
+    /// making the variant out of values for its fields.
+    fn constructor(&self, enum_name: &str, variant: &Variant) -> Value {
+        let enum_name = enum_name.to_owned();
+        let name = variant.name.to_owned();
+        let types = variant.fields.clone();
+        let expressions = Arc::clone(&self.expressions);
+
+        if types.is_empty() {
+            return Value::Enum(Rc::new(EnumValue {
+                enum_name,
+                variant: name,
+                fields: Vec::new(),
+            }));
+        }
+
+        let function = Function {
+            name: name.to_owned(),
+            body: FunctionBody::Native(Box::new(move |arguments| {
+                if arguments.len() != types.len() {
+                    return Err(RuntimeError::new(
+                        format!("Variant \"{}\" expects {} fields but got {}!",
+                                name, types.len(), arguments.len())));
+                }
+
+                for (index, (r#type, value)) in types.iter().zip(arguments).enumerate() {
+                    if !type_admits(r#type, value) {
+                        return Err(RuntimeError::new(
+                            format!("Field {} of \"{}\" expects {} but got {}!",
+                                    index + 1, name, format_type(&expressions, r#type), value.type_name())));
+                    }
+                }
+
+                Ok(Value::Enum(Rc::new(EnumValue {
+                    enum_name: enum_name.to_owned(),
+                    variant: name.to_owned(),
+                    fields: arguments.to_vec(),
+                })))
+            })),
+        };
+
+        Value::Function(Rc::new(function))
+    }
+
+    /// Define the functions, methods and enum variants of `statements`
+    /// so uses may precede their definitions.
+    fn define_hoisted(
+        &mut self,
+        statements: &[Statement]
+    ) {
+        let mut functions: Vec<(String, Vec<Rc<Function>>)> = Vec::new();
+
+        for statement in statements {
+            match statement {
+                Statement::FunctionDefinition {
+                    callee_name,
+                    ..
+                } => self.hoist(&mut functions, callee_name.to_owned(), statement),
+                Statement::Implementation {
+                    type_name,
+                    functions: methods,
+                    ..
+                } => {
+                    for method in methods {
+                        if let Statement::FunctionDefinition {
+                            callee_name,
+                            ..
+                        } = method {
+                            self.hoist(&mut functions, method_name(type_name, callee_name), method);
+                        }
+                    }
+                },
+                Statement::Enum {
+                    name,
+                    variants,
+                    ..
+                } => {
+                    for variant in variants {
+                        self.define(&variant.name, Some(self.constructor(name, variant)));
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        for (name, mut overloads) in functions {
+            let function = match overloads.len() {
+                1 => overloads.pop().unwrap(),
+                _ => Rc::new(Function {
+                    name: name.clone(),
+                    body: FunctionBody::Overloaded(overloads),
+                }),
+            };
+
+            self.define(&name, Some(Value::Function(function)));
+        }
+    }
+
+    fn exec_statements(
+        &mut self,
+        statements: &[Statement]
+    ) -> Result<Flow, RuntimeError> {
+        let mut value = Value::Unit;
+
+        self.define_hoisted(statements);
+
+        for statement in statements {
+            if !self.hooks.is_empty() {
+                self.before_statement(statement)?;
+            }
+
+            match self.exec_statement(statement)? {
+                Flow::Normal(result) => value = result,
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal(value))
+    }
+
+    /// Let the hooks look at the program before `statement` runs,
+    /// unless the statement has no location.
+    fn before_statement(
+        &mut self,
+        statement: &Statement
+    ) -> Result<(), RuntimeError> {
+        let span = match statement_span(&self.expressions, statement) {
+            Some(span) => span,
+            None => return Ok(()),
+        };
+        let scopes = match self.frames.last() {
+            Some(frame) => frame,
+            None => &self.env.scopes,
+        };
+        let state = State {
+            span,
+            scopes,
+            calls: &self.calls,
+        };
+
+        for hooks in &mut self.hooks {
+            hooks.before_statement(&state).map_err(|err| err.with_span(span))?;
+        }
+
+        Ok(())
+    }
+
+    /// Tell the hooks that `function` is called from `span`.
+    fn enter_function(
+        &mut self,
+        function: &Function,
+        span: Span
+    ) -> Result<(), RuntimeError> {
+        for hooks in &mut self.hooks {
+            hooks.enter_function(&function.name, span).map_err(|err| err.with_span(span))?;
+        }
+
+        Ok(())
+    }
+
+    /// Tell the hooks that the body of `function` stopped running.
+    fn exit_function(
+        &mut self,
+        function: &Function
+    ) {
+        for hooks in &mut self.hooks {
+            hooks.exit_function(&function.name);
+        }
+    }
+
+    /// Tell the hooks that the program built `value`.
+    fn allocate(
+        &mut self,
+        value: Value
+    ) -> Result<Value, RuntimeError> {
+        for hooks in &mut self.hooks {
+            hooks.allocate(&value)?;
+        }
+
+        Ok(value)
+    }
+
+    fn exec_statement(
+        &mut self,
+        statement: &Statement
+    ) -> Result<Flow, RuntimeError> {
+        match statement {
+            Statement::VariableDefinition {
+                identifier,
+                r#type,
+                value,
+                span,
+            } => {
+                let value = self.eval_definition(r#type.as_ref(), *value)
+                    .map_err(|err| err.with_span(*span))?;
+
+                self.define(identifier, value);
+            },
+            Statement::Destructuring { .. } => self.exec_destructuring(statement)?,
+            Statement::ConstantDefinition { .. } |
+            Statement::FunctionDefinition { .. } |
+            Statement::Implementation { .. } |
+            Statement::Trait { .. } |
+            Statement::Enum { .. } => {},
+            Statement::Return {
+                expression,
+                ..
+            } => return self.eval_return(*expression),
+            Statement::Expression {
+                expression,
+            } => {
+                let value = self.eval_expression(*expression)?;
+
+                return Ok(Flow::Normal(value));
+            },
+            Statement::Block {
+                statements,
+            } => {
+                self.scopes().push(Scope::new());
+                let flow: Result<Flow, RuntimeError> = self.exec_statements(statements);
+                self.scopes().pop();
+
+                return match flow? {
+                    Flow::Normal(_) => Ok(Flow::Normal(Value::Unit)),
+                    flow => Ok(flow),
+                };
+            },
+            Statement::For { .. } => return self.exec_for(statement),
+            Statement::Conditional {
+                span,
+                ..
+            } => return Err(RuntimeError::new("Conditional compilation must be resolved before running!")
+                .with_span(*span)),
+            Statement::Attributed {
+                span,
+                ..
+            } => return Err(RuntimeError::new("Attributes must be resolved before running!")
+                .with_span(*span)),
+            Statement::MacroDefinition {
+                span,
+                ..
+            } => return Err(RuntimeError::new("Macros must be expanded before running!")
+                .with_span(*span)),
+        }
+
+        Ok(Flow::Normal(Value::Unit))
+    }
+
+    /// Define the variables the pattern of the destructuring `let`
+    /// `statement` binds, failing if its value doesn't match.
+    fn exec_destructuring(
+        &mut self,
+        statement: &Statement
+    ) -> Result<(), RuntimeError> {
+        let (pattern, value, span) = match statement {
+            Statement::Destructuring {
+                pattern,
+                value,
+                span,
+            } => (pattern, *value, *span),
+            _ => unreachable!("only destructuring definitions are executed as such"),
+        };
+        let value = self.eval_expression(value)?;
+        let mut bindings: Vec<(String, Value)> = Vec::new();
+
+        if !self.match_pattern(pattern, &value, &mut bindings)? {
+            return Err(RuntimeError::new(
+                format!("{} doesn't match the pattern {}!", value, format_pattern(pattern))).with_span(span));
+        }
+
+        for (name, value) in bindings {
+            self.define(&name, Some(value));
+        }
+
+        Ok(())
+    }
+
+    /// Run the body of the `for` loop `statement` once for every item
+    /// of its collection, each time in a new scope binding the item.
+    fn exec_for(
+        &mut self,
+        statement: &Statement
+    ) -> Result<Flow, RuntimeError> {
+        let (variable, iterable, statements, span) = match statement {
+            Statement::For {
+                variable,
+                iterable,
+                statements,
+                span,
+            } => (variable, *iterable, statements, *span),
+            _ => unreachable!("only for loops are executed as such"),
+        };
+        let collection = self.eval_expression(iterable)?;
+
+        for item in iterate(&collection).map_err(|err| err.with_span(span))? {
+            self.scopes().push(Scope::new());
+            self.define(variable, Some(item));
+            let flow: Result<Flow, RuntimeError> = self.exec_statements(statements);
+            self.scopes().pop();
+
+            match flow? {
+                Flow::Normal(_) => {},
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal(Value::Unit))
+    }
+
+    fn eval_expression(
+        &mut self,
+        expression: ExprId
+    ) -> Result<Value, RuntimeError> {
+        let expressions = Arc::clone(&self.expressions);
+
+        match &expressions[expression.index()] {
+            Expression::Identifier {
+                name,
+                span,
+            } => self.lookup(name).map_err(|err| err.with_span(*span)),
+            Expression::Number(num) => Ok(Value::Int(*num)),
+            Expression::String(str) => Ok(Value::String(str.to_owned())),
+            Expression::Nil => Ok(Value::Nil),
+            Expression::Unit => Ok(Value::Unit),
+            Expression::Try {
+                operand,
+                span,
+            } => self.eval_try(*operand, *span),
+            Expression::Cast {
+                operand,
+                r#type,
+                span,
+            } => self.eval_cast(*operand, r#type, *span),
+            Expression::Array {
+                elements,
+            } => self.eval_array(elements),
+            Expression::Map {
+                entries,
+            } => self.eval_map(entries),
+            Expression::Index {
+                target,
+                index,
+                span,
+            } => self.eval_index(*target, *index, *span),
+
+            Expression::Range { .. } => self.eval_range(expression),
+            Expression::Match { .. } => self.eval_match(expression),
+            Expression::UnaryOperation {
+                operator,
+                operand,
+            } => {
+                let value = self.eval_expression(*operand)?;
+
+                /* The operation has no span of its own, so its errors
+                 * point at the operand. */
+                eval_unary_operation(operator, value).map_err(|err| match expression_span(&self.expressions, *operand) {
+                    Some(span) => err.with_span(span),
+                    None => err,
+                })
+            },
+            Expression::BinaryOperation {
+                operator: BinaryOperator::Assign,
+                operand_left,
+                operand_right,
+                ..
+            } => {
+                let value = self.eval_expression(*operand_right)?;
+
+                self.assign_place(*operand_left, value.clone())?;
+
+                Ok(value)
+            },
+            Expression::BinaryOperation { .. } => self.eval_operation(expression),
+            Expression::FunctionCall {
+                callee_name,
+                arguments,
+                span,
+            } => {
+                let (function, values) = self.eval_callee(callee_name, arguments, *span)?;
+
+                self.call(function, values, *span)
+            },
+            Expression::MethodCall {
+                receiver,
+                method,
+                arguments,
+                span,
+            } => self.eval_method_call(*receiver, method, arguments, *span),
+        }
+    }
+
+    /// Function a call of `callee_name` runs, along with the values of
+    /// its arguments ordered like the function's parameters.
+    fn eval_callee(
+        &mut self,
+        callee_name: &str,
+        arguments: &[Argument],
+        span: Span
+    ) -> Result<(Rc<Function>, Vec<Value>), RuntimeError> {
+        let mut values: Vec<Value> = Vec::new();
+
+        for argument in arguments {
+            values.push(self.eval_expression(argument.value)?);
+        }
+
+        match &self.lookup(callee_name).map_err(|err| err.with_span(span))? {
+            Value::Function(function) => {
+                let (function, values) = select_overload(function, arguments, values, span)?;
+
+                Ok((Rc::clone(function), values))
+            },
+            value => Err(RuntimeError::new(
+                format!("\"{}\" is a {}, not a function!",
+                        callee_name, value.type_name())).with_span(span)),
+        }
+    }
+
+    /// Flow of returning the value of `expression` from the running
+    /// function.
+    ///
+    /// A call there, or in an arm of a match there, is a tail call:
+    /// its arguments are evaluated but the function is left for the
+    /// enclosing call to run in place of the returning one, so
+    /// recursion this way doesn't grow the stack.
+    fn eval_return(
+        &mut self,
+        expression: ExprId
+    ) -> Result<Flow, RuntimeError> {
+        let expressions = Arc::clone(&self.expressions);
+
+        match &expressions[expression.index()] {
+            Expression::FunctionCall {
+                callee_name,
+                arguments,
+                span,
+            } if !self.frames.is_empty() => {
+                let (function, arguments) = self.eval_callee(callee_name, arguments, *span)?;
+
+                Ok(Flow::TailCall {
+                    function,
+                    arguments,
+                    span: *span,
+                })
+            },
+            Expression::Match { .. } => {
+                let (arm, bindings) = self.select_arm(expression)?;
+
+                self.scopes().push(Scope::new());
+
+                for (name, value) in bindings {
+                    self.define(&name, Some(value));
+                }
+
+                let flow: Result<Flow, RuntimeError> = self.eval_return(arm);
+                self.scopes().pop();
+
+                flow
+            },
+            _ => Ok(Flow::Return(self.eval_expression(expression)?)),
+        }
+    }
+
+    /// Convert the value of `operand` to `r#type` for the cast at
+    /// `span`.
+    fn eval_cast(
+        &mut self,
+        operand: ExprId,
+        r#type: &Type,
+        span: Span
+    ) -> Result<Value, RuntimeError> {
+        let value = self.eval_expression(operand)?;
+
+        cast(value, r#type, &self.expressions).map_err(|err| err.with_span(span))
+    }
+
+    fn eval_array(
+        &mut self,
+        elements: &[ExprId]
+    ) -> Result<Value, RuntimeError> {
+        let mut values: Vec<Value> = Vec::new();
+
+        for element in elements {
+            values.push(self.eval_expression(*element)?);
+        }
+
+        self.allocate(Value::Array(Rc::new(values)))
+    }
+
+    fn eval_map(
+        &mut self,
+        entries: &[(ExprId, ExprId)]
+    ) -> Result<Value, RuntimeError> {
+        let mut map: Vec<(Value, Value)> = Vec::new();
+
+        for (key, value) in entries {
+            let key = self.eval_expression(*key)?;
+            let value = self.eval_expression(*value)?;
+
+            map_insert(&mut map, key, value)?;
+        }
+
+        self.allocate(Value::Map(Rc::new(map)))
+    }
+
+    /// Element of the array `target` at `index`, or its slice if the
+    /// index is a range, for the indexing at `span`.
+    fn eval_index(
+        &mut self,
+        target: ExprId,
+        index: ExprId,
+        span: Span
+    ) -> Result<Value, RuntimeError> {
+        let array = self.eval_expression(target)?;
+        let index = self.eval_expression(index)?;
+
+        index_value(&array, &index).map_err(|err| err.with_span(span))
+    }
+
+    /// Value of the range expression `range`.
+    fn eval_range(
+        &mut self,
+        range: ExprId
+    ) -> Result<Value, RuntimeError> {
+        let expressions = Arc::clone(&self.expressions);
+        let (start, end, inclusive) = match &expressions[range.index()] {
+            Expression::Range {
+                start,
+                end,
+                inclusive,
+            } => (*start, *end, *inclusive),
+            _ => unreachable!("only ranges are evaluated as such"),
+        };
+        let start = match start {
+            Some(start) => Some(self.eval_expression(start)?),
+            None => None,
+        };
+        let end = match end {
+            Some(end) => Some(self.eval_expression(end)?),
+            None => None,
+        };
+
+        range_value(start, end, inclusive)
+    }
+
+    /// Apply the binary operation `expression`. On a value of a user
+    /// type, an operator calls the method it stands for, like `add`
+    /// for `+`.
+    fn eval_operation(
+        &mut self,
+        expression: ExprId
+    ) -> Result<Value, RuntimeError> {
+        let (operator, operand_left, operand_right, span) = match self.expressions[expression.index()] {
+            Expression::BinaryOperation {
+                operator,
+                operand_left,
+                operand_right,
+                span,
+            } => (operator, operand_left, operand_right, span),
+            _ => unreachable!("only binary operations are evaluated as such"),
+        };
+        let left = self.eval_expression(operand_left)?;
+        let right = self.eval_expression(operand_right)?;
+        let method = match (&left, operator_method(&operator)) {
+            (Value::Enum(_), Some(method)) => method,
+            _ => return eval_binary_operation(&operator, left, right).map_err(|err| err.with_span(span)),
+        };
+        let function = match &self.lookup(&method_name(left.type_name(), method)) {
+            Ok(Value::Function(function)) => Rc::clone(function),
+            _ => return Err(RuntimeError::new(
+                format!("Type \"{}\" has no method \"{}\" for \"{}\"!",
+                        left.type_name(), method, operator.symbol())).with_span(span)),
+        };
+        let arguments = [operand_left, operand_right].map(|value| Argument {
+            name: None,
+            value,
+            span,
+        });
+        let (function, values) = select_overload(&function, &arguments, vec![left, right], span)?;
+
+        self.call(Rc::clone(function), values, span)
+    }
+
+    /// Value of the first arm of the match expression `expression`
+    /// whose pattern matches, evaluated with the variables the pattern
+    /// binds in a new scope.
+    fn eval_match(
+        &mut self,
+        expression: ExprId
+    ) -> Result<Value, RuntimeError> {
+        let (arm, bindings) = self.select_arm(expression)?;
+
+        self.scopes().push(Scope::new());
+
+        for (name, value) in bindings {
+            self.define(&name, Some(value));
+        }
+
+        let result: Result<Value, RuntimeError> = self.eval_expression(arm);
+        self.scopes().pop();
+
+        result
+    }
+
+    /// Value of the first arm of the match `expression` whose pattern
+    /// the scrutinee matches, with the variables the pattern binds.
+    fn select_arm(
+        &mut self,
+        expression: ExprId
+    ) -> Result<(ExprId, Vec<(String, Value)>), RuntimeError> {
+        let expressions = Arc::clone(&self.expressions);
+        let (scrutinee, arms, span) = match &expressions[expression.index()] {
+            Expression::Match {
+                scrutinee,
+                arms,
+                span,
+            } => (*scrutinee, arms, *span),
+            _ => unreachable!("only match expressions are evaluated as such"),
+        };
+        let value = self.eval_expression(scrutinee)?;
+
+        for arm in arms {
+            let mut bindings: Vec<(String, Value)> = Vec::new();
+
+            if self.match_pattern(&arm.pattern, &value, &mut bindings)? {
+                return Ok((arm.value, bindings));
+            }
+        }
+
+        Err(RuntimeError::new(
+            format!("No arm of the match covers {}!", value)).with_span(span))
+    }
+
+    /// Whether `value` matches `pattern`, collecting the variables the
+    /// pattern binds in `bindings`.
+    fn match_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &Value,
+        bindings: &mut Vec<(String, Value)>
+    ) -> Result<bool, RuntimeError> {
+        let matched = match pattern {
+            Pattern::Wildcard => true,
+            Pattern::Number(num) => *value == Value::Int(*num),
+            Pattern::String(str) => matches!(value, Value::String(other) if other == str),
+            Pattern::Nil => matches!(value, Value::Nil),
+            Pattern::Identifier {
+                name,
+                ..
+            } => match self.slot(name) {
+                Some(Some(Value::Enum(variant))) if variant.fields.is_empty() =>
+                    *value == Value::Enum(Rc::clone(variant)),
+                _ => {
+                    bindings.push((name.to_owned(), value.clone()));
+
+                    true
+                },
+            },
+            Pattern::Variant {
+                name,
+                fields,
+                span,
+            } => match value {
+                Value::Enum(variant) if variant.variant == *name => {
+                    if variant.fields.len() != fields.len() {
+                        return Err(RuntimeError::new(
+                            format!("Variant \"{}\" has {} fields but the pattern has {}!",
+                                    name, variant.fields.len(), fields.len())).with_span(*span));
+                    }
+
+                    for (field, value) in fields.iter().zip(&variant.fields) {
+                        if !self.match_pattern(field, value, bindings)? {
+                            return Ok(false);
+                        }
+                    }
+
+                    true
+                },
+                _ => false,
+            },
+            Pattern::Array(patterns) => match value.elements() {
+                Some(elements) if elements.len() == patterns.len() => {
+                    for (pattern, element) in patterns.iter().zip(elements) {
+                        if !self.match_pattern(pattern, element, bindings)? {
+                            return Ok(false);
+                        }
+                    }
+
+                    true
+                },
+                _ => false,
+            },
+        };
+
+        Ok(matched)
+    }
+
+    /// Store `value` in the place `target` names, a variable or an
+    /// element of an array or map held by one.
+    fn assign_place(
+        &mut self,
+        target: ExprId,
+        value: Value
+    ) -> Result<(), RuntimeError> {
+        let expressions = Arc::clone(&self.expressions);
+
+        match &expressions[target.index()] {
+            Expression::Identifier {
+                name,
+                span,
+            } => self.assign(name, value).map_err(|err| err.with_span(*span)),
+            Expression::Index {
+                target,
+                index,
+                span,
+            } => {
+                let mut array = self.eval_expression(*target)?;
+                let index = self.eval_expression(*index)?;
+
+                if let Value::Range(_) = index {
+                    return Err(RuntimeError::new("Cannot assign to a slice!").with_span(*span));
+                }
+
+                if let Value::Map(entries) = &mut array {
+                    map_insert(Rc::make_mut(entries), index, value).map_err(|err| err.with_span(*span))?;
+
+                    return self.assign_place(*target, array);
+                }
+
+                let slot = array_slot(&array, &index).map_err(|err| err.with_span(*span))?;
+
+                match &mut array {
+                    Value::Array(elements) => Rc::make_mut(elements)[slot] = value,
+                    Value::Slice {
+                        elements,
+                        start,
+                        ..
+                    } => Rc::make_mut(elements)[*start + slot] = value,
+                    _ => unreachable!("only arrays and slices have slots"),
+                }
+
+                self.assign_place(*target, array)
+            },
+            _ => Err(RuntimeError::new("Invalid assignment target!")),
+        }
+    }
+
+    /// Initial value of a variable defined with the optional type and
+    /// value.
+    ///
+    /// A variable with an array type holds an array of that length,
+    /// which is filled with default elements if it has no value.
+    fn eval_definition(
+        &mut self,
+        r#type: Option<&Type>,
+        value: Option<ExprId>
+    ) -> Result<Option<Value>, RuntimeError> {
+        let value = match value {
+            Some(expression) => Some(self.eval_expression(expression)?),
+            None => None,
+        };
+        let size = match r#type {
+            Some(Type::Array {
+                size,
+                ..
+            }) => self.eval_size(*size)?,
+            _ => return Ok(value),
+        };
+
+        match &value {
+            Some(Value::Array(elements)) if elements.len() == size => Ok(value),
+            Some(Value::Array(elements)) => Err(RuntimeError::new(
+                format!("Expected an array of length {} but got {}!", size, elements.len()))),
+            Some(value) => Err(RuntimeError::new(
+                format!("Expected an array but got {}!", value.type_name()))),
+            None => self.default_value(r#type.unwrap()).map(Some),
+        }
+    }
+
+    /// Value of the size of an array type.
+    fn eval_size(
+        &mut self,
+        size: ExprId
+    ) -> Result<usize, RuntimeError> {
+        match self.eval_expression(size)? {
+            Value::Int(size) if size >= 0 => Ok(size as usize),
+            _ => Err(RuntimeError::new("Array size must be a non-negative int!")),
+        }
+    }
+
+    /// Value a variable of `r#type` starts with when it has none.
+    fn default_value(
+        &mut self,
+        r#type: &Type
+    ) -> Result<Value, RuntimeError> {
+        let value = match r#type {
+            Type::Named(name) => match name.as_str() {
+                "int" => Value::Int(0),
+                "bool" => Value::Bool(false),
+                "string" => Value::String(String::new()),
+                _ => return Err(RuntimeError::new(
+                    format!("Type \"{}\" has no default value!", name))),
+            },
+            Type::Unit => Value::Unit,
+            Type::Nullable(_) => Value::Nil,
+            Type::Slice(_) => Value::Slice {
+                elements: Rc::new(Vec::new()),
+                start: 0,
+                end: 0,
+            },
+            Type::Map { .. } => self.allocate(Value::Map(Rc::new(Vec::new())))?,
+            Type::Array {
+                element,
+                size,
+            } => {
+                let size = self.eval_size(*size)?;
+                let mut elements: Vec<Value> = Vec::new();
+
+                for _ in 0..size {
+                    elements.push(self.default_value(element)?);
+                }
+
+                self.allocate(Value::Array(Rc::new(elements)))?
+            },
+        };
+
+        Ok(value)
+    }
+
+    /// Unwrap the result `operand` holds, or start returning it from
+    /// the enclosing function if it failed.
+    fn eval_try(
+        &mut self,
+        operand: ExprId,
+        span: Span
+    ) -> Result<Value, RuntimeError> {
+        match self.eval_expression(operand)? {
+            mut value @ Value::Ok(_) => Ok(value.take_result().unwrap()),
+            value @ Value::Err(_) => {
+                self.propagating = Some(value);
+
+                Err(RuntimeError::new("Failed result returned by \"?\"!").with_span(span))
+            },
+            value => Err(RuntimeError::new(
+                format!("Cannot apply \"?\" to {}!", value.type_name())).with_span(span)),
+        }
+    }
+
+    /// Call the method of the receiver's type from the call at `span`,
+    /// passing the receiver as `self`.
+    fn eval_method_call(
+        &mut self,
+        receiver: ExprId,
+        method: &str,
+        arguments: &[Argument],
+        span: Span
+    ) -> Result<Value, RuntimeError> {
+        let mut values: Vec<Value> = vec![self.eval_expression(receiver)?];
+        let mut receiver_and_arguments: Vec<Argument> = Vec::new();
+        let type_name = values[0].type_name().to_owned();
+
+        for argument in arguments {
+            values.push(self.eval_expression(argument.value)?);
+        }
+
+        let function: Rc<Function> = match &self.lookup(&method_name(&type_name, method)) {
+            Ok(Value::Function(function)) => Rc::clone(function),
+            _ if matches!(values[0], Value::Nil) => return Err(RuntimeError::new(
+                format!("Cannot call method \"{}\" on nil!", method)).with_span(span)),
+            _ => return Err(RuntimeError::new(
+                format!("Type \"{}\" has no method \"{}\"!", type_name, method)).with_span(span)),
+        };
+
+        receiver_and_arguments.push(Argument {
+            name: None,
+            value: receiver,
+            span,
+        });
+        receiver_and_arguments.extend(arguments.iter().cloned());
+
+        let (function, values) = select_overload(&function, &receiver_and_arguments, values, span)?;
+
+        self.call(Rc::clone(function), values, span)
+    }
+
+    /// Call the function from the call at `span`.
+    ///
+    /// The function a tail call leaves to the call runs in place of
+    /// the one making it, in the same frame, so it's the one errors
+    /// trace back to. Its value must still be of the return type of
+    /// every function it runs in place of.
+    fn call(
+        &mut self,
+        mut function: Rc<Function>,
+        mut arguments: Vec<Value>,
+        mut span: Span
+    ) -> Result<Value, RuntimeError> {
+        let mut returning: Vec<(Rc<Function>, Span)> = Vec::new();
+        let value = loop {
+            let (parameters, statements, expressions) = match &function.body {
+                FunctionBody::Native(native) =>
+                    break native(&arguments).map_err(|err| err.with_span(span))?,
+                FunctionBody::Overloaded(_) =>
+                    unreachable!("overloads are selected before the call"),
+                FunctionBody::Defined {
+                    parameters,
+                    statements,
+                    expressions,
+                    ..
+                } => (parameters, statements, expressions),
+            };
+            let mut scope = Scope::new();
+
+            if parameters.len() != arguments.len() {
+                return Err(RuntimeError::new(
+                    format!("Function \"{}\" expects {} arguments but got {}!",
+                            function.name, parameters.len(), arguments.len())).with_span(span));
+            }
+
+            check_parameter_types(&function, &arguments).map_err(|err| err.with_span(span))?;
+
+            if self.frames.len() >= MAX_CALL_DEPTH {
+                return Err(RuntimeError::new("Maximum call depth exceeded!").with_span(span));
+            }
+
+            for (parameter, argument) in parameters.iter().zip(arguments) {
+                scope.insert(parameter.name.to_owned(), Some(argument));
+            }
+
+            /* Run the body against the arena of the program defining it. */
+            let caller_expressions: Arc<Vec<Expression>> = std::mem::replace(&mut self.expressions, Arc::clone(expressions));
+            self.frames.push(vec![scope]);
+            self.calls.push((Rc::clone(&function), span));
+            let flow: Result<Flow, RuntimeError> = match self.enter_function(&function, span) {
+                Ok(()) => {
+                    let flow = self.exec_statements(statements);
+
+                    self.exit_function(&function);
+                    flow
+                },
+                Err(err) => Err(err),
+            };
+            self.calls.pop();
+            self.frames.pop();
+            self.expressions = caller_expressions;
+
+            match flow {
+                Ok(Flow::Normal(_)) => break Value::Unit,
+                Ok(Flow::Return(value)) => break value,
+                Ok(Flow::TailCall {
+                    function: callee,
+                    arguments: callee_arguments,
+                    span: callee_span,
+                }) => {
+                    /* Only the return types left to check are kept, so
+                     * the memory of a loop of tail calls stays bounded
+                     * unless its functions have one. */
+                    if let FunctionBody::Defined {
+                        return_type: Some(_),
+                        ..
+                    } = &function.body {
+                        returning.push((Rc::clone(&function), span));
+                    }
+
+                    function = callee;
+                    arguments = callee_arguments;
+                    span = callee_span;
+                },
+                Err(_) if self.propagating.is_some() => return Ok(self.propagating.take().unwrap()),
+                Err(mut err) => {
+                    err.trace.push(TraceFrame {
+                        function: function.name.to_owned(),
+                        span,
+                    });
+
+                    return Err(err);
+                },
+            }
+        };
+
+        returning.push((function, span));
+
+        for (function, span) in returning.iter().rev() {
+            if let FunctionBody::Defined {
+                return_type: Some(r#type),
+                expressions,
+                ..
+            } = &function.body {
+                if !type_admits(r#type, &value) {
+                    return Err(RuntimeError::new(
+                        format!("Function \"{}\" should return {} but returned {}!",
+                                function.name, format_type(expressions, r#type), value.type_name())).with_span(*span));
+                }
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+/// Where `statement` starts as far as the spans in it tell, `None` for
+/// statements that only declare something and for those whose
+/// expressions have no span, like `return 1;`.
+///
+/// These are the statements [`Hooks::before_statement`] is called for.
+pub fn statement_span(expressions: &[Expression], statement: &Statement) -> Option<Span> {
+    match statement {
+        Statement::VariableDefinition {
+            span,
+            ..
+        } |
+        Statement::Destructuring {
+            span,
+            ..
+        } |
+        Statement::For {
+            span,
+            ..
+        } => Some(*span),
+        Statement::Return {
+            expression,
+            ..
+        } |
+        Statement::Expression {
+            expression,
+        } => expression_span(expressions, *expression),
+        _ => None,
+    }
+}
+
+/// Earliest span of `expression` and the expressions nested in it.
+fn expression_span(expressions: &[Expression], expression: ExprId) -> Option<Span> {
+    let mut expression = expressions[expression.index()].clone();
+    let mut first: Option<Span> = None;
+    let mut children: Vec<ExprId> = Vec::new();
+
+    expression.visit_spans(&mut |span| first = earliest(first, Some(*span)));
+    expression.visit_ids(&mut |child| children.push(*child));
+
+    for child in children {
+        first = earliest(first, expression_span(expressions, child));
+    }
+
+    first
+}
+
+/// Whichever of the spans starts first.
+fn earliest(span: Option<Span>, other: Option<Span>) -> Option<Span> {
+    match (span, other) {
+        (Some(span), Some(other)) if other.start < span.start => Some(other),
+        (None, other) => other,
+        (span, _) => span,
+    }
+}
+
+/// Name the methods of a type are defined under, which no variable can
+/// have.
+fn method_name(type_name: &str, method: &str) -> String {
+    format!("{}.{}", type_name, method)
+}
+
+/// Method an operator applied to a value of a user type calls.
+fn operator_method(operator: &BinaryOperator) -> Option<&'static str> {
+    match operator {
+        BinaryOperator::Addition => Some("add"),
+        BinaryOperator::Subtraction => Some("sub"),
+        BinaryOperator::Multiplication => Some("mul"),
+        BinaryOperator::Division => Some("div"),
+        BinaryOperator::Equal |
+        BinaryOperator::NotEqual |
+        BinaryOperator::Assign => None,
+    }
+}
+
+/// Text of the function's signature like `area(width: int, height)`.
+fn signature(function: &Function) -> String {
+    let parameters: Vec<String> = match &function.body {
+        FunctionBody::Defined {
+            parameters,
+            expressions,
+            ..
+        } => parameters.iter()
+            .map(|parameter| match &parameter.r#type {
+                Some(r#type) => format!("{}: {}", parameter.name, format_type(expressions, r#type)),
+                None => parameter.name.to_owned(),
+            })
+            .collect(),
+        _ => vec![String::from("...")],
+    };
+
+    format!("{}({})", function.name, parameters.join(", "))
+}
+
+/// Whether a value of the type `r#type` may be `value`.
+///
+/// A type like `int?` admits `nil` besides the values of `int`. Array
+/// types admit arrays of any length whose elements they admit, slice
+/// types admit such arrays and slices, and map types admit maps whose
+/// keys and values they admit.
+pub fn type_admits(r#type: &Type, value: &Value) -> bool {
+    match (r#type, value) {
+        (Type::Named(name), value) => name == value.type_name(),
+        (Type::Unit, Value::Unit) => true,
+        (Type::Nullable(_), Value::Nil) => true,
+        (Type::Nullable(r#type), value) => type_admits(r#type, value),
+        (Type::Array {
+            element,
+            ..
+        }, Value::Array(elements)) => elements.iter().all(|value| type_admits(element, value)),
+        (Type::Slice(element), Value::Array(_) | Value::Slice { .. }) =>
+            value.elements().unwrap().iter().all(|value| type_admits(element, value)),
+        (Type::Map {
+            key: key_type,
+            value: value_type,
+        }, Value::Map(entries)) =>
+            entries.iter().all(|(key, value)| type_admits(key_type, key) && type_admits(value_type, value)),
+        _ => false,
+    }
+}
+
+/// Name of the array or slice `array` in error messages.
+fn sequence_name(array: &Value) -> &'static str {
+    match array {
+        Value::Slice { .. } => "a slice",
+        _ => "an array",
+    }
+}
+
+/// Position among the elements of the array or slice `array` of the
+/// element at `index`.
+fn array_slot(array: &Value, index: &Value) -> Result<usize, RuntimeError> {
+    match array.elements() {
+        Some(elements) => element_slot(index, elements.len(), sequence_name(array)),
+        None => Err(RuntimeError::new(
+            format!("Cannot index {}!", array.type_name()))),
+    }
+}
+
+/// Position of the element at `index` in `sequence`, an array or slice
+/// of `length` elements named like in `"an array"`.
+pub fn element_slot(index: &Value, length: usize, sequence: &str) -> Result<usize, RuntimeError> {
+    match index {
+        Value::Int(index) if *index >= 0 && (*index as usize) < length => Ok(*index as usize),
+        Value::Int(index) => Err(RuntimeError::new(
+            format!("Index {} is out of bounds for {} of length {}!", index, sequence, length))),
+        index => Err(RuntimeError::new(
+            format!("Cannot index {} with {}!", sequence, index.type_name()))),
+    }
+}
+
+/// Range from `start` to `end`, which must be ints if given.
+pub fn range_value(start: Option<Value>, end: Option<Value>, inclusive: bool) -> Result<Value, RuntimeError> {
+    let bound = |bound: Option<Value>| match bound {
+        Some(Value::Int(bound)) => Ok(Some(bound)),
+        Some(bound) => Err(RuntimeError::new(
+            format!("Range bounds must be ints, not {}!", bound.type_name()))),
+        None => Ok(None),
+    };
+
+    Ok(Value::Range(Box::new(Range {
+        start: bound(start)?,
+        end: bound(end)?,
+        inclusive,
+    })))
+}
+
+/// Positions of the slice `range` covers in `sequence`, an array or
+/// slice of `length` elements named like in `"an array"`, the end
+/// excluded. Missing bounds default to those of the sequence.
+pub fn slice_bounds(range: &Range, length: usize, sequence: &str) -> Result<(usize, usize), RuntimeError> {
+    let start = range.start.unwrap_or(0);
+    let end = match range.end {
+        Some(end) if range.inclusive => end.saturating_add(1),
+        Some(end) => end,
+        None => length as isize,
+    };
+
+    if start < 0 || start > end || end as usize > length {
+        return Err(RuntimeError::new(
+            format!("Range {} is out of bounds for {} of length {}!", range, sequence, length)));
+    }
+
+    Ok((start as usize, end as usize))
+}
+
+/// Element of the array or slice `array` at `index`, its slice if
+/// `index` is a range, or value of the map `array` at the key `index`.
+pub fn index_value(array: &Value, index: &Value) -> Result<Value, RuntimeError> {
+    if let Value::Range(range) = index {
+        return slice_value(array, range);
+    }
+
+    if let Value::Map(entries) = array {
+        return match map_get(entries, index) {
+            Some(value) => Ok(value.clone()),
+            None => Err(RuntimeError::new(
+                format!("Key {} is not in the map!", quote_key(index)))),
+        };
+    }
+
+    let slot = array_slot(array, index)?;
+
+    Ok(array.elements().unwrap()[slot].clone())
+}
+
+/// Value of the map entries at `key`.
+fn map_get<'v>(entries: &'v [(Value, Value)], key: &Value) -> Option<&'v Value> {
+    entries.iter().find(|(other, _)| other == key).map(|(_, value)| value)
+}
+
+/// Set the value of the map entries at `key`, adding it after the
+/// others if it's new. Keys are ints, bools or strings.
+pub fn map_insert(entries: &mut Vec<(Value, Value)>, key: Value, value: Value) -> Result<(), RuntimeError> {
+    if !matches!(key, Value::Int(_) | Value::Bool(_) | Value::String(_)) {
+        return Err(RuntimeError::new(
+            format!("Cannot use {} as a map key!", key.type_name())));
+    }
+
+    match entries.iter_mut().find(|(other, _)| *other == key) {
+        Some((_, slot)) => *slot = value,
+        None => entries.push((key, value)),
+    }
+
+    Ok(())
+}
+
+/// Key as written in source code, for error messages.
+fn quote_key(key: &Value) -> String {
+    match key {
+        Value::String(str) => format!("\"{}\"", str),
+        key => key.to_string(),
+    }
+}
+
+/// Slice of the array or slice `array` that `range` covers, viewing
+/// the same elements.
+pub fn slice_value(array: &Value, range: &Range) -> Result<Value, RuntimeError> {
+    let (elements, offset) = match array {
+        Value::Array(elements) => (elements, 0),
+        Value::Slice {
+            elements,
+            start,
+            ..
+        } => (elements, *start),
+        _ => return Err(RuntimeError::new(
+            format!("Cannot slice {}!", array.type_name()))),
+    };
+    let length = array.elements().unwrap().len();
+    let (start, end) = slice_bounds(range, length, sequence_name(array))?;
+
+    Ok(Value::Slice {
+        elements: Rc::clone(elements),
+        start: offset + start,
+        end: offset + end,
+    })
+}
+
+/// Items a `for` loop over `collection` visits: the elements of an
+/// array or slice, the keys of a map in insertion order, the ints a
+/// range with a start covers, or the characters of a string.
+pub fn iterate(collection: &Value) -> Result<Box<dyn Iterator<Item = Value>>, RuntimeError> {
+    let items: Box<dyn Iterator<Item = Value>> = match collection {
+        Value::Array(elements) => {
+            let elements = Rc::clone(elements);
+
+            Box::new((0..elements.len()).map(move |slot| elements[slot].clone()))
+        },
+        Value::Slice {
+            elements,
+            start,
+            end,
+        } => {
+            let elements = Rc::clone(elements);
+
+            Box::new((*start..*end).map(move |slot| elements[slot].clone()))
+        },
+        Value::Map(entries) => {
+            let entries = Rc::clone(entries);
+
+            Box::new((0..entries.len()).map(move |slot| entries[slot].0.clone()))
+        },
+        Value::Range(range) => match (range.start, range.end) {
+            (None, _) => return Err(RuntimeError::new(
+                format!("Cannot iterate over range {} without a start!", range))),
+            (Some(start), Some(end)) if range.inclusive =>
+                Box::new((start..=end).map(Value::Int)),
+            (Some(start), Some(end)) => Box::new((start..end).map(Value::Int)),
+            (Some(start), None) => Box::new((start..=isize::MAX).map(Value::Int)),
+        },
+        Value::String(str) => {
+            let chars: Vec<char> = str.chars().collect();
+
+            Box::new(chars.into_iter().map(|char| Value::String(char.to_string())))
+        },
+        collection => return Err(RuntimeError::new(
+            format!("Cannot iterate over {}!", collection.type_name()))),
+    };
+
+    Ok(items)
+}
+
+/// Pick the overload of the function fitting the call's arguments
+/// best, along with the arguments ordered like its parameters.
+///
+/// An overload fits if the arguments bind to its parameters and every
+/// parameter with a type gets a value of that type. Among those that
+/// fit, the one with the most typed parameters wins.
+fn select_overload<'f>(
+    function: &'f Rc<Function>,
+    arguments: &[Argument],
+    values: Vec<Value>,
+    span: Span
+) -> Result<(&'f Rc<Function>, Vec<Value>), RuntimeError> {
+    let mut fits: Vec<(&'f Rc<Function>, Vec<Value>, usize)> = Vec::new();
+    let overloads = match &function.body {
+        FunctionBody::Overloaded(overloads) => overloads,
+        _ => return Ok((function, bind_arguments(function, arguments, values, span)?)),
+    };
+
+    for overload in overloads {
+        let parameters = match &overload.body {
+            FunctionBody::Defined {
+                parameters,
+                ..
+            } => parameters,
+            _ => continue,
+        };
+        let bound = match bind_arguments(overload, arguments, values.clone(), span) {
+            Ok(bound) => bound,
+            Err(_) => continue,
+        };
+
+        if parameters.len() != bound.len() {
+            continue;
+        }
+
+        if parameters.iter().zip(&bound).all(|(parameter, value)| match &parameter.r#type {
+            Some(r#type) => type_admits(r#type, value),
+            None => true,
+        }) {
+            let typed = parameters.iter().filter(|parameter| parameter.r#type.is_some()).count();
+
+            fits.push((overload, bound, typed));
+        }
+    }
+
+    fits.sort_by_key(|(_, _, typed)| std::cmp::Reverse(*typed));
+
+    match fits.as_slice() {
+        [] => {
+            let types: Vec<&str> = values.iter().map(Value::type_name).collect();
+
+            Err(RuntimeError::new(
+                format!("No overload of \"{}\" takes arguments ({})!",
+                        function.name, types.join(", "))).with_span(span))
+        },
+        [(first, _, typed), (second, _, other), ..] if typed == other => Err(RuntimeError::new(
+            format!("Call to \"{}\" is ambiguous between {} and {}!",
+                    function.name, signature(first), signature(second))).with_span(span)),
+        _ => {
+            let (overload, bound, _) = fits.swap_remove(0);
+
+            Ok((overload, bound))
+        },
+    }
+}
+
+/// Error if a parameter of `function` with a type is given an argument
+/// of another type.
+fn check_parameter_types(function: &Function, arguments: &[Value]) -> Result<(), RuntimeError> {
+    if let FunctionBody::Defined {
+        parameters,
+        expressions,
+        ..
+    } = &function.body {
+        for (parameter, argument) in parameters.iter().zip(arguments) {
+            match &parameter.r#type {
+                Some(r#type) if !type_admits(r#type, argument) => return Err(RuntimeError::new(
+                    format!("Parameter \"{}\" of \"{}\" expects {} but got {}!",
+                            parameter.name, function.name, format_type(expressions, r#type), argument.type_name()))),
+                _ => {},
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Order the values of the call's arguments like the parameters of the
+/// function, placing named arguments by their name.
+fn bind_arguments(
+    function: &Function,
+    arguments: &[Argument],
+    values: Vec<Value>,
+    span: Span
+) -> Result<Vec<Value>, RuntimeError> {
+    let mut slots: Vec<Option<Value>>;
+    let parameters = match &function.body {
+        _ if arguments.iter().all(|argument| argument.name.is_none()) => return Ok(values),
+        FunctionBody::Defined {
+            parameters,
+            ..
+        } => parameters,
+        FunctionBody::Native(_) => return Err(RuntimeError::new(
+            format!("Function \"{}\" takes no named arguments!", function.name)).with_span(span)),
+        FunctionBody::Overloaded(_) =>
+            unreachable!("overloads are selected before binding arguments"),
+    };
+
+    slots = vec![None; parameters.len()];
+
+    for (index, (argument, value)) in arguments.iter().zip(values).enumerate() {
+        let slot = match &argument.name {
+            Some(name) => match parameters.iter().position(|parameter| &parameter.name == name) {
+                Some(slot) => slot,
+                None => return Err(RuntimeError::new(
+                    format!("Function \"{}\" has no parameter \"{}\"!",
+                            function.name, name)).with_span(argument.span)),
+            },
+            None if index < slots.len() => index,
+            None => return Err(RuntimeError::new(
+                format!("Function \"{}\" expects {} arguments but got {}!",
+                        function.name, parameters.len(), arguments.len())).with_span(span)),
+        };
+
+        if slots[slot].is_some() {
+            return Err(RuntimeError::new(
+                format!("Parameter \"{}\" is given more than once!",
+                        parameters[slot].name)).with_span(argument.span));
+        }
+
+        slots[slot] = Some(value);
+    }
+
+    if let Some(slot) = slots.iter().position(Option::is_none) {
+        return Err(RuntimeError::new(
+            format!("Function \"{}\" is missing an argument for \"{}\"!",
+                    function.name, parameters[slot].name)).with_span(span));
+    }
+
+    Ok(slots.into_iter().flatten().collect())
+}
+
+pub fn eval_unary_operation(
+    operator: &UnaryOperator,
+    value: Value
+) -> Result<Value, RuntimeError> {
+    match (operator, &value) {
+        (UnaryOperator::Negation, Value::Int(num)) => match num.checked_neg() {
+            Some(num) => Ok(Value::Int(num)),
+            None => Err(RuntimeError::new("Integer overflow!")),
+        },
+        _ => Err(RuntimeError::new(
+            format!("Cannot apply \"{}\" to {}!",
+                    operator.symbol(), value.type_name()))),
+    }
+}
+
+/// Convert the value to `r#type`, whose array sizes live in
+/// `expressions`.
+///
+/// Every value converts to a type admitting it, arrays becoming slices
+/// of all their elements for slice types. Ints, bools, arrays, slices,
+/// maps and ranges convert to strings, bools to ints and ints to bools,
+/// and strings holding a number to ints.
+pub fn cast(
+    value: Value,
+    r#type: &Type,
+    expressions: &[Expression]
+) -> Result<Value, RuntimeError> {
+    let name = match r#type {
+        Type::Slice(_) if matches!(value, Value::Array(_)) && type_admits(r#type, &value) =>
+            return slice_value(&value, &Range {
+                start: None,
+                end: None,
+                inclusive: false,
+            }),
+        _ if type_admits(r#type, &value) => return Ok(value),
+        Type::Named(name) => name.as_str(),
+        Type::Unit => "()",
+        Type::Nullable(r#type) => return cast(value, r#type, expressions),
+        Type::Array { .. } |
+        Type::Slice(_) |
+        Type::Map { .. } => "",
+    };
+    let result = match (&value, name) {
+        (Value::Int(_), "string") |
+        (Value::Bool(_), "string") |
+        (Value::Array(_), "string") |
+        (Value::Slice { .. }, "string") |
+        (Value::Map(_), "string") |
+        (Value::Range(_), "string") |
+        (Value::Enum(_), "string") => Some(Value::String(value.to_string())),
+        (Value::Bool(bool), "int") => Some(Value::Int(*bool as isize)),
+        (Value::Int(num), "bool") => Some(Value::Bool(*num != 0)),
+        (Value::String(str), "int") => match str.parse() {
+            Ok(num) => Some(Value::Int(num)),
+            Err(_) => return Err(RuntimeError::new(
+                format!("Cannot convert \"{}\" to int!", str))),
+        },
+        _ => None,
+    };
+
+    match result {
+        Some(value) => Ok(value),
+        None => Err(RuntimeError::new(
+            format!("Cannot cast {} to {}!", value.type_name(), format_type(expressions, r#type)))),
+    }
+}
+
+pub fn eval_binary_operation(
+    operator: &BinaryOperator,
+    left: Value,
+    right: Value
+) -> Result<Value, RuntimeError> {
+    let result = match (operator, &left, &right) {
+        (BinaryOperator::Equal, _, _) => Some(Value::Bool(left == right)),
+        (BinaryOperator::NotEqual, _, _) => Some(Value::Bool(left != right)),
+        (BinaryOperator::Addition, Value::String(str_1), Value::String(str_2)) =>
+            Some(Value::String(format!("{}{}", str_1, str_2))),
+        (BinaryOperator::Division, Value::Int(_), Value::Int(0)) =>
+            return Err(RuntimeError::new("Division by zero!")),
+        (operator, Value::Int(num_1), Value::Int(num_2)) => {
+            let result = match operator {
+                BinaryOperator::Addition => num_1.checked_add(*num_2),
+                BinaryOperator::Subtraction => num_1.checked_sub(*num_2),
+                BinaryOperator::Multiplication => num_1.checked_mul(*num_2),
+                BinaryOperator::Division => num_1.checked_div(*num_2),
+                _ => None,
+            };
+
+            match result {
+                Some(num) => Some(Value::Int(num)),
+                None => return Err(RuntimeError::new("Integer overflow!")),
+            }
+        },
+        _ => None,
+    };
+
+    match result {
+        Some(value) => Ok(value),
+        None => Err(RuntimeError::new(
+            format!("Cannot apply \"{}\" to {} and {}!",
+                    operator.symbol(), left.type_name(), right.type_name()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::VirtualClock;
+    use crate::compile_str;
+    use super::*;
+
+    macro_rules! eval_program {
+        ($env:expr, $text:expr) => {{
+            compile_str($text).unwrap().eval($env)
+        }};
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut env = Env::new();
+        let overflow = "let a = 1;\nlet b = a * 9223372036854775807 * 2;";
+
+        assert_eq!(eval_program!(&mut env, "(1 + 2) * 3 - 8 / 4;"), Ok(Value::Int(7)));
+        assert_eq!(eval_program!(&mut env, "\"Alex\" + \" \" + \"Chen\";"),
+                   Ok(Value::String(String::from("Alex Chen"))));
+        assert_eq!(eval_program!(&mut env, "1 + 1 == 2;"), Ok(Value::Bool(true)));
+        assert_eq!(eval_program!(&mut env, "1 / 0;"),
+                   Err(RuntimeError::new("Division by zero!").with_span(Span::new(2, 3))));
+        assert_eq!(eval_program!(&mut env, overflow).unwrap_err().render("main.fang", overflow),
+                   "main.fang:2:33: runtime error: Integer overflow!\n");
+        assert_eq!(eval_program!(&mut env, "-(2 + 3) * -2;"), Ok(Value::Int(10)));
+        assert_eq!(eval_program!(&mut env, "-\"text\";"),
+                   Err(RuntimeError::new("Cannot apply \"-\" to string!")));
+        assert_eq!(eval_program!(&mut env, "let a; let b; a = b = 47; a + b;"), Ok(Value::Int(94)));
+    }
+
+    #[test]
+    fn named_arguments() {
+        let mut env = Env::with_builtins();
+        let twice = "span(1, 2, from = 3);";
+        let unknown = "span(1, size = 3);";
+
+        assert_eq!(eval_program!(&mut env, "
+            func span(from, to, step) {
+                return (to - from) / step;
+            }
+            span(step = 2, to = 10, from = 4) + span(0, step = 5, to = 20);
+        "), Ok(Value::Int(7)));
+
+        assert_eq!(eval_program!(&mut env, twice),
+                   Err(RuntimeError::new("Parameter \"from\" is given more than once!")
+                       .with_span(Span::new(11, 15))));
+        assert_eq!(eval_program!(&mut env, unknown),
+                   Err(RuntimeError::new("Function \"span\" has no parameter \"size\"!")
+                       .with_span(Span::new(8, 12))));
+        assert_eq!(eval_program!(&mut env, "span(1, step = 3);"),
+                   Err(RuntimeError::new("Function \"span\" is missing an argument for \"to\"!")
+                       .with_span(Span::new(0, 4))));
+        assert_eq!(eval_program!(&mut env, "print(text = 1);"),
+                   Err(RuntimeError::new("Function \"print\" takes no named arguments!")
+                       .with_span(Span::new(0, 5))));
+    }
+
+    #[test]
+    fn overloads() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            func area(side) {
+                return side * side;
+            }
+            func area(width, height) {
+                return width * height;
+            }
+            func area(name: string) {
+                return name + \" area\";
+            }
+            area(3) + area(2, height = 5);
+        "), Ok(Value::Int(19)));
+        assert_eq!(eval_program!(&mut env, "area(\"Square\");"),
+                   Ok(Value::String(String::from("Square area"))));
+        assert_eq!(eval_program!(&mut env, "area(1, 2, 3);"),
+                   Err(RuntimeError::new("No overload of \"area\" takes arguments (int, int, int)!")
+                       .with_span(Span::new(0, 4))));
+        assert_eq!(eval_program!(&mut env, "
+            func scale(value: int, factor) {
+                return value * factor;
+            }
+            func scale(value, factor: int) {
+                return value + factor;
+            }
+            scale(1, 2);
+        "), Err(RuntimeError::new("Call to \"scale\" is ambiguous between scale(value: int, factor) and scale(value, factor: int)!")
+                .with_span(Span::new(209, 214))));
+    }
+
+    #[test]
+    fn nil() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            func describe(value: int?) -> string {
+                return \"maybe int\";
+            }
+            func describe(value) -> string {
+                return \"other\";
+            }
+            describe(nil) + \", \" + describe(1) + \", \" + describe(\"a\");
+        "), Ok(Value::String(String::from("maybe int, maybe int, other"))));
+        assert_eq!(eval_program!(&mut env, "let a: int? = nil; a == nil;"), Ok(Value::Bool(true)));
+        assert_eq!(eval_program!(&mut env, "nil + 1;"),
+                   Err(RuntimeError::new("Cannot apply \"+\" to nil and int!").with_span(Span::new(4, 5))));
+        assert_eq!(eval_program!(&mut env, "a.double();"),
+                   Err(RuntimeError::new("Cannot call method \"double\" on nil!")
+                       .with_span(Span::new(2, 8))));
+    }
+
+    #[test]
+    fn results() {
+        let mut env = Env::with_builtins();
+
+        assert_eq!(eval_program!(&mut env, "
+            func half(value) {
+                return err(\"odd\");
+            }
+            func quarter(value) {
+                let result = ok(0);
+
+                {
+                    result = half(value)?;
+                }
+                return ok(result);
+            }
+            quarter(3);
+        "), Ok(Value::Err(Box::new(Value::String(String::from("odd"))))));
+        assert_eq!(eval_program!(&mut env, "ok(2)? * 3;"), Ok(Value::Int(6)));
+        assert_eq!(eval_program!(&mut env, "err(1)?; 5;"), Ok(Value::Err(Box::new(Value::Int(1)))));
+        assert_eq!(eval_program!(&mut env, "(1 == 1)?;"),
+                   Err(RuntimeError::new("Cannot apply \"?\" to bool!")
+                       .with_span(Span::new(8, 9))));
+    }
+
+    #[test]
+    fn constants() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            func area() {
+                return SIDE * SIDE;
+            }
+            const SIDE = HALF * 2;
+            let value = area();
+            const HALF = 3;
+            value + HALF;
+        "), Ok(Value::Int(39)));
+    }
+
+    #[test]
+    fn casts() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "\"4\" as int * 2 as string as int;"), Ok(Value::Int(8)));
+        assert_eq!(eval_program!(&mut env, "-3 as string + (1 == 1) as int as string;"),
+                   Ok(Value::String(String::from("-31"))));
+        assert_eq!(eval_program!(&mut env, "(2 as bool) as string;"),
+                   Ok(Value::String(String::from("true"))));
+        assert_eq!(eval_program!(&mut env, "\"x1\" as int;"),
+                   Err(RuntimeError::new("Cannot convert \"x1\" to int!")
+                       .with_span(Span::new(5, 7))));
+        assert_eq!(eval_program!(&mut env, "\"true\" as bool;"),
+                   Err(RuntimeError::new("Cannot cast string to bool!")
+                       .with_span(Span::new(7, 9))));
+
+        env = Env::with_builtins();
+        assert_eq!(eval_program!(&mut env, "int(\"4\") * 2 + int(string(1) + \"0\");"), Ok(Value::Int(18)));
+        assert_eq!(eval_program!(&mut env, "int(\"x1\");"),
+                   Err(RuntimeError::new("Cannot convert \"x1\" to int!")
+                       .with_span(Span::new(0, 3))));
+    }
+
+    #[test]
+    fn prelude() {
+        let env = Env::with_builtins();
+
+        for name in PRELUDE {
+            assert!(matches!(env.get(name), Some(Value::Function(_))), "{}", name);
+        }
+    }
+
+    #[test]
+    fn args() {
+        let mut env = Env::with_builtins();
+
+        assert_eq!(eval_program!(&mut env, "len(args());"), Ok(Value::Int(0)));
+
+        env.set_args(vec![String::from("a"), String::from("b c")]);
+        assert_eq!(eval_program!(&mut env, "let all = args(); all[1] + \"/\" + len(all) as string;"),
+                   Ok(Value::String(String::from("b c/2"))));
+    }
+
+    #[test]
+    fn environment_variables() {
+        let mut env = Env::with_builtins();
+        let home = std::env::var("HOME").map_or(Value::Nil, Value::String);
+
+        assert_eq!(eval_program!(&mut env, "env(\"HOME\");"), Ok(home));
+
+        env.set_variables(HashMap::from([(String::from("LANG"), String::from("C"))]), false);
+        assert_eq!(eval_program!(&mut env, "
+            set_env(\"FANG_TEST_VARIABLE\", \"on\");
+            env(\"FANG_TEST_VARIABLE\") + env(\"LANG\");
+        "), Ok(Value::String(String::from("onC"))));
+        assert_eq!(std::env::var_os("FANG_TEST_VARIABLE"), None);
+        assert_eq!(eval_program!(&mut env, "env(\"HOME\");"), Ok(Value::Nil));
+        assert!(eval_program!(&mut env, "set_env(\"A=B\", \"on\");").is_err());
+
+        env.deny("env");
+        assert_eq!(eval_program!(&mut env, "env(\"FANG_TEST_VARIABLE\");"),
+                   Err(RuntimeError::new("\"env\" is denied in this environment!").with_span(Span::new(0, 3))));
+    }
+
+    #[test]
+    fn clocks() {
+        let mut env = Env::with_builtins();
+
+        assert!(matches!(eval_program!(&mut env, "let start = now_millis(); sleep_millis(5); now_millis() - start;"),
+                         Ok(Value::Int(elapsed)) if elapsed >= 5));
+        assert!(eval_program!(&mut env, "sleep_millis(-1);").is_err());
+
+        env.set_clock(VirtualClock::new(1000));
+        assert_eq!(eval_program!(&mut env, "sleep_millis(60000); now_millis();"), Ok(Value::Int(61000)));
+    }
+
+    #[test]
+    fn random_numbers() {
+        let mut env = Env::with_builtins();
+        let draw = "let rolls = [0, 0, 0]; for i in 0..3 { rolls[i] = rand_int(1, 7); } rolls;";
+
+        env.set_rng(Rng::new(7));
+        let first = eval_program!(&mut env, draw);
+
+        env.set_rng(Rng::new(7));
+        assert_eq!(eval_program!(&mut env, draw), first);
+        assert_eq!(eval_program!(&mut env, "rand_int(3, 3);"),
+                   Err(RuntimeError::new("Cannot draw a number from the empty range 3..3!").with_span(Span::new(0, 8))));
+    }
+
+    #[test]
+    fn file_builtins() {
+        let mut env = Env::with_builtins();
+        let path = std::env::temp_dir().join(format!("fang-file-builtins-{}.txt", std::process::id()));
+
+        env.set("path", Value::String(path.to_string_lossy().into_owned()));
+        assert_eq!(eval_program!(&mut env, "
+            write_file(path, \"one, \");
+            append_file(path, \"two\");
+            read_file(path);
+        "), Ok(Value::String(String::from("one, two"))));
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(eval_program!(&mut env, "read_file(path);"),
+                         Err(RuntimeError { message, .. }) if message.starts_with("Failed to read")));
+        assert!(eval_program!(&mut env, "write_file(path, 1);").is_err());
+    }
+
+    #[test]
+    fn arrays() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            const SIZE = 2;
+            let grid: [[int; SIZE]; SIZE + 1];
+            let row = grid[2];
+
+            grid[2][1] = 7;
+            grid[0] = [1, 2];
+            grid as string + row as string;
+        "), Ok(Value::String(String::from("[[1, 2], [0, 0], [0, 7]][0, 0]"))));
+        assert_eq!(eval_program!(&mut env, "let names: [string?; 1]; let flags: [bool; 0]; (names[0] == nil) == (flags == []);"),
+                   Ok(Value::Bool(true)));
+        assert_eq!(eval_program!(&mut env, "let a = [1, 2]; let i = 2; a[i];"),
+                   Err(RuntimeError::new("Index 2 is out of bounds for an array of length 2!")
+                       .with_span(Span::new(28, 29))));
+        assert_eq!(eval_program!(&mut env, "let a: [int; 3] = [1, 2];"),
+                   Err(RuntimeError::new("Expected an array of length 3 but got 2!")
+                       .with_span(Span::new(4, 5))));
+        assert_eq!(eval_program!(&mut env, "func f(a: [int; 1]) { return a[1]; } func f(a: string) { return 2; } f([1, 2]) + f(\"x\");"),
+                   Ok(Value::Int(4)));
+    }
+
+    #[test]
+    fn slices() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            func sum(xs: [int]) -> int {
+                return xs[0] + xs[1];
+            }
+
+            let a = [1, 2, 3, 4, 5];
+            let s = a[1..4][1..];
+
+            s[0] = 10;
+            sum(s) + sum(a) + sum(a[3..]);
+        "), Ok(Value::Int(26)));
+        assert_eq!(eval_program!(&mut env, "let a = [1, 2, 3]; (a[..] as string + (a as [int])[..1] as string) == \"[1, 2, 3][1]\";"),
+                   Ok(Value::Bool(true)));
+        assert_eq!(eval_program!(&mut env, "let a = [1, 2, 3][1..]; let n = 3; a[..n];"),
+                   Err(RuntimeError::new("Range ..3 is out of bounds for a slice of length 2!")
+                       .with_span(Span::new(36, 37))));
+        assert_eq!(eval_program!(&mut env, "let a = [1, 2, 3]; a[1..2] = [4];"),
+                   Err(RuntimeError::new("Cannot assign to a slice!")
+                       .with_span(Span::new(20, 21))));
+    }
+
+    #[test]
+    fn ranges() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            let a = [1, 2, 3, 4];
+            let middle = 1..=2;
+
+            a[middle] as string + (..3) as string + (middle == 1..=2) as string + a[1..][..=0] as string;
+        "), Ok(Value::String(String::from("[2, 3]..3true[2]"))));
+        assert_eq!(eval_program!(&mut env, "let n = 2; [1, 2][0..=n];"),
+                   Err(RuntimeError::new("Range 0..=2 is out of bounds for an array of length 2!")
+                       .with_span(Span::new(17, 18))));
+        assert_eq!(eval_program!(&mut env, "0..\"a\";"),
+                   Err(RuntimeError::new("Range bounds must be ints, not string!")));
+    }
+
+    #[test]
+    fn maps() {
+        let mut env = Env::with_builtins();
+
+        assert_eq!(eval_program!(&mut env, "
+            func oldest(ages: map[string, int]) -> string {
+                return keys(ages)[0];
+            }
+
+            let ages = {\"alex\": 30, \"kim\": 25, \"alex\": 31};
+            let copy = ages;
+
+            ages[\"lee\"] = 40;
+            ages[\"kim\"] = ages[\"kim\"] + 1;
+            oldest(ages) + \" \" + ages as string + \" \" + values(copy) as string + \" \" + len(ages) as string;
+        "), Ok(Value::String(String::from("alex {alex: 31, kim: 26, lee: 40} [31, 25] 3"))));
+        assert_eq!(eval_program!(&mut env, "let grid: map[int, bool] = {}; grid[1] = 1 == 1; grid == {1: 2 == 2};"),
+                   Ok(Value::Bool(true)));
+        assert_eq!(eval_program!(&mut env, "let a = {\"a\": 1}; a[\"b\"];"),
+                   Err(RuntimeError::new("Key \"b\" is not in the map!")
+                       .with_span(Span::new(19, 20))));
+        assert_eq!(eval_program!(&mut env, "let a = {[1]: 1};"),
+                   Err(RuntimeError::new("Cannot use array as a map key!")
+                       .with_span(Span::new(4, 5))));
+    }
+
+    #[test]
+    fn loops() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            func first_square_above(limit) -> int {
+                for n in 1.. {
+                    let found = {1 == 2: [], 1 == 1: [n * n]}[n * n == limit + 1];
+
+                    for square in found {
+                        return square;
+                    }
+                }
+            }
+
+            let text = \"\";
+            let total = 0;
+
+            for word in [\"a\", \"b\"][1..] { text = text + word; }
+            for key in {\"c\": 1, \"d\": 2} { text = text + key; }
+            for char in \"ef\" { text = text + char; }
+            for i in 1..=4 { total = total + i; }
+            for i in 5..5 { total = 0; }
+            text + \" \" + total as string + \" \" + first_square_above(15) as string;
+        "), Ok(Value::String(String::from("bcdef 10 16"))));
+        assert_eq!(eval_program!(&mut env, "for i in ..3 {}"),
+                   Err(RuntimeError::new("Cannot iterate over range ..3 without a start!")
+                       .with_span(Span::new(4, 5))));
+        assert_eq!(eval_program!(&mut env, "for i in 3 {}"),
+                   Err(RuntimeError::new("Cannot iterate over int!")
+                       .with_span(Span::new(4, 5))));
+    }
+
+    #[test]
+    fn enums() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            enum Shape {
+                Circle(int),
+                Rect(int, int),
+                Empty,
+            }
+
+            impl Shape {
+                func area(self) -> int {
+                    return match self {
+                        Circle(r) => 3 * r * r,
+                        Rect(w, 1) => w,
+                        Rect(w, h) => w * h,
+                        Empty => 0,
+                    };
+                }
+            }
+
+            let shapes = [Circle(2), Rect(5, 1), Rect(2, 3), Empty];
+            let total = 0;
+
+            for shape in shapes { total = total + shape.area(); }
+            total as string + \" \" + shapes as string + \" \" + (Rect(1, 2) == Rect(1, 2)) as string;
+        "), Ok(Value::String(String::from("23 [Circle(2), Rect(5, 1), Rect(2, 3), Empty] true"))));
+        assert_eq!(eval_program!(&mut env, "match \"b\" { \"a\" => 1, nil => 2, name => name + \"!\" };"),
+                   Ok(Value::String(String::from("b!"))));
+        assert_eq!(eval_program!(&mut env, "let x = \"b\"; match x { \"a\" => 1 };"),
+                   Err(RuntimeError::new("No arm of the match covers b!")
+                       .with_span(Span::new(13, 18))));
+        assert_eq!(eval_program!(&mut env, "enum E { A(int?) } A(\"x\");"),
+                   Err(RuntimeError::new("Field 1 of \"A\" expects int? but got string!")
+                       .with_span(Span::new(19, 20))));
+    }
+
+    #[test]
+    fn deep_values() {
+        let mut env = Env::with_builtins();
+
+        /* Comparing, formatting and dropping values nested far deeper
+         * than the stack would allow recursing through. */
+        assert_eq!(eval_program!(&mut env, "
+            enum List { Empty, Cons(int, List) }
+
+            let xs = [];
+            let ys = [];
+            let list = Empty;
+
+            for i in 0..100000 {
+                xs = [xs];
+                ys = [ys];
+                list = Cons(i, list);
+            }
+
+            (xs == ys) as string + \" \" + (list == list) as string + \" \" + len(xs as string) as string;
+        "), Ok(Value::String(String::from("true true 200002"))));
+    }
+
+    #[test]
+    fn destructuring() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            enum Pair { Pair(int, [int]) }
+
+            let Pair(a, [b, c]) = Pair(1, [2, 3]);
+            let [_, last] = [4, 5, 6][1..];
+            a + b + c + last;
+        "), Ok(Value::Int(12)));
+        assert_eq!(eval_program!(&mut env, "let xs = [1, 2, 3]; let [x, y] = xs;"),
+                   Err(RuntimeError::new("[1, 2, 3] doesn't match the pattern [x, y]!")
+                       .with_span(Span::new(24, 25))));
+    }
+
+    #[test]
+    fn methods() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            impl int {
+                func double(self) -> int {
+                    return self * 2;
+                }
+                func shifted(self, by) -> int {
+                    return self + by;
+                }
+            }
+            impl string {
+                func shout(self) -> string {
+                    return self + \"!\";
+                }
+            }
+            -3.double() + 40.shifted(by = 1).double();
+        "), Ok(Value::Int(76)));
+        assert_eq!(eval_program!(&mut env, "\"hey\".shout();"),
+                   Ok(Value::String(String::from("hey!"))));
+        assert_eq!(eval_program!(&mut env, "\"hey\".double();"),
+                   Err(RuntimeError::new("Type \"string\" has no method \"double\"!")
+                       .with_span(Span::new(6, 12))));
+
+        assert_eq!(eval_program!(&mut env, "
+            trait Describe {
+                func describe(self) -> string;
+            }
+            impl Describe for int {
+                func describe(self) -> string {
+                    return \"int\";
+                }
+            }
+            impl Describe for string {
+                func describe(self) -> string {
+                    return self.shout();
+                }
+            }
+            7.describe() + \"hey\".describe();
+        "), Ok(Value::String(String::from("inthey!"))));
+    }
+
+    #[test]
+    fn implicit_returns() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            func square(x: int) -> int { x * x }
+            func describe(x) { \"x is \" + x as string }
+            describe(square(3));
+        "), Ok(Value::String(String::from("x is 9"))));
+        assert_eq!(eval_program!(&mut env, "func name() -> string { 1 } name();"),
+                   Err(RuntimeError::new("Function \"name\" should return string but returned int!")
+                       .with_span(Span::new(28, 32))));
+        assert_eq!(eval_program!(&mut env, "func nothing() -> int? { return nil; } nothing();"),
+                   Ok(Value::Nil));
+    }
+
+    #[test]
+    fn operator_methods() {
+        let mut env = Env::new();
+        let text = "
+            enum Vec2 { Vec2(int, int) }
+
+            trait Add {
+                func add(self, other) -> Vec2;
+            }
+
+            impl Add for Vec2 {
+                func add(self, other) -> Vec2 {
+                    let Vec2(a, b) = self;
+                    let Vec2(c, d) = other;
+
+                    Vec2(a + c, b + d)
+                }
+            }
+
+            impl Vec2 {
+                func mul(self, by: int) -> Vec2 {
+                    let Vec2(a, b) = self;
+
+                    Vec2(a * by, b * by)
+                }
+            }
+        ";
+
+        assert_eq!(eval_program!(&mut env, &format!("{} ((Vec2(1, 2) + Vec2(3, 4)) * 2) as string;", text)),
+                   Ok(Value::String(String::from("Vec2(8, 12)"))));
+        assert_eq!(eval_program!(&mut env, &format!("{} Vec2(1, 2) == Vec2(1, 2);", text)), Ok(Value::Bool(true)));
+        assert_eq!(eval_program!(&mut env, "enum E { A } A - A;"),
+                   Err(RuntimeError::new("Type \"E\" has no method \"sub\" for \"-\"!")
+                       .with_span(Span::new(15, 16))));
+    }
+
+    #[test]
+    fn unit() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            func nothing() {}
+            func log(x) -> () { x; }
+            let a: () = nothing();
+            a == log(1);
+        "), Ok(Value::Bool(true)));
+        assert_eq!(eval_program!(&mut env, "func one() -> () { 1 } one();"),
+                   Err(RuntimeError::new("Function \"one\" should return () but returned int!")
+                       .with_span(Span::new(23, 26))));
+    }
+
+    #[test]
+    fn functions() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            let total = add_num(20, 27);
+            func add_num(a: int, b: int) -> int {
+                return a + b;
+            }
+            total;
+        "), Ok(Value::Int(47)));
+        assert_eq!(eval_program!(&mut env, "add_num(1);"),
+                   Err(RuntimeError::new("Function \"add_num\" expects 2 arguments but got 1!")
+                       .with_span(Span::new(0, 7))));
+        assert_eq!(eval_program!(&mut env, "let s = \"s\"; add_num(s, 1);"),
+                   Err(RuntimeError::new("Parameter \"a\" of \"add_num\" expects int but got string!")
+                       .with_span(Span::new(13, 20))));
+        assert_eq!(eval_program!(&mut env, "let n = nil; add_num(1, n);"),
+                   Err(RuntimeError::new("Parameter \"b\" of \"add_num\" expects int but got nil!")
+                       .with_span(Span::new(13, 20))));
+
+        let err = eval_program!(&mut env, "func loop_forever() { loop_forever(); } loop_forever();").unwrap_err();
+
+        assert_eq!(err.message, "Maximum call depth exceeded!");
+        assert_eq!(err.trace.len(), MAX_CALL_DEPTH);
+    }
+
+    #[test]
+    fn functions_share_the_arena() {
+        let program = compile_str("func one() -> int { return 1; }").unwrap();
+        let mut env = Env::new();
+
+        program.eval(&mut env).unwrap();
+
+        /* The function runs against the arena of the program, not a copy. */
+        match env.get("one") {
+            Some(Value::Function(function)) => match &function.body {
+                FunctionBody::Defined {
+                    expressions,
+                    ..
+                } => assert!(Arc::ptr_eq(expressions, &program.program().expressions)),
+                _ => panic!("\"one\" is defined in Fang"),
+            },
+            _ => panic!("\"one\" is a function"),
+        }
+    }
+
+    #[test]
+    fn tail_calls() {
+        let mut env = Env::new();
+
+        assert_eq!(eval_program!(&mut env, "
+            func sum(n, total) {
+                match n { 0 => total, _ => sum(n - 1, total + n) }
+            }
+            func odd(n) { return match n { 0 => 0, _ => even(n - 1) }; }
+            func even(n) { match n { 0 => 1, _ => odd(n - 1) } }
+            sum(10000, 0) + even(5001);
+        "), Ok(Value::Int(50005000)));
+
+        /* The value must be of the return type of every function along
+         * the tail calls. */
+        assert_eq!(eval_program!(&mut env, "func f() -> string { g() } func g() { 1 } f();"),
+                   Err(RuntimeError::new("Function \"f\" should return string but returned int!")
+                       .with_span(Span::new(42, 43))));
+    }
+
+    #[test]
+    fn host_environment() {
+        let mut env = Env::new();
+
+        env.set("input", Value::Int(40));
+        env.define_native("double", |arguments| match arguments {
+            [Value::Int(num)] => Ok(Value::Int(num * 2)),
+            _ => Err(RuntimeError::new("Expected an int!")),
+        });
+
+        assert_eq!(eval_program!(&mut env, "let output = double(input) + 7;"), Ok(Value::Unit));
+        assert_eq!(env.get("output"), Some(&Value::Int(87)));
+        assert_eq!(eval_program!(&mut env, "let value; { value = 3; }"), Ok(Value::Unit));
+        assert_eq!(env.get("value"), Some(&Value::Int(3)));
+        assert_eq!(eval_program!(&mut env, "{ let hidden = 1; } hidden;"),
+                   Err(RuntimeError::new("Undefined variable \"hidden\"!")
+                       .with_span(Span::new(20, 26))));
+        assert_eq!(eval_program!(&mut env, "{ let hidden = 1; } hidden = 2;"),
+                   Err(RuntimeError::new("Undefined variable \"hidden\"!")
+                       .with_span(Span::new(20, 26))));
+        assert_eq!(eval_program!(&mut env, "let min = -9223372036854775807 - 1; -min;"),
+                   Err(RuntimeError::new("Integer overflow!")
+                       .with_span(Span::new(37, 40))));
+    }
+
+    #[test]
+    fn hooks() {
+
+        /// Hooks logging the calls and aborting after a few statements.
+        struct Tracer {
+            log: Vec<String>,
+            budget: usize,
+        }
+
+        impl Hooks for Tracer {
+            fn before_statement(&mut self, _: &State) -> Result<(), RuntimeError> {
+                if self.budget == 0 {
+                    return Err(RuntimeError::new("Out of time!"));
+                }
+
+                self.budget -= 1;
+
+                Ok(())
+            }
+
+            fn enter_function(&mut self, name: &str, _: Span) -> Result<(), RuntimeError> {
+                self.log.push(format!("enter {}", name));
+
+                Ok(())
+            }
+
+            fn exit_function(&mut self, name: &str) {
+                self.log.push(format!("exit {}", name));
+            }
+
+            fn allocate(&mut self, value: &Value) -> Result<(), RuntimeError> {
+                self.log.push(format!("allocate {}", value));
+
+                Ok(())
+            }
+        }
+
+        let program = compile_str("
+            func pair(value) {
+                return [value, value];
+            }
+            func twice(value) {
+                return pair(value * 2);
+            }
+            twice(1);
+            twice(2);
+        ").unwrap();
+        let mut tracer = Tracer {
+            log: Vec::new(),
+            budget: 4,
+        };
+
+        assert_eq!(program.eval_with_hooks(&mut Env::new(), &mut tracer),
+                   Err(RuntimeError {
+                       message: String::from("Out of time!"),
+                       span: Some(Span::new(140, 144)),
+                       trace: vec![TraceFrame {
+                           function: String::from("twice"),
+                           span: Span::new(205, 210),
+                       }],
+                   }));
+        assert_eq!(tracer.log, [
+            "enter twice", "exit twice", "enter pair", "allocate [2, 2]", "exit pair",
+            "enter twice", "exit twice",
+        ]);
+    }
+
+    #[test]
+    fn assert_and_panic() {
+        let mut env = Env::with_builtins();
+        let text = "func check(value) {\n    assert(value == 1, \"value is not 1\");\n}\ncheck(1);\ncheck(2);";
+
+        assert_eq!(eval_program!(&mut env, "assert(1 == 1);"), Ok(Value::Unit));
+        assert_eq!(eval_program!(&mut env, "panic(\"stop\");"),
+                   Err(RuntimeError::new("Panicked: stop").with_span(Span::new(0, 5))));
+        assert_eq!(eval_program!(&mut env, text).unwrap_err().render("main.fang", text), "\
+main.fang:2:5: runtime error: Assertion failed: value is not 1
+    in \"check\" called at main.fang:5:1
+");
+    }
+}
@@ -1,32 +1,88 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, Read};
 use std::vec::Vec;
 use std::fmt::Debug;
+use serde::{Serialize, Deserialize};
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::parser::Limits;
+use crate::span::Span;
 
 /// Tokens scanned out by the lexer.
-#[derive(Clone)]
-pub enum Token {
+///
+/// Identifiers and strings borrow their text from the scanned source
+/// code rather than copying it.
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
+pub enum Token<'src> {
 
     /// Keyword `let`.
     Let,
 
+    /// Keyword `var`, defining a variable just like `let`.
+    Variable,
+
+    /// Keyword `const`.
+    Constant,
+
     /// Keyword `func`.
     Function,
 
     /// Keyword `return`.
     Return,
 
+    /// Keyword `impl`.
+    Implementation,
+
+    /// Keyword `trait`.
+    Trait,
+
+    /// Keyword `for`.
+    For,
+
+    /// Keyword `in`.
+    In,
+
+    /// Keyword `enum`.
+    Enum,
+
+    /// Keyword `match`.
+    Match,
+
+    /// Keyword `macro`.
+    Macro,
+
+    /// Keyword `nil`, the literal of a missing value.
+    Nil,
+
+    /// Keyword `as`.
+    As,
+
     /// Identifiers like `var_1`, or `add_num`.
-    Identifier(String),
+    Identifier(#[serde(borrow)] Cow<'src, str>),
 
-    /// Numeric literals like `0`, and `47`.
+    /// Numeric literals like `0`, and `47`, which are ints and so
+    /// can't be larger than `isize::MAX`.
     Number(isize),
 
     /// String literals enclosed by double quote.
     /// For example, `"Hello"` and `"Alex Chen"`.
-    String(String),
+    String(#[serde(borrow)] Cow<'src, str>),
 
     /// Symbol `,`.
     Comma,
 
+    /// Symbol `.`.
+    Dot,
+
+    /// Symbol `..`.
+    DotDot,
+
+    /// Symbol `..=`.
+    DotDotEqual,
+
+    /// Symbol `?`.
+    QuestionMark,
+
     /// Symbol `=`.
     Assign,
 
@@ -36,6 +92,12 @@ pub enum Token {
     /// Symbol `)`.
     RightRoundBracket,
 
+    /// Symbol `[`.
+    LeftSquareBracket,
+
+    /// Symbol `]`.
+    RightSquareBracket,
+
     /// Symbol `{`.
     LeftCurlyBracket,
 
@@ -48,12 +110,21 @@ pub enum Token {
     /// Symbol `->`.
     ReturnTypeIndicator,
 
+    /// Symbol `=>`.
+    MatchArmIndicator,
+
     /// Symbol `==`.
     Equal,
 
     /// Symbol `!=`.
     NotEqual,
 
+    /// Symbol `#!`, opening a module-level attribute.
+    InnerAttribute,
+
+    /// Symbol `#`, starting a directive like `#if`.
+    Hash,
+
     /// Symbol `+`.
     Add,
 
@@ -73,22 +144,161 @@ pub enum Token {
     EndOfProgram,
 }
 
-fn escape_string(str: &String) -> String {
-    let str_buf = str.as_bytes();
-    let str_len = str_buf.len();
-    let mut line = String::new();
+/// Kinds of tokens, telling tokens apart regardless of their payload.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TokenKind {
+    Let,
+    Variable,
+    Constant,
+    Function,
+    Return,
+    Implementation,
+    Trait,
+    For,
+    In,
+    Enum,
+    Match,
+    Macro,
+    Nil,
+    As,
+    Identifier,
+    Number,
+    String,
+    Comma,
+    Dot,
+    DotDot,
+    DotDotEqual,
+    QuestionMark,
+    Assign,
+    LeftRoundBracket,
+    RightRoundBracket,
+    LeftSquareBracket,
+    RightSquareBracket,
+    LeftCurlyBracket,
+    RightCurlyBracket,
+    VariableTypeIndicator,
+    ReturnTypeIndicator,
+    MatchArmIndicator,
+    Equal,
+    NotEqual,
+    InnerAttribute,
+    Hash,
+    Add,
+    Minus,
+    Times,
+    Divide,
+    EndOfStatement,
+    EndOfProgram,
+}
+
+impl<'src> Token<'src> {
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::Let => TokenKind::Let,
+            Token::Variable => TokenKind::Variable,
+            Token::Constant => TokenKind::Constant,
+            Token::Function => TokenKind::Function,
+            Token::Return => TokenKind::Return,
+            Token::Implementation => TokenKind::Implementation,
+            Token::Trait => TokenKind::Trait,
+            Token::For => TokenKind::For,
+            Token::In => TokenKind::In,
+            Token::Enum => TokenKind::Enum,
+            Token::Match => TokenKind::Match,
+            Token::Macro => TokenKind::Macro,
+            Token::Nil => TokenKind::Nil,
+            Token::As => TokenKind::As,
+            Token::Identifier(_) => TokenKind::Identifier,
+            Token::Number(_) => TokenKind::Number,
+            Token::String(_) => TokenKind::String,
+            Token::Comma => TokenKind::Comma,
+            Token::Dot => TokenKind::Dot,
+            Token::DotDot => TokenKind::DotDot,
+            Token::DotDotEqual => TokenKind::DotDotEqual,
+            Token::QuestionMark => TokenKind::QuestionMark,
+            Token::Assign => TokenKind::Assign,
+            Token::LeftRoundBracket => TokenKind::LeftRoundBracket,
+            Token::RightRoundBracket => TokenKind::RightRoundBracket,
+            Token::LeftSquareBracket => TokenKind::LeftSquareBracket,
+            Token::RightSquareBracket => TokenKind::RightSquareBracket,
+            Token::LeftCurlyBracket => TokenKind::LeftCurlyBracket,
+            Token::RightCurlyBracket => TokenKind::RightCurlyBracket,
+            Token::VariableTypeIndicator => TokenKind::VariableTypeIndicator,
+            Token::ReturnTypeIndicator => TokenKind::ReturnTypeIndicator,
+            Token::MatchArmIndicator => TokenKind::MatchArmIndicator,
+            Token::Equal => TokenKind::Equal,
+            Token::NotEqual => TokenKind::NotEqual,
+            Token::InnerAttribute => TokenKind::InnerAttribute,
+            Token::Hash => TokenKind::Hash,
+            Token::Add => TokenKind::Add,
+            Token::Minus => TokenKind::Minus,
+            Token::Times => TokenKind::Times,
+            Token::Divide => TokenKind::Divide,
+            Token::EndOfStatement => TokenKind::EndOfStatement,
+            Token::EndOfProgram => TokenKind::EndOfProgram,
+        }
+    }
 
-    for index in 0..str_len {
-        let byte = str_buf[index];
+    /// Copy of the token owning its text, free of the source code.
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::Identifier(text) => Token::Identifier(Cow::Owned(text.into_owned())),
+            Token::String(str) => Token::String(Cow::Owned(str.into_owned())),
+            Token::Let => Token::Let,
+            Token::Variable => Token::Variable,
+            Token::Constant => Token::Constant,
+            Token::Function => Token::Function,
+            Token::Return => Token::Return,
+            Token::Implementation => Token::Implementation,
+            Token::Trait => Token::Trait,
+            Token::For => Token::For,
+            Token::In => Token::In,
+            Token::Enum => Token::Enum,
+            Token::Match => Token::Match,
+            Token::Macro => Token::Macro,
+            Token::Nil => Token::Nil,
+            Token::As => Token::As,
+            Token::Number(num) => Token::Number(num),
+            Token::Comma => Token::Comma,
+            Token::Dot => Token::Dot,
+            Token::DotDot => Token::DotDot,
+            Token::DotDotEqual => Token::DotDotEqual,
+            Token::QuestionMark => Token::QuestionMark,
+            Token::Assign => Token::Assign,
+            Token::LeftRoundBracket => Token::LeftRoundBracket,
+            Token::RightRoundBracket => Token::RightRoundBracket,
+            Token::LeftSquareBracket => Token::LeftSquareBracket,
+            Token::RightSquareBracket => Token::RightSquareBracket,
+            Token::LeftCurlyBracket => Token::LeftCurlyBracket,
+            Token::RightCurlyBracket => Token::RightCurlyBracket,
+            Token::VariableTypeIndicator => Token::VariableTypeIndicator,
+            Token::ReturnTypeIndicator => Token::ReturnTypeIndicator,
+            Token::MatchArmIndicator => Token::MatchArmIndicator,
+            Token::Equal => Token::Equal,
+            Token::NotEqual => Token::NotEqual,
+            Token::InnerAttribute => Token::InnerAttribute,
+            Token::Hash => Token::Hash,
+            Token::Add => Token::Add,
+            Token::Minus => Token::Minus,
+            Token::Times => Token::Times,
+            Token::Divide => Token::Divide,
+            Token::EndOfStatement => Token::EndOfStatement,
+            Token::EndOfProgram => Token::EndOfProgram,
+        }
+    }
+}
+
+fn escape_string(str: &str) -> String {
+    let mut line = String::new();
 
+    for byte in str.bytes() {
         if byte == b'\r' {
             line.push_str("\\r");
         } else if byte == b'\n' {
             line.push_str("\\n");
         } else if byte == b'"' {
             line.push_str("\\\"");
-        } else if byte >= 32 &&
-                  byte <= 126 {
+        } else if (32..=126).contains(&byte) {
             line.push(byte as char);
         } else {
             line.push_str(&format!("\\x{:02X}", byte))
@@ -98,25 +308,45 @@ fn escape_string(str: &String) -> String {
     line
 }
 
-impl Debug for Token {
+impl Debug for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Token::Let => write!(f, "LET"),
+            Token::Variable => write!(f, "VARIABLE"),
+            Token::Constant => write!(f, "CONSTANT"),
             Token::Function => write!(f, "FUNCTION"),
             Token::Return => write!(f, "RETURN"),
+            Token::Implementation => write!(f, "IMPLEMENTATION"),
+            Token::Trait => write!(f, "TRAIT"),
+            Token::For => write!(f, "FOR"),
+            Token::In => write!(f, "IN"),
+            Token::Enum => write!(f, "ENUM"),
+            Token::Match => write!(f, "MATCH"),
+            Token::Macro => write!(f, "MACRO"),
+            Token::Nil => write!(f, "NIL"),
+            Token::As => write!(f, "AS"),
             Token::Identifier(text) => write!(f, "IDENTIFIER \"{}\"", text),
             Token::Number(num) => write!(f, "NUMBER {}", num),
             Token::String(str) => write!(f, "STRING \"{}\"", escape_string(str)),
             Token::Comma => write!(f, "COMMA"),
+            Token::Dot => write!(f, "DOT"),
+            Token::DotDot => write!(f, "DOT_DOT"),
+            Token::DotDotEqual => write!(f, "DOT_DOT_EQUAL"),
+            Token::QuestionMark => write!(f, "QUESTION_MARK"),
             Token::Assign => write!(f, "ASSIGN"),
             Token::LeftRoundBracket => write!(f, "("),
             Token::RightRoundBracket => write!(f, ")"),
+            Token::LeftSquareBracket => write!(f, "["),
+            Token::RightSquareBracket => write!(f, "]"),
             Token::LeftCurlyBracket => write!(f, "{{"),
             Token::RightCurlyBracket => write!(f, "}}"),
             Token::VariableTypeIndicator => write!(f, "VARIABLE TYPE INDICATOR"),
             Token::ReturnTypeIndicator => write!(f, "RETURN TYPE INDICATOR"),
+            Token::MatchArmIndicator => write!(f, "MATCH ARM INDICATOR"),
             Token::Equal => write!(f, "EQUAL"),
             Token::NotEqual => write!(f, "NOT EQUAL"),
+            Token::InnerAttribute => write!(f, "INNER ATTRIBUTE"),
+            Token::Hash => write!(f, "HASH"),
             Token::Add => write!(f, "ADD"),
             Token::Minus => write!(f, "MINUS"),
             Token::Times => write!(f, "TIMES"),
@@ -127,89 +357,273 @@ impl Debug for Token {
     }
 }
 
-impl PartialEq for Token {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Token::Let, Token::Let) |
-            (Token::Function, Token::Function) |
-            (Token::Return, Token::Return) |
-            (Token::Identifier(_), Token::Identifier(_)) |
-            (Token::Number(_), Token::Number(_)) |
-            (Token::String(_), Token::String(_)) |
-            (Token::Comma, Token::Comma) |
-            (Token::Assign, Token::Assign) |
-            (Token::LeftRoundBracket, Token::LeftRoundBracket) |
-            (Token::RightRoundBracket, Token::RightRoundBracket) |
-            (Token::LeftCurlyBracket, Token::LeftCurlyBracket) |
-            (Token::RightCurlyBracket, Token::RightCurlyBracket) |
-            (Token::VariableTypeIndicator, Token::VariableTypeIndicator) |
-            (Token::ReturnTypeIndicator, Token::ReturnTypeIndicator) |
-            (Token::Equal, Token::Equal) |
-            (Token::NotEqual, Token::NotEqual) |
-            (Token::Add, Token::Add) |
-            (Token::Minus, Token::Minus) |
-            (Token::Times, Token::Times) |
-            (Token::Divide, Token::Divide) |
-            (Token::EndOfStatement, Token::EndOfStatement) |
-            (Token::EndOfProgram, Token::EndOfProgram) => true,
-            _ => false,
+/// Tokens handed to the parser one at a time.
+///
+/// A stream either holds every token of a source scanned up front, or
+/// scans the source on demand and only buffers the tokens the parser
+/// hasn't consumed yet.
+pub struct Stream<'src> {
+
+    /// Tokens scanned but not consumed yet, the next one first.
+    tokens: VecDeque<Token<'src>>,
+
+    /// Source span of each token in `tokens`.
+    spans: VecDeque<Span>,
+
+    /// Whether a line break comes before each token in `tokens`.
+    line_breaks: VecDeque<bool>,
+
+    /// Span of the most recently consumed token.
+    previous: Option<Span>,
+
+    /// Tokenizer scanning the rest of the source, `None` once all of
+    /// it has been scanned.
+    tokenizer: Option<Tokenizer<'src>>,
+
+    /// Offset of the next byte to feed `tokenizer`.
+    position: usize,
+
+    /// Bytes the tokenizer couldn't make sense of so far.
+    diagnostics: Diagnostics,
+
+    /// Bounds on the tokens scanned, the tokens past them being
+    /// reported in `diagnostics`.
+    limits: Limits,
+
+    /// Number of tokens scanned so far, not counting the end of the
+    /// program.
+    scanned: usize,
+}
+
+impl<'src> Stream<'src> {
+    pub fn new(
+        tokens: Vec<Token<'src>>,
+        spans: Vec<Span>,
+        line_breaks: Vec<bool>
+    ) -> Stream<'src> {
+        Stream {
+            tokens: VecDeque::from(tokens),
+            spans: VecDeque::from(spans),
+            line_breaks: VecDeque::from(line_breaks),
+            previous: None,
+            tokenizer: None,
+            position: 0,
+            diagnostics: Diagnostics::new(),
+            limits: Limits::default(),
+            scanned: 0,
         }
     }
-}
 
-#[derive(Debug)]
-pub struct Stream {
-    tokens: Vec<Token>,
-    current: usize,
-}
+    /// Stream scanning `source` as the tokens are asked for.
+    ///
+    /// Invalid bytes don't stop the scan, they are skipped and
+    /// collected for [`Stream::finish`] to report.
+    pub fn from_source(source: &'src str) -> Stream<'src> {
+        Stream::from_source_with(source, Limits::default())
+    }
+
+    /// Stream scanning `source` like [`Stream::from_source`], reporting
+    /// the string literals longer than `limits` allow and ending early
+    /// at the first token past the token limit.
+    pub fn from_source_with(source: &'src str, limits: Limits) -> Stream<'src> {
+        let mut tokenizer = Tokenizer::new();
+        let mut stream: Stream;
+
+        tokenizer.source = Cow::Borrowed(source);
+
+        stream = Stream::new(Vec::new(), Vec::new(), Vec::new());
+        stream.tokenizer = Some(tokenizer);
+        stream.limits = limits;
+        stream.fill();
+
+        stream
+    }
+
+    /// Copy of the stream owning the text of its tokens.
+    ///
+    /// The rest of the source is scanned first, so the copy holds
+    /// every token left.
+    pub fn into_owned(mut self) -> Stream<'static> {
+        while self.scan_byte() {}
 
-impl Stream {
-    pub fn new(tokens: Vec<Token>) -> Stream {
         Stream {
-            tokens: tokens,
-            current: 0,
+            tokens: self.tokens.into_iter().map(Token::into_owned).collect(),
+            spans: self.spans,
+            line_breaks: self.line_breaks,
+            previous: self.previous,
+            tokenizer: None,
+            position: self.position,
+            diagnostics: self.diagnostics,
+            limits: self.limits,
+            scanned: self.scanned,
         }
     }
 
-    pub fn consume(&mut self) -> Option<Token>{
-        if self.current < self.tokens.len() {
-            let token = self.tokens[self.current].clone();
+    /// Feed the tokenizer one more byte, buffering the tokens it
+    /// completes. Returns `false` once the whole source is scanned.
+    #[inline(always)]
+    fn scan_byte(&mut self) -> bool {
+        let mut exceeded = false;
+        let tokenizer = match &mut self.tokenizer {
+            Some(tokenizer) => tokenizer,
+            None => return false,
+        };
+
+        let diagnostic = if self.position < tokenizer.source.len() {
+            tokenizer.scan_byte(self.position)
+        } else {
+            tokenizer.scan_end()
+        };
+
+        if let Some(diagnostic) = diagnostic {
+            self.diagnostics.push(diagnostic);
+        }
+
+        for (index, (token, span)) in tokenizer.tokens.iter().zip(&tokenizer.spans).enumerate() {
+            if *token == Token::EndOfProgram {
+                continue;
+            }
+
+            if self.limits.max_tokens == Some(self.scanned) {
+                self.diagnostics.push(Diagnostic::error("Token limit exceeded!")
+                    .with_span(*span)
+                    .with_help(format!("the source may hold {} tokens", self.scanned)));
 
-            self.current += 1;
+                tokenizer.tokens.truncate(index);
+                tokenizer.spans.truncate(index);
+                tokenizer.line_breaks.truncate(index);
+                exceeded = true;
+                break;
+            }
+
+            self.scanned += 1;
+
+            if let (Token::String(string), Some(max)) = (token, self.limits.max_string_length) {
+                if string.len() > max {
+                    self.diagnostics.push(Diagnostic::error("String length limit exceeded!")
+                        .with_span(*span)
+                        .with_help(format!("string literals may hold {} bytes", max)));
+                }
+            }
+        }
 
-            Some(token)
+        if !tokenizer.tokens.is_empty() {
+            self.tokens.extend(tokenizer.tokens.drain(..));
+            self.spans.extend(tokenizer.spans.drain(..));
+            self.line_breaks.extend(tokenizer.line_breaks.drain(..));
+        }
+
+        /* Nobody asks a lazy stream for comments. */
+        tokenizer.comments.clear();
+
+        /* Scanning stops at the token limit, leaving the parser to
+         * run out of tokens. */
+        if exceeded || self.position == tokenizer.source.len() {
+            self.tokenizer = None;
         } else {
-            None
+            self.position += 1;
         }
+
+        true
     }
 
-    pub fn peek(&self) -> Option<&Token> {
-        if self.current < self.tokens.len() {
-            Some(&self.tokens[self.current])
-        } else {
-            None
+    /// Scan until the next two tokens are known, if there are as many.
+    fn fill(&mut self) {
+        while self.tokens.len() < 2 && self.scan_byte() {}
+    }
+
+    /// Scan the rest of the source, dropping the tokens left, and take
+    /// the errors met by the tokenizer.
+    pub fn finish(&mut self) -> Diagnostics {
+        self.tokens.clear();
+        self.spans.clear();
+        self.line_breaks.clear();
+
+        while self.scan_byte() {
+            self.tokens.clear();
+            self.spans.clear();
+            self.line_breaks.clear();
         }
+
+        std::mem::take(&mut self.diagnostics)
     }
 
-    pub fn match_token(&mut self, expected: Token) -> bool {
-        if self.peek() == Some(&expected) {
-            true
-        } else {
-            false
+    /// Move the next token out of the stream.
+    pub fn consume(&mut self) -> Option<Token<'src>>{
+        let token = self.tokens.pop_front()?;
+
+        self.previous = self.spans.pop_front();
+        self.line_breaks.pop_front();
+        self.fill();
+
+        Some(token)
+    }
+
+    pub fn peek(&self) -> Option<&Token<'src>> {
+        self.tokens.front()
+    }
+
+    /// Token right after the next one.
+    pub fn peek_second(&self) -> Option<&Token<'src>> {
+        self.tokens.get(1)
+    }
+
+    /// Span of the next token, or of the last one at the end.
+    pub fn span(&self) -> Span {
+        match self.spans.front() {
+            Some(span) => *span,
+            None => self.previous.unwrap_or_default(),
+        }
+    }
+
+    /// Whether a line break separates the next token from the one
+    /// before it.
+    pub fn line_break_before(&self) -> bool {
+        self.line_breaks.front().copied().unwrap_or(false)
+    }
+
+    /// Span of the most recently consumed token.
+    pub fn previous_span(&self) -> Span {
+        match self.previous {
+            Some(span) => span,
+            None => self.span(),
         }
     }
+
+    /// Whether the next token is of the `expected` kind.
+    pub fn match_token(&self, expected: TokenKind) -> bool {
+        self.peek().map(Token::kind) == Some(expected)
+    }
+}
+
+impl Debug for Stream<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Stream")
+            .field("tokens", &self.tokens)
+            .field("spans", &self.spans)
+            .finish()
+    }
 }
 
 /// States used for the lexer's FSM.
 enum State {
     Start,
 
+    /// Have character `.`.
+    HaveCharDot,
+
+    /// Have characters `..`.
+    HaveCharDotDot,
+
     /// Have character `=`.
     HaveCharEqual,
 
     /// Have character `!`.
     HaveCharExclamationMark,
 
+    /// Have character `#`.
+    HaveCharHash,
+
     /// Have character `-`.
     HaveCharHyphen,
 
@@ -236,12 +650,47 @@ enum State {
     HaveMultiLineCommentEndCharAsterisk,
 }
 
-pub struct Tokenizer {
+/// Size of the chunks `Tokenizer::scan_reader` reads at once.
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Words the lexer scans out as keywords rather than identifiers.
+pub const KEYWORDS: [&str; 14] = [
+    "let", "var", "const", "func", "return", "impl", "trait",
+    "for", "in", "enum", "match", "macro", "nil", "as",
+];
+
+pub struct Tokenizer<'src> {
     state: State,
-    tokens: Vec<Token>,
-    identifier: String,
+
+    /// Text being scanned, or the current chunk of it when scanning
+    /// a reader.
+    source: Cow<'src, str>,
+
+    /// Offset of the first byte of `source` in the whole text.
+    base: usize,
+
+    /// Text from `start` up to `base` of a token split across chunks.
+    carry: String,
+    tokens: Vec<Token<'src>>,
+    spans: Vec<Span>,
+    line_breaks: Vec<bool>,
+
+    /// Whether a line break was skipped over since the last token.
+    line_break: bool,
+
+    /// Spans of the comments skipped over.
+    comments: Vec<Span>,
+
+    /// Offset of the byte being fed.
+    offset: usize,
+
+    /// Offset of the first byte of the token being scanned.
+    start: usize,
     number: isize,
-    string: String,
+
+    /// Whether the numeric literal being scanned is too large for an
+    /// int, which is reported at the digit it overflows at.
+    overflow: bool,
 }
 
 #[derive(Debug)]
@@ -249,64 +698,102 @@ enum Result {
     Continue,
     Again,
     InvalidByte,
+
+    /// Digit making a numeric literal too large for an int.
+    Overflow,
+
+    /// End of the text within a string literal.
+    UnterminatedString,
+
+    /// End of the text within a block comment.
+    UnterminatedComment,
     Done,
 }
 
-fn is_identifier_first_byte(byte: u8) -> bool {
-    if (byte >= b'a' &&
-        byte <= b'z') ||
-       (byte >= b'A' &&
-        byte <= b'Z') ||
-       byte == b'_' {
-        true
-    } else {
-        false
-    }
+/// Classes of bytes telling the lexer what a byte may start.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ByteClass {
+    Invalid,
+    Space,
+
+    /// Letters and `_`, which start identifiers.
+    Letter,
+    Digit,
+    Quote,
+    Comma,
+    Dot,
+    QuestionMark,
+    Equal,
+    ExclamationMark,
+    Hash,
+    Colon,
+    Plus,
+    Hyphen,
+    Asterisk,
+    ForwardSlash,
+    LeftRoundBracket,
+    RightRoundBracket,
+    LeftSquareBracket,
+    RightSquareBracket,
+    LeftCurlyBracket,
+    RightCurlyBracket,
+    Semicolon,
 }
 
-fn is_identifier_other_byte(byte: u8) -> bool {
-    if (byte >= b'a' &&
-        byte <= b'z') ||
-       (byte >= b'A' &&
-        byte <= b'Z') ||
-       (byte >= b'0' &&
-        byte <= b'9') ||
-       byte == b'_' {
-        true
-    } else {
-        false
+/// Class of every byte value, so the lexer dispatches on a byte with
+/// a single lookup instead of a chain of comparisons.
+static BYTE_CLASSES: [ByteClass; 256] = build_byte_classes();
+
+const fn build_byte_classes() -> [ByteClass; 256] {
+    let mut classes = [ByteClass::Invalid; 256];
+    let mut byte = 0;
+
+    while byte < 256 {
+        classes[byte] = match byte as u8 {
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => ByteClass::Letter,
+            b'0'..=b'9' => ByteClass::Digit,
+            b' ' | b'\t' | b'\r' | b'\n' => ByteClass::Space,
+            b'"' => ByteClass::Quote,
+            b',' => ByteClass::Comma,
+            b'.' => ByteClass::Dot,
+            b'?' => ByteClass::QuestionMark,
+            b'=' => ByteClass::Equal,
+            b'!' => ByteClass::ExclamationMark,
+            b'#' => ByteClass::Hash,
+            b':' => ByteClass::Colon,
+            b'+' => ByteClass::Plus,
+            b'-' => ByteClass::Hyphen,
+            b'*' => ByteClass::Asterisk,
+            b'/' => ByteClass::ForwardSlash,
+            b'(' => ByteClass::LeftRoundBracket,
+            b')' => ByteClass::RightRoundBracket,
+            b'[' => ByteClass::LeftSquareBracket,
+            b']' => ByteClass::RightSquareBracket,
+            b'{' => ByteClass::LeftCurlyBracket,
+            b'}' => ByteClass::RightCurlyBracket,
+            b';' => ByteClass::Semicolon,
+            _ => ByteClass::Invalid,
+        };
+        byte += 1;
     }
+
+    classes
 }
 
-fn is_number_byte(byte: u8) -> bool {
-    if byte >= b'0' &&
-       byte <= b'9' {
-        true
-    } else {
-        false
-    }
+fn byte_class(byte: u8) -> ByteClass {
+    BYTE_CLASSES[byte as usize]
 }
 
-fn is_space_byte(byte: u8) -> bool {
-    if byte == b' ' ||
-       byte == b'\r' ||
-       byte == b'\n' {
-        true
-    } else {
-        false
-    }
+fn is_identifier_other_byte(byte: u8) -> bool {
+    matches!(byte_class(byte), ByteClass::Letter | ByteClass::Digit)
 }
 
 fn is_ascii_printable_byte(byte: u8) -> bool {
-    if byte >= 32 &&
-       byte <= 126 {
-        true
-    } else {
-        false
-    }
+    (32..=126).contains(&byte)
 }
 
-fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
+#[inline(always)]
+fn fsm_proc(tokenizer: &mut Tokenizer<'_>, byte: Option<u8>) -> Result {
     match tokenizer.state {
         State::Start => {
             let byte = match byte {
@@ -314,58 +801,94 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
                 Some(byte) => byte,
             };
 
-            if is_identifier_first_byte(byte) {
-                tokenizer.identifier.clear();
-                tokenizer.identifier.push(byte as char);
+            tokenizer.start = tokenizer.offset;
 
-                tokenizer.state = State::HaveIdentifierChar;
-            } else if is_number_byte(byte) {
-                let value = byte - b'0';
+            match byte_class(byte) {
+                ByteClass::Letter => tokenizer.state = State::HaveIdentifierChar,
+                ByteClass::Digit => {
+                    let value = byte - b'0';
 
-                tokenizer.number = value as isize;
-
-                tokenizer.state = State::HaveNumericChar;
-            } else if byte == b'"' {
-                tokenizer.string.clear();
-
-                tokenizer.state = State::HaveStringStart;
-            } else if byte == b',' {
-                tokenizer.tokens.push(Token::Comma);
-            } else if byte == b'=' {
-                tokenizer.state = State::HaveCharEqual;
-            } else if byte == b'!' {
-                tokenizer.state = State::HaveCharExclamationMark;
-            } else if byte == b':' {
-                tokenizer.tokens.push(Token::VariableTypeIndicator);
-            } else if byte == b'+' {
-                tokenizer.tokens.push(Token::Add);
-            } else if byte == b'-' {
-                tokenizer.state = State::HaveCharHyphen;
-            } else if byte == b'*' {
-                tokenizer.tokens.push(Token::Times);
-            } else if byte == b'/' {
-                tokenizer.state = State::HaveCharForwardSlash;
-            } else if byte == b'(' {
-                tokenizer.tokens.push(Token::LeftRoundBracket);
-            } else if byte == b')' {
-                tokenizer.tokens.push(Token::RightRoundBracket);
-            } else if byte == b'{' {
-                tokenizer.tokens.push(Token::LeftCurlyBracket);
-            } else if byte == b'}' {
-                tokenizer.tokens.push(Token::RightCurlyBracket);
-            } else if byte == b';' {
-                tokenizer.tokens.push(Token::EndOfStatement);
-            } else if is_space_byte(byte) {
+                    tokenizer.number = value as isize;
+                    tokenizer.overflow = false;
+
+                    tokenizer.state = State::HaveNumericChar;
+                },
+                ByteClass::Quote => tokenizer.state = State::HaveStringStart,
+                ByteClass::Comma => tokenizer.emit(Token::Comma, tokenizer.offset + 1),
+                ByteClass::Dot => tokenizer.state = State::HaveCharDot,
+                ByteClass::QuestionMark => tokenizer.emit(Token::QuestionMark, tokenizer.offset + 1),
+                ByteClass::Equal => tokenizer.state = State::HaveCharEqual,
+                ByteClass::ExclamationMark => tokenizer.state = State::HaveCharExclamationMark,
+                ByteClass::Hash => tokenizer.state = State::HaveCharHash,
+                ByteClass::Colon => tokenizer.emit(Token::VariableTypeIndicator, tokenizer.offset + 1),
+                ByteClass::Plus => tokenizer.emit(Token::Add, tokenizer.offset + 1),
+                ByteClass::Hyphen => tokenizer.state = State::HaveCharHyphen,
+                ByteClass::Asterisk => tokenizer.emit(Token::Times, tokenizer.offset + 1),
+                ByteClass::ForwardSlash => tokenizer.state = State::HaveCharForwardSlash,
+                ByteClass::LeftRoundBracket => tokenizer.emit(Token::LeftRoundBracket, tokenizer.offset + 1),
+                ByteClass::RightRoundBracket => tokenizer.emit(Token::RightRoundBracket, tokenizer.offset + 1),
+                ByteClass::LeftSquareBracket => tokenizer.emit(Token::LeftSquareBracket, tokenizer.offset + 1),
+                ByteClass::RightSquareBracket => tokenizer.emit(Token::RightSquareBracket, tokenizer.offset + 1),
+                ByteClass::LeftCurlyBracket => tokenizer.emit(Token::LeftCurlyBracket, tokenizer.offset + 1),
+                ByteClass::RightCurlyBracket => tokenizer.emit(Token::RightCurlyBracket, tokenizer.offset + 1),
+                ByteClass::Semicolon => tokenizer.emit(Token::EndOfStatement, tokenizer.offset + 1),
+                ByteClass::Space => {
+                    if byte == b'\n' {
+                        tokenizer.line_break = true;
+                    }
+                },
+                ByteClass::Invalid => return Result::InvalidByte,
+            }
+        },
+
+        State::HaveCharDot => {
+            let byte = match byte {
+                None => {
+                    tokenizer.emit(Token::Dot, tokenizer.offset);
 
+                    return Result::Done;
+                },
+                Some(byte) => byte,
+            };
+
+            if byte == b'.' {
+                tokenizer.state = State::HaveCharDotDot;
             } else {
-                return Result::InvalidByte;
+                tokenizer.emit(Token::Dot, tokenizer.offset);
+
+                tokenizer.state = State::Start;
+
+                return Result::Again;
+            }
+        },
+
+        State::HaveCharDotDot => {
+            let byte = match byte {
+                None => {
+                    tokenizer.emit(Token::DotDot, tokenizer.offset);
+
+                    return Result::Done;
+                },
+                Some(byte) => byte,
+            };
+
+            if byte == b'=' {
+                tokenizer.emit(Token::DotDotEqual, tokenizer.offset + 1);
+
+                tokenizer.state = State::Start;
+            } else {
+                tokenizer.emit(Token::DotDot, tokenizer.offset);
+
+                tokenizer.state = State::Start;
+
+                return Result::Again;
             }
         },
 
         State::HaveCharEqual => {
             let byte = match byte {
                 None => {
-                    tokenizer.tokens.push(Token::Assign);
+                    tokenizer.emit(Token::Assign, tokenizer.offset);
 
                     return Result::Done;
                 },
@@ -373,11 +896,15 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
             };
 
             if byte == b'=' {
-                tokenizer.tokens.push(Token::Equal);
+                tokenizer.emit(Token::Equal, tokenizer.offset + 1);
+
+                tokenizer.state = State::Start;
+            } else if byte == b'>' {
+                tokenizer.emit(Token::MatchArmIndicator, tokenizer.offset + 1);
 
                 tokenizer.state = State::Start;
             } else {
-                tokenizer.tokens.push(Token::Assign);
+                tokenizer.emit(Token::Assign, tokenizer.offset);
 
                 tokenizer.state = State::Start;
 
@@ -392,7 +919,7 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
             };
 
             if byte == b'=' {
-                tokenizer.tokens.push(Token::NotEqual);
+                tokenizer.emit(Token::NotEqual, tokenizer.offset + 1);
 
                 tokenizer.state = State::Start;
             } else {
@@ -402,22 +929,22 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
             }
         },
 
-        State::HaveCharHyphen => {
+        State::HaveCharHash => {
             let byte = match byte {
                 None => {
-                    tokenizer.tokens.push(Token::Minus);
+                    tokenizer.emit(Token::Hash, tokenizer.offset);
 
                     return Result::Done;
                 },
                 Some(byte) => byte,
             };
 
-            if byte == b'>' {
-                tokenizer.tokens.push(Token::ReturnTypeIndicator);
+            if byte == b'!' {
+                tokenizer.emit(Token::InnerAttribute, tokenizer.offset + 1);
 
                 tokenizer.state = State::Start;
             } else {
-                tokenizer.tokens.push(Token::Minus);
+                tokenizer.emit(Token::Hash, tokenizer.offset);
 
                 tokenizer.state = State::Start;
 
@@ -425,30 +952,22 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
             }
         },
 
-        State::HaveIdentifierChar => {
+        State::HaveCharHyphen => {
             let byte = match byte {
-                None => return Result::Done,
+                None => {
+                    tokenizer.emit(Token::Minus, tokenizer.offset);
+
+                    return Result::Done;
+                },
                 Some(byte) => byte,
             };
 
-            if is_identifier_other_byte(byte) {
-                tokenizer.identifier.push(byte as char);
-            } else {
-                let identifier = tokenizer.identifier.to_owned();
-                let text = identifier.as_str();
-                let token: Token;
-
-                if text == "let" {
-                    token = Token::Let;
-                } else if text == "func" {
-                    token = Token::Function;
-                } else if text == "return" {
-                    token = Token::Return;
-                } else {
-                    token = Token::Identifier(identifier);
-                }
+            if byte == b'>' {
+                tokenizer.emit(Token::ReturnTypeIndicator, tokenizer.offset + 1);
 
-                tokenizer.tokens.push(token);
+                tokenizer.state = State::Start;
+            } else {
+                tokenizer.emit(Token::Minus, tokenizer.offset);
 
                 tokenizer.state = State::Start;
 
@@ -456,46 +975,79 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
             }
         },
 
-        State::HaveNumericChar => {
-            let byte = match byte {
-                None => return Result::Done,
-                Some(byte) => byte,
-            };
+        State::HaveIdentifierChar => {
+
+            /* The end of the text ends the identifier too. */
+            if !byte.is_some_and(is_identifier_other_byte) {
+                let text = tokenizer.text(tokenizer.start, tokenizer.offset);
+                let token = match &*text {
+                    "let" => Token::Let,
+                    "var" => Token::Variable,
+                    "const" => Token::Constant,
+                    "func" => Token::Function,
+                    "return" => Token::Return,
+                    "impl" => Token::Implementation,
+                    "trait" => Token::Trait,
+                    "for" => Token::For,
+                    "in" => Token::In,
+                    "enum" => Token::Enum,
+                    "match" => Token::Match,
+                    "macro" => Token::Macro,
+                    "nil" => Token::Nil,
+                    "as" => Token::As,
+                    _ => Token::Identifier(text),
+                };
+
+                tokenizer.emit(token, tokenizer.offset);
+
+                tokenizer.state = State::Start;
+
+                return Result::Again;
+            }
+        },
 
-            if is_number_byte(byte) {
+        State::HaveNumericChar => match byte {
+            Some(byte) if byte_class(byte) == ByteClass::Digit => {
                 let value = byte - b'0';
+                let number = tokenizer.number.checked_mul(10)
+                    .and_then(|number| number.checked_add(value as isize));
 
-                tokenizer.number *= 10;
-                tokenizer.number += value as isize;
-            } else {
+                match number {
+                    Some(number) => tokenizer.number = number,
+                    None if tokenizer.overflow => {},
+                    None => {
+                        tokenizer.overflow = true;
+
+                        return Result::Overflow;
+                    },
+                }
+            },
+            _ => {
                 let token = Token::Number(tokenizer.number);
 
-                tokenizer.tokens.push(token);
+                tokenizer.emit(token, tokenizer.offset);
 
                 tokenizer.state = State::Start;
 
                 return Result::Again;
-            }
+            },
         },
 
         State::HaveStringStart => {
             let byte = match byte {
-                None => return Result::Done,
+                None => return Result::UnterminatedString,
                 Some(byte) => byte,
             };
 
             if byte == b'"' {
-                let string = tokenizer.string.to_owned();
-                let token = Token::String(string);
+                let text = tokenizer.text(tokenizer.start + 1, tokenizer.offset);
 
-                tokenizer.tokens.push(token);
+                tokenizer.emit(Token::String(text), tokenizer.offset + 1);
 
                 tokenizer.state = State::Start;
-            } else if byte == b'\r' ||
-                      byte == b'\n' ||
-                      is_ascii_printable_byte(byte) {
-                tokenizer.string.push(byte as char);
-            } else {
+            } else if byte != b'\r' &&
+                      byte != b'\n' &&
+                      !is_ascii_printable_byte(byte) {
                 return Result::InvalidByte;
             }
         },
@@ -503,7 +1055,7 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
         State::HaveCharForwardSlash => {
             let byte = match byte {
                 None => {
-                    tokenizer.tokens.push(Token::Divide);
+                    tokenizer.emit(Token::Divide, tokenizer.offset);
 
                     return Result::Done
                 },
@@ -515,7 +1067,7 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
             } else if byte == b'*' {
                 tokenizer.state = State::HaveMultiLineCommentStart;
             } else {
-                tokenizer.tokens.push(Token::Divide);
+                tokenizer.emit(Token::Divide, tokenizer.offset);
 
                 tokenizer.state = State::Start;
 
@@ -525,37 +1077,62 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
 
         State::HaveSingleLineCommentStart => {
             let byte = match byte {
-                None => return Result::Done,
+                None => {
+                    tokenizer.comment(tokenizer.offset);
+
+                    return Result::Done;
+                },
                 Some(byte) => byte,
             };
 
             if byte == b'\r' ||
                byte == b'\n' {
+                tokenizer.comment(tokenizer.offset);
+
                 tokenizer.state = State::Start;
+
+                return Result::Again;
             }
         },
 
         State::HaveMultiLineCommentStart => {
             let byte = match byte {
-                None => return Result::Done,
+                None => {
+                    tokenizer.comment(tokenizer.offset);
+
+                    return Result::UnterminatedComment;
+                },
                 Some(byte) => byte,
             };
 
             if byte == b'*' {
                 tokenizer.state = State::HaveMultiLineCommentEndCharAsterisk;
+            } else if byte == b'\n' {
+                tokenizer.line_break = true;
             }
         },
 
         State::HaveMultiLineCommentEndCharAsterisk => {
             let byte = match byte {
-                None => return Result::Done,
+                None => {
+                    tokenizer.comment(tokenizer.offset);
+
+                    return Result::UnterminatedComment;
+                },
                 Some(byte) => byte,
             };
 
             if byte == b'/' {
+                tokenizer.comment(tokenizer.offset + 1);
+
                 tokenizer.state = State::Start;
             } else {
+
+                /* Look at the byte again, it may be a line break or
+                 * another `*`. */
                 tokenizer.state = State::HaveMultiLineCommentStart;
+
+                return Result::Again;
             }
         },
     }
@@ -563,17 +1140,67 @@ fn fsm_proc(tokenizer: &mut Tokenizer, byte: Option<u8>) -> Result {
     Result::Continue
 }
 
-impl Tokenizer {
-    pub fn new() -> Tokenizer {
+impl<'src> Default for Tokenizer<'src> {
+    fn default() -> Tokenizer<'src> {
+        Tokenizer::new()
+    }
+}
+
+impl<'src> Tokenizer<'src> {
+    pub fn new() -> Tokenizer<'src> {
         Tokenizer {
             state: State::Start,
+            source: Cow::Borrowed(""),
+            base: 0,
+            carry: String::new(),
             tokens: Vec::new(),
-            identifier: String::new(),
+            spans: Vec::new(),
+            line_breaks: Vec::new(),
+            line_break: false,
+            comments: Vec::new(),
+            offset: 0,
+            start: 0,
             number: 0,
-            string: String::new(),
+            overflow: false,
         }
     }
 
+    /// Source text from `start` to `end`, borrowed when the whole
+    /// text is at hand.
+    fn text(&self, start: usize, end: usize) -> Cow<'src, str> {
+        if start < self.base {
+            let mut text = self.carry[start - self.start..].to_owned();
+
+            text.push_str(&self.source[..end - self.base]);
+
+            return Cow::Owned(text);
+        }
+
+        match &self.source {
+            Cow::Borrowed(source) => Cow::Borrowed(&source[start - self.base..end - self.base]),
+            Cow::Owned(source) => Cow::Owned(source[start - self.base..end - self.base].to_owned()),
+        }
+    }
+
+    /// Append a token spanning from the current token start to `end`.
+    fn emit(&mut self, token: Token<'src>, end: usize) {
+        self.tokens.push(token);
+        self.spans.push(Span::new(self.start, end));
+        self.line_breaks.push(std::mem::take(&mut self.line_break));
+    }
+
+    /// Record a comment spanning from the current token start to `end`.
+    fn comment(&mut self, end: usize) {
+        self.comments.push(Span::new(self.start, end));
+    }
+
+    /// Spans of the comments in the scanned text, until the next
+    /// `extract`.
+    pub fn comments(&self) -> &[Span] {
+        &self.comments
+    }
+
+    #[inline(always)]
     fn feed(&mut self, byte: Option<u8>) -> Result {
         let mut result: Result;
 
@@ -588,27 +1215,412 @@ impl Tokenizer {
         result
     }
 
-    pub fn scan(&mut self, text: &str) {
-        let text_buf = text.as_bytes();
-        let text_len = text.len();
+    /// Scan the whole text into tokens, reporting every byte
+    /// the lexer can't make sense of.
+    pub fn scan(&mut self, text: &'src str) -> std::result::Result<(), Diagnostics> {
+        let mut diagnostics = Diagnostics::new();
 
-        for index in 0..text_len {
-            self.feed(Some(text_buf[index]));
+        self.source = Cow::Borrowed(text);
+        self.base = 0;
+
+        for index in 0..text.len() {
+            if let Some(diagnostic) = self.scan_byte(index) {
+                diagnostics.push(diagnostic);
+            }
         }
 
-        self.feed(None);
+        if let Some(diagnostic) = self.scan_end() {
+            diagnostics.push(diagnostic);
+        }
 
-        self.tokens.push(Token::EndOfProgram);
+        if diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(diagnostics)
+        }
     }
 
-    pub fn extract(&mut self) -> Stream {
-        let tokens = self.tokens.to_owned();
+    /// Scan everything `reader` yields into tokens, a chunk at a time.
+    ///
+    /// The lexer picks up where it left off at each chunk boundary, so
+    /// tokens may be split across reads. Their text is owned since the
+    /// chunks don't outlive the scan.
+    pub fn scan_reader<R: Read>(&mut self, mut reader: R) -> std::result::Result<(), Diagnostics> {
+        let mut diagnostics = Diagnostics::new();
+        let mut buf: Vec<u8> = vec![0; READ_CHUNK_SIZE];
+
+        /* Bytes of a character cut off by the end of the last read,
+         * kept at the start of `buf`. */
+        let mut pending: usize = 0;
+
+        self.source = Cow::Owned(String::new());
+        self.base = 0;
+
+        loop {
+            let filled: usize = match reader.read(&mut buf[pending..]) {
+                Ok(0) => break,
+                Ok(count) => pending + count,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    diagnostics.push(Diagnostic::error(format!("Failed to read: {}!", err)));
+
+                    return Err(diagnostics);
+                },
+            };
+
+            let valid: usize = match std::str::from_utf8(&buf[..filled]) {
+                Ok(_) => filled,
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                Err(err) => {
+                    let offset = self.base + self.source.len() + err.valid_up_to();
+
+                    diagnostics.push(Diagnostic::error("Invalid UTF-8!")
+                        .with_span(Span::new(offset, offset + 1)));
+
+                    return Err(diagnostics);
+                },
+            };
+
+            self.next_chunk(String::from_utf8(buf[..valid].to_vec()).unwrap());
+
+            for index in self.base..self.base + valid {
+                if let Some(diagnostic) = self.scan_byte(index) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+
+            buf.copy_within(valid..filled, 0);
+            pending = filled - valid;
+        }
+
+        if pending > 0 {
+            let offset = self.base + self.source.len();
+
+            diagnostics.push(Diagnostic::error("Invalid UTF-8!")
+                .with_span(Span::new(offset, offset + pending)));
+        }
+
+        if let Some(diagnostic) = self.scan_end() {
+            diagnostics.push(diagnostic);
+        }
+
+        if diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(diagnostics)
+        }
+    }
+
+    /// Move on to the next chunk of the text, keeping the text of a
+    /// token split by the boundary.
+    fn next_chunk(&mut self, chunk: String) {
+        let end = self.base + self.source.len();
+
+        match self.state {
+            State::HaveIdentifierChar |
+            State::HaveStringStart => self.carry = self.text(self.start, end).into_owned(),
+            _ => self.carry.clear(),
+        }
+
+        self.base = end;
+        self.source = Cow::Owned(chunk);
+    }
+
+    /// Feed the byte at `index` of the text, describing it if the
+    /// lexer can't make sense of it.
+    #[inline(always)]
+    fn scan_byte(&mut self, index: usize) -> Option<Diagnostic> {
+        let byte = self.source.as_bytes()[index - self.base];
+
+        self.offset = index;
+
+        match self.feed(Some(byte)) {
+            Result::InvalidByte => Some(Diagnostic::error(
+                format!("Invalid byte 0x{:02X}!", byte))
+                .with_span(Span::new(index, index + 1))),
+            Result::Overflow => Some(Diagnostic::error("Number too large for int!")
+                .with_span(Span::new(self.start, index + 1))
+                .with_help(format!("int literals may be at most {}", isize::MAX))),
+            _ => None,
+        }
+    }
+
+    /// Feed the end of the text and append the end of program token.
+    fn scan_end(&mut self) -> Option<Diagnostic> {
+        let text_len = self.base + self.source.len();
+
+        self.offset = text_len;
+
+        let diagnostic: Option<Diagnostic> = match self.feed(None) {
+            Result::InvalidByte => Some(Diagnostic::error("Unexpected end of program!")
+                .with_span(Span::new(text_len, text_len))),
+            Result::UnterminatedString => Some(Diagnostic::error("Unterminated string literal!")
+                .with_span(Span::new(self.start, self.start + 1))
+                .with_help("the string starts here and needs a closing \"")),
+            Result::UnterminatedComment => Some(Diagnostic::error("Unterminated block comment!")
+                .with_span(Span::new(self.start, self.start + 2))
+                .with_help("the comment starts here and needs a closing */")),
+            _ => None,
+        };
+
+        self.start = text_len;
+        self.emit(Token::EndOfProgram, text_len);
+
+        diagnostic
+    }
+
+    pub fn extract(&mut self) -> Stream<'src> {
+        let tokens = std::mem::take(&mut self.tokens);
+        let spans = std::mem::take(&mut self.spans);
+        let line_breaks = std::mem::take(&mut self.line_breaks);
 
         self.state = State::Start;
-        self.tokens = Vec::new();
-        self.identifier = String::new();
+        self.source = Cow::Borrowed("");
+        self.base = 0;
+        self.carry.clear();
+        self.line_break = false;
+        self.comments = Vec::new();
+        self.offset = 0;
+        self.start = 0;
         self.number = 0;
+        self.overflow = false;
+
+        Stream::new(tokens, spans, line_breaks)
+    }
+}
+
+/// Keywords a declaration may start with, right before the name it
+/// declares.
+const DECLARATION_KEYWORDS: [&str; 7] = ["let", "var", "const", "func", "enum", "trait", "macro"];
+
+/// Text of the `///` doc comment written on the lines right above the
+/// declaration of the name at `offset`, without the leading slashes.
+///
+/// The comment must end right before the first token of the
+/// declaration, so the name of a parameter written after that of its
+/// function doesn't take the function's doc comment.
+pub fn doc_comment_before(source: &str, offset: usize) -> Option<String> {
+    let offset = offset.min(source.len());
+    let line_start = |start: usize| source[..start].rfind('\n').map_or(0, |index| index + 1);
+
+    /* Only the indentation may come before the declaration on its
+     * line, or the comment ends before some other token. */
+    let starts_line = |start: usize| source[line_start(start)..start].trim().is_empty();
+    let before = source[..offset].trim_end();
+    let start = DECLARATION_KEYWORDS.iter()
+        .filter(|keyword| before.ends_with(**keyword))
+        .map(|keyword| before.len() - keyword.len())
+        .find(|start| !before[..*start].ends_with(|char: char| char.is_alphanumeric() || char == '_'))
+        .filter(|start| starts_line(*start))
+        .unwrap_or(offset);
+    let mut lines: Vec<&str> = Vec::new();
+
+    if !starts_line(start) {
+        return None;
+    }
+
+    for line in source[..line_start(start)].lines().rev() {
+        match line.trim_start().strip_prefix("///") {
+            Some(text) => lines.push(text.strip_prefix(' ').unwrap_or(text)),
+            None => break,
+        }
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+
+    lines.reverse();
+
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_borrow_source() {
+        let mut tokenizer = Tokenizer::new();
+        let text = String::from("let greeting = \"Hello\";");
+        let mut stream: Stream;
+
+        tokenizer.scan(&text).unwrap();
+        stream = tokenizer.extract();
+
+        assert!(stream.match_token(TokenKind::Let));
+        assert_eq!(stream.consume(), Some(Token::Let));
+        assert!(stream.match_token(TokenKind::Identifier));
+        assert!(matches!(stream.consume(), Some(Token::Identifier(Cow::Borrowed("greeting")))));
+        assert_eq!(stream.consume(), Some(Token::Assign));
+        assert!(matches!(stream.consume(), Some(Token::String(Cow::Borrowed("Hello")))));
+
+        /* An owned stream outlives the source text. */
+        stream = stream.into_owned();
+        drop(text);
+        assert_eq!(stream.consume(), Some(Token::EndOfStatement));
+        assert_eq!(stream.consume(), Some(Token::EndOfProgram));
+    }
+
+    #[test]
+    fn tokens_at_end_of_text() {
+        for (text, last) in [("a = b", Token::Identifier(Cow::Borrowed("b"))), ("a = 12", Token::Number(12)), ("a = nil", Token::Nil)] {
+            let mut tokenizer = Tokenizer::new();
+            let mut stream: Stream;
+
+            tokenizer.scan(text).unwrap();
+            stream = tokenizer.extract();
+            stream.consume();
+            stream.consume();
+
+            assert_eq!(stream.consume(), Some(last), "{}", text);
+            assert_eq!(stream.consume(), Some(Token::EndOfProgram), "{}", text);
+        }
+    }
+
+    #[test]
+    fn lazy_stream() {
+        let text = "let a = 1; @ func f() { return a; }";
+        let mut tokenizer = Tokenizer::new();
+        let mut eager: Stream;
+        let mut lazy = Stream::from_source(text);
+
+        let _ = tokenizer.scan(text);
+        eager = tokenizer.extract();
+
+        /* Only the next two tokens are scanned ahead, or three when a
+         * byte completes two, like the `;` ending a number. */
+        assert_eq!(lazy.tokens.len(), 2);
+
+        while let Some(token) = eager.consume() {
+            assert_eq!(lazy.span(), eager.previous_span());
+            assert_eq!(lazy.consume(), Some(token));
+            assert!(lazy.tokens.len() <= 3);
+        }
+
+        assert_eq!(lazy.consume(), None);
+
+        let diagnostics: Diagnostics = lazy.finish();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics.iter().next().unwrap().span, Some(Span::new(11, 12)));
+    }
+
+    /// Reader handing out at most `size` bytes per read.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        size: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = self.size.min(buf.len()).min(self.bytes.len());
+
+            buf[..count].copy_from_slice(&self.bytes[..count]);
+            self.bytes = &self.bytes[count..];
+
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn tokens_split_across_chunks() {
+        let text = "/* caf\u{e9} */ func greet(name) -> str { return \"Hello\" != name; } let answer = 4217;\n";
+        let mut tokenizer = Tokenizer::new();
+
+        tokenizer.scan(text).unwrap();
+        let expected: Stream = tokenizer.extract();
+
+        for size in 1..=7 {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan_reader(Trickle {
+                bytes: text.as_bytes(),
+                size,
+            }).unwrap();
+            let stream: Stream = tokenizer.extract();
+
+            assert_eq!(stream.tokens, expected.tokens, "chunks of {} bytes", size);
+            assert_eq!(stream.spans, expected.spans, "chunks of {} bytes", size);
+        }
+    }
+
+    #[test]
+    fn reader_errors() {
+        let mut tokenizer = Tokenizer::new();
+        let diagnostics = tokenizer.scan_reader(Trickle {
+            bytes: b"let a @ 1; /* \xC3",
+            size: 2,
+        }).unwrap_err();
+        let spans: Vec<Option<Span>> = diagnostics.iter().map(|diagnostic| diagnostic.span).collect();
+
+        assert_eq!(spans, vec![Some(Span::new(6, 7)), Some(Span::new(14, 15)), Some(Span::new(11, 13))]);
+    }
+
+    #[test]
+    fn number_overflow() {
+        let max = isize::MAX.to_string();
+        let mut tokenizer = Tokenizer::new();
+        let mut stream: Stream;
+
+        tokenizer.scan(&max).unwrap();
+        stream = tokenizer.extract();
+        assert_eq!(stream.consume(), Some(Token::Number(isize::MAX)));
+
+        /* An overflowing literal is reported once, at the digit it
+         * overflows at. */
+        for text in [format!("a = {}0;", max), format!("a = {}000;", max)] {
+            let mut tokenizer = Tokenizer::new();
+            let diagnostics = tokenizer.scan(&text).unwrap_err();
+            let diagnostic = diagnostics.iter().next().unwrap();
+
+            assert_eq!(diagnostics.len(), 1, "{}", text);
+            assert_eq!(diagnostic.message, "Number too large for int!");
+            assert_eq!(diagnostic.span, Some(Span::new(4, 5 + max.len())));
+        }
+    }
+
+    #[test]
+    fn unterminated() {
+        let text = "let a = \"one\";\nlet b = \"two\nthree;\n";
+        let mut tokenizer = Tokenizer::new();
+        let diagnostics = tokenizer.scan(text).unwrap_err();
+
+        assert_eq!(diagnostics.render("main.fang", text), "\
+main.fang:2:9: error: Unterminated string literal!
+main.fang:2:9: help: the string starts here and needs a closing \"
+");
+
+        /* The opening quote is found again in a text read in chunks. */
+        for size in 1..=3 {
+            let mut tokenizer = Tokenizer::new();
+            let diagnostics = tokenizer.scan_reader(Trickle {
+                bytes: text.as_bytes(),
+                size,
+            }).unwrap_err();
+
+            assert_eq!(diagnostics.iter().next().unwrap().span, Some(Span::new(23, 24)));
+        }
+
+        for (text, span) in [("a; /* b", Span::new(3, 5)), ("a; /* b *", Span::new(3, 5)), ("/* */ /*", Span::new(6, 8))] {
+            let mut tokenizer = Tokenizer::new();
+            let diagnostics = tokenizer.scan(text).unwrap_err();
+            let diagnostic = diagnostics.iter().next().unwrap();
+
+            assert_eq!(diagnostic.message, "Unterminated block comment!", "{}", text);
+            assert_eq!(diagnostic.span, Some(span), "{}", text);
+        }
+    }
 
-        Stream::new(tokens)
+    #[test]
+    fn doc_comments() {
+        let source = "/// Adds.\n/// Twice.\nfunc add(a, b) {}\n\n/// Shape.\nenum Shape {\n    /// Round.\n    Circle,\n}\n// Plain.\nconst ONE = 1;\n";
+        let doc = |name: &str| doc_comment_before(source, source.find(name).unwrap());
+
+        assert_eq!(doc("add").as_deref(), Some("Adds.\nTwice."));
+        assert_eq!(doc("func").as_deref(), Some("Adds.\nTwice."));
+        assert_eq!(doc("a,"), None);
+        assert_eq!(doc("Shape {").as_deref(), Some("Shape."));
+        assert_eq!(doc("Circle").as_deref(), Some("Round."));
+        assert_eq!(doc("ONE"), None);
     }
 }
@@ -0,0 +1,114 @@
+//! Fang, a small programming language written to learn the
+//! principles of compiler design.
+//!
+//! Besides backing the `yuan` compiler, the crate can be embedded in
+//! Rust applications to run Fang code as a scripting language:
+//! compile the source once with [`compile_str`], then evaluate the
+//! [`CompiledProgram`] in an [`Env`] seeded by the host.
+
+pub mod span;
+pub mod diagnostic;
+pub mod encoding;
+pub mod lexer;
+pub mod parser;
+pub mod visit;
+pub mod printer;
+pub mod highlight;
+pub mod tags;
+pub mod doc;
+pub mod resolver;
+pub mod conditional;
+pub mod constant;
+pub mod macros;
+pub mod exhaustiveness;
+pub mod template;
+pub mod arguments;
+pub mod lint;
+pub mod clock;
+pub mod random;
+pub mod interpreter;
+pub mod debugger;
+pub mod profiler;
+pub mod coverage;
+pub mod testing;
+pub mod cache;
+pub mod query;
+pub mod manifest;
+pub mod plugin;
+pub mod frontend;
+pub mod repl;
+pub mod lsp;
+
+#[cfg(test)]
+mod generator;
+
+pub use diagnostic::{Diagnostic, Diagnostics};
+pub use interpreter::{Env, Hooks, RuntimeError, Value};
+
+use diagnostic::LintLevels;
+use frontend::check_program;
+use lint::LintRegistry;
+use interpreter::Interpreter;
+use parser::{parse_source, Program};
+
+/// Program that went through the frontend without errors.
+#[derive(PartialEq, Debug, Clone)]
+pub struct CompiledProgram {
+    program: Program,
+    warnings: Diagnostics,
+}
+
+impl CompiledProgram {
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Likely mistakes found in the source, which didn't prevent it
+    /// from compiling.
+    pub fn warnings(&self) -> &Diagnostics {
+        &self.warnings
+    }
+
+    /// Evaluate the program in `env`, returning the value of its last
+    /// statement or of a top-level `return`.
+    pub fn eval(&self, env: &mut Env) -> Result<Value, RuntimeError> {
+        let mut interpreter = Interpreter::new(env);
+
+        interpreter.run(&self.program)
+    }
+
+    /// Evaluate the program like [`CompiledProgram::eval`], calling
+    /// `hooks` as it runs, to trace it or bound its time and memory.
+    pub fn eval_with_hooks(&self, env: &mut Env, hooks: &mut dyn Hooks) -> Result<Value, RuntimeError> {
+        let mut interpreter = Interpreter::new(env).with_hooks(hooks);
+
+        interpreter.run(&self.program)
+    }
+}
+
+/// Lex and parse `source` into a program ready to be evaluated, expand
+/// its macros, and check that its constants evaluate, its format
+/// strings fit their values and its matches are exhaustive.
+///
+/// No symbol of conditional compilation is defined, so only the
+/// `#else` blocks are kept. On failure, the diagnostics may hold
+/// warnings besides the errors.
+pub fn compile_str(source: &str) -> Result<CompiledProgram, Diagnostics> {
+    compile_program(parse_source(source)?, &[], &LintLevels::new())
+}
+
+/// Check a parsed `program` like [`compile_str`], keeping the blocks of
+/// conditional compilation for `symbols` and reporting the lints at
+/// `lint_levels`.
+pub fn compile_program(
+    mut program: Program,
+    symbols: &[String],
+    lint_levels: &LintLevels
+) -> Result<CompiledProgram, Diagnostics> {
+    let warnings = check_program(&mut program, Some(symbols), &LintRegistry::with_builtins(), lint_levels)?;
+
+    Ok(CompiledProgram {
+        program,
+        warnings,
+    })
+}
@@ -0,0 +1,524 @@
+use crate::diagnostic::{Diagnostic, Diagnostics, Level, WARNINGS};
+use crate::parser::{Attribute, Program, Statement};
+use crate::span::Span;
+use crate::visit::{walk_program, walk_statement, Visitor};
+use std::mem;
+
+/// Statements a function may hold, counting the nested ones, before
+/// `long_function` warns about it.
+pub const MAX_FUNCTION_LENGTH: usize = 50;
+
+/// Lints the passes of the compiler report themselves, rather than a
+/// [`Lint`] of the registry.
+pub const PASS_LINTS: [&str; 1] = ["unreachable_arms"];
+
+/// Check of the style of programs, which `yuan lint` runs and reports
+/// at the level the user sets for it.
+pub trait Lint {
+
+    /// Name the level of the lint is set by, in snake case.
+    fn name(&self) -> &'static str;
+
+    /// What the lint warns about, in one sentence.
+    fn description(&self) -> &'static str;
+
+    /// Push the warnings about `program` to `warnings`.
+    fn check(&self, program: &Program, warnings: &mut Diagnostics);
+}
+
+/// Lints `yuan lint` runs.
+pub struct LintRegistry {
+    lints: Vec<Box<dyn Lint>>,
+}
+
+impl Default for LintRegistry {
+    fn default() -> LintRegistry {
+        LintRegistry::new()
+    }
+}
+
+impl LintRegistry {
+    pub fn new() -> LintRegistry {
+        LintRegistry {
+            lints: Vec::new(),
+        }
+    }
+
+    /// Registry of the lints built into the compiler.
+    pub fn with_builtins() -> LintRegistry {
+        let mut registry = LintRegistry::new();
+
+        registry.register(Box::new(NamingConvention));
+        registry.register(Box::new(Shadowing));
+        registry.register(Box::new(LongFunction));
+        registry
+    }
+
+    pub fn register(&mut self, lint: Box<dyn Lint>) {
+        self.lints.push(lint);
+    }
+
+    pub fn lints(&self) -> impl Iterator<Item = &dyn Lint> {
+        self.lints.iter().map(Box::as_ref)
+    }
+
+    /// Names of the lints users can allow or deny, those of the passes
+    /// first.
+    pub fn names(&self) -> Vec<&'static str> {
+        PASS_LINTS.into_iter().chain(self.lints().map(|lint| lint.name())).collect()
+    }
+
+    /// Whether `lint` names a lint of the registry or of the passes, or
+    /// stands for every lint.
+    pub fn knows(&self, lint: &str) -> bool {
+        lint == WARNINGS || PASS_LINTS.contains(&lint) || self.lints().any(|known| known.name() == lint)
+    }
+
+    /// Check that the attributes of `program` setting lint levels name
+    /// only lints of the registry.
+    pub fn check_attributes(&self, program: &Program) -> Result<(), Diagnostics> {
+        let mut diagnostics = Diagnostics::new();
+        let mut attributes = Attributes {
+            found: program.attributes.iter()
+                .chain(program.scoped_attributes.iter().flat_map(|(_, attributes)| attributes))
+                .collect(),
+        };
+
+        walk_program(&mut attributes, program);
+
+        for attribute in attributes.found.into_iter().filter(|attribute| Level::from_attribute(&attribute.name).is_some()) {
+            if let Some(lint) = attribute.arguments.iter().find(|lint| !self.knows(lint)) {
+                diagnostics.push(Diagnostic::error(format!("Unknown lint \"{}\"!", lint))
+                    .with_span(attribute.span)
+                    .with_help(format!("lints are {}", self.names().join(", "))));
+            }
+        }
+
+        if diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(diagnostics)
+        }
+    }
+
+    /// Run every lint over `program`, each warning naming the lint that
+    /// reported it.
+    pub fn check(&self, program: &Program) -> Diagnostics {
+        let mut all = Diagnostics::new();
+
+        for lint in &self.lints {
+            let mut warnings = Diagnostics::new();
+
+            lint.check(program, &mut warnings);
+
+            for warning in warnings.iter() {
+                all.push(warning.clone().with_lint(lint.name()));
+            }
+        }
+
+        all
+    }
+}
+
+/// Pass finding the attributes of statements not yet moved to
+/// [`Program::scoped_attributes`].
+struct Attributes<'ast> {
+    found: Vec<&'ast Attribute>,
+}
+
+impl<'ast> Visitor<'ast> for Attributes<'ast> {
+    fn visit_statement(&mut self, program: &'ast Program, statement: &'ast Statement) {
+        if let Statement::Attributed {
+            attributes,
+            ..
+        } = statement {
+            self.found.extend(attributes);
+        }
+
+        walk_statement(self, program, statement);
+    }
+}
+
+/// Functions, variables and parameters in snake case, constants in
+/// upper snake case, and types and variants in camel case.
+pub struct NamingConvention;
+
+impl Lint for NamingConvention {
+    fn name(&self) -> &'static str {
+        "naming_convention"
+    }
+
+    fn description(&self) -> &'static str {
+        "Names follow the case of what they name."
+    }
+
+    fn check(&self, program: &Program, warnings: &mut Diagnostics) {
+        let mut names = Names {
+            warnings,
+        };
+
+        walk_program(&mut names, program);
+    }
+}
+
+struct Names<'w> {
+    warnings: &'w mut Diagnostics,
+}
+
+impl Names<'_> {
+    fn expect(&mut self, what: &str, name: &str, span: Span, case: &str, convert: fn(&str) -> String) {
+        let expected = convert(name);
+
+        if expected != name.trim_start_matches('_') {
+            self.warnings.push(Diagnostic::warning(format!("{} \"{}\" should be named in {}!", what, name, case))
+                .with_span(span)
+                .with_help(format!("rename it to \"{}\"", expected)));
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Names<'_> {
+    fn visit_statement(&mut self, program: &'ast Program, statement: &'ast Statement) {
+        match statement {
+            Statement::VariableDefinition {
+                identifier,
+                span,
+                ..
+            } => self.expect("Variable", identifier, *span, "snake case", snake_case),
+            Statement::ConstantDefinition {
+                identifier,
+                span,
+                ..
+            } => self.expect("Constant", identifier, *span, "upper snake case", upper_snake_case),
+            Statement::FunctionDefinition {
+                callee_name,
+                parameters,
+                span,
+                ..
+            } => {
+                self.expect("Function", callee_name, *span, "snake case", snake_case);
+
+                for parameter in parameters {
+                    self.expect("Parameter", &parameter.name, parameter.span, "snake case", snake_case);
+                }
+            },
+            Statement::For {
+                variable,
+                span,
+                ..
+            } => self.expect("Variable", variable, *span, "snake case", snake_case),
+            Statement::Trait {
+                name,
+                span,
+                ..
+            } => self.expect("Trait", name, *span, "camel case", camel_case),
+            Statement::Enum {
+                name,
+                variants,
+                span,
+            } => {
+                self.expect("Enum", name, *span, "camel case", camel_case);
+
+                for variant in variants {
+                    self.expect("Variant", &variant.name, variant.span, "camel case", camel_case);
+                }
+            },
+            _ => {},
+        }
+
+        walk_statement(self, program, statement);
+    }
+}
+
+/// `name` in snake case, like `parse_file`, without leading `_`.
+fn snake_case(name: &str) -> String {
+    let mut converted = String::new();
+    let mut previous: Option<char> = None;
+
+    for char in name.trim_start_matches('_').chars() {
+        if char.is_uppercase() && previous.is_some_and(|previous| previous.is_lowercase() || previous.is_ascii_digit()) {
+            converted.push('_');
+        }
+
+        converted.extend(char.to_lowercase());
+        previous = Some(char);
+    }
+
+    converted
+}
+
+/// `name` in upper snake case, like `MAX_SIZE`.
+fn upper_snake_case(name: &str) -> String {
+    snake_case(name).to_uppercase()
+}
+
+/// `name` in camel case, like `ParseError`.
+fn camel_case(name: &str) -> String {
+    let mut converted = String::new();
+
+    for word in name.split('_').filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+
+        converted.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+        converted.push_str(chars.as_str());
+    }
+
+    converted
+}
+
+/// Variables defined again in a block where an enclosing one already
+/// defines them.
+pub struct Shadowing;
+
+impl Lint for Shadowing {
+    fn name(&self) -> &'static str {
+        "shadowing"
+    }
+
+    fn description(&self) -> &'static str {
+        "Variables don't hide variables of the same name."
+    }
+
+    fn check(&self, program: &Program, warnings: &mut Diagnostics) {
+        let mut scopes = Scopes {
+            scopes: vec![Vec::new()],
+            warnings,
+        };
+
+        walk_program(&mut scopes, program);
+    }
+}
+
+/// Names of the variables in scope, innermost block last.
+struct Scopes<'ast, 'w> {
+    scopes: Vec<Vec<&'ast str>>,
+    warnings: &'w mut Diagnostics,
+}
+
+impl<'ast> Scopes<'ast, '_> {
+    fn define(&mut self, name: &'ast str, span: Span) {
+        if self.scopes.iter().any(|scope| scope.contains(&name)) {
+            self.warnings.push(Diagnostic::warning(format!("Variable \"{}\" shadows a variable in scope!", name))
+                .with_span(span)
+                .with_help("give it a name of its own"));
+        }
+
+        self.scopes.last_mut().unwrap().push(name);
+    }
+}
+
+impl<'ast> Visitor<'ast> for Scopes<'ast, '_> {
+    fn visit_statement(&mut self, program: &'ast Program, statement: &'ast Statement) {
+        match statement {
+            Statement::VariableDefinition {
+                identifier,
+                span,
+                ..
+            } => {
+                walk_statement(self, program, statement);
+                self.define(identifier, *span);
+            },
+
+            /* Function bodies see the top-level variables but not the
+             * locals of the enclosing functions. */
+            Statement::FunctionDefinition {
+                parameters,
+                ..
+            } => {
+                let top_level = self.scopes[0].clone();
+                let enclosing = mem::replace(&mut self.scopes, vec![top_level, Vec::new()]);
+
+                for parameter in parameters {
+                    self.define(&parameter.name, parameter.span);
+                }
+
+                walk_statement(self, program, statement);
+                self.scopes = enclosing;
+            },
+            Statement::For {
+                variable,
+                span,
+                ..
+            } => {
+                self.scopes.push(Vec::new());
+                self.define(variable, *span);
+                walk_statement(self, program, statement);
+                self.scopes.pop();
+            },
+            Statement::Block { .. } => {
+                self.scopes.push(Vec::new());
+                walk_statement(self, program, statement);
+                self.scopes.pop();
+            },
+            _ => walk_statement(self, program, statement),
+        }
+    }
+}
+
+/// Functions holding more than [`MAX_FUNCTION_LENGTH`] statements.
+pub struct LongFunction;
+
+impl Lint for LongFunction {
+    fn name(&self) -> &'static str {
+        "long_function"
+    }
+
+    fn description(&self) -> &'static str {
+        "Functions hold at most 50 statements."
+    }
+
+    fn check(&self, program: &Program, warnings: &mut Diagnostics) {
+        let mut lengths = Lengths {
+            functions: Vec::new(),
+            open: Vec::new(),
+        };
+
+        walk_program(&mut lengths, program);
+
+        for (name, length, span) in lengths.functions {
+            if length > MAX_FUNCTION_LENGTH {
+                warnings.push(Diagnostic::warning(
+                    format!("Function \"{}\" holds {} statements, more than {}!", name, length, MAX_FUNCTION_LENGTH))
+                    .with_span(span)
+                    .with_help("split it into smaller functions"));
+            }
+        }
+    }
+}
+
+/// Functions with the statements they hold, counting those of the
+/// functions nested in them.
+struct Lengths<'ast> {
+    functions: Vec<(&'ast str, usize, Span)>,
+
+    /// Indices of the functions whose body is being visited.
+    open: Vec<usize>,
+}
+
+impl<'ast> Visitor<'ast> for Lengths<'ast> {
+    fn visit_statement(&mut self, program: &'ast Program, statement: &'ast Statement) {
+        for index in &self.open {
+            self.functions[*index].1 += 1;
+        }
+
+        if let Statement::FunctionDefinition {
+            callee_name,
+            span,
+            ..
+        } = statement {
+            self.open.push(self.functions.len());
+            self.functions.push((callee_name, 0, *span));
+            walk_statement(self, program, statement);
+            self.open.pop();
+        } else {
+            walk_statement(self, program, statement);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_source;
+    use super::*;
+
+    struct Unused;
+
+    impl Lint for Unused {
+        fn name(&self) -> &'static str {
+            "unused"
+        }
+
+        fn description(&self) -> &'static str {
+            "Everything is used."
+        }
+
+        fn check(&self, _program: &Program, _warnings: &mut Diagnostics) {}
+    }
+
+    macro_rules! lint_source {
+        ($lint:expr, $text:expr) => {{
+            let text = $text;
+            let mut registry = LintRegistry::new();
+
+            registry.register(Box::new($lint));
+            registry.check(&parse_source(text).unwrap()).render("main.fang", text)
+        }};
+    }
+
+    #[test]
+    fn naming_convention() {
+        assert_eq!(lint_source!(NamingConvention, "
+const maxSize = 4;
+func parseFile(filePath, _unused) { let MyVar = 1; for Item in [] {} }
+enum shape_kind { Circle, big_square }
+trait Printable {}
+"), "\
+main.fang:2:7: warning: Constant \"maxSize\" should be named in upper snake case!
+main.fang:2:7: help: rename it to \"MAX_SIZE\"
+main.fang:3:6: warning: Function \"parseFile\" should be named in snake case!
+main.fang:3:6: help: rename it to \"parse_file\"
+main.fang:3:16: warning: Parameter \"filePath\" should be named in snake case!
+main.fang:3:16: help: rename it to \"file_path\"
+main.fang:3:41: warning: Variable \"MyVar\" should be named in snake case!
+main.fang:3:41: help: rename it to \"my_var\"
+main.fang:3:56: warning: Variable \"Item\" should be named in snake case!
+main.fang:3:56: help: rename it to \"item\"
+main.fang:4:6: warning: Enum \"shape_kind\" should be named in camel case!
+main.fang:4:6: help: rename it to \"ShapeKind\"
+main.fang:4:27: warning: Variant \"big_square\" should be named in camel case!
+main.fang:4:27: help: rename it to \"BigSquare\"
+");
+    }
+
+    #[test]
+    fn shadowing() {
+        assert_eq!(lint_source!(Shadowing, "
+let total = 0;
+func sum(items) { let total = 0; for items in items {} }
+{ let total = 1; }
+let other = 1;
+"), "\
+main.fang:3:23: warning: Variable \"total\" shadows a variable in scope!
+main.fang:3:23: help: give it a name of its own
+main.fang:3:38: warning: Variable \"items\" shadows a variable in scope!
+main.fang:3:38: help: give it a name of its own
+main.fang:4:7: warning: Variable \"total\" shadows a variable in scope!
+main.fang:4:7: help: give it a name of its own
+");
+    }
+
+    #[test]
+    fn long_functions() {
+        let body = "a;".repeat(MAX_FUNCTION_LENGTH);
+        let text = format!("func long() {{ {} func inner() {{ b; }} }} func short() {{ {} }}", body, body);
+        let diagnostics = LintRegistry::with_builtins().check(&parse_source(&text).unwrap());
+        let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+
+        assert_eq!(messages, ["Function \"long\" holds 52 statements, more than 50!"]);
+        assert_eq!(diagnostics.iter().next().unwrap().lint, Some("long_function"));
+    }
+
+    #[test]
+    fn lint_attributes() {
+        let check = |registry: &LintRegistry, text: &str| registry.check_attributes(&parse_source(text).unwrap())
+            .map_err(|diagnostics| diagnostics.render("main.fang", text));
+        let mut registry = LintRegistry::with_builtins();
+
+        assert_eq!(check(&registry, "#![allow(shadowing, warnings)]
+#[deny(unreachable_arms)] let a = 1;"), Ok(()));
+        assert_eq!(check(&registry, "#![allow(unused)]
+#[warn(shadowing)] let a = 1;
+#[deny(shadowing, long)] let b = 1;"), Err(String::from("\
+main.fang:1:1: error: Unknown lint \"unused\"!
+main.fang:1:1: help: lints are unreachable_arms, naming_convention, shadowing, long_function
+main.fang:3:1: error: Unknown lint \"long\"!
+main.fang:3:1: help: lints are unreachable_arms, naming_convention, shadowing, long_function
+")));
+
+        /* Lints registered besides the built-in ones are known too. */
+        registry.register(Box::new(Unused));
+        assert_eq!(check(&registry, "#![allow(unused)]"), Ok(()));
+        assert!(registry.knows("unused"));
+        assert_eq!(registry.names().last(), Some(&"unused"));
+    }
+}
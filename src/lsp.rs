@@ -0,0 +1,783 @@
+use crate::diagnostic::{Diagnostics, LintLevels, Severity};
+use crate::highlight::{classify, TokenClass};
+use crate::lexer::doc_comment_before;
+use crate::parser::{Expression, ParseOptions, Program, Statement};
+use crate::printer::{format_parameter, format_signature, format_variant};
+use crate::query::{Compiled, Database};
+use crate::resolver::{SymbolId, SymbolIndex, SymbolKind};
+use crate::span::{SourceFile, Span};
+use serde_json::{json, Value as Json};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+/// LSP `SymbolKind` of enums.
+const SYMBOL_KIND_ENUM: u32 = 10;
+
+/// LSP `SymbolKind` of functions.
+const SYMBOL_KIND_FUNCTION: u32 = 12;
+
+/// LSP `SymbolKind` of variables.
+const SYMBOL_KIND_VARIABLE: u32 = 13;
+
+/// LSP `SymbolKind` of constants.
+const SYMBOL_KIND_CONSTANT: u32 = 14;
+
+/// LSP `SymbolKind` of enum variants.
+const SYMBOL_KIND_ENUM_MEMBER: u32 = 22;
+
+/// Semantic token types, indexed by `token_type`.
+const TOKEN_TYPES: [&str; 7] = [
+    "keyword",
+    "variable",
+    "function",
+    "number",
+    "string",
+    "comment",
+    "operator",
+];
+
+/// JSON-RPC error code for requests the server doesn't implement.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Language server speaking LSP over JSON-RPC.
+///
+/// Documents are synchronized in full on every change, then go through
+/// the queries of a [`Database`] to publish fresh diagnostics and
+/// rebuild their symbol index, which only runs the passes over what
+/// the change affected. Other requests are answered from the parsed
+/// program and the index.
+pub struct Server {
+
+    /// Every open document, keyed by URI.
+    documents: HashMap<String, Document>,
+
+    database: Database,
+}
+
+/// Open document with everything known about it.
+struct Document {
+    file: SourceFile,
+
+    /// Program compiled from the text, or the errors of the text.
+    compiled: Rc<Compiled>,
+
+    /// Symbols of the program, empty if the text has errors.
+    index: Rc<SymbolIndex>,
+}
+
+impl Document {
+    fn new(database: &mut Database, uri: &str, text: &str) -> (Document, Diagnostics) {
+        database.set_text(uri, text);
+        let document = Document {
+            file: SourceFile::new(uri, text),
+            compiled: database.compile(uri),
+            index: database.symbols(uri),
+        };
+
+        (document, database.diagnostics(uri))
+    }
+
+    /// Program compiled from the text, `None` if the text has errors.
+    fn program(&self) -> Option<&Program> {
+        self.compiled.as_ref().as_ref().ok().map(|program| program.program())
+    }
+}
+
+/// Something defined in a document: a function, variable or parameter.
+struct Definition {
+    span: Span,
+
+    /// Declaration as it'd be written in source code.
+    signature: String,
+}
+
+/// Collect every definition in `statements`, in source order.
+fn collect_definitions(
+    expressions: &[Expression],
+    statements: &[Statement],
+    definitions: &mut Vec<Definition>
+) {
+    for statement in statements.iter().map(Statement::unattributed) {
+        match statement {
+            Statement::VariableDefinition {
+                span,
+                ..
+            } |
+            Statement::ConstantDefinition {
+                span,
+                ..
+            } => definitions.push(Definition {
+                span: *span,
+                signature: format_signature(expressions, statement).unwrap(),
+            }),
+            Statement::FunctionDefinition {
+                parameters,
+                statements,
+                span,
+                ..
+            } => {
+                definitions.push(Definition {
+                    span: *span,
+                    signature: format_signature(expressions, statement).unwrap(),
+                });
+
+                for parameter in parameters {
+                    definitions.push(Definition {
+                        span: parameter.span,
+                        signature: format_parameter(expressions, parameter),
+                    });
+                }
+
+                collect_definitions(expressions, statements, definitions);
+            },
+            Statement::Block {
+                statements,
+            } => collect_definitions(expressions, statements, definitions),
+            Statement::For {
+                variable,
+                statements,
+                span,
+                ..
+            } => {
+                definitions.push(Definition {
+                    span: *span,
+                    signature: format!("let {}", variable),
+                });
+
+                collect_definitions(expressions, statements, definitions);
+            },
+            Statement::Enum {
+                variants,
+                span,
+                ..
+            } => {
+                definitions.push(Definition {
+                    span: *span,
+                    signature: format_signature(expressions, statement).unwrap(),
+                });
+
+                for variant in variants {
+                    definitions.push(Definition {
+                        span: variant.span,
+                        signature: format_variant(expressions, variant),
+                    });
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+/// LSP position of the byte `offset` of the text of `file`.
+fn offset_to_position(file: &SourceFile, offset: usize) -> Json {
+    let (line, character) = file.position(offset);
+
+    json!({ "line": line, "character": character })
+}
+
+/// Byte offset of the LSP position in the text of `file`.
+fn position_to_offset(file: &SourceFile, position: &Json) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+
+    file.offset(line, character)
+}
+
+fn span_to_range(file: &SourceFile, span: Span) -> Json {
+    json!({
+        "start": offset_to_position(file, span.start),
+        "end": offset_to_position(file, span.end),
+    })
+}
+
+fn document_symbols(file: &SourceFile, expressions: &[Expression], statements: &[Statement]) -> Vec<Json> {
+    let mut symbols = Vec::new();
+
+    for statement in statements.iter().map(Statement::unattributed) {
+        match statement {
+            Statement::VariableDefinition {
+                identifier,
+                span,
+                ..
+            } => symbols.push(json!({
+                "name": identifier,
+                "detail": format_signature(expressions, statement),
+                "kind": SYMBOL_KIND_VARIABLE,
+                "range": span_to_range(file, *span),
+                "selectionRange": span_to_range(file, *span),
+            })),
+            Statement::ConstantDefinition {
+                identifier,
+                span,
+                ..
+            } => symbols.push(json!({
+                "name": identifier,
+                "detail": format_signature(expressions, statement),
+                "kind": SYMBOL_KIND_CONSTANT,
+                "range": span_to_range(file, *span),
+                "selectionRange": span_to_range(file, *span),
+            })),
+            Statement::FunctionDefinition {
+                callee_name,
+                parameters,
+                statements,
+                span,
+                ..
+            } => {
+                let mut children: Vec<Json> = parameters.iter().map(|parameter| json!({
+                    "name": parameter.name,
+                    "detail": format_parameter(expressions, parameter),
+                    "kind": SYMBOL_KIND_VARIABLE,
+                    "range": span_to_range(file, parameter.span),
+                    "selectionRange": span_to_range(file, parameter.span),
+                })).collect();
+
+                children.extend(document_symbols(file, expressions, statements));
+                symbols.push(json!({
+                    "name": callee_name,
+                    "detail": format_signature(expressions, statement),
+                    "kind": SYMBOL_KIND_FUNCTION,
+                    "range": span_to_range(file, *span),
+                    "selectionRange": span_to_range(file, *span),
+                    "children": children,
+                }));
+            },
+            Statement::Block {
+                statements,
+            } |
+            Statement::For {
+                statements,
+                ..
+            } => symbols.extend(document_symbols(file, expressions, statements)),
+            Statement::Enum {
+                name,
+                variants,
+                span,
+            } => {
+                let children: Vec<Json> = variants.iter().map(|variant| json!({
+                    "name": variant.name,
+                    "detail": format_variant(expressions, variant),
+                    "kind": SYMBOL_KIND_ENUM_MEMBER,
+                    "range": span_to_range(file, variant.span),
+                    "selectionRange": span_to_range(file, variant.span),
+                })).collect();
+
+                symbols.push(json!({
+                    "name": name,
+                    "detail": format_signature(expressions, statement),
+                    "kind": SYMBOL_KIND_ENUM,
+                    "range": span_to_range(file, *span),
+                    "selectionRange": span_to_range(file, *span),
+                    "children": children,
+                }));
+            },
+            _ => {},
+        }
+    }
+
+    symbols
+}
+
+fn hover(document: &Document, program: &Program, offset: usize) -> Json {
+    let mut definitions = Vec::new();
+    let (id, span) = match document.index.symbol_at(offset) {
+        Some(symbol) => symbol,
+        None => return Json::Null,
+    };
+    let symbol = document.index.symbol(id);
+
+    if symbol.kind == SymbolKind::Builtin {
+        let value = format!("```fang\nfunc {}\n```\n\nBuiltin function of the prelude.", symbol.name);
+
+        return json!({
+            "contents": { "kind": "markdown", "value": value },
+            "range": span_to_range(&document.file, span),
+        });
+    }
+
+    collect_definitions(&program.expressions, &program.statements, &mut definitions);
+
+    let definition = match definitions.iter().find(|definition| definition.span == symbol.span) {
+        Some(definition) => definition,
+        None => return Json::Null,
+    };
+    let mut value = format!("```fang\n{}\n```", definition.signature);
+
+    if let Some(doc) = doc_comment_before(&document.file.text, definition.span.start) {
+        value.push_str("\n\n");
+        value.push_str(&doc);
+    }
+
+    json!({
+        "contents": { "kind": "markdown", "value": value },
+        "range": span_to_range(&document.file, span),
+    })
+}
+
+fn location(uri: &str, file: &SourceFile, span: Span) -> Json {
+    json!({ "uri": uri, "range": span_to_range(file, span) })
+}
+
+fn token_type(class: TokenClass) -> usize {
+    match class {
+        TokenClass::Keyword => 0,
+        TokenClass::Identifier => 1,
+        TokenClass::Function => 2,
+        TokenClass::Number => 3,
+        TokenClass::String => 4,
+        TokenClass::Comment => 5,
+        TokenClass::Operator => 6,
+    }
+}
+
+/// Semantic tokens of `text`, encoded relative to each other.
+///
+/// Ranges spanning several lines, like multi-line comments, are
+/// split into one token per line since not every client supports
+/// multi-line tokens.
+fn semantic_tokens(file: &SourceFile) -> Vec<usize> {
+    let text = &file.text;
+    let mut data: Vec<usize> = Vec::new();
+    let mut previous_line = 0;
+    let mut previous_character = 0;
+
+    for highlight in classify(text) {
+        let mut start = highlight.span.start;
+
+        for part in text[highlight.span.start..highlight.span.end].split('\n') {
+            let (line, character) = file.position(start);
+            let length = part.trim_end_matches('\r').encode_utf16().count();
+
+            start += part.len() + 1;
+
+            if length == 0 {
+                continue;
+            }
+
+            if line != previous_line {
+                previous_character = 0;
+            }
+
+            data.extend([
+                line - previous_line,
+                character - previous_character,
+                length,
+                token_type(highlight.class),
+                0,
+            ]);
+            previous_line = line;
+            previous_character = character;
+        }
+    }
+
+    data
+}
+
+fn response(id: &Json, result: Json) -> Json {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    notification("textDocument/publishDiagnostics", json!({
+        "uri": uri,
+        "diagnostics": diagnostics,
+    }))
+}
+
+/// Read one JSON-RPC message framed by a `Content-Length` header.
+fn read_message(input: &mut impl BufRead) -> Option<Json> {
+    let mut length: Option<usize> = None;
+
+    loop {
+        let mut line = String::new();
+
+        if input.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+
+    let mut body = vec![0; length?];
+
+    input.read_exact(&mut body).ok()?;
+
+    serde_json::from_slice(&body).ok()
+}
+
+/// Write one JSON-RPC message framed by a `Content-Length` header.
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+impl Default for Server {
+    fn default() -> Server {
+        Server::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server {
+            documents: HashMap::new(),
+            database: Database::new(ParseOptions::default(), Vec::new(), LintLevels::new()),
+        }
+    }
+
+    /// Replace the text of a document, returning its new diagnostics.
+    fn update(&mut self, uri: &str, text: &str) -> Json {
+        let (document, reported) = Document::new(&mut self.database, uri, text);
+        let diagnostics: Vec<Json> = reported.iter().map(|diagnostic| json!({
+            "range": span_to_range(&document.file, diagnostic.span.unwrap_or_default()),
+            "severity": match diagnostic.severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+            },
+            "source": "yuan",
+            "message": match &diagnostic.help {
+                Some(help) => format!("{}\n{}", diagnostic.message, help),
+                None => diagnostic.message.to_owned(),
+            },
+        })).collect();
+
+        self.documents.insert(uri.to_owned(), document);
+
+        publish_diagnostics(uri, diagnostics)
+    }
+
+    /// Document targeted by a request, with its program if it parsed.
+    fn document(&self, params: &Json) -> Option<(&Document, &Program)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let document = self.documents.get(uri)?;
+
+        Some((document, document.program()?))
+    }
+
+    /// Document targeted by a request and the symbol under its position.
+    fn symbol_at<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a Document, SymbolId)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let document = self.documents.get(uri)?;
+        let offset = position_to_offset(&document.file, &params["position"]);
+        let (id, _) = document.index.symbol_at(offset)?;
+
+        Some((uri, document, id))
+    }
+
+    /// Handle one message from the client, returning the messages
+    /// to send back.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = &message["id"];
+
+        match method {
+            "initialize" => vec![response(id, json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "documentSymbolProvider": true,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "semanticTokensProvider": {
+                        "legend": {
+                            "tokenTypes": TOKEN_TYPES,
+                            "tokenModifiers": [],
+                        },
+                        "full": true,
+                    },
+                },
+                "serverInfo": {
+                    "name": "yuan",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            }))],
+            "shutdown" => vec![response(id, Json::Null)],
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+
+                vec![self.update(uri, text)]
+            },
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                let changes = params["contentChanges"].as_array();
+
+                /* Full synchronization: the last change holds the text. */
+                match changes.and_then(|changes| changes.last()) {
+                    Some(change) => vec![self.update(uri, change["text"].as_str().unwrap_or(""))],
+                    None => Vec::new(),
+                }
+            },
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+
+                self.documents.remove(uri);
+                self.database.remove(uri);
+
+                vec![publish_diagnostics(uri, Vec::new())]
+            },
+            "textDocument/documentSymbol" => {
+                let symbols = match self.document(params) {
+                    Some((document, program)) => document_symbols(&document.file, &program.expressions, &program.statements),
+                    None => Vec::new(),
+                };
+
+                vec![response(id, Json::Array(symbols))]
+            },
+            "textDocument/hover" => {
+                let result = match self.document(params) {
+                    Some((document, program)) => {
+                        let offset = position_to_offset(&document.file, &params["position"]);
+
+                        hover(document, program, offset)
+                    },
+                    None => Json::Null,
+                };
+
+                vec![response(id, result)]
+            },
+            "textDocument/semanticTokens/full" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                let data = match self.documents.get(uri) {
+                    Some(document) => semantic_tokens(&document.file),
+                    None => Vec::new(),
+                };
+
+                vec![response(id, json!({ "data": data }))]
+            },
+            "textDocument/definition" => {
+                /* Builtins are defined outside the source. */
+                let result = match self.symbol_at(params) {
+                    Some((uri, document, symbol)) if document.index.symbol(symbol).kind != SymbolKind::Builtin =>
+                        location(uri, &document.file, document.index.symbol(symbol).span),
+                    _ => Json::Null,
+                };
+
+                vec![response(id, result)]
+            },
+            "textDocument/references" => {
+                let mut locations = Vec::new();
+
+                if let Some((uri, document, symbol)) = self.symbol_at(params) {
+                    let mut spans = document.index.references(symbol);
+
+                    if params["context"]["includeDeclaration"] == true
+                        && document.index.symbol(symbol).kind != SymbolKind::Builtin {
+                        spans.insert(0, document.index.symbol(symbol).span);
+                    }
+
+                    for span in spans {
+                        locations.push(location(uri, &document.file, span));
+                    }
+                }
+
+                vec![response(id, Json::Array(locations))]
+            },
+
+            /* Notifications need no answer, unknown requests do. */
+            _ if id.is_null() => Vec::new(),
+            _ => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": METHOD_NOT_FOUND,
+                    "message": format!("Method \"{}\" is not supported!", method),
+                },
+            })],
+        }
+    }
+
+    /// Serve the client on the standard input and output until it
+    /// sends `exit` or closes the connection, failing if a reply can't
+    /// be written.
+    pub fn run(&mut self) -> io::Result<()> {
+        let stdin = std::io::stdin();
+        let stdout = std::io::stdout();
+        let mut input = stdin.lock();
+        let mut output = stdout.lock();
+
+        while let Some(message) = read_message(&mut input) {
+            if message["method"] == "exit" {
+                break;
+            }
+
+            for reply in self.handle(&message) {
+                write_message(&mut output, &reply)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(server: &mut Server, text: &str) -> Vec<Json> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": { "uri": "file:///main.fang", "text": text },
+            },
+        }))
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut server = Server::new();
+        let replies = open(&mut server, "let value = 17;\nlet = 3;");
+
+        assert_eq!(replies[0]["params"]["diagnostics"][0]["range"]["start"],
+                   json!({ "line": 1, "character": 4 }));
+        assert_eq!(replies[0]["params"]["diagnostics"][0]["message"], "Expected identifier!");
+
+        let replies = open(&mut server, "let value = 17;");
+
+        assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn symbols_and_hover() {
+        let mut server = Server::new();
+
+        open(&mut server, "/// Add two numbers.\nfunc add_num(a: int, b: int) -> int {\n    return a + b;\n}\nlet sum = add_num(1, 2);");
+
+        let replies: Vec<Json> = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "textDocument/documentSymbol",
+            "params": { "textDocument": { "uri": "file:///main.fang" } },
+        }));
+        assert_eq!(replies[0]["result"][0]["name"], "add_num");
+        assert_eq!(replies[0]["result"][0]["children"][1]["name"], "b");
+        assert_eq!(replies[0]["result"][1]["name"], "sum");
+
+        let replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/hover",
+            "params": {
+                "textDocument": { "uri": "file:///main.fang" },
+                "position": { "line": 4, "character": 12 },
+            },
+        }));
+        assert_eq!(replies[0]["result"]["contents"]["value"],
+                   "```fang\nfunc add_num(a: int, b: int) -> int\n```\n\nAdd two numbers.");
+
+        /* The doc comment of a function isn't that of its parameters. */
+        let replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "textDocument/hover",
+            "params": {
+                "textDocument": { "uri": "file:///main.fang" },
+                "position": { "line": 2, "character": 11 },
+            },
+        }));
+        assert_eq!(replies[0]["result"]["contents"]["value"], "```fang\na: int\n```");
+    }
+
+    #[test]
+    fn definition_and_references() {
+        let mut server = Server::new();
+        let request = |method: &str| json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": method,
+            "params": {
+                "textDocument": { "uri": "file:///main.fang" },
+                "position": { "line": 1, "character": 8 },
+                "context": { "includeDeclaration": true },
+            },
+        });
+
+        open(&mut server, "let value = 17;\nvalue = value + 1;\n{ let value = 2; }");
+
+        let replies = server.handle(&request("textDocument/definition"));
+
+        assert_eq!(replies[0]["result"]["range"]["start"], json!({ "line": 0, "character": 4 }));
+
+        let replies = server.handle(&request("textDocument/references"));
+        let starts: Vec<&Json> = replies[0]["result"].as_array().unwrap().iter()
+            .map(|location| &location["range"]["start"])
+            .collect();
+
+        assert_eq!(starts, vec![
+            &json!({ "line": 0, "character": 4 }),
+            &json!({ "line": 1, "character": 0 }),
+            &json!({ "line": 1, "character": 8 }),
+        ]);
+
+        /* Builtins have uses but no definition in the source. */
+        open(&mut server, "print(1);\nlet a = print(2);");
+
+        let replies = server.handle(&request("textDocument/definition"));
+
+        assert_eq!(replies[0]["result"], Json::Null);
+
+        let replies = server.handle(&request("textDocument/references"));
+
+        assert_eq!(replies[0]["result"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn semantic_tokens_full() {
+        let mut server = Server::new();
+
+        open(&mut server, "/* a\nb */ let x;");
+
+        let replies: Vec<Json> = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 4,
+            "method": "textDocument/semanticTokens/full",
+            "params": { "textDocument": { "uri": "file:///main.fang" } },
+        }));
+        assert_eq!(replies[0]["result"]["data"], json!([
+            0, 0, 4, 5, 0,
+            1, 0, 4, 5, 0,
+            0, 5, 3, 0, 0,
+            0, 4, 1, 1, 0,
+        ]));
+    }
+
+    #[test]
+    fn write_messages() {
+        struct Closed;
+
+        impl Write for Closed {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut output: Vec<u8> = Vec::new();
+
+        write_message(&mut output, &json!({ "id": 1 })).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "Content-Length: 8\r\n\r\n{\"id\":1}");
+
+        /* A client gone away fails the server rather than panicking it. */
+        assert_eq!(write_message(&mut Closed, &json!({ "id": 1 })).map_err(|err| err.kind()), Err(io::ErrorKind::BrokenPipe));
+    }
+}
@@ -0,0 +1,346 @@
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::parser::{Argument, ExprId, Expression, Program, Statement};
+use crate::visit::{fold_program, fold_statements, walk_statement_mut, Folder};
+use std::collections::HashMap;
+
+/// How deeply the expansion of a macro may invoke macros in turn,
+/// which stops macros invoking themselves.
+pub const EXPANSION_LIMIT: usize = 64;
+
+/// Macro defined by the program.
+struct Macro {
+    parameters: Vec<String>,
+    statements: Vec<Statement>,
+}
+
+/// Replace every invocation of a macro by the statements of its body
+/// and drop the macro definitions, so later passes never see a macro.
+///
+/// The expression passed for a parameter is evaluated wherever the
+/// body uses the parameter. Variables the body defines are renamed
+/// apart, so they neither shadow the variables of the invoking code
+/// nor are shadowed by them.
+pub fn expand_macros(program: &mut Program) -> Result<(), Diagnostics> {
+    let mut expander = Expander {
+        macros: HashMap::new(),
+        depth: 0,
+        expansions: 0,
+        diagnostics: Diagnostics::new(),
+    };
+    let mut statements: Vec<Statement> = Vec::new();
+
+    for statement in std::mem::take(&mut program.statements) {
+        match statement {
+            Statement::MacroDefinition {
+                name,
+                parameters,
+                statements,
+                span,
+            } => {
+                if expander.macros.contains_key(&name) {
+                    expander.diagnostics.push(Diagnostic::error(
+                        format!("Macro \"{}\" is already defined!", name)).with_span(span));
+                    continue;
+                }
+
+                expander.macros.insert(name, Macro {
+                    parameters,
+                    statements,
+                });
+            },
+            statement => statements.push(statement),
+        }
+    }
+
+    program.statements = statements;
+    fold_program(&mut expander, program);
+
+    for statement in &mut program.statements {
+        statement.visit_ids(&mut |id| expander.reject_invocations(&program.expressions, *id));
+    }
+
+    if expander.diagnostics.has_errors() {
+        return Err(expander.diagnostics);
+    }
+
+    Ok(())
+}
+
+/// Expressions and variable names a macro body is instantiated with.
+struct Substitution {
+
+    /// Expression passed for each parameter.
+    arguments: HashMap<String, ExprId>,
+
+    /// New name of each variable the body defines.
+    renames: HashMap<String, String>,
+}
+
+struct Expander {
+    macros: HashMap<String, Macro>,
+
+    /// How many expansions the statements being expanded are from the
+    /// source.
+    depth: usize,
+
+    /// Number of macro invocations expanded so far, numbering the
+    /// variables renamed apart.
+    expansions: usize,
+    diagnostics: Diagnostics,
+}
+
+/// Replaces the invocations of macros by the expanded statements of
+/// their bodies.
+impl Folder for Expander {
+    fn fold_statement(&mut self, expressions: &mut Vec<Expression>, mut statement: Statement) -> Vec<Statement> {
+        match &statement {
+            Statement::Expression {
+                expression,
+            } |
+            Statement::Return {
+                expression,
+                implicit: true,
+            } if self.invokes_macro(expressions, *expression) => {
+                let body = self.instantiate(expressions, *expression);
+
+                self.depth += 1;
+                let expanded: Vec<Statement> = fold_statements(self, expressions, body);
+                self.depth -= 1;
+
+                expanded
+            },
+            Statement::MacroDefinition {
+                span,
+                ..
+            } => {
+                self.diagnostics.push(Diagnostic::error("Macros must be defined at the top level!")
+                    .with_span(*span));
+
+                Vec::new()
+            },
+            _ => {
+                walk_statement_mut(self, expressions, &mut statement);
+
+                vec![statement]
+            },
+        }
+    }
+}
+
+impl Expander {
+
+    /// Whether `expression` is a call of a macro.
+    fn invokes_macro(&self, expressions: &[Expression], expression: ExprId) -> bool {
+        match &expressions[expression.index()] {
+            Expression::FunctionCall {
+                callee_name,
+                ..
+            } => self.macros.contains_key(callee_name),
+            _ => false,
+        }
+    }
+
+    /// Statements of the body of the macro `invocation` calls, with
+    /// the arguments of the call in place of the parameters.
+    fn instantiate(&mut self, expressions: &mut Vec<Expression>, invocation: ExprId) -> Vec<Statement> {
+        let (name, arguments, span) = match &expressions[invocation.index()] {
+            Expression::FunctionCall {
+                callee_name,
+                arguments,
+                span,
+            } => (callee_name.clone(), arguments.clone(), *span),
+            _ => unreachable!("only calls invoke macros"),
+        };
+        let parameters = self.macros[&name].parameters.clone();
+        let mut statements = self.macros[&name].statements.clone();
+        let mut substitution = Substitution {
+            arguments: HashMap::new(),
+            renames: HashMap::new(),
+        };
+
+        if self.depth == EXPANSION_LIMIT {
+            self.diagnostics.push(Diagnostic::error(format!("Macro \"{}\" expands too deeply!", name))
+                .with_span(span)
+                .with_help(format!("expansions may invoke macros {} levels deep", EXPANSION_LIMIT)));
+            return Vec::new();
+        }
+
+        if arguments.iter().any(|argument| argument.name.is_some()) {
+            self.diagnostics.push(Diagnostic::error(
+                format!("Macro \"{}\" takes no named arguments!", name)).with_span(span));
+            return Vec::new();
+        }
+
+        if arguments.len() != parameters.len() {
+            self.diagnostics.push(Diagnostic::error(
+                format!("Macro \"{}\" expects {} arguments but got {}!",
+                    name, parameters.len(), arguments.len())).with_span(span));
+            return Vec::new();
+        }
+
+        for (parameter, Argument { value, .. }) in parameters.into_iter().zip(arguments) {
+            substitution.arguments.insert(parameter, value);
+        }
+
+        self.expansions += 1;
+        rename_definitions(&mut statements, &mut substitution.renames, self.expansions);
+
+        for statement in &mut statements {
+            statement.visit_ids(&mut |id| *id = copy(expressions, *id, &substitution));
+        }
+
+        statements
+    }
+
+    /// Report the invocations of macros within the expression `id`,
+    /// since only a statement can be replaced by a macro body.
+    fn reject_invocations(&mut self, expressions: &[Expression], id: ExprId) {
+        let mut expression = expressions[id.index()].clone();
+
+        if let Expression::FunctionCall {
+            callee_name,
+            span,
+            ..
+        } = &expression {
+            if self.macros.contains_key(callee_name) {
+                self.diagnostics.push(Diagnostic::error(
+                    format!("Macro \"{}\" can only be invoked as a statement!", callee_name))
+                    .with_span(*span));
+            }
+        }
+
+        expression.visit_ids(&mut |child| self.reject_invocations(expressions, *child));
+    }
+}
+
+/// Copy the expression `id` of a macro body and every expression under
+/// it, substituting the parameters and renamed variables.
+fn copy(expressions: &mut Vec<Expression>, id: ExprId, substitution: &Substitution) -> ExprId {
+    let mut expression = expressions[id.index()].clone();
+
+    if let Expression::Identifier {
+        name,
+        ..
+    } = &mut expression {
+        if let Some(argument) = substitution.arguments.get(name) {
+            return *argument;
+        }
+
+        if let Some(rename) = substitution.renames.get(name) {
+            *name = rename.clone();
+        }
+    }
+
+    expression.visit_ids(&mut |child| *child = copy(expressions, *child, substitution));
+    expressions.push(expression);
+
+    ExprId(expressions.len() as u32 - 1)
+}
+
+/// Rename the variables `statements` define, and those of the blocks
+/// and loops nested in them, recording their new names in `renames`.
+/// Identifiers can't hold `#`, so no other variable has such a name.
+fn rename_definitions(statements: &mut [Statement], renames: &mut HashMap<String, String>, expansion: usize) {
+    for statement in statements {
+        match statement {
+            Statement::VariableDefinition {
+                identifier,
+                ..
+            } => {
+                let rename = format!("{}#{}", identifier, expansion);
+
+                renames.insert(std::mem::replace(identifier, rename.clone()), rename);
+            },
+            Statement::For {
+                variable,
+                statements,
+                ..
+            } => {
+                let rename = format!("{}#{}", variable, expansion);
+
+                renames.insert(std::mem::replace(variable, rename.clone()), rename);
+                rename_definitions(statements, renames, expansion);
+            },
+            Statement::Block {
+                statements,
+            } => rename_definitions(statements, renames, expansion),
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_str;
+    use crate::interpreter::{Env, Value};
+    use crate::parser::parse_source;
+    use crate::printer::format_program;
+    use super::*;
+
+    macro_rules! expand_source {
+        ($text:expr) => {{
+            let mut program = parse_source($text).unwrap();
+
+            expand_macros(&mut program).map(|_| format_program(&program))
+        }};
+    }
+
+    #[test]
+    fn expansion() {
+        assert_eq!(expand_source!("macro twice(x) { x; x; } twice(print(1)); func f() { twice(g()); }"), Ok(String::from("\
+print(1);
+print(1);
+
+func f() {
+    g();
+    g();
+}
+")));
+
+        /* Macros may invoke macros defined after them. */
+        assert_eq!(expand_source!("macro four(x) { twice(x); twice(x); } macro twice(x) { x; x; } four(a);"),
+            Ok(String::from("a;\na;\na;\na;\n")));
+    }
+
+    #[test]
+    fn hygiene() {
+        let mut env = Env::with_builtins();
+        let program = compile_str("
+macro swap(a, b) {
+    let tmp = a;
+    a = b;
+    b = tmp;
+}
+
+let tmp = 1;
+let other = 2;
+swap(tmp, other);
+tmp * 10 + other;
+").unwrap();
+
+        assert_eq!(program.eval(&mut env), Ok(Value::Int(21)));
+        assert_eq!(expand_source!("macro count(n) { let i = n; i; } count(i);"),
+            Ok(String::from("let i#1 = i;\ni#1;\n")));
+    }
+
+    #[test]
+    fn errors() {
+        let errors = [
+            ("macro f() { f(); } f();", "Macro \"f\" expands too deeply!"),
+            ("macro f(x) {} f();", "Macro \"f\" expects 1 arguments but got 0!"),
+            ("macro f(x) {} f(x = 1);", "Macro \"f\" takes no named arguments!"),
+            ("macro f() {} macro f() {}", "Macro \"f\" is already defined!"),
+            ("func g() { macro f() {} }", "Macros must be defined at the top level!"),
+            ("macro f() {} let a = f();", "Macro \"f\" can only be invoked as a statement!"),
+            ("macro f(x) { let a = x; } f(f(1));", "Macro \"f\" can only be invoked as a statement!"),
+        ];
+
+        for (text, message) in errors {
+            let diagnostics = expand_source!(text).unwrap_err();
+
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{}", text);
+        }
+
+        assert_eq!(compile_str("macro one() { 1; } one();").unwrap().eval(&mut Env::new()), Ok(Value::Int(1)));
+    }
+}
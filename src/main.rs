@@ -1,24 +1,1239 @@
-mod lexer;
-mod parser;
-mod frontend;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use fang::cache::Cache;
+use fang::conditional::configure;
+use fang::coverage::Coverage;
+use fang::debugger::Debugger;
+use fang::diagnostic::{Level, LintLevels, WARNINGS};
+use fang::doc::{document_index, document_module};
+use fang::frontend::Frontend;
+use fang::interpreter::{Env, Interpreter};
+use fang::lint::LintRegistry;
+use fang::lsp::Server;
+use fang::manifest::{create_project, find_root, load_packages, MANIFEST_NAME};
+use fang::parser::{parse_source_with, Edition, ParseOptions, Program};
+use fang::printer::format_program;
+use fang::profiler::Profiler;
+use fang::query::Database;
+use fang::random::Rng;
+use fang::repl::Repl;
+use fang::span::SourceFile;
+use fang::tags::{collect_tags, ctags, etags, Tag};
+use fang::testing::{collect_tests, run_test, run_test_with_hooks, Test};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::panic;
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
-use clap::Parser;
-use frontend::Frontend;
+/// How often `yuan check --watch` polls the files for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Exit code when the files don't compile or the program fails.
+const EXIT_ERROR: u8 = 1;
+
+/// Exit code when the command line makes no sense, as clap uses too.
+const EXIT_USAGE: u8 = 2;
+
+/// Exit code when the compiler itself fails, as Rust uses for panics.
+const EXIT_INTERNAL_ERROR: u8 = 101;
+
+/// Where users report bugs in the compiler.
+const BUG_REPORT_URL: &str = "https://github.com/laplacedoge/fang-lang/issues";
 
 #[derive(Parser)]
 #[command(name = "yuan")]
 #[command(version = "1.0.0")]
 #[command(about = "The compiler for Fang programming language", long_about = None)]
 struct Cli {
-    file_path: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+
+    /// Compile source files and emit the selected artifact.
+    Build {
+
+        /// Files to compile, the entry point of the project in the
+        /// current directory if none is given.
+        file_paths: Vec<String>,
+
+        /// Kind of artifact to emit.
+        #[arg(long, value_enum, default_value_t = Emit::Ast)]
+        emit: Emit,
+
+        /// File to write the artifact to instead of the standard output,
+        /// or instead of the project's `target` directory.
+        #[arg(short, long)]
+        output_path: Option<String>,
+
+        /// Directory caching the parse results of unchanged files,
+        /// `target/cache` of the project the current directory is in by
+        /// default, or `.fang-cache` outside of any project.
+        #[arg(long)]
+        cache_dir: Option<String>,
+
+        /// Parse every file from scratch without using the cache.
+        #[arg(long)]
+        no_cache: bool,
+
+        #[command(flatten)]
+        syntax: Syntax,
+
+        #[command(flatten)]
+        config: Config,
+    },
+
+    /// Compile and run source files.
+    Run {
+
+        /// Files to run, the entry point of the project in the current
+        /// directory if none is given.
+        file_paths: Vec<String>,
+
+        /// Arguments after `--`, which the program gets from `args()`.
+        #[arg(last = true)]
+        args: Vec<String>,
+
+        /// Seed of the numbers `rand_int` draws, for the program to draw
+        /// the same ones on every run, the time of the system if none
+        /// is given.
+        #[arg(long)]
+        seed: Option<u64>,
+
+        #[command(flatten)]
+        instrumentation: Instrumentation,
+
+        #[command(flatten)]
+        syntax: Syntax,
+
+        #[command(flatten)]
+        config: Config,
+    },
+
+    /// Run the functions of source files marked `#[test]`, each in an
+    /// environment of its own, reporting those that fail.
+    Test {
+
+        /// Files to test, the entry point of the project in the current
+        /// directory if none is given.
+        file_paths: Vec<String>,
+
+        /// Run only the tests whose name contains this text.
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// File to write an lcov report of the lines the tests ran to,
+        /// printing a summary to the standard error when they end.
+        #[arg(long, value_name = "PATH")]
+        coverage: Option<String>,
+
+        #[command(flatten)]
+        syntax: Syntax,
+
+        #[command(flatten)]
+        config: Config,
+    },
+
+    /// Run source files under the debugger, reading commands from the
+    /// standard input.
+    Debug {
+
+        /// Files to debug, the entry point of the project in the current
+        /// directory if none is given.
+        file_paths: Vec<String>,
+
+        #[command(flatten)]
+        syntax: Syntax,
+
+        #[command(flatten)]
+        config: Config,
+    },
+
+    /// Check source files for errors without compiling them.
+    Check {
+
+        /// Files to check, the entry point of the project in the current
+        /// directory if none is given.
+        file_paths: Vec<String>,
+
+        /// Check again whenever one of the files changes.
+        #[arg(long)]
+        watch: bool,
+
+        #[command(flatten)]
+        syntax: Syntax,
+
+        #[command(flatten)]
+        config: Config,
+    },
+
+    /// Check source files for errors and for the style lints, whose
+    /// levels `-A`, `-W` and `--deny` set.
+    Lint {
+
+        /// Files to lint, the entry point of the project in the current
+        /// directory if none is given.
+        file_paths: Vec<String>,
+
+        /// Print the lints with what they warn about instead.
+        #[arg(long)]
+        list: bool,
+
+        #[command(flatten)]
+        syntax: Syntax,
+
+        #[command(flatten)]
+        config: Config,
+    },
+
+    /// Print source files in the canonical format.
+    Fmt {
+        #[arg(required = true)]
+        file_paths: Vec<String>,
+
+        #[command(flatten)]
+        syntax: Syntax,
+    },
+
+    /// Write a Markdown page documenting the definitions of each source
+    /// file, with their doc comments, and an index of the pages.
+    ///
+    /// A page is named after the path of its file, or within a project
+    /// after the package and the path in it, like `dep/src/main.md`.
+    Doc {
+
+        /// Files to document, the entry point of the project in the
+        /// current directory if none is given.
+        file_paths: Vec<String>,
+
+        /// Directory to write the pages to.
+        #[arg(short, long, default_value = "doc")]
+        output_dir: String,
+
+        #[command(flatten)]
+        syntax: Syntax,
+
+        #[command(flatten)]
+        config: Config,
+    },
+
+    /// Write a tags file of the definitions in source files, for the
+    /// editors that don't speak the language server protocol.
+    Tags {
+
+        /// Files, or directories whose `.fang` files are all tagged.
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// Format of the tags file.
+        #[arg(long, value_enum, default_value_t = TagsFormat::Ctags)]
+        format: TagsFormat,
+
+        /// File to write the tags to instead of `tags` for ctags and
+        /// `TAGS` for etags, `-` for the standard output.
+        #[arg(short, long)]
+        output_path: Option<String>,
+
+        #[command(flatten)]
+        syntax: Syntax,
+    },
+
+    /// Create a project with a manifest and an entry point.
+    New {
+
+        /// Directory of the project, whose name is the project name.
+        path: String,
+    },
+
+    /// Evaluate statements interactively.
+    Repl,
+
+    /// Run the language server on the standard input and output.
+    Lsp,
+}
+
+/// Grammar options of the commands parsing files.
+#[derive(Args)]
+struct Syntax {
+
+    /// Edition of the files that don't name one on their first line
+    /// like `// edition: 2026`, 2025 unless the manifest names one.
+    #[arg(long, value_parser = parse_edition)]
+    edition: Option<Edition>,
+
+    /// Let a line break end a statement in place of `;`.
+    #[arg(long)]
+    optional_semicolons: bool,
+
+    /// How deeply statements and expressions may nest, 128 unless the
+    /// manifest says otherwise.
+    #[arg(long, value_name = "DEPTH")]
+    nesting_limit: Option<usize>,
+
+    /// Read bytes invalid in the encoding of a file as U+FFFD instead
+    /// of failing.
+    #[arg(long)]
+    lossy_utf8: bool,
+
+    /// Columns a tab takes in the locations of diagnostics, reaching
+    /// the next multiple of the width, 1 unless the manifest says
+    /// otherwise.
+    #[arg(long, value_name = "WIDTH")]
+    tab_width: Option<usize>,
+}
+
+impl Syntax {
+
+    /// Options given on the command line, falling back on `defaults`.
+    fn options(&self, defaults: ParseOptions) -> ParseOptions {
+        ParseOptions {
+            edition: self.edition.unwrap_or(defaults.edition),
+            optional_semicolons: self.optional_semicolons || defaults.optional_semicolons,
+            nesting_limit: self.nesting_limit.unwrap_or(defaults.nesting_limit),
+            limits: defaults.limits,
+            lossy_utf8: self.lossy_utf8 || defaults.lossy_utf8,
+            tab_width: self.tab_width.unwrap_or(defaults.tab_width),
+        }
+    }
+}
+
+/// Configuration of the commands compiling files.
+#[derive(Args)]
+struct Config {
+
+    /// Define a symbol of conditional compilation, keeping the
+    /// `#if NAME` blocks instead of their `#else` blocks.
+    #[arg(short = 'D', value_name = "NAME")]
+    symbols: Vec<String>,
+
+    /// Drop the warnings of a lint, or all of them with `warnings`.
+    #[arg(short = 'A', long = "allow", value_name = "LINT")]
+    allow: Vec<String>,
+
+    /// Report the warnings of a lint as warnings, or all of them with
+    /// `warnings`.
+    #[arg(short = 'W', long = "warn", value_name = "LINT")]
+    warn: Vec<String>,
+
+    /// Report the warnings of a lint as errors, or all of them with
+    /// `warnings`.
+    #[arg(long = "deny", value_name = "LINT")]
+    deny: Vec<String>,
+
+    /// Report every warning as an error, like `--deny warnings`.
+    #[arg(long)]
+    deny_warnings: bool,
+}
+
+impl Config {
+
+    /// Levels of the lints of `registry` given on the command line over
+    /// `defaults`, denying coming last.
+    fn lint_levels(&self, registry: &LintRegistry, mut defaults: LintLevels) -> Result<LintLevels, Failure> {
+        let levels = [(&self.allow, Level::Allow), (&self.warn, Level::Warn), (&self.deny, Level::Deny)];
+
+        for (lints, level) in levels {
+            for lint in lints {
+                defaults.set(registry, lint, level).map_err(Failure::Usage)?;
+            }
+        }
+
+        if self.deny_warnings {
+            defaults.set(registry, WARNINGS, Level::Deny).map_err(Failure::Usage)?;
+        }
+
+        Ok(defaults)
+    }
+}
+
+/// Measurements `yuan run` can take of the program it runs.
+#[derive(Args)]
+struct Instrumentation {
+
+    /// Time the calls of every function, printing a report to the
+    /// standard error when the program ends.
+    #[arg(long)]
+    profile: bool,
+
+    /// File to write the time spent in each chain of calls to, in the
+    /// folded format of flame graph tools.
+    #[arg(long, value_name = "PATH")]
+    profile_folded: Option<String>,
+
+    /// File to write an lcov report of the lines that ran to, printing
+    /// a summary to the standard error when the program ends.
+    #[arg(long, value_name = "PATH")]
+    coverage: Option<String>,
+}
+
+fn parse_edition(name: &str) -> Result<Edition, String> {
+    Edition::from_name(name).ok_or_else(|| String::from("editions are 2025 and 2026"))
+}
+
+/// Artifacts `yuan build` can emit.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+
+    /// Tokens scanned out by the lexer.
+    Tokens,
+
+    /// Abstract syntax tree built by the parser.
+    Ast,
+
+    /// Source code after expanding the macros.
+    Expanded,
+}
+
+impl Emit {
+
+    /// Extension of the files holding the artifact.
+    fn extension(&self) -> &'static str {
+        match self {
+            Emit::Tokens => "tokens",
+            Emit::Ast => "ast",
+            Emit::Expanded => "fang",
+        }
+    }
+}
+
+/// Formats of the files `yuan tags` writes.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TagsFormat {
+
+    /// Format of vi and the editors following it.
+    Ctags,
+
+    /// Format of Emacs.
+    Etags,
+}
+
+impl TagsFormat {
+
+    /// File the editors look for the tags in.
+    fn default_path(&self) -> &'static str {
+        match self {
+            TagsFormat::Ctags => "tags",
+            TagsFormat::Etags => "TAGS",
+        }
+    }
+}
+
+/// Files and configuration of a command compiling files.
+struct Inputs {
+    file_paths: Vec<String>,
+
+    /// Name of the module each file is, which names the page
+    /// documenting it, like `dep/src/main` for the entry point of the
+    /// package `dep`.
+    modules: Vec<String>,
+    options: ParseOptions,
+    symbols: Vec<String>,
+    lint_levels: LintLevels,
+
+    /// Path of the artifacts of a project, without the extension.
+    output_stem: Option<PathBuf>,
+
+    /// Directory caching the parse results of unchanged files by
+    /// default.
+    cache_dir: PathBuf,
+}
+
+/// Why a command failed, which tells the exit code of `yuan`.
+enum Failure {
+
+    /// The files don't compile, the program failed at run time, or a
+    /// file couldn't be read or written.
+    Error(String),
+
+    /// The command line asks for something that makes no sense.
+    Usage(String),
+}
+
+impl Failure {
+    fn exit_code(&self) -> ExitCode {
+        match self {
+            Failure::Error(_) => ExitCode::from(EXIT_ERROR),
+            Failure::Usage(_) => ExitCode::from(EXIT_USAGE),
+        }
+    }
+}
+
+impl From<String> for Failure {
+    fn from(report: String) -> Failure {
+        Failure::Error(report)
+    }
+}
+
+/// Inputs of a command given `file_paths`, or without any, the entry
+/// points of the project the current directory is in and of the
+/// packages it depends on, configured by the project's manifest and
+/// then by the command line.
+fn inputs(file_paths: Vec<String>, syntax: &Syntax, config: &Config) -> Result<Inputs, Failure> {
+    let registry = Frontend::new().lint_registry();
+    let root = env::current_dir().ok().and_then(|dir| find_root(&dir));
+
+    if !file_paths.is_empty() {
+        return Ok(Inputs {
+            modules: file_paths.iter().map(|path| module_name(path)).collect(),
+            file_paths,
+            options: syntax.options(ParseOptions::default()),
+            symbols: config.symbols.clone(),
+            lint_levels: config.lint_levels(&registry, LintLevels::new())?,
+            output_stem: None,
+            cache_dir: root.map_or_else(|| PathBuf::from(".fang-cache"), |root| root.join("target").join("cache")),
+        });
+    }
+
+    let root = root.ok_or_else(|| Failure::Usage(format!("No files given and no \"{}\" in the current directory or its parents.", MANIFEST_NAME)))?;
+    let packages = load_packages(&root)?;
+    let (_, manifest) = packages.last().unwrap();
+
+    Ok(Inputs {
+        file_paths: packages.iter()
+            .map(|(root, manifest)| root.join(manifest.entry()).to_string_lossy().into_owned())
+            .collect(),
+        modules: packages.iter()
+            .map(|(_, manifest)| format!("{}/{}", manifest.package.name, module_name(manifest.entry())))
+            .collect(),
+        options: syntax.options(manifest.options()),
+        symbols: manifest.build.symbols.iter().chain(&config.symbols).cloned().collect(),
+        lint_levels: config.lint_levels(&registry, manifest.lint_levels(&registry)?)?,
+        output_stem: Some(root.join("target").join(manifest.output())),
+        cache_dir: root.join("target").join("cache"),
+    })
+}
+
+/// Name of the module at `path`: the path without its extension if it
+/// stays below the current directory, or else the name of the file.
+fn module_name(path: &str) -> String {
+    let path = Path::new(path).with_extension("");
+    let mut names: Vec<String> = Vec::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => names.push(name.to_string_lossy().into_owned()),
+            Component::CurDir => {},
+            _ => return path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+        }
+    }
+
+    names.join("/")
+}
+
+/// Create the project at `path`, named after its directory.
+fn new(path: &str) -> Result<(), Failure> {
+    let root = Path::new(path);
+    let name = match root.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => return Err(Failure::Usage(format!("\"{}\" doesn't name a directory.", path))),
+    };
+
+    create_project(root, &name).map_err(|err| format!("Failed to create \"{}\": {}", path, err))?;
+    println!("Created project \"{}\".", name);
+
+    Ok(())
+}
+
+/// Write `artifact` to `path`, creating missing parent directories.
+fn write_artifact(path: &str, artifact: &str) -> Result<(), Failure> {
+    let write = || {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, artifact)
+    };
+
+    write().map_err(|err: io::Error| Failure::Error(format!("Failed to write \"{}\": {}", path, err)))
+}
+
+/// Compile the files as one program, printing the warnings to the
+/// standard error, or fail with every diagnostic if any file has errors.
+fn compile(frontend: &mut Frontend, file_paths: &[String]) -> Result<Program, Failure> {
+    let result = frontend.process_files(file_paths);
+    let report = result.render(frontend.sources());
+
+    match result.program {
+        Some(program) => {
+            eprint!("{}", report);
 
-    #[arg(short, long)]
-    output_path: Option<String>,
+            Ok(program)
+        },
+        None => Err(Failure::Error(report)),
+    }
 }
 
-fn main() {
+fn build(
+    file_paths: &[String],
+    emit: Emit,
+    output_path: Option<&str>,
+    cache_dir: Option<&Path>,
+    options: ParseOptions,
+    symbols: &[String],
+    lint_levels: &LintLevels
+) -> Result<(), Failure> {
+    let mut frontend = match cache_dir {
+        Some(dir) => Frontend::with_cache(Cache::new(dir)),
+        None => Frontend::new(),
+    };
+    let mut artifact = String::new();
+
+    frontend.set_options(options);
+    frontend.set_symbols(Some(symbols.to_vec()));
+    frontend.set_lint_levels(lint_levels.clone());
+
+    match emit {
+        Emit::Tokens => {
+            let mut report = String::new();
+
+            for path in file_paths {
+                match frontend.tokenize_file(path) {
+                    Ok(stream) => artifact.push_str(&format!("{:#?}\n", stream)),
+                    Err(text) => report.push_str(&text),
+                }
+            }
+
+            if !report.is_empty() {
+                return Err(Failure::Error(report));
+            }
+        },
+        Emit::Ast => artifact.push_str(&format!("{:#?}\n", compile(&mut frontend, file_paths)?)),
+        Emit::Expanded => artifact.push_str(&format_program(&compile(&mut frontend, file_paths)?)),
+    }
+
+    match output_path {
+        Some(path) => write_artifact(path, &artifact)?,
+        None => print!("{}", artifact),
+    }
+
+    Ok(())
+}
+
+/// Run the files with the arguments `args` and the numbers `seed`
+/// seeds, taking the measurements `instrumentation` asks for.
+fn run(
+    file_paths: &[String],
+    args: Vec<String>,
+    seed: Option<u64>,
+    options: ParseOptions,
+    symbols: &[String],
+    lint_levels: &LintLevels,
+    instrumentation: &Instrumentation
+) -> Result<(), Failure> {
+    let mut frontend = Frontend::new();
+
+    frontend.set_options(options);
+    frontend.set_symbols(Some(symbols.to_vec()));
+    frontend.set_lint_levels(lint_levels.clone());
+
+    let program = compile(&mut frontend, file_paths)?;
+    let mut env = if program.uses_prelude() {
+        let mut env = Env::with_builtins();
+
+        env.set_args(args);
+
+        if let Some(seed) = seed {
+            env.set_rng(Rng::new(seed));
+        }
+
+        env
+    } else {
+        Env::new()
+    };
+
+    let mut profiler = Profiler::new();
+    let mut coverage = Coverage::new(&program);
+    let mut interpreter = Interpreter::new(&mut env);
+    let mut reports: Vec<(&str, String)> = Vec::new();
+
+    if instrumentation.profile || instrumentation.profile_folded.is_some() {
+        interpreter = interpreter.with_hooks(&mut profiler);
+    }
+
+    if instrumentation.coverage.is_some() {
+        interpreter = interpreter.with_hooks(&mut coverage);
+    }
+
+    let result = interpreter.run(&program);
+
+    if instrumentation.profile {
+        eprint!("{}", profiler.report());
+    }
+
+    if let Some(path) = &instrumentation.profile_folded {
+        reports.push((path, profiler.folded()));
+    }
+
+    if let Some(path) = &instrumentation.coverage {
+        eprint!("{}", coverage.summary(frontend.sources()));
+        reports.push((path, coverage.lcov(frontend.sources())));
+    }
+
+    for (path, report) in reports {
+        write_artifact(path, &report)?;
+    }
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Failure::Error(err.render_in(frontend.sources()))),
+    }
+}
+
+fn test(
+    file_paths: &[String],
+    filter: Option<&str>,
+    coverage_path: Option<&str>,
+    options: ParseOptions,
+    symbols: &[String],
+    lint_levels: &LintLevels
+) -> Result<(), Failure> {
+    let mut frontend = Frontend::new();
+    let mut failures = String::new();
+    let mut failed = 0;
+
+    frontend.set_options(options);
+    frontend.set_symbols(Some(symbols.to_vec()));
+    frontend.set_lint_levels(lint_levels.clone());
+
+    let program = compile(&mut frontend, file_paths)?;
+    let tests: Vec<Test> = collect_tests(&program).into_iter()
+        .filter(|test| filter.is_none_or(|filter| test.name.contains(filter)))
+        .collect();
+
+    let mut coverage = Coverage::new(&program);
+
+    println!("Running {} test(s).", tests.len());
+
+    for test in &tests {
+        let mut env = if program.uses_prelude() { Env::with_builtins() } else { Env::new() };
+        let result = match coverage_path {
+            Some(_) => run_test_with_hooks(&program, test, &mut env, &mut coverage),
+            None => run_test(&program, test, &mut env),
+        };
+
+        match result {
+            Ok(()) => println!("test {} ... ok", test.name),
+            Err(err) => {
+                println!("test {} ... FAILED", test.name);
+                failures.push_str(&err.render_in(frontend.sources()));
+                failed += 1;
+            },
+        }
+    }
+
+    println!("{} passed, {} failed.", tests.len() - failed, failed);
+
+    if let Some(path) = coverage_path {
+        eprint!("{}", coverage.summary(frontend.sources()));
+        write_artifact(path, &coverage.lcov(frontend.sources()))?;
+    }
+
+    if failed > 0 {
+        return Err(Failure::Error(failures));
+    }
+
+    Ok(())
+}
+
+fn debug(
+    file_paths: &[String],
+    options: ParseOptions,
+    symbols: &[String],
+    lint_levels: &LintLevels
+) -> Result<(), Failure> {
+    let mut frontend = Frontend::new();
+
+    frontend.set_options(options);
+    frontend.set_symbols(Some(symbols.to_vec()));
+    frontend.set_lint_levels(lint_levels.clone());
+
+    let program = compile(&mut frontend, file_paths)?;
+    let mut env = if program.uses_prelude() {
+        Env::with_builtins()
+    } else {
+        Env::new()
+    };
+    let stdin = io::stdin();
+    let mut debugger = Debugger::new(frontend.sources(), stdin.lock(), io::stdout());
+
+    println!("Paused before the first statement, \"help\" lists the commands.");
+
+    match Interpreter::new(&mut env).with_hooks(&mut debugger).run(&program) {
+        Ok(_) => {
+            println!("Program finished.");
+
+            Ok(())
+        },
+        Err(_) if debugger.has_quit() => Ok(()),
+        Err(err) => Err(Failure::Error(err.render_in(frontend.sources()))),
+    }
+}
+
+fn check(
+    file_paths: &[String],
+    options: ParseOptions,
+    symbols: &[String],
+    lint_levels: &LintLevels
+) -> Result<(), Failure> {
+    let mut frontend = Frontend::new();
+
+    frontend.set_options(options);
+    frontend.set_symbols(Some(symbols.to_vec()));
+    frontend.set_lint_levels(lint_levels.clone());
+    compile(&mut frontend, file_paths)?;
+
+    Ok(())
+}
+
+/// Check the files, then run the lints over them, failing if any of
+/// their warnings is denied.
+fn lint(
+    file_paths: &[String],
+    options: ParseOptions,
+    symbols: &[String],
+    lint_levels: &LintLevels
+) -> Result<(), Failure> {
+    let mut frontend = Frontend::new();
+
+    frontend.set_options(options);
+    frontend.set_symbols(Some(symbols.to_vec()));
+    frontend.set_lint_levels(lint_levels.clone());
+
+    let program = compile(&mut frontend, file_paths)?;
+    let diagnostics = lint_levels.apply(&program, frontend.lint_registry().check(&program));
+    let report = diagnostics.render_in_map(frontend.sources());
+
+    if diagnostics.has_errors() {
+        return Err(Failure::Error(report));
+    }
+
+    eprint!("{}", report);
+
+    Ok(())
+}
+
+/// Print the name of each lint with what it warns about.
+fn list_lints() {
+    let registry = Frontend::new().lint_registry();
+    let width = registry.lints().map(|lint| lint.name().len()).max().unwrap_or(0);
+
+    for lint in registry.lints() {
+        println!("{:<width$}  {}", lint.name(), lint.description());
+    }
+}
+
+/// Modification times of the files, `None` for unreadable ones.
+fn modification_times(file_paths: &[String]) -> Vec<Option<SystemTime>> {
+    file_paths.iter()
+        .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+/// Check the files, then check them again on every change until
+/// the process is interrupted.
+fn watch(
+    file_paths: &[String],
+    options: ParseOptions,
+    symbols: &[String],
+    lint_levels: &LintLevels
+) -> Result<(), Failure> {
+    let mut times = modification_times(file_paths);
+    let mut reader = Frontend::new();
+    let mut database = Database::new(options, symbols.to_vec(), lint_levels.clone());
+
+    reader.set_options(options);
+
+    /* The database keeps what it computed between changes, so only the
+     * files that changed are parsed again. */
+    loop {
+        let mut failed = false;
+
+        /* Clear the screen and move the cursor home. */
+        print!("\x1B[2J\x1B[1;1H");
+        io::stdout().flush().unwrap();
+
+        for path in file_paths {
+            match reader.read_file(path) {
+                Ok(text) => database.set_text(path, &text),
+                Err(report) => {
+                    failed = true;
+                    eprint!("{}", report);
+                },
+            }
+        }
+
+        /* The files are checked together once they can all be read. */
+        if !failed {
+            let unit = database.compile_unit(file_paths);
+
+            failed = unit.program.is_none();
+
+            for (path, diagnostics) in &unit.diagnostics {
+                eprint!("{}", diagnostics.render_in(&reader.source_file(path, database.text(path).unwrap().as_str())));
+            }
+        }
+
+        if !failed {
+            println!("No errors.");
+        }
+
+        println!("Watching {} file(s) for changes...", file_paths.len());
+
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+
+            let latest = modification_times(file_paths);
+
+            if latest != times {
+                times = latest;
+                break;
+            }
+        }
+    }
+}
+
+fn fmt(file_paths: &[String], options: ParseOptions) -> Result<(), Failure> {
+    let mut frontend = Frontend::new();
+    let mut report = String::new();
+
+    frontend.set_options(options);
+
+    /* Formatting keeps the blocks of every configuration. */
+    frontend.set_symbols(None);
+
+    for path in file_paths {
+        let result = frontend.process_file(path);
+        let text = result.render(frontend.sources());
+
+        match result.program {
+            Some(program) => {
+                eprint!("{}", text);
+                print!("{}", format_program(&program));
+            },
+            None => report.push_str(&text),
+        }
+    }
+
+    if !report.is_empty() {
+        return Err(Failure::Error(report));
+    }
+
+    Ok(())
+}
+
+/// Document each file as a module named after it, writing its page and
+/// the index of the pages to `output_dir`.
+fn doc(
+    file_paths: &[String],
+    modules: &[String],
+    output_dir: &Path,
+    options: ParseOptions,
+    symbols: &[String],
+    lint_levels: &LintLevels
+) -> Result<(), Failure> {
+    let mut reader = Frontend::new();
+    let mut database = Database::new(options, symbols.to_vec(), lint_levels.clone());
+    let mut pages: Vec<(String, String)> = Vec::new();
+    let mut report = String::new();
+
+    reader.set_options(options);
+
+    for (index, module) in modules.iter().enumerate() {
+        if let Some(other) = modules[..index].iter().position(|other| other == module) {
+            return Err(Failure::Error(format!("Files \"{}\" and \"{}\" would both be documented in \"{}.md\".",
+                                              file_paths[other], file_paths[index], module)));
+        }
+    }
+
+    for path in file_paths {
+        match reader.read_file(path) {
+            Ok(text) => database.set_text(path, &text),
+            Err(text) => report.push_str(&text),
+        }
+    }
+
+    if !report.is_empty() {
+        return Err(Failure::Error(report));
+    }
+
+    let unit = database.compile_unit(file_paths);
+
+    if unit.program.is_none() {
+        for (path, diagnostics) in &unit.diagnostics {
+            report.push_str(&diagnostics.render_in(&reader.source_file(path, database.text(path).unwrap().as_str())));
+        }
+
+        return Err(Failure::Error(report));
+    }
+
+    /* The files are checked as one unit, then each is documented from
+     * its own program, whose spans are within its text. */
+    for (path, name) in file_paths.iter().zip(modules) {
+        let mut program = database.parse(path).as_ref().clone().unwrap();
+
+        configure(&mut program, symbols);
+
+        let page = document_module(name, &database.text(path).unwrap(), &program);
+
+        pages.push((name.to_owned(), page));
+    }
+
+    let modules: Vec<String> = pages.iter().map(|(name, _)| name.to_owned()).collect();
+
+    for (name, page) in &pages {
+        write_artifact(&output_dir.join(format!("{}.md", name)).to_string_lossy(), page)?;
+    }
+
+    write_artifact(&output_dir.join("index.md").to_string_lossy(), &document_index(&modules))?;
+    println!("Documented {} module(s) in \"{}\".", modules.len(), output_dir.display());
+
+    Ok(())
+}
+
+/// Add the `.fang` files under the directory `dir` to `found`.
+fn find_sources(dir: &Path, found: &mut Vec<String>) -> Result<(), Failure> {
+    let fail = |err: io::Error| Failure::Error(format!("Failed to read \"{}\": {}", dir.display(), err));
+    let mut entries: Vec<PathBuf> = Vec::new();
+
+    for entry in fs::read_dir(dir).map_err(fail)? {
+        entries.push(entry.map_err(fail)?.path());
+    }
+
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            find_sources(&path, found)?;
+        } else if path.extension().is_some_and(|extension| extension == "fang") {
+            found.push(path.to_string_lossy().into_owned());
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the tags of the definitions in the files, or in the `.fang`
+/// files under the directories, to `output_path`.
+fn tags(paths: &[String], format: TagsFormat, output_path: &str, options: ParseOptions) -> Result<(), Failure> {
+    let mut frontend = Frontend::new();
+    let mut file_paths: Vec<String> = Vec::new();
+    let mut files: Vec<(SourceFile, Vec<Tag>)> = Vec::new();
+    let mut report = String::new();
+
+    frontend.set_options(options);
+
+    for path in paths {
+        if Path::new(path).is_dir() {
+            find_sources(Path::new(path), &mut file_paths)?;
+        } else {
+            file_paths.push(path.to_owned());
+        }
+    }
+
+    /* Conditional compilation isn't resolved, so every branch is tagged. */
+    for path in file_paths {
+        let text = match frontend.read_file(&path) {
+            Ok(text) => text,
+            Err(text) => {
+                report.push_str(&text);
+                continue;
+            },
+        };
+
+        match parse_source_with(&text, options) {
+            Ok(program) => {
+                let tags = collect_tags(&program);
+
+                files.push((SourceFile::new(path, text), tags));
+            },
+            Err(diagnostics) => report.push_str(&diagnostics.render_in(&frontend.source_file(&path, text))),
+        }
+    }
+
+    if !report.is_empty() {
+        return Err(Failure::Error(report));
+    }
+
+    let artifact = match format {
+        TagsFormat::Ctags => ctags(&files),
+        TagsFormat::Etags => etags(&files),
+    };
+
+    match output_path {
+        "-" => print!("{}", artifact),
+        path => write_artifact(path, &artifact)?,
+    }
+
+    Ok(())
+}
+
+/// Print what made a command fail to the standard error.
+fn report(failure: &Failure) {
+    match failure {
+        Failure::Error(text) |
+        Failure::Usage(text) => eprintln!("{}", text.trim_end()),
+    }
+}
+
+/// Report a panic as a bug of the compiler, on top of the standard
+/// message.
+fn report_internal_errors() {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        eprintln!("\nerror: internal compiler error, this is a bug in yuan.");
+        eprintln!("Please file an issue at {} with the input that triggered it.", BUG_REPORT_URL);
+    }));
+}
+
+fn execute(command: Command) -> Result<(), Failure> {
+    match command {
+        Command::Build {
+            file_paths,
+            emit,
+            output_path,
+            cache_dir,
+            no_cache,
+            syntax,
+            config,
+        } => {
+            let inputs = inputs(file_paths, &syntax, &config)?;
+            let cache_dir = match cache_dir {
+                _ if no_cache => None,
+                Some(dir) => Some(PathBuf::from(dir)),
+                None => Some(inputs.cache_dir.clone()),
+            };
+            let output_path = output_path.or_else(|| {
+                inputs.output_stem.as_ref().map(|stem| format!("{}.{}", stem.display(), emit.extension()))
+            });
+
+            build(&inputs.file_paths, emit, output_path.as_deref(), cache_dir.as_deref(), inputs.options, &inputs.symbols, &inputs.lint_levels)
+        },
+        Command::Run {
+            file_paths,
+            args,
+            seed,
+            instrumentation,
+            syntax,
+            config,
+        } => {
+            let inputs = inputs(file_paths, &syntax, &config)?;
+
+            run(&inputs.file_paths, args, seed, inputs.options, &inputs.symbols, &inputs.lint_levels, &instrumentation)
+        },
+        Command::Test {
+            file_paths,
+            filter,
+            coverage,
+            syntax,
+            config,
+        } => {
+            let inputs = inputs(file_paths, &syntax, &config)?;
+
+            test(&inputs.file_paths, filter.as_deref(), coverage.as_deref(), inputs.options, &inputs.symbols, &inputs.lint_levels)
+        },
+        Command::Debug {
+            file_paths,
+            syntax,
+            config,
+        } => {
+            let inputs = inputs(file_paths, &syntax, &config)?;
+
+            debug(&inputs.file_paths, inputs.options, &inputs.symbols, &inputs.lint_levels)
+        },
+        Command::Check {
+            file_paths,
+            watch: false,
+            syntax,
+            config,
+        } => {
+            let inputs = inputs(file_paths, &syntax, &config)?;
+
+            check(&inputs.file_paths, inputs.options, &inputs.symbols, &inputs.lint_levels)
+        },
+        Command::Check {
+            file_paths,
+            watch: true,
+            syntax,
+            config,
+        } => {
+            let inputs = inputs(file_paths, &syntax, &config)?;
+
+            watch(&inputs.file_paths, inputs.options, &inputs.symbols, &inputs.lint_levels)
+        },
+        Command::Lint {
+            list: true,
+            ..
+        } => {
+            list_lints();
+
+            Ok(())
+        },
+        Command::Lint {
+            file_paths,
+            list: false,
+            syntax,
+            config,
+        } => {
+            let inputs = inputs(file_paths, &syntax, &config)?;
+
+            lint(&inputs.file_paths, inputs.options, &inputs.symbols, &inputs.lint_levels)
+        },
+        Command::Fmt {
+            file_paths,
+            syntax,
+        } => fmt(&file_paths, syntax.options(ParseOptions::default())),
+        Command::Doc {
+            file_paths,
+            output_dir,
+            syntax,
+            config,
+        } => {
+            let inputs = inputs(file_paths, &syntax, &config)?;
+
+            doc(&inputs.file_paths, &inputs.modules, Path::new(&output_dir), inputs.options, &inputs.symbols, &inputs.lint_levels)
+        },
+        Command::Tags {
+            paths,
+            format,
+            output_path,
+            syntax,
+        } => {
+            let output_path = output_path.unwrap_or_else(|| String::from(format.default_path()));
+
+            tags(&paths, format, &output_path, syntax.options(ParseOptions::default()))
+        },
+        Command::New {
+            path,
+        } => new(&path),
+        Command::Repl => {
+            Repl::new(Env::with_builtins()).run();
+
+            Ok(())
+        },
+        Command::Lsp => Server::new().run()
+            .map_err(|err| Failure::Error(format!("Failed to reply to the client: {}", err))),
+    }
+}
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
-    let frontend = Frontend::new();
 
-    frontend.process_file(&cli.file_path)
+    report_internal_errors();
+
+    match panic::catch_unwind(|| execute(cli.command)) {
+        Ok(Ok(())) => ExitCode::SUCCESS,
+        Ok(Err(failure)) => {
+            report(&failure);
+
+            failure.exit_code()
+        },
+        Err(_) => ExitCode::from(EXIT_INTERNAL_ERROR),
+    }
 }
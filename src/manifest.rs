@@ -0,0 +1,344 @@
+use crate::diagnostic::{Level, LintLevels, WARNINGS};
+use crate::lint::LintRegistry;
+use crate::parser::{Edition, Limits, ParseOptions, NESTING_LIMIT};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest file at the root of a project.
+pub const MANIFEST_NAME: &str = "fang.toml";
+
+/// Source of the entry point of a new project.
+const MAIN_SOURCE: &str = "print(\"Hello, world!\");\n";
+
+/// Ignore file of a new project, keeping its artifacts and cache out
+/// of version control.
+const GITIGNORE: &str = "/target/\n";
+
+/// Manifest of a project, describing how `yuan build` and `yuan run`
+/// compile it when they're given no files.
+///
+/// ```toml
+/// [package]
+/// name = "greeter"
+///
+/// [deps]
+/// utils = { path = "../utils" }
+///
+/// [build]
+/// entry = "src/main.fang"
+/// output = "greeter"
+/// edition = "2026"
+/// symbols = ["DEBUG"]
+///
+/// [lints]
+/// warnings = "deny"
+/// unreachable_arms = "allow"
+/// ```
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub package: Package,
+
+    /// Packages the project depends on, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub deps: BTreeMap<String, Dependency>,
+
+    #[serde(default, skip_serializing_if = "Build::is_default")]
+    pub build: Build,
+
+    /// Level of each lint, or of every lint for `warnings`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lints: BTreeMap<String, Level>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Package {
+    pub name: String,
+}
+
+/// Package a project depends on, whose files are compiled before the
+/// project's.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+
+    /// Root of the package, relative to the root of the project.
+    pub path: String,
+}
+
+/// Compiler flags of a project, each standing for the command-line
+/// option of the same name.
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Build {
+
+    /// Path of the file to compile, relative to the project root,
+    /// `src/main.fang` if it's left out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+
+    /// Name of the artifacts `yuan build` writes, the package name if
+    /// it's left out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edition: Option<Edition>,
+
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub optional_semicolons: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nesting_limit: Option<usize>,
+
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub lossy_utf8: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tab_width: Option<usize>,
+
+    /// Symbols of conditional compilation.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<String>,
+}
+
+impl Build {
+    fn is_default(&self) -> bool {
+        *self == Build::default()
+    }
+}
+
+impl Manifest {
+    pub fn new(name: &str) -> Manifest {
+        Manifest {
+            package: Package {
+                name: String::from(name),
+            },
+            deps: BTreeMap::new(),
+            build: Build::default(),
+            lints: BTreeMap::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Manifest, String> {
+        toml::from_str(text).map_err(|err| err.message().to_string())
+    }
+
+    /// Read the manifest of the project rooted at `root`.
+    pub fn load(root: &Path) -> Result<Manifest, String> {
+        let path = root.join(MANIFEST_NAME);
+        let text = fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read \"{}\": {}", path.display(), err))?;
+
+        Manifest::parse(&text).map_err(|message| format!("Invalid \"{}\": {}", path.display(), message.trim_end()))
+    }
+
+    /// Path of the file to compile, relative to the project root.
+    pub fn entry(&self) -> &str {
+        self.build.entry.as_deref().unwrap_or("src/main.fang")
+    }
+
+    /// Name of the artifacts `yuan build` writes.
+    pub fn output(&self) -> &str {
+        self.build.output.as_deref().unwrap_or(&self.package.name)
+    }
+
+    pub fn options(&self) -> ParseOptions {
+        ParseOptions {
+            edition: self.build.edition.unwrap_or_default(),
+            optional_semicolons: self.build.optional_semicolons,
+            nesting_limit: self.build.nesting_limit.unwrap_or(NESTING_LIMIT),
+            limits: Limits::default(),
+            lossy_utf8: self.build.lossy_utf8,
+            tab_width: self.build.tab_width.unwrap_or(1),
+        }
+    }
+
+    /// Levels of the lints of `registry`, `warnings` coming first so the
+    /// other lints override it.
+    pub fn lint_levels(&self, registry: &LintRegistry) -> Result<LintLevels, String> {
+        let mut levels = LintLevels::new();
+        let (warnings, lints): (Vec<_>, Vec<_>) = self.lints.iter().partition(|(lint, _)| *lint == WARNINGS);
+
+        for (lint, level) in warnings.into_iter().chain(lints) {
+            levels.set(registry, lint, *level).map_err(|message| format!("Invalid [lints]: {}", message))?;
+        }
+
+        Ok(levels)
+    }
+}
+
+/// Root of the project `dir` is in, the closest of `dir` and its
+/// ancestors holding a manifest.
+pub fn find_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|dir| dir.join(MANIFEST_NAME).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Packages of the project rooted at `root` with their roots, each
+/// after the packages it depends on, and the project last.
+pub fn load_packages(root: &Path) -> Result<Vec<(PathBuf, Manifest)>, String> {
+    let mut packages: Vec<(PathBuf, Manifest)> = Vec::new();
+    let mut path: Vec<(PathBuf, String)> = Vec::new();
+
+    visit_package(root, None, &mut packages, &mut path)?;
+
+    Ok(packages)
+}
+
+/// Load the package rooted at `root` after its dependencies, unless
+/// it's loaded already, where `path` holds the packages depending on
+/// it down from the project and `name` is what its dependent calls it.
+fn visit_package(
+    root: &Path,
+    name: Option<&str>,
+    packages: &mut Vec<(PathBuf, Manifest)>,
+    path: &mut Vec<(PathBuf, String)>
+) -> Result<(), String> {
+    let root = fs::canonicalize(root)
+        .map_err(|err| format!("Failed to find the package at \"{}\": {}", root.display(), err))?;
+
+    if let Some(index) = path.iter().position(|(dependent, _)| *dependent == root) {
+        let cycle: Vec<&str> = path[index..].iter()
+            .chain([&path[index]])
+            .map(|(_, name)| name.as_str())
+            .collect();
+
+        return Err(format!("Packages depend on each other in a cycle: {}", cycle.join(" -> ")));
+    }
+
+    let loaded = packages.iter().find(|(loaded, _)| *loaded == root).map(|(_, manifest)| manifest);
+    let manifest = match loaded {
+        Some(manifest) => manifest.clone(),
+        None => Manifest::load(&root)?,
+    };
+
+    if let Some(name) = name.filter(|name| *name != manifest.package.name) {
+        return Err(format!("Dependency \"{}\" at \"{}\" is the package \"{}\"!",
+            name, root.display(), manifest.package.name));
+    }
+
+    if loaded.is_some() {
+        return Ok(());
+    }
+
+    path.push((root.clone(), manifest.package.name.clone()));
+
+    for (name, dependency) in &manifest.deps {
+        visit_package(&root.join(&dependency.path), Some(name), packages, path)?;
+    }
+
+    path.pop();
+    packages.push((root, manifest));
+
+    Ok(())
+}
+
+/// Create the project `name` in the new directory `root`, with a
+/// manifest, an entry point printing a greeting and a `.gitignore`.
+pub fn create_project(root: &Path, name: &str) -> io::Result<()> {
+    if root.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, "the directory already exists"));
+    }
+
+    fs::create_dir_all(root.join("src"))?;
+    fs::write(root.join(MANIFEST_NAME), toml::to_string(&Manifest::new(name)).unwrap())?;
+    fs::write(root.join(".gitignore"), GITIGNORE)?;
+    fs::write(root.join("src").join("main.fang"), MAIN_SOURCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest() {
+        let manifest = Manifest::parse("
+[package]
+name = \"greeter\"
+
+[build]
+entry = \"src/app.fang\"
+edition = \"2026\"
+nesting_limit = 64
+tab_width = 4
+symbols = [\"DEBUG\"]
+
+[lints]
+warnings = \"deny\"
+unreachable_arms = \"allow\"
+").unwrap();
+        let registry = LintRegistry::with_builtins();
+        let mut levels = LintLevels::new();
+
+        assert_eq!(manifest.entry(), "src/app.fang");
+        assert_eq!(manifest.output(), "greeter");
+        assert_eq!(manifest.options(), ParseOptions {
+            edition: Edition::E2026,
+            optional_semicolons: false,
+            nesting_limit: 64,
+            limits: Limits::default(),
+            lossy_utf8: false,
+            tab_width: 4,
+        });
+        assert_eq!(manifest.build.symbols, vec!["DEBUG"]);
+
+        levels.set(&registry, WARNINGS, Level::Deny).unwrap();
+        levels.set(&registry, "unreachable_arms", Level::Allow).unwrap();
+        assert_eq!(manifest.lint_levels(&registry), Ok(levels));
+        assert!(Manifest::parse("[package]\nname = \"a\"\n[lints]\nunused = \"deny\"\n").unwrap().lint_levels(&registry).is_err());
+        assert!(Manifest::parse("[package]\nname = \"a\"\n[lints]\nwarnings = \"forbid\"\n").is_err());
+
+        assert!(Manifest::parse("[package]\nname = \"a\"\n[build]\nedition = \"2030\"\n").is_err());
+        assert!(Manifest::parse("[package]\nname = \"a\"\n[build]\nentry_point = \"a.fang\"\n").is_err());
+        assert!(Manifest::parse("[build]\n").is_err());
+    }
+
+    #[test]
+    fn new_project() {
+        let dir = std::env::temp_dir().join(format!("fang-project-test-{}", std::process::id()));
+        let root = dir.join("greeter");
+
+        create_project(&root, "greeter").unwrap();
+
+        assert_eq!(Manifest::load(&root), Ok(Manifest::new("greeter")));
+        assert_eq!(fs::read_to_string(root.join("src/main.fang")).unwrap(), MAIN_SOURCE);
+        assert_eq!(fs::read_to_string(root.join(".gitignore")).unwrap(), "/target/\n");
+        assert_eq!(find_root(&root.join("src")), Some(root.clone()));
+        assert!(create_project(&root, "greeter").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn dependencies() {
+        let dir = std::env::temp_dir().join(format!("fang-deps-test-{}", std::process::id()));
+        let write = |name: &str, deps: &str| {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            fs::write(dir.join(name).join(MANIFEST_NAME), format!("{}[package]\nname = \"{}\"\n", deps, name)).unwrap();
+        };
+        let names = |packages: Vec<(PathBuf, Manifest)>| -> Vec<String> {
+            packages.into_iter().map(|(_, manifest)| manifest.package.name).collect()
+        };
+
+        write("app", "deps.utils = { path = \"../utils\" }\ndeps.core = { path = \"../core\" }\n");
+        write("utils", "deps.core = { path = \"../core\" }\n");
+        write("core", "");
+        assert_eq!(names(load_packages(&dir.join("app")).unwrap()), vec!["core", "utils", "app"]);
+
+        write("core", "deps.app = { path = \"../app\" }\n");
+        assert_eq!(load_packages(&dir.join("app")).unwrap_err(),
+            "Packages depend on each other in a cycle: app -> core -> app");
+
+        write("core", "");
+        write("utils", "deps.base = { path = \"../core\" }\n");
+        assert!(load_packages(&dir.join("app")).unwrap_err().starts_with("Dependency \"base\" at"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -2,24 +2,61 @@
 
 GRAMMAR:
 
-EXPR ::= COMP_OPERAND ("==" COMP_OPERAND | "!=" COMP_OPERAND)*
+EXPR ::= UNARY (INFIX_OP UNARY | RANGE_OP UNARY? | "as" TYPE)*
 
-COMP_OPERAND ::= TERM ("+" TERM | "-" TERM)*
+UNARY ::= PREFIX_OP UNARY
+        | RANGE_OP UNARY?
+        | POSTFIX
 
-TERM ::= FACTOR ("*" FACTOR | "/" FACTOR)*
+POSTFIX ::= FACTOR ("." IDENT "(" ARGUMENTS ")" | "[" EXPR "]" | "?")*
+
+INFIX_OP ::= "=" | "==" | "!=" | "+" | "-" | "*" | "/"
+
+PREFIX_OP ::= "-"
+
+RANGE_OP ::= ".." | "..="
+
+Operators bind and associate as listed in `INFIX_OPERATORS`, except
+that comparisons don't chain unparenthesized, ranges bind at
+`RANGE_PRECEDENCE` and don't chain either, `as` binds tighter than
+any infix operator and prefix operators tighter still. Only `..` may
+leave out the end of a range, when nothing follows that could start
+one.
 
 FACTOR ::= "(" EXPR ")"
+         | "[" (EXPR ("," EXPR)* ","?)? "]"
+         | "{" (ENTRY ("," ENTRY)* ","?)? "}"
+         | "match" EXPR "{" (ARM ("," ARM)* ","?)? "}"
          | IDENT
          | LITERAL
 
+ENTRY ::= EXPR ":" EXPR
+
+ARM ::= PATTERN "=>" EXPR
+
+PATTERN ::= "_"
+          | IDENT ("(" (PATTERN ("," PATTERN)* ","?)? ")")?
+          | "[" (PATTERN ("," PATTERN)* ","?)? "]"
+          | "-"? NUMBER
+          | STRING
+          | "nil"
+
 LITERAL ::= NUMBER
+          | STRING
+          | "nil"
 
 */
 
-use crate::lexer::{Token, Stream};
+use crate::lexer::{Token, TokenKind, Stream};
+use crate::diagnostic::{Diagnostic, Diagnostics, Level, WARNINGS};
+use crate::printer::format_type;
+use crate::span::Span;
+use serde::{Serialize, Deserialize};
+use std::time::{Duration, Instant};
+use std::sync::Arc;
 
-#[derive(PartialEq, Debug)]
-enum BinaryOperator {
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum BinaryOperator {
     Addition,
     Subtraction,
     Multiplication,
@@ -31,32 +68,392 @@ enum BinaryOperator {
     Assign,
 }
 
-#[derive(PartialEq, Debug)]
-enum Expression {
-    Identifier(String),
+impl BinaryOperator {
+
+    /// Symbol of the operator in source code.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Addition => "+",
+            BinaryOperator::Subtraction => "-",
+            BinaryOperator::Multiplication => "*",
+            BinaryOperator::Division => "/",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Assign => "=",
+        }
+    }
+
+    /// Binding strength of the operator, higher binds tighter.
+    pub fn precedence(&self) -> u8 {
+        self.info().2
+    }
+
+    pub fn associativity(&self) -> Associativity {
+        self.info().3
+    }
+
+    /// Whether the operator compares its operands, which can't be
+    /// chained like `a == b == c`.
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, BinaryOperator::Equal | BinaryOperator::NotEqual)
+    }
+
+    fn info(&self) -> &'static (TokenKind, BinaryOperator, u8, Associativity) {
+        INFIX_OPERATORS.iter().find(|info| info.1 == *self).unwrap()
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum UnaryOperator {
+    Negation,
+}
+
+impl UnaryOperator {
+
+    /// Symbol of the operator in source code.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOperator::Negation => "-",
+        }
+    }
+}
+
+/// Side an operator groups with when chained with operators of the
+/// same precedence.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Associativity {
+
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+
+    /// `a = b = c` is `a = (b = c)`.
+    Right,
+}
+
+/// Infix operators, by the token spelling them, with their precedence
+/// and associativity.
+const INFIX_OPERATORS: [(TokenKind, BinaryOperator, u8, Associativity); 7] = [
+    (TokenKind::Assign, BinaryOperator::Assign, 1, Associativity::Right),
+    (TokenKind::Equal, BinaryOperator::Equal, 2, Associativity::Left),
+    (TokenKind::NotEqual, BinaryOperator::NotEqual, 2, Associativity::Left),
+    (TokenKind::Add, BinaryOperator::Addition, 4, Associativity::Left),
+    (TokenKind::Minus, BinaryOperator::Subtraction, 4, Associativity::Left),
+    (TokenKind::Times, BinaryOperator::Multiplication, 5, Associativity::Left),
+    (TokenKind::Divide, BinaryOperator::Division, 5, Associativity::Left),
+];
+
+/// Prefix operators, by the token spelling them.
+const PREFIX_OPERATORS: [(TokenKind, UnaryOperator); 1] = [
+    (TokenKind::Minus, UnaryOperator::Negation),
+];
+
+/// Precedence of `..` and `..=`, between comparisons and arithmetic.
+pub const RANGE_PRECEDENCE: u8 = 3;
+
+/// Precedence of `as`, tighter than any infix operator.
+pub const CAST_PRECEDENCE: u8 = 6;
+
+/// Precedence of every prefix operator, tighter than `as`.
+pub const PREFIX_PRECEDENCE: u8 = 7;
+
+/// How deeply statements, expressions, types and patterns may nest
+/// unless the options say otherwise, which keeps the parser and the
+/// passes after it from running out of stack on input like a thousand
+/// `(` or a thousand terms of `1 + 1 + ...`.
+pub const NESTING_LIMIT: usize = 128;
+
+/// Infix operator spelled by the token, with its precedence and
+/// associativity.
+fn infix_operator(token: &Token) -> Option<(BinaryOperator, u8, Associativity)> {
+    let kind = token.kind();
+
+    INFIX_OPERATORS.iter()
+        .find(|info| info.0 == kind)
+        .map(|info| (info.1, info.2, info.3))
+}
+
+/// Prefix operator spelled by the token.
+fn prefix_operator(token: &Token) -> Option<UnaryOperator> {
+    let kind = token.kind();
+
+    PREFIX_OPERATORS.iter()
+        .find(|info| info.0 == kind)
+        .map(|info| info.1)
+}
+
+/// Index of an expression in the arena of its program.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ExprId(pub u32);
+
+impl ExprId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Expression {
+
+    /// Use of a variable or function, spanning its name.
+    Identifier {
+        name: String,
+        span: Span,
+    },
     Number(isize),
     String(String),
+    Nil,
+
+    /// `()`, the only value of the type `()`.
+    Unit,
+    UnaryOperation {
+        operator: UnaryOperator,
+        operand: ExprId,
+    },
+    /// Binary operation, spanning the operator.
     BinaryOperation {
         operator: BinaryOperator,
-        operand_left: Box<Expression>,
-        operand_right: Box<Expression>,
+        operand_left: ExprId,
+        operand_right: ExprId,
+        span: Span,
     },
+
+    /// Function call, spanning the callee name.
     FunctionCall {
         callee_name: String,
-        arguments: Vec<Expression>,
+        arguments: Vec<Argument>,
+        span: Span,
+    },
+
+    /// `operand as type`, converting the value of `operand`, spanning
+    /// the `as`.
+    Cast {
+        operand: ExprId,
+        r#type: Type,
+        span: Span,
+    },
+
+    /// Array literal like `[1, 2, 3]`.
+    Array {
+        elements: Vec<ExprId>,
+    },
+
+    /// Map literal like `{"alex": 30, "kim": 25}`, with its entries
+    /// in order.
+    Map {
+        entries: Vec<(ExprId, ExprId)>,
+    },
+
+    /// Range like `1..3`, or `1..=3` if it includes its end. Either
+    /// bound may be left out, like in `xs[1..]`.
+    Range {
+        start: Option<ExprId>,
+        end: Option<ExprId>,
+        inclusive: bool,
+    },
+
+    /// Element `index` of the array `target`, spanning the `[`. If the
+    /// index is a range, the slice of the array it covers.
+    Index {
+        target: ExprId,
+        index: ExprId,
+        span: Span,
+    },
+
+    /// `operand?`, returning the error held by `operand` from the
+    /// enclosing function or else giving its value, spanning the `?`.
+    Try {
+        operand: ExprId,
+        span: Span,
+    },
+
+    /// Call of a method on the value of `receiver`, spanning the
+    /// method name.
+    MethodCall {
+        receiver: ExprId,
+        method: String,
+        arguments: Vec<Argument>,
+        span: Span,
+    },
+
+    /// `match scrutinee { pattern => value, ... }`, giving the value
+    /// of the first arm whose pattern matches, spanning the `match`.
+    Match {
+        scrutinee: ExprId,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
+}
+
+/// Arm of a `match`, like `Circle(r) => r * r`.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub value: ExprId,
+
+    /// Span of the first token of the pattern.
+    pub span: Span,
+}
+
+/// Pattern a value is matched against.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Pattern {
+
+    /// `_`, matching any value.
+    Wildcard,
+
+    /// Name matching the variant it names if it's a variant without
+    /// fields, or else any value, which it binds to the name.
+    Identifier {
+        name: String,
+        span: Span,
+    },
+
+    /// `Variant(a, b)`, matching the values of the variant whose
+    /// fields match the patterns in parentheses, spanning the name.
+    Variant {
+        name: String,
+        fields: Vec<Pattern>,
+        span: Span,
+    },
+
+    /// `[a, b]`, matching the arrays and slices with as many elements
+    /// as patterns, each matching its pattern.
+    Array(Vec<Pattern>),
+    Number(isize),
+    String(String),
+    Nil,
+}
+
+/// Variant of an enum, like `Rect(int, int)`.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+
+    /// Types of the values the variant holds, empty for a variant
+    /// written without parentheses.
+    pub fields: Vec<Type>,
+
+    /// Span of the variant name.
+    pub span: Span,
+}
+
+/// Type written in an annotation, like `int` or `[string; 4]`.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Type {
+
+    /// Type named by an identifier, like `int`.
+    Named(String),
+
+    /// `()`, the type of what functions return when they end without
+    /// `return`.
+    Unit,
+
+    /// `T?`, admitting `nil` besides the values of `T`.
+    Nullable(Box<Type>),
+
+    /// `[T; SIZE]`, holding `SIZE` values of `T`, where `SIZE` is a
+    /// constant expression.
+    Array {
+        element: Box<Type>,
+        size: ExprId,
+    },
+
+    /// `[T]`, viewing any number of consecutive values of `T` in an
+    /// array.
+    Slice(Box<Type>),
+
+    /// `map[K, V]`, mapping keys of `K` to values of `V`.
+    Map {
+        key: Box<Type>,
+        value: Box<Type>,
+    },
+}
+
+impl Type {
+
+    /// Call `visit` on every expression id the type holds.
+    pub fn visit_ids(&mut self, visit: &mut dyn FnMut(&mut ExprId)) {
+        match self {
+            Type::Named(_) |
+            Type::Unit => {},
+            Type::Nullable(r#type) |
+            Type::Slice(r#type) => r#type.visit_ids(visit),
+            Type::Map {
+                key,
+                value,
+            } => {
+                key.visit_ids(visit);
+                value.visit_ids(visit);
+            },
+            Type::Array {
+                element,
+                size,
+            } => {
+                element.visit_ids(visit);
+                visit(size);
+            },
+        }
     }
 }
 
+/// Argument of a function call, like `47` or `color = "red"`.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Argument {
+
+    /// Name of the parameter the argument is passed to, if it's given.
+    pub name: Option<String>,
+    pub value: ExprId,
+
+    /// Span of the name, or of the first token of an unnamed argument.
+    pub span: Span,
+}
+
 /// Function parameter.
-#[derive(PartialEq, Debug)]
-struct Parameter {
-    name: String,
-    r#type: Option<String>,
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Parameter {
+    pub name: String,
+    pub r#type: Option<Type>,
+
+    /// Span of the parameter name.
+    pub span: Span,
+}
+
+/// Method a trait requires, like `func print(self);`.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct MethodSignature {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Option<Type>,
+
+    /// Span of the method name.
+    pub span: Span,
+}
+
+impl MethodSignature {
+
+    /// Whether a method defined with `parameters` and `return_type`
+    /// implements this signature, given the arena of both. The
+    /// receiver `self` may be of any type.
+    fn matches(
+        &self,
+        parameters: &[Parameter],
+        return_type: &Option<Type>,
+        expressions: &[Expression]
+    ) -> bool {
+        let same = |left: &Option<Type>, right: &Option<Type>| {
+            left.as_ref().map(|r#type| format_type(expressions, r#type)) ==
+                right.as_ref().map(|r#type| format_type(expressions, r#type))
+        };
+
+        self.parameters.len() == parameters.len() &&
+            self.parameters.iter().zip(parameters).skip(1).all(|(left, right)| same(&left.r#type, &right.r#type)) &&
+            same(&self.return_type, return_type)
+    }
 }
 
 /// Statement, the basic element to form a program.
-#[derive(PartialEq, Debug)]
-enum Statement {
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Statement {
 
     /// Variable definition statement.
     /// 
@@ -72,10 +469,51 @@ enum Statement {
     /// - `identifier` Identifier of the defined variable.
     /// - `type` Type of the defined variable.
     /// - `value` Initial value of the defined variable.
+    /// - `span` Span of the identifier.
     VariableDefinition {
         identifier: String,
-        r#type: Option<String>,
-        value: Option<Expression>,
+        r#type: Option<Type>,
+        value: Option<ExprId>,
+        span: Span,
+    },
+
+    /// Variable definitions destructuring a value with a pattern.
+    ///
+    /// # Examples
+    /// ```fang
+    /// let [x, y] = point;
+    /// let Rect(width, _) = shape;
+    /// ```
+    ///
+    /// # Fields
+    /// - `pattern` Pattern the value must match, defining a variable
+    ///   for each name it binds.
+    /// - `value` Destructured value.
+    /// - `span` Span of the first token of the pattern.
+    Destructuring {
+        pattern: Pattern,
+        value: ExprId,
+        span: Span,
+    },
+
+    /// Constant definition statement, only allowed at the top level.
+    ///
+    /// # Examples
+    /// ```fang
+    /// const SIZE = 4 * 16;
+    /// const NAME: string = "buffer";
+    /// ```
+    ///
+    /// # Fields
+    /// - `identifier` Identifier of the defined constant.
+    /// - `type` Type of the defined constant.
+    /// - `value` Value of the constant, evaluated at compile time.
+    /// - `span` Span of the identifier.
+    ConstantDefinition {
+        identifier: String,
+        r#type: Option<Type>,
+        value: ExprId,
+        span: Span,
     },
 
     /// Function definition statement.
@@ -92,14 +530,17 @@ enum Statement {
     /// - `parameters` All parameters.
     /// - `return_type` Type of the return value.
     /// - `statements` All statements inside the function body.
+    /// - `span` Span of the function name.
     FunctionDefinition {
         callee_name: String,
         parameters: Vec<Parameter>,
-        return_type: Option<String>,
+        return_type: Option<Type>,
         statements: Vec<Statement>,
+        span: Span,
     },
 
-    /// Return statement.
+    /// Return statement, or the expression ending a function body
+    /// without `;`.
     /// 
     /// # Examples
     /// ```fang
@@ -108,8 +549,10 @@ enum Statement {
     /// 
     /// # Fields
     /// - `expression` Returned expression.
+    /// - `implicit` Whether it's written without `return` and `;`.
     Return {
-        expression: Expression,
+        expression: ExprId,
+        implicit: bool,
     },
 
     /// Expression statement.
@@ -123,7 +566,7 @@ enum Statement {
     /// # Fields
     /// - `expression` Expression.
     Expression {
-        expression: Expression,
+        expression: ExprId,
     },
 
     /// Block statement.
@@ -140,197 +583,2021 @@ enum Statement {
     Block {
         statements: Vec<Statement>,
     },
+
+    /// Loop running its body once for every item of a collection.
+    ///
+    /// # Examples
+    /// ```fang
+    /// for name in names {
+    ///     print(name);
+    /// }
+    /// ```
+    ///
+    /// # Fields
+    /// - `variable` Identifier bound to the current item.
+    /// - `iterable` Expression evaluating to the iterated collection.
+    /// - `statements` All statements inside the loop body.
+    /// - `span` Span of the variable.
+    For {
+        variable: String,
+        iterable: ExprId,
+        statements: Vec<Statement>,
+        span: Span,
+    },
+
+    /// Implementation block defining methods of a type.
+    ///
+    /// # Examples
+    /// ```fang
+    /// impl int {
+    ///     func double(self) -> int {
+    ///         return self * 2;
+    ///     }
+    /// }
+    ///
+    /// impl Printable for int {
+    ///     func print(self) {
+    ///         print(self);
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Fields
+    /// - `type_name` Type the methods are called on.
+    /// - `trait_name` Trait the methods implement, if any.
+    /// - `functions` Function definitions of the methods, each taking
+    ///   the receiver as its first parameter `self`.
+    /// - `span` Span of the type name.
+    Implementation {
+        type_name: String,
+        trait_name: Option<String>,
+        functions: Vec<Statement>,
+        span: Span,
+    },
+
+    /// Trait declaration, the methods a type must implement to
+    /// implement the trait.
+    ///
+    /// # Examples
+    /// ```fang
+    /// trait Printable {
+    ///     func print(self);
+    /// }
+    /// ```
+    ///
+    /// # Fields
+    /// - `name` Trait name.
+    /// - `methods` Signatures of the required methods.
+    /// - `span` Span of the trait name.
+    Trait {
+        name: String,
+        methods: Vec<MethodSignature>,
+        span: Span,
+    },
+
+    /// Enum declaration, a type whose values are one of its variants,
+    /// each holding values of the types listed after it.
+    ///
+    /// # Examples
+    /// ```fang
+    /// enum Shape {
+    ///     Circle(int),
+    ///     Rect(int, int),
+    ///     Empty,
+    /// }
+    /// ```
+    ///
+    /// # Fields
+    /// - `name` Enum name.
+    /// - `variants` All variants.
+    /// - `span` Span of the enum name.
+    Enum {
+        name: String,
+        variants: Vec<Variant>,
+        span: Span,
+    },
+
+    /// Macro definition, whose invocations like `twice(print(1));` are
+    /// replaced by its statements before the program is checked, each
+    /// parameter standing for the expression passed in its place.
+    ///
+    /// # Examples
+    /// ```fang
+    /// macro twice(x) {
+    ///     x;
+    ///     x;
+    /// }
+    /// ```
+    ///
+    /// # Fields
+    /// - `name` Macro name.
+    /// - `parameters` Names of the parameters.
+    /// - `statements` All statements inside the macro body.
+    /// - `span` Span of the macro name.
+    MacroDefinition {
+        name: String,
+        parameters: Vec<String>,
+        statements: Vec<Statement>,
+        span: Span,
+    },
+
+    /// Conditional compilation, keeping the first block if the symbol
+    /// is defined when compiling and the `#else` block otherwise.
+    ///
+    /// # Examples
+    /// ```fang
+    /// #if DEBUG {
+    ///     print("debugging");
+    /// } #else {
+    ///     print("releasing");
+    /// }
+    /// ```
+    ///
+    /// # Fields
+    /// - `symbol` Symbol deciding the kept block.
+    /// - `statements` Statements kept if the symbol is defined.
+    /// - `otherwise` Statements kept if it's not.
+    /// - `span` Span of the symbol.
+    Conditional {
+        symbol: String,
+        statements: Vec<Statement>,
+        otherwise: Vec<Statement>,
+        span: Span,
+    },
+
+    /// Statement with attributes setting the level of lints within it,
+    /// which [`configure`](crate::conditional::configure) moves to
+    /// [`Program::scoped_attributes`].
+    /// # Examples
+    /// ```fang
+    /// #[allow(unreachable_arms)]
+    /// func sign(value) {
+    ///     match value { _ => 1, 0 => 0 }
+    /// }
+    /// ```
+    /// # Fields
+    /// - `attributes` Attributes in the order they're written.
+    /// - `statement` Statement they apply to.
+    /// - `span` Span of the whole statement.
+    Attributed {
+        attributes: Vec<Attribute>,
+        statement: Box<Statement>,
+        span: Span,
+    },
 }
 
-#[derive(PartialEq, Debug)]
-pub struct Program {
-    statements: Vec<Statement>,
+impl Expression {
+
+    /// Call `visit` on every expression id the expression holds,
+    /// without going into the expressions they refer to.
+    pub fn visit_ids(&mut self, visit: &mut dyn FnMut(&mut ExprId)) {
+        match self {
+            Expression::UnaryOperation {
+                operand,
+                ..
+            } |
+            Expression::Try {
+                operand,
+                ..
+            } => visit(operand),
+            Expression::Cast {
+                operand,
+                r#type,
+                ..
+            } => {
+                visit(operand);
+                r#type.visit_ids(visit);
+            },
+            Expression::Array {
+                elements,
+            } => {
+                for element in elements {
+                    visit(element);
+                }
+            },
+            Expression::Map {
+                entries,
+            } => {
+                for (key, value) in entries {
+                    visit(key);
+                    visit(value);
+                }
+            },
+            Expression::Range {
+                start,
+                end,
+                ..
+            } => {
+                for bound in [start, end].into_iter().flatten() {
+                    visit(bound);
+                }
+            },
+            Expression::Index {
+                target,
+                index,
+                ..
+            } => {
+                visit(target);
+                visit(index);
+            },
+            Expression::BinaryOperation {
+                operand_left,
+                operand_right,
+                ..
+            } => {
+                visit(operand_left);
+                visit(operand_right);
+            },
+            Expression::FunctionCall {
+                arguments,
+                ..
+            } => {
+                for argument in arguments {
+                    visit(&mut argument.value);
+                }
+            },
+            Expression::MethodCall {
+                receiver,
+                arguments,
+                ..
+            } => {
+                visit(receiver);
+
+                for argument in arguments {
+                    visit(&mut argument.value);
+                }
+            },
+            Expression::Match {
+                scrutinee,
+                arms,
+                ..
+            } => {
+                visit(scrutinee);
+
+                for arm in arms {
+                    visit(&mut arm.value);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Call `visit` on every span the expression holds, without going
+    /// into the expressions it refers to.
+    pub fn visit_spans(&mut self, visit: &mut dyn FnMut(&mut Span)) {
+        match self {
+            Expression::Identifier {
+                span,
+                ..
+            } |
+            Expression::BinaryOperation {
+                span,
+                ..
+            } |
+            Expression::Cast {
+                span,
+                ..
+            } |
+            Expression::Index {
+                span,
+                ..
+            } |
+            Expression::Try {
+                span,
+                ..
+            } => visit(span),
+            Expression::FunctionCall {
+                arguments,
+                span,
+                ..
+            } |
+            Expression::MethodCall {
+                arguments,
+                span,
+                ..
+            } => {
+                visit(span);
+
+                for argument in arguments {
+                    visit(&mut argument.span);
+                }
+            },
+            Expression::Match {
+                arms,
+                span,
+                ..
+            } => {
+                visit(span);
+
+                for arm in arms {
+                    arm.pattern.visit_spans(visit);
+                    visit(&mut arm.span);
+                }
+            },
+            _ => {},
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct Parser {
-    stream: Stream,
+impl Pattern {
+
+    /// Call `visit` on every span the pattern holds, including those
+    /// of the patterns nested in it.
+    pub fn visit_spans(&mut self, visit: &mut dyn FnMut(&mut Span)) {
+        match self {
+            Pattern::Identifier {
+                span,
+                ..
+            } => visit(span),
+            Pattern::Variant {
+                fields,
+                span,
+                ..
+            } => {
+                visit(span);
+
+                for field in fields {
+                    field.visit_spans(visit);
+                }
+            },
+            Pattern::Array(elements) => {
+                for element in elements {
+                    element.visit_spans(visit);
+                }
+            },
+            _ => {},
+        }
+    }
 }
 
-impl Parser {
-    pub fn new(stream: Stream) -> Parser {
-        Parser {
-            stream,
+/// Call `visit` on every expression id the types of a function
+/// signature hold.
+fn visit_signature(parameters: &mut [Parameter], return_type: &mut Option<Type>, visit: &mut dyn FnMut(&mut ExprId)) {
+    for parameter in parameters {
+        if let Some(r#type) = &mut parameter.r#type {
+            r#type.visit_ids(visit);
         }
     }
 
-    pub fn parse_program(&mut self) -> Program {
-        let mut statements: Vec<Statement> = Vec::new();
+    if let Some(return_type) = return_type {
+        return_type.visit_ids(visit);
+    }
+}
 
-        while self.stream.peek() != Some(&Token::EndOfProgram) {
-            let statement = self.parse_statement();
+impl Statement {
 
-            statements.push(statement);
+    /// The statement itself, or the one it gives attributes to.
+    pub fn unattributed(&self) -> &Statement {
+        match self {
+            Statement::Attributed {
+                statement,
+                ..
+            } => statement.unattributed(),
+            _ => self,
         }
+    }
 
-        Program {
-            statements,
+    /// Call `visit` on every expression id the statement holds,
+    /// including those of the statements nested in it.
+    pub fn visit_ids(&mut self, visit: &mut dyn FnMut(&mut ExprId)) {
+        match self {
+            Statement::VariableDefinition {
+                r#type,
+                value,
+                ..
+            } => {
+                if let Some(r#type) = r#type {
+                    r#type.visit_ids(visit);
+                }
+
+                if let Some(value) = value {
+                    visit(value);
+                }
+            },
+            Statement::ConstantDefinition {
+                r#type,
+                value,
+                ..
+            } => {
+                if let Some(r#type) = r#type {
+                    r#type.visit_ids(visit);
+                }
+
+                visit(value);
+            },
+            Statement::Trait {
+                methods,
+                ..
+            } => {
+                for method in methods {
+                    visit_signature(&mut method.parameters, &mut method.return_type, visit);
+                }
+            },
+            Statement::Enum {
+                variants,
+                ..
+            } => {
+                for variant in variants {
+                    for field in &mut variant.fields {
+                        field.visit_ids(visit);
+                    }
+                }
+            },
+            Statement::FunctionDefinition {
+                parameters,
+                return_type,
+                statements,
+                ..
+            } => {
+                visit_signature(parameters, return_type, visit);
+
+                for statement in statements {
+                    statement.visit_ids(visit);
+                }
+            },
+            Statement::Block {
+                statements,
+            } |
+            Statement::Implementation {
+                functions: statements,
+                ..
+            } |
+            Statement::MacroDefinition {
+                statements,
+                ..
+            } => {
+                for statement in statements {
+                    statement.visit_ids(visit);
+                }
+            },
+            Statement::For {
+                iterable,
+                statements,
+                ..
+            } => {
+                visit(iterable);
+
+                for statement in statements {
+                    statement.visit_ids(visit);
+                }
+            },
+            Statement::Conditional {
+                statements,
+                otherwise,
+                ..
+            } => {
+                for statement in statements.iter_mut().chain(otherwise) {
+                    statement.visit_ids(visit);
+                }
+            },
+            Statement::Attributed {
+                statement,
+                ..
+            } => statement.visit_ids(visit),
+            Statement::Return {
+                expression,
+                ..
+            } |
+            Statement::Expression {
+                expression,
+            } |
+            Statement::Destructuring {
+                value: expression,
+                ..
+            } => visit(expression),
         }
     }
 
-    fn parse_statement(&mut self) -> Statement {
-        let statement: Statement;
+    /// Call `visit` on every span the statement holds, including those
+    /// of the statements nested in it but not those of its expressions.
+    pub fn visit_spans(&mut self, visit: &mut dyn FnMut(&mut Span)) {
+        match self {
+            Statement::VariableDefinition {
+                span,
+                ..
+            } |
+            Statement::ConstantDefinition {
+                span,
+                ..
+            } => visit(span),
+            Statement::Destructuring {
+                pattern,
+                span,
+                ..
+            } => {
+                pattern.visit_spans(visit);
+                visit(span);
+            },
+            Statement::FunctionDefinition {
+                parameters,
+                statements,
+                span,
+                ..
+            } => {
+                for parameter in parameters {
+                    visit(&mut parameter.span);
+                }
+
+                for statement in statements {
+                    statement.visit_spans(visit);
+                }
+
+                visit(span);
+            },
+            Statement::For {
+                statements,
+                span,
+                ..
+            } |
+            Statement::Implementation {
+                functions: statements,
+                span,
+                ..
+            } |
+            Statement::MacroDefinition {
+                statements,
+                span,
+                ..
+            } => {
+                for statement in statements {
+                    statement.visit_spans(visit);
+                }
+
+                visit(span);
+            },
+            Statement::Conditional {
+                statements,
+                otherwise,
+                span,
+                ..
+            } => {
+                for statement in statements.iter_mut().chain(otherwise) {
+                    statement.visit_spans(visit);
+                }
+
+                visit(span);
+            },
+            Statement::Attributed {
+                attributes,
+                statement,
+                span,
+            } => {
+                for attribute in attributes {
+                    visit(&mut attribute.span);
+                }
+
+                statement.visit_spans(visit);
+                visit(span);
+            },
+            Statement::Block {
+                statements,
+            } => {
+                for statement in statements {
+                    statement.visit_spans(visit);
+                }
+            },
+            Statement::Trait {
+                methods,
+                span,
+                ..
+            } => {
+                for method in methods {
+                    for parameter in &mut method.parameters {
+                        visit(&mut parameter.span);
+                    }
+
+                    visit(&mut method.span);
+                }
+
+                visit(span);
+            },
+            Statement::Enum {
+                variants,
+                span,
+                ..
+            } => {
+                for variant in variants {
+                    visit(&mut variant.span);
+                }
+
+                visit(span);
+            },
+            Statement::Return {
+                ..
+            } |
+            Statement::Expression {
+                ..
+            } => {},
+        }
+    }
+}
+
+/// Module-level attribute `#![name]` or `#![name(argument, ...)]`,
+/// heading the program and directing the passes after the parser, or
+/// attribute `#[name(argument, ...)]` heading a statement.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Attribute {
+    pub name: String,
+    pub arguments: Vec<String>,
+    pub span: Span,
+}
+
+/// Program with the arena its expressions are allocated in.
+///
+/// Statements refer to expressions by `ExprId`, so the whole tree of
+/// expressions lives in one flat vector instead of separate boxes. The
+/// arena is shared, so the interpreter and the functions a program
+/// defines hold it without copying it.
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Program {
+    pub attributes: Vec<Attribute>,
+
+    /// Attributes of single statements with the span of the statement
+    /// they apply to, a statement coming before those nested in it.
+    #[serde(default)]
+    pub scoped_attributes: Vec<(Span, Vec<Attribute>)>,
+    pub statements: Vec<Statement>,
+    pub expressions: Arc<Vec<Expression>>,
+}
+
+impl Program {
+    pub fn expression(&self, id: ExprId) -> &Expression {
+        &self.expressions[id.index()]
+    }
+
+    /// Whether the program has the attribute `#![name]`.
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.attributes.iter().any(|attribute| attribute.name == name)
+    }
+
+    /// Whether the builtin functions are in scope, unless the program
+    /// opts out of them or of the whole runtime.
+    pub fn uses_prelude(&self) -> bool {
+        !self.has_attribute("no_prelude") && !self.has_attribute("no_std_runtime")
+    }
+
+    /// Whether an `#![allow(...)]` attribute silences `lint`.
+    pub fn allows(&self, lint: &str) -> bool {
+        self.attributes.iter().any(|attribute| {
+            attribute.name == "allow" && attribute.arguments.iter().any(|argument| argument == lint)
+        })
+    }
+
+    /// Level the innermost attribute setting the level of `lint`, or
+    /// of every lint, gives it at `span`, with `None` for a diagnostic
+    /// without a span being within the program attributes only.
+    pub fn lint_level(&self, lint: &str, span: Option<Span>) -> Option<Level> {
+        let scoped = self.scoped_attributes.iter()
+            .rev()
+            .filter(|(scope, _)| span.is_some_and(|span| scope.start <= span.start && span.end <= scope.end))
+            .flat_map(|(_, attributes)| attributes.iter().rev());
+
+        scoped.chain(self.attributes.iter().rev())
+            .filter(|attribute| attribute.arguments.iter().any(|argument| argument == lint || argument == WARNINGS))
+            .find_map(|attribute| Level::from_attribute(&attribute.name))
+    }
+
+    /// Move every span of the program `offset` bytes further, as when
+    /// its source is laid out after other files in a
+    /// [`SourceMap`](crate::span::SourceMap).
+    pub fn shift_spans(&mut self, offset: usize) {
+        let visit = &mut |span: &mut Span| span.shift(offset);
+
+        for attribute in &mut self.attributes {
+            visit(&mut attribute.span);
+        }
+
+        for (span, attributes) in &mut self.scoped_attributes {
+            visit(span);
+
+            for attribute in attributes {
+                visit(&mut attribute.span);
+            }
+        }
+
+        for statement in &mut self.statements {
+            statement.visit_spans(visit);
+        }
+
+        for expression in Arc::make_mut(&mut self.expressions) {
+            expression.visit_spans(visit);
+        }
+    }
+
+    /// Append the statements of `other`, moving its expressions into
+    /// this program's arena.
+    pub fn append(&mut self, other: Program) {
+        let offset = self.expressions.len() as u32;
+        let expressions = Arc::make_mut(&mut self.expressions);
+
+        self.attributes.extend(other.attributes);
+        self.scoped_attributes.extend(other.scoped_attributes);
+
+        for mut expression in Arc::unwrap_or_clone(other.expressions) {
+            expression.visit_ids(&mut |id| id.0 += offset);
+            expressions.push(expression);
+        }
+
+        for mut statement in other.statements {
+            statement.visit_ids(&mut |id| id.0 += offset);
+            self.statements.push(statement);
+        }
+    }
+}
+
+/// Lex and parse `source`, scanning it only as far as the parser has
+/// got instead of holding all of its tokens at once.
+///
+/// Bytes the lexer can't make sense of are reported in place of any
+/// syntax error, since the error is most likely caused by them.
+pub fn parse_source(source: &str) -> Result<Program, Diagnostics> {
+    parse_source_with(source, ParseOptions::default())
+}
+
+/// Lex and parse `source` like [`parse_source`], with the grammar
+/// chosen by `options` unless the source names its own edition.
+pub fn parse_source_with(source: &str, mut options: ParseOptions) -> Result<Program, Diagnostics> {
+    let mut parser: Parser;
+
+    if let Some(edition) = edition_pragma(source)? {
+        options.edition = edition;
+    }
+
+    parser = Parser::with_options(Stream::from_source_with(source, options.limits), options);
+    let result = parser.parse_program();
+    let diagnostics = parser.stream.finish();
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    result
+}
+
+/// Edition named by a first line like `// edition: 2026`, `None` if
+/// the source doesn't name one.
+pub fn edition_pragma(source: &str) -> Result<Option<Edition>, Diagnostics> {
+    let line = source.lines().next().unwrap_or("");
+    let name = match line.strip_prefix("// edition:") {
+        Some(name) => name.trim(),
+        None => return Ok(None),
+    };
+
+    match Edition::from_name(name) {
+        Some(edition) => Ok(Some(edition)),
+        None => {
+            let mut diagnostics = Diagnostics::new();
+
+            diagnostics.push(Diagnostic::error(format!("Unknown edition \"{}\"!", name))
+                .with_span(Span::new(0, line.len()))
+                .with_help("editions are 2025 and 2026"));
+
+            Err(diagnostics)
+        },
+    }
+}
+
+/// Collect the trait declarations and the implementations of traits
+/// in `statements` and every statement nested in them.
+fn collect_traits<'a>(
+    statements: &'a [Statement],
+    traits: &mut Vec<&'a Statement>,
+    implementations: &mut Vec<&'a Statement>
+) {
+    for statement in statements {
+        match statement {
+            Statement::Trait { .. } => traits.push(statement),
+            Statement::Implementation {
+                trait_name: Some(_),
+                ..
+            } => implementations.push(statement),
+            Statement::FunctionDefinition {
+                statements,
+                ..
+            } |
+            Statement::Block {
+                statements,
+            } |
+            Statement::For {
+                statements,
+                ..
+            } => collect_traits(statements, traits, implementations),
+            Statement::Conditional {
+                statements,
+                otherwise,
+                ..
+            } => {
+                collect_traits(statements, traits, implementations);
+                collect_traits(otherwise, traits, implementations);
+            },
+            Statement::Attributed {
+                statement,
+                ..
+            } => collect_traits(std::slice::from_ref(statement), traits, implementations),
+            _ => {},
+        }
+    }
+}
+
+fn function_name(statement: &Statement) -> &str {
+    match statement {
+        Statement::FunctionDefinition {
+            callee_name,
+            ..
+        } => callee_name,
+        _ => "",
+    }
+}
+
+/// Version of the grammar, so that changes breaking existing
+/// programs only apply to the files opting in.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Edition {
+
+    /// First edition, where every statement ends with `;`.
+    #[default]
+    #[serde(rename = "2025")]
+    E2025,
+
+    /// Edition where a line break may end a statement in place of `;`.
+    #[serde(rename = "2026")]
+    E2026,
+}
+
+impl Edition {
+
+    /// Name of the edition, as written after `--edition`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Edition::E2025 => "2025",
+            Edition::E2026 => "2026",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Edition> {
+        match name {
+            "2025" => Some(Edition::E2025),
+            "2026" => Some(Edition::E2026),
+            _ => None,
+        }
+    }
+}
+
+/// Choices of grammar and of how to read sources made by whoever runs
+/// the parser rather than by the source.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct ParseOptions {
+
+    /// Edition of the files that don't name one themselves.
+    pub edition: Edition,
+
+    /// Whether a line break may end a statement in place of `;`, as
+    /// may a `}` or the end of the program, even before the edition
+    /// allowing it.
+    pub optional_semicolons: bool,
+
+    /// How deeply statements, expressions, types and patterns may
+    /// nest. Deeper code takes more stack in every pass.
+    pub nesting_limit: usize,
+
+    /// Bounds on the work parsing a source may take.
+    pub limits: Limits,
+
+    /// Whether bytes of a file invalid in its encoding are read as
+    /// U+FFFD instead of being reported.
+    pub lossy_utf8: bool,
+
+    /// Width of a tab in the columns of diagnostics, one counting a tab
+    /// as any other character.
+    pub tab_width: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            edition: Edition::default(),
+            optional_semicolons: false,
+            nesting_limit: NESTING_LIMIT,
+            limits: Limits::default(),
+            lossy_utf8: false,
+            tab_width: 1,
+        }
+    }
+}
+
+/// Bounds on the size of a source and the time spent parsing it, for
+/// whoever compiles sources they don't trust. Nothing is bounded by
+/// default.
+///
+/// Parsing stops at the first limit exceeded, with an error saying
+/// which one.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Limits {
+
+    /// How many tokens the source may hold.
+    pub max_tokens: Option<usize>,
+
+    /// How many expressions the syntax tree may hold.
+    pub max_nodes: Option<usize>,
+
+    /// How many bytes a string literal may hold.
+    pub max_string_length: Option<usize>,
+
+    /// How long parsing may take.
+    pub time_budget: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct Parser<'src> {
+    stream: Stream<'src>,
+    options: ParseOptions,
+
+    /// Arena of the expressions parsed so far.
+    expressions: Vec<Expression>,
+
+    /// Errors the parser recovered from so far.
+    diagnostics: Diagnostics,
+
+    /// Whether the statements being parsed are a function body, whose
+    /// last expression may leave out `;` to return its value.
+    tail: bool,
+
+    /// How many statements, expressions, types and patterns enclose
+    /// the one being parsed.
+    depth: usize,
+
+    /// How deep the tree of each expression in the arena is, which
+    /// operators chained in a loop grow without going any deeper.
+    heights: Vec<usize>,
+
+    /// When the time budget of the options runs out, if they set one.
+    deadline: Option<Instant>,
+}
+
+impl<'src> Parser<'src> {
+    pub fn new(stream: Stream<'src>) -> Parser<'src> {
+        Parser::with_options(stream, ParseOptions::default())
+    }
+
+    pub fn with_options(stream: Stream<'src>, options: ParseOptions) -> Parser<'src> {
+        Parser {
+            stream,
+            options,
+            expressions: Vec::new(),
+            diagnostics: Diagnostics::new(),
+            tail: false,
+            depth: 0,
+            heights: Vec::new(),
+            deadline: options.limits.time_budget.map(|budget| Instant::now() + budget),
+        }
+    }
+
+    /// Parse with `parse` one level deeper, unless that would nest
+    /// deeper than the limit or the parser is past any other limit.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, Diagnostic>) -> Result<T, Diagnostic> {
+        if self.depth == self.options.nesting_limit {
+            return Err(self.too_deep());
+        }
+
+        self.check_limits()?;
+
+        self.depth += 1;
+        let result: Result<T, Diagnostic> = parse(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    /// Error if the expressions parsed or the time taken so far
+    /// exceed the limits of the options. The stream checks the limits
+    /// on tokens itself.
+    ///
+    /// Every statement and every operand is parsed one level down, so
+    /// [`Parser::nested`] checks often enough for no limit to be
+    /// overrun by much.
+    fn check_limits(&self) -> Result<(), Diagnostic> {
+        let limits = self.options.limits;
+
+        if let Some(max) = limits.max_nodes.filter(|max| self.expressions.len() > *max) {
+            return Err(self.error_at_next("Node limit exceeded!")
+                .with_help(format!("the syntax tree may hold {} expressions", max)));
+        }
+
+        if let (Some(deadline), Some(budget)) = (self.deadline, limits.time_budget) {
+            if Instant::now() >= deadline {
+                return Err(self.error_at_next("Time limit exceeded!")
+                    .with_help(format!("parsing may take {} ms", budget.as_millis())));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Move the expression into the arena, unless its tree would then
+    /// nest deeper than the limit. A chain like `1 + 1 + ...` or
+    /// `a[0][0]...` is parsed in a loop, so it's caught here rather
+    /// than by [`Parser::nested`].
+    fn alloc(&mut self, mut expression: Expression) -> Result<ExprId, Diagnostic> {
+        let mut height = 0;
+
+        expression.visit_ids(&mut |id| height = height.max(self.heights[id.index()]));
+        height += 1;
+
+        /* A leaf parsed at the current depth is as deep as the limit
+         * allows, so the tree may only add `height - 1` to it. */
+        if self.depth + height - 1 > self.options.nesting_limit {
+            return Err(self.too_deep());
+        }
+
+        self.expressions.push(expression);
+        self.heights.push(height);
+
+        Ok(ExprId(self.expressions.len() as u32 - 1))
+    }
+
+    fn too_deep(&self) -> Diagnostic {
+        self.error_at_next("Nesting too deep!")
+            .with_help(format!("code may nest {} levels deep", self.options.nesting_limit))
+    }
+
+    /// Error pointing at the next token.
+    fn error_at_next(&self, message: &str) -> Diagnostic {
+        Diagnostic::error(message).with_span(self.stream.span())
+    }
+
+    /// Error pointing at the token just consumed.
+    fn error_at_consumed(&self, message: &str) -> Diagnostic {
+        Diagnostic::error(message).with_span(self.stream.previous_span())
+    }
+
+    /// Report functions of a statement list defined again with the
+    /// same parameter types, since no call could tell them apart.
+    ///
+    /// Functions sharing a name are otherwise overloads of each other.
+    fn check_overloads(&mut self, statements: &[Statement]) {
+        let mut signatures: Vec<(&str, Vec<Option<String>>)> = Vec::new();
+
+        for statement in statements {
+            if let Statement::FunctionDefinition {
+                callee_name,
+                parameters,
+                span,
+                ..
+            } = statement.unattributed() {
+                let signature = (
+                    callee_name.as_str(),
+                    parameters.iter()
+                        .map(|parameter| parameter.r#type.as_ref().map(|r#type| format_type(&self.expressions, r#type)))
+                        .collect(),
+                );
+
+                if signatures.contains(&signature) {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("Function \"{}\" is already defined with the same parameter types!", callee_name))
+                        .with_span(*span));
+                } else {
+                    signatures.push(signature);
+                }
+            }
+        }
+    }
+
+    /// Report definitions of a statement list clashing with a variant
+    /// of an enum, at the later of the two. Variants are defined in the
+    /// scope of their enum like functions, so no call or pattern could
+    /// tell them apart.
+    fn check_variants(&mut self, statements: &[Statement]) {
+        let mut names: Vec<&str> = Vec::new();
+        let mut variants: Vec<&str> = Vec::new();
+
+        for statement in statements {
+            let (kind, name, span) = match statement.unattributed() {
+                Statement::Enum {
+                    variants: defined,
+                    ..
+                } => {
+                    for variant in defined {
+                        if names.contains(&variant.name.as_str()) {
+                            self.diagnostics.push(Diagnostic::error(
+                                format!("Variant \"{}\" is already defined!", variant.name))
+                                .with_span(variant.span));
+                        } else {
+                            names.push(&variant.name);
+                            variants.push(&variant.name);
+                        }
+                    }
+
+                    continue;
+                },
+                Statement::FunctionDefinition {
+                    callee_name,
+                    span,
+                    ..
+                } => ("Function", callee_name, span),
+                Statement::VariableDefinition {
+                    identifier,
+                    span,
+                    ..
+                } => ("Variable", identifier, span),
+                Statement::ConstantDefinition {
+                    identifier,
+                    span,
+                    ..
+                } => ("Constant", identifier, span),
+                _ => continue,
+            };
+
+            /* Functions may be overloaded and variables redefined, so
+             * only a variant rules out a name for the others. */
+            if variants.contains(&name.as_str()) {
+                self.diagnostics.push(Diagnostic::error(format!("{} \"{}\" is already defined!", kind, name))
+                    .with_span(*span)
+                    .with_help(format!("\"{}\" is a variant of an enum", name)));
+            } else {
+                names.push(name);
+            }
+        }
+    }
+
+    /// Check that every `impl Trait for Type` block implements exactly
+    /// the methods the trait declares, with matching signatures.
+    ///
+    /// Traits share one namespace across the program wherever they're
+    /// declared.
+    fn check_traits(&mut self, statements: &[Statement]) {
+        let mut traits: Vec<&Statement> = Vec::new();
+        let mut implementations: Vec<&Statement> = Vec::new();
+
+        collect_traits(statements, &mut traits, &mut implementations);
+
+        for (index, statement) in traits.iter().enumerate() {
+            if let Statement::Trait {
+                name,
+                span,
+                ..
+            } = statement {
+                if traits[..index].iter().any(|other| matches!(other, Statement::Trait {
+                    name: other,
+                    ..
+                } if other == name)) {
+                    self.diagnostics.push(Diagnostic::error(format!("Trait \"{}\" is already defined!", name))
+                        .with_span(*span));
+                }
+            }
+        }
+
+        for implementation in implementations {
+            if let Statement::Implementation {
+                type_name,
+                trait_name: Some(trait_name),
+                functions,
+                span,
+            } = implementation {
+                let methods = traits.iter().find_map(|statement| match statement {
+                    Statement::Trait {
+                        name,
+                        methods,
+                        ..
+                    } if name == trait_name => Some(methods),
+                    _ => None,
+                });
+                let methods = match methods {
+                    Some(methods) => methods,
+                    None => {
+                        self.diagnostics.push(Diagnostic::error(format!("Unknown trait \"{}\"!", trait_name))
+                            .with_span(*span));
+                        continue;
+                    },
+                };
+
+                for method in methods.iter() {
+                    if !functions.iter().any(|function| function_name(function) == method.name) {
+                        self.diagnostics.push(Diagnostic::error(
+                            format!("Implementation of \"{}\" for \"{}\" is missing method \"{}\"!",
+                                    trait_name, type_name, method.name))
+                            .with_span(*span));
+                    }
+                }
+
+                for function in functions {
+                    if let Statement::FunctionDefinition {
+                        callee_name,
+                        parameters,
+                        return_type,
+                        span,
+                        ..
+                    } = function {
+                        let method = methods.iter().find(|method| method.name == *callee_name);
+
+                        match method {
+                            None => self.diagnostics.push(Diagnostic::error(
+                                format!("Method \"{}\" is not a member of trait \"{}\"!", callee_name, trait_name))
+                                .with_span(*span)),
+                            Some(method) if !method.matches(parameters, return_type, &self.expressions) =>
+                                self.diagnostics.push(Diagnostic::error(
+                                    format!("Method \"{}\" does not match its signature in trait \"{}\"!",
+                                            callee_name, trait_name))
+                                    .with_span(*span)),
+                            Some(_) => {},
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn optional_semicolons(&self) -> bool {
+        self.options.optional_semicolons || self.options.edition >= Edition::E2026
+    }
+
+    /// Consume the `;` ending a statement.
+    ///
+    /// A `;` missing before a line break is most likely forgotten at
+    /// the end of the line, it's reported and parsing carries on as if
+    /// it was there.
+    ///
+    /// With optional semicolons, a line break, a `}` or the end of the
+    /// program ends the statement instead. The statement has already
+    /// taken in everything that could continue it, so a line starting
+    /// with an infix operator still belongs to the one before.
+    fn parse_end_of_statement(&mut self) -> Result<(), Diagnostic> {
+        if self.stream.match_token(TokenKind::EndOfStatement) {
+            self.stream.consume();
+        } else if self.optional_semicolons() && (self.stream.line_break_before()
+            || self.stream.match_token(TokenKind::RightCurlyBracket)
+            || self.stream.match_token(TokenKind::EndOfProgram)) {
+
+            /* Nothing to consume. */
+        } else if self.stream.line_break_before() {
+            let end = self.stream.previous_span().end;
+
+            self.diagnostics.push(Diagnostic::error("Expected \";\"!")
+                .with_span(Span::new(end, end))
+                .with_help("add `;` after this expression"));
+        } else {
+            self.stream.consume();
+
+            return Err(self.error_at_consumed("Expected \";\"!"));
+        }
+
+        Ok(())
+    }
+
+    /// Parse the whole program, reporting every error recovered from
+    /// along with the one parsing stopped at, if any.
+    pub fn parse_program(&mut self) -> Result<Program, Diagnostics> {
+        let mut attributes: Vec<Attribute> = Vec::new();
+        let mut statements: Vec<Statement> = Vec::new();
+
+        while self.stream.match_token(TokenKind::InnerAttribute) {
+            match self.parse_attribute(true) {
+                Ok(attribute) => attributes.push(attribute),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    return Err(std::mem::take(&mut self.diagnostics));
+                },
+            }
+        }
+
+        while !self.stream.match_token(TokenKind::EndOfProgram) {
+            match self.parse_statement() {
+                Ok(statement) => statements.push(statement),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    break;
+                },
+            }
+        }
+
+        self.check_overloads(&statements);
+        self.check_variants(&statements);
+        self.check_traits(&statements);
+
+        if !self.diagnostics.is_empty() {
+            return Err(std::mem::take(&mut self.diagnostics));
+        }
+
+        Ok(Program {
+            attributes,
+            scoped_attributes: Vec::new(),
+            statements,
+            expressions: Arc::new(std::mem::take(&mut self.expressions)),
+        })
+    }
+
+    /// Parse the attribute `#![name]` or `#![name(argument, ...)]` of
+    /// the program if `inner`, or else `#[name(argument, ...)]` of the
+    /// next statement, checking that later passes know what to make of
+    /// it.
+    fn parse_attribute(&mut self, inner: bool) -> Result<Attribute, Diagnostic> {
+        let mut arguments: Vec<String> = Vec::new();
+
+        self.stream.consume();
+        let start: usize = self.stream.previous_span().start;
+
+        match self.stream.consume() {
+            Some(Token::LeftSquareBracket) => {},
+            _ => return Err(self.error_at_consumed("Expected \"[\"!")),
+        }
+
+        let name: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected attribute name!")),
+        };
+
+        if self.stream.match_token(TokenKind::LeftRoundBracket) {
+            self.stream.consume();
+
+            loop {
+                match self.stream.consume() {
+                    Some(Token::Identifier(id)) => arguments.push(id.into_owned()),
+                    _ => return Err(self.error_at_consumed("Expected identifier!")),
+                }
+
+                match self.stream.consume() {
+                    Some(Token::Comma) => {},
+                    Some(Token::RightRoundBracket) => break,
+                    _ => return Err(self.error_at_consumed("Expected \",\" or \")\"!")),
+                }
+            }
+        }
+
+        match self.stream.consume() {
+            Some(Token::RightSquareBracket) => {},
+            _ => return Err(self.error_at_consumed("Expected \"]\"!")),
+        }
+
+        let attribute = Attribute {
+            name,
+            arguments,
+            span: Span::new(start, self.stream.previous_span().end),
+        };
+
+        match (attribute.name.as_str(), attribute.arguments.len()) {
+            ("no_std_runtime", 0) |
+            ("no_prelude", 0) if inner => {},
+            ("test", 0) if !inner => {},
+            ("allow" | "warn" | "deny", 1..) => {},
+            ("no_std_runtime", _) |
+            ("no_prelude", _) if inner => return Err(Diagnostic::error(
+                format!("Wrong arguments for attribute \"{}\"!", attribute.name))
+                .with_span(attribute.span)),
+            ("test", _) if !inner => return Err(Diagnostic::error(
+                format!("Wrong arguments for attribute \"{}\"!", attribute.name))
+                .with_span(attribute.span)),
+            ("allow" | "warn" | "deny", _) => return Err(Diagnostic::error(
+                format!("Wrong arguments for attribute \"{}\"!", attribute.name))
+                .with_span(attribute.span)),
+            _ if inner => return Err(Diagnostic::error(format!("Unknown attribute \"{}\"!", attribute.name))
+                .with_span(attribute.span)
+                .with_help("attributes are allow, warn, deny, no_prelude and no_std_runtime")),
+            _ => return Err(Diagnostic::error(format!("Unknown attribute \"{}\"!", attribute.name))
+                .with_span(attribute.span)
+                .with_help("attributes of statements are allow, warn, deny and test")),
+        }
+
+        Ok(attribute)
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, Diagnostic> {
+        self.nested(|parser| {
+            let statement: Statement = match parser.stream.peek() {
+                Some(Token::LeftCurlyBracket) =>
+                    parser.parse_block_statement()?,
+                Some(Token::Let) |
+                Some(Token::Variable) =>
+                    parser.parse_variable_definition_statement()?,
+                Some(Token::Constant) =>
+                    parser.parse_constant_definition_statement()?,
+                Some(Token::Function) =>
+                    parser.parse_function_definition_statement()?,
+                Some(Token::Return) =>
+                    parser.parse_return_statement()?,
+                Some(Token::For) =>
+                    parser.parse_for_statement()?,
+                Some(Token::Implementation) =>
+                    parser.parse_implementation_statement()?,
+                Some(Token::Trait) =>
+                    parser.parse_trait_statement()?,
+                Some(Token::Enum) =>
+                    parser.parse_enum_statement()?,
+                Some(Token::Macro) =>
+                    parser.parse_macro_definition_statement()?,
+                Some(Token::Hash) if matches!(parser.stream.peek_second(), Some(Token::LeftSquareBracket)) =>
+                    parser.parse_attributed_statement()?,
+                Some(Token::Hash) =>
+                    parser.parse_conditional_statement()?,
+                Some(Token::InnerAttribute) =>
+                    return Err(parser.error_at_next("Attributes must come before any statement!")),
+                _ => parser.parse_expression_statement()?,
+            };
+
+            Ok(statement)
+        })
+    }
+
+    /// Parse the attributes `#[name(argument, ...)]` heading a
+    /// statement, then the statement.
+    fn parse_attributed_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let mut attributes: Vec<Attribute> = Vec::new();
+
+        while self.stream.match_token(TokenKind::Hash) &&
+              matches!(self.stream.peek_second(), Some(Token::LeftSquareBracket)) {
+            attributes.push(self.parse_attribute(false)?);
+        }
+
+        let start: usize = self.stream.span().start;
+        let statement: Statement = self.parse_statement()?;
+
+        if let Some(test) = attributes.iter().find(|attribute| attribute.name == "test") {
+            if !matches!(statement, Statement::FunctionDefinition { .. }) {
+                return Err(Diagnostic::error("Only functions can be tests!").with_span(test.span));
+            }
+        }
+
+        Ok(Statement::Attributed {
+            attributes,
+            statement: Box::new(statement),
+            span: Span::new(start, self.stream.previous_span().end),
+        })
+    }
+
+    fn parse_macro_definition_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let mut parameters: Vec<String> = Vec::new();
+
+        self.stream.consume();
+
+        let name: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected identifier!")),
+        };
+        let span: Span = self.stream.previous_span();
+
+        match self.stream.consume() {
+            Some(Token::LeftRoundBracket) => {},
+            _ => return Err(self.error_at_consumed("Expected \"(\"!")),
+        }
+
+        while !self.stream.match_token(TokenKind::RightRoundBracket) {
+            match self.stream.consume() {
+                Some(Token::Identifier(id)) => parameters.push(id.into_owned()),
+                _ => return Err(self.error_at_consumed("Expected identifier!")),
+            }
+
+            match self.stream.peek() {
+                Some(Token::Comma) => {
+                    self.stream.consume();
+                },
+                Some(Token::RightRoundBracket) => {},
+                _ => return Err(self.error_at_next("Expected \",\" or \")\"!")),
+            }
+        }
+
+        self.stream.consume();
+
+        let statements: Vec<Statement> = self.parse_function_body(false)?;
+
+        Ok(Statement::MacroDefinition {
+            name,
+            parameters,
+            statements,
+            span,
+        })
+    }
+
+    fn parse_conditional_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let mut otherwise: Vec<Statement> = Vec::new();
+
+        self.stream.consume();
+
+        match self.stream.consume() {
+            Some(Token::Identifier(id)) if id == "if" => {},
+            _ => return Err(self.error_at_consumed("Expected \"if\"!")),
+        }
+
+        let symbol: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected identifier!")),
+        };
+        let span: Span = self.stream.previous_span();
+
+        let statements: Vec<Statement> = self.parse_conditional_block()?;
+
+        if self.stream.match_token(TokenKind::Hash) &&
+           matches!(self.stream.peek_second(), Some(Token::Identifier(id)) if id == "else") {
+            self.stream.consume();
+            self.stream.consume();
+
+            otherwise = self.parse_conditional_block()?;
+        }
+
+        Ok(Statement::Conditional {
+            symbol,
+            statements,
+            otherwise,
+            span,
+        })
+    }
+
+    /// Parse a block of conditional compilation into its statements.
+    fn parse_conditional_block(&mut self) -> Result<Vec<Statement>, Diagnostic> {
+        if !self.stream.match_token(TokenKind::LeftCurlyBracket) {
+            return Err(self.error_at_next("Expected \"{\"!"));
+        }
+
+        match self.parse_block_statement()? {
+            Statement::Block {
+                statements,
+            } => Ok(statements),
+            _ => unreachable!("blocks are parsed into block statements"),
+        }
+    }
+
+    fn parse_block_statement(
+        &mut self
+    ) -> Result<Statement, Diagnostic> {
+        let mut statements: Vec<Statement> = Vec::new();
+        let tail = std::mem::replace(&mut self.tail, false);
+
+        self.stream.consume();
+
+        loop {
+            match self.stream.peek() {
+                None => return Err(self.error_at_next("Expected statements or \"}\"!")),
+                Some(Token::RightCurlyBracket) => break,
+                _ => statements.push(self.parse_statement()?),
+            }
+        }
+
+        self.tail = tail;
+
+        match self.stream.consume() {
+            Some(Token::RightCurlyBracket) => {},
+            _ => return Err(self.error_at_consumed("Expected \"}\"!")),
+        }
+
+        self.check_overloads(&statements);
+        self.check_variants(&statements);
+
+        let statement = Statement::Block {
+            statements,
+        };
+
+        Ok(statement)
+    }
+
+    fn parse_variable_definition_statement(
+        &mut self
+    ) -> Result<Statement, Diagnostic> {
+        let value: Option<ExprId>;
+
+        self.stream.consume();
+
+        match (self.stream.peek(), self.stream.peek_second()) {
+            (Some(Token::LeftSquareBracket), _) |
+            (Some(Token::Identifier(_)), Some(Token::LeftRoundBracket)) =>
+                return self.parse_destructuring(),
+            _ => {},
+        }
+
+        let identifier: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected identifier!")),
+        };
+        let span: Span = self.stream.previous_span();
+
+        let r#type = if self.stream.match_token(TokenKind::VariableTypeIndicator) {
+            self.stream.consume();
+
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        if self.stream.match_token(TokenKind::EndOfStatement) {
+            self.stream.consume();
+
+            value = None;
+        } else {
+            match self.stream.consume() {
+                Some(Token::Assign) => {},
+                _ => return Err(self.error_at_consumed("Expected \"=\"!")),
+            };
+
+            value = Some(self.parse_expression()?);
+
+            self.parse_end_of_statement()?;
+        }
+
+        let statement = Statement::VariableDefinition {
+            identifier,
+            r#type,
+            value,
+            span,
+        };
+
+        Ok(statement)
+    }
+
+    /// Parse the pattern, `=`, value and `;` of a destructuring `let`.
+    fn parse_destructuring(
+        &mut self
+    ) -> Result<Statement, Diagnostic> {
+        let span = self.stream.span();
+        let pattern = self.parse_pattern()?;
+
+        match self.stream.consume() {
+            Some(Token::Assign) => {},
+            _ => return Err(self.error_at_consumed("Expected \"=\"!")),
+        }
+
+        let value: ExprId = self.parse_expression()?;
+
+        self.parse_end_of_statement()?;
+
+        Ok(Statement::Destructuring {
+            pattern,
+            value,
+            span,
+        })
+    }
+
+    fn parse_constant_definition_statement(
+        &mut self
+    ) -> Result<Statement, Diagnostic> {
+
+        /* Consume `const`. */
+        self.stream.consume();
+
+        let identifier: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected identifier!")),
+        };
+        let span: Span = self.stream.previous_span();
+
+        let r#type = if self.stream.match_token(TokenKind::VariableTypeIndicator) {
+            self.stream.consume();
+
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        match self.stream.consume() {
+            Some(Token::Assign) => {},
+            _ => return Err(self.error_at_consumed("Expected \"=\"!")),
+        };
+
+        let value: ExprId = self.parse_expression()?;
+
+        self.parse_end_of_statement()?;
+
+        let statement = Statement::ConstantDefinition {
+            identifier,
+            r#type,
+            value,
+            span,
+        };
+
+        Ok(statement)
+    }
+
+    fn parse_function_definition_statement(
+        &mut self
+    ) -> Result<Statement, Diagnostic> {
+        self.stream.consume();
+
+        let callee_name: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected identifier!")),
+        };
+        let span: Span = self.stream.previous_span();
+
+        let parameters: Vec<Parameter> = self.parse_function_parameters()?;
+        let return_type: Option<Type> = self.parse_function_return_type()?;
+        let statements: Vec<Statement> = self.parse_function_body(true)?;
+
+        let statement = Statement::FunctionDefinition {
+            callee_name,
+            parameters,
+            return_type,
+            statements,
+            span,
+        };
+
+        Ok(statement)
+    }
+
+    fn parse_function_return_type(
+        &mut self
+    ) -> Result<Option<Type>, Diagnostic> {
+        if !self.stream.match_token(TokenKind::ReturnTypeIndicator) {
+            return Ok(None);
+        }
+
+        self.stream.consume();
+
+        Ok(Some(self.parse_type()?))
+    }
+
+    /// Parse a type like `int`, `[int; 16]`, or `int?` which also
+    /// admits `nil`.
+    fn parse_type(
+        &mut self
+    ) -> Result<Type, Diagnostic> {
+        self.nested(|parser| {
+            let mut r#type: Type;
+
+            r#type = match parser.stream.consume() {
+
+                /* `map` is only special before a `[`. */
+                Some(Token::Identifier(id)) if id == "map" && parser.stream.match_token(TokenKind::LeftSquareBracket) => {
+                    /* Consume `[`. */
+                    parser.stream.consume();
+                    let key: Type = parser.parse_type()?;
+
+                    match parser.stream.consume() {
+                        Some(Token::Comma) => {},
+                        _ => return Err(parser.error_at_consumed("Expected \",\"!")),
+                    }
+
+                    let value: Type = parser.parse_type()?;
+
+                    match parser.stream.consume() {
+                        Some(Token::RightSquareBracket) => {},
+                        _ => return Err(parser.error_at_consumed("Expected \"]\"!")),
+                    }
+
+                    Type::Map {
+                        key: Box::new(key),
+                        value: Box::new(value),
+                    }
+                },
+                Some(Token::Identifier(id)) => Type::Named(id.into_owned()),
+                Some(Token::LeftRoundBracket) => match parser.stream.consume() {
+                    Some(Token::RightRoundBracket) => Type::Unit,
+                    _ => return Err(parser.error_at_consumed("Expected \")\"!")),
+                },
+                Some(Token::LeftSquareBracket) => {
+                    let element = Box::new(parser.parse_type()?);
+
+                    match parser.stream.consume() {
+                        Some(Token::RightSquareBracket) => Type::Slice(element),
+                        Some(Token::EndOfStatement) => {
+                            let size = parser.parse_expression()?;
+
+                            match parser.stream.consume() {
+                                Some(Token::RightSquareBracket) => {},
+                                _ => return Err(parser.error_at_consumed("Expected \"]\"!")),
+                            }
+
+                            Type::Array {
+                                element,
+                                size,
+                            }
+                        },
+                        _ => return Err(parser.error_at_consumed("Expected \";\" or \"]\"!")),
+                    }
+                },
+                _ => return Err(parser.error_at_consumed("Expected type!")),
+            };
+
+            if parser.stream.match_token(TokenKind::QuestionMark) {
+                parser.stream.consume();
 
-        statement = match self.stream.peek() {
-            Some(Token::LeftCurlyBracket) =>
-                self.parse_block_statement(),
-            Some(Token::Let) =>
-                self.parse_variable_definition_statement(),
-            Some(Token::Function) =>
-                self.parse_function_definition_statement(),
-            Some(Token::Return) =>
-                self.parse_return_statement(),
-            _ => self.parse_expression_statement(),
-        };
+                r#type = Type::Nullable(Box::new(r#type));
+            }
 
-        statement
+            Ok(r#type)
+        })
     }
 
-    fn parse_block_statement(
+    fn parse_implementation_statement(
         &mut self
-    ) -> Statement {
-        let mut statements: Vec<Statement> = Vec::new();
-        let statement: Statement;
+    ) -> Result<Statement, Diagnostic> {
+        let mut functions: Vec<Statement> = Vec::new();
+        let mut type_name: String;
+        let mut span: Span;
+        let trait_name: Option<String>;
 
+        /* Consume `impl`. */
         self.stream.consume();
 
+        type_name = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected type name!")),
+        };
+        span = self.stream.previous_span();
+
+        /* `impl Trait for Type` names the trait first. */
+        if self.stream.match_token(TokenKind::For) {
+            self.stream.consume();
+
+            trait_name = Some(type_name);
+            type_name = match self.stream.consume() {
+                Some(Token::Identifier(id)) => id.into_owned(),
+                _ => return Err(self.error_at_consumed("Expected type name!")),
+            };
+            span = self.stream.previous_span();
+        } else {
+            trait_name = None;
+        }
+
+        match self.stream.consume() {
+            Some(Token::LeftCurlyBracket) => {},
+            _ => return Err(self.error_at_consumed("Expected \"{\"!")),
+        }
+
         loop {
             match self.stream.peek() {
-                None => panic!("Expected statements or \"}}\"!"),
                 Some(Token::RightCurlyBracket) => break,
-                _ => statements.push(self.parse_statement()),
+                Some(Token::Function) => {},
+                _ => return Err(self.error_at_next("Expected \"func\" or \"}\"!")),
             }
-        }
 
-        match self.stream.consume() {
-            Some(Token::RightCurlyBracket) => {},
-            _ => panic!("Expected \"}}\"!"),
+            let function = self.parse_function_definition_statement()?;
+
+            if let Statement::FunctionDefinition {
+                parameters,
+                span,
+                ..
+            } = &function {
+                if parameters.first().map(|parameter| parameter.name.as_str()) != Some("self") {
+                    return Err(Diagnostic::error("Expected \"self\" as the first parameter of a method!")
+                        .with_span(*span));
+                }
+            }
+
+            functions.push(function);
         }
 
-        statement = Statement::Block {
-            statements,
-        };
+        /* Consume `}`. */
+        self.stream.consume();
 
-        statement
+        self.check_overloads(&functions);
+
+        Ok(Statement::Implementation {
+            type_name,
+            trait_name,
+            functions,
+            span,
+        })
     }
 
-    fn parse_variable_definition_statement(
+    fn parse_trait_statement(
         &mut self
-    ) -> Statement {
-        let statement: Statement;
-        let identifier: String;
-        let r#type: Option<String>;
-        let value: Option<Expression>;
+    ) -> Result<Statement, Diagnostic> {
+        let mut methods: Vec<MethodSignature> = Vec::new();
 
+        /* Consume `trait`. */
         self.stream.consume();
 
-        identifier = match self.stream.consume() {
-            Some(Token::Identifier(id)) => id,
-            _ => panic!("Expected identifier!"),
+        let name: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected trait name!")),
         };
+        let span: Span = self.stream.previous_span();
 
-        if self.stream.match_token(Token::VariableTypeIndicator) {
-            self.stream.consume();
-
-            r#type = match self.stream.consume() {
-                Some(Token::Identifier(id)) => Some(id),
-                _ => panic!("Expected identifier!"),
-            };
-        } else {
-            r#type = None;
+        match self.stream.consume() {
+            Some(Token::LeftCurlyBracket) => {},
+            _ => return Err(self.error_at_consumed("Expected \"{\"!")),
         }
 
-        if self.stream.match_token(Token::EndOfStatement) {
-            self.stream.consume();
-
-            value = None;
-        } else {
+        loop {
             match self.stream.consume() {
-                Some(Token::Assign) => {},
-                _ => panic!("Expected \"=\"!"),
+                Some(Token::RightCurlyBracket) => break,
+                Some(Token::Function) => {},
+                _ => return Err(self.error_at_consumed("Expected \"func\" or \"}\"!")),
+            }
+
+            let name = match self.stream.consume() {
+                Some(Token::Identifier(id)) => id.into_owned(),
+                _ => return Err(self.error_at_consumed("Expected identifier!")),
             };
+            let span = self.stream.previous_span();
+            let parameters = self.parse_function_parameters()?;
+            let return_type = self.parse_function_return_type()?;
 
-            value = Some(self.parse_expression());
+            if parameters.first().map(|parameter| parameter.name.as_str()) != Some("self") {
+                return Err(Diagnostic::error("Expected \"self\" as the first parameter of a method!")
+                    .with_span(span));
+            }
 
             match self.stream.consume() {
                 Some(Token::EndOfStatement) => {},
-                _ => panic!("Expected \";\"!"),
-            };
-        }
+                _ => return Err(self.error_at_consumed("Expected \";\"!")),
+            }
 
-        statement = Statement::VariableDefinition {
-            identifier,
-            r#type,
-            value,
-        };
+            methods.push(MethodSignature {
+                name,
+                parameters,
+                return_type,
+                span,
+            });
+        }
 
-        statement
+        Ok(Statement::Trait {
+            name,
+            methods,
+            span,
+        })
     }
 
-    fn parse_function_definition_statement(
+    fn parse_enum_statement(
         &mut self
-    ) -> Statement {
-        let statement: Statement;
-        let callee_name: String;
-        let parameters: Vec<Parameter>;
-        let return_type: Option<String>;
-        let statements: Vec<Statement>;
+    ) -> Result<Statement, Diagnostic> {
+        let mut variants: Vec<Variant> = Vec::new();
 
+        /* Consume `enum`. */
         self.stream.consume();
 
-        callee_name = match self.stream.consume() {
-            Some(Token::Identifier(id)) => id,
-            _ => panic!("Expected identifier!"),
+        let name: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected enum name!")),
         };
+        let span: Span = self.stream.previous_span();
 
-        parameters = self.parse_function_parameters();
+        match self.stream.consume() {
+            Some(Token::LeftCurlyBracket) => {},
+            _ => return Err(self.error_at_consumed("Expected \"{\"!")),
+        }
+
+        /* A comma may follow the last variant. */
+        while !self.stream.match_token(TokenKind::RightCurlyBracket) {
+            let mut fields: Vec<Type> = Vec::new();
+            let name = match self.stream.consume() {
+                Some(Token::Identifier(id)) => id.into_owned(),
+                _ => return Err(self.error_at_consumed("Expected variant name!")),
+            };
+            let span = self.stream.previous_span();
+
+            if variants.iter().any(|variant| variant.name == name) {
+                return Err(Diagnostic::error(format!("Variant \"{}\" is already defined!", name))
+                    .with_span(span));
+            }
 
-        match self.stream.peek() {
-            Some(Token::ReturnTypeIndicator) => {
+            if self.stream.match_token(TokenKind::LeftRoundBracket) {
                 self.stream.consume();
 
-                return_type = match self.stream.consume() {
-                    Some(Token::Identifier(id)) => Some(id),
-                    _ => panic!("Expected identifier!"),
+                while !self.stream.match_token(TokenKind::RightRoundBracket) {
+                    fields.push(self.parse_type()?);
+
+                    match self.stream.peek() {
+                        Some(Token::Comma) => {
+                            self.stream.consume();
+                        },
+                        Some(Token::RightRoundBracket) => break,
+                        _ => return Err(self.error_at_next("Expected \",\" or \")\"!")),
+                    }
                 }
-            },
-            _ => return_type = None,
-        }
 
-        statements = self.parse_function_body();
+                /* Consume `)`. */
+                self.stream.consume();
+            }
+
+            variants.push(Variant {
+                name,
+                fields,
+                span,
+            });
 
-        statement = Statement::FunctionDefinition {
-            callee_name,
-            parameters,
-            return_type,
-            statements,
-        };
+            match self.stream.peek() {
+                Some(Token::Comma) => {
+                    self.stream.consume();
+                },
+                Some(Token::RightCurlyBracket) => break,
+                _ => return Err(self.error_at_next("Expected \",\" or \"}\"!")),
+            }
+        }
+
+        /* Consume `}`. */
+        self.stream.consume();
 
-        statement
+        Ok(Statement::Enum {
+            name,
+            variants,
+            span,
+        })
     }
 
     fn parse_function_parameters(
         &mut self
-    ) -> Vec<Parameter> {
+    ) -> Result<Vec<Parameter>, Diagnostic> {
         let mut parameters: Vec<Parameter> = Vec::new();
 
         /* Consume `(`. */
         match self.stream.consume() {
             Some(Token::LeftRoundBracket) => {},
-            _ => panic!("Expected \"(\"!"),
+            _ => return Err(self.error_at_consumed("Expected \"(\"!")),
         };
 
+        /* A comma may follow the last parameter. */
         loop {
             match self.stream.peek() {
                 Some(Token::RightRoundBracket) => break,
                 Some(Token::Identifier(_)) =>
-                    parameters.push(self.parse_function_parameter()),
-                _ => panic!("Expected parameters or \")\"!"),
+                    parameters.push(self.parse_function_parameter()?),
+                _ => return Err(self.error_at_next("Expected parameters or \")\"!")),
             }
 
             match self.stream.peek() {
@@ -338,363 +2605,704 @@ impl Parser {
                     self.stream.consume();
                 },
                 Some(Token::RightRoundBracket) => break,
-                _ => panic!("Expected \",\" or \")\"!"),
+                _ => return Err(self.error_at_next("Expected \",\" or \")\"!")),
             }
         }
 
         /* Consume `)`. */
         match self.stream.consume() {
             Some(Token::RightRoundBracket) => {},
-            _ => panic!("Expected \")\"!"),
+            _ => return Err(self.error_at_consumed("Expected \")\"!")),
         };
 
-        parameters
+        Ok(parameters)
     }
 
     fn parse_function_parameter(
         &mut self
-    ) -> Parameter {
-        let parameter: Parameter;
-        let name: String;
-        let r#type: Option<String>;
+    ) -> Result<Parameter, Diagnostic> {
 
         /* Consume parameter name. */
-        name = match self.stream.consume() {
-            Some(Token::Identifier(id)) => id,
-            _ => panic!("Expected identifier!"),
+        let name: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected identifier!")),
         };
+        let span: Span = self.stream.previous_span();
 
         /* Try to parse parameter type. */
-        match self.stream.peek() {
+        let r#type = match self.stream.peek() {
             Some(Token::VariableTypeIndicator) => {
                 self.stream.consume();
 
-                r#type = match self.stream.consume() {
-                    Some(Token::Identifier(id)) => Some(id),
-                    _ => panic!("Expected identifier!"),
-                }
+                Some(self.parse_type()?)
             },
-            _ => r#type = None,
-        }
+            _ => None,
+        };
 
-        parameter = Parameter {
+        let parameter = Parameter {
             name,
             r#type,
+            span,
         };
 
-        parameter
+        Ok(parameter)
     }
 
+    /// Parse a body in `{}`, whose last expression may leave out `;` to
+    /// return its value if `tail` is set.
     fn parse_function_body(
-        &mut self
-    ) -> Vec<Statement> {
+        &mut self,
+        tail: bool
+    ) -> Result<Vec<Statement>, Diagnostic> {
         let mut statements: Vec<Statement> = Vec::new();
+        let outer_tail = std::mem::replace(&mut self.tail, tail);
 
         /* Consume `{`. */
         match self.stream.consume() {
             Some(Token::LeftCurlyBracket) => {},
-            _ => panic!("Expected \"{{\"!"),
+            _ => return Err(self.error_at_consumed("Expected \"{\"!")),
         }
 
         /* Parse all statements. */
         loop {
             match self.stream.peek() {
-                None => panic!("Expected statements or \"}}\"!"),
+                None => return Err(self.error_at_next("Expected statements or \"}\"!")),
                 Some(Token::RightCurlyBracket) => break,
-                _ => statements.push(self.parse_statement()),
+                _ => statements.push(self.parse_statement()?),
             }
         }
 
         /* Consume `}`. */
         match self.stream.consume() {
             Some(Token::RightCurlyBracket) => {},
-            _ => panic!("Expected \"}}\"!"),
+            _ => return Err(self.error_at_consumed("Expected \"}\"!")),
         }
 
-        statements
+        self.tail = outer_tail;
+        self.check_overloads(&statements);
+        self.check_variants(&statements);
+
+        Ok(statements)
     }
 
     fn parse_return_statement(
         &mut self
-    ) -> Statement {
-        let statement: Statement;
-        let expression: Expression;
-
+    ) -> Result<Statement, Diagnostic> {
         /* Consume `return`. */
         self.stream.consume();
 
         /* Parse expression. */
-        expression = self.parse_expression();
+        let expression: ExprId = self.parse_expression()?;
 
         /* Consume `;`. */
-        match self.stream.consume() {
-            Some(Token::EndOfStatement) => {},
-            _ => panic!("Expected \";\"!"),
-        };
+        self.parse_end_of_statement()?;
 
-        statement = Statement::Return {
+        let statement = Statement::Return {
             expression,
+            implicit: false,
         };
 
-        statement
+        Ok(statement)
     }
 
-    fn parse_expression_statement(&mut self) -> Statement {
-        let expression: Expression;
+    fn parse_for_statement(
+        &mut self
+    ) -> Result<Statement, Diagnostic> {
+        /* Consume `for`. */
+        self.stream.consume();
 
-        expression = self.parse_expression();
+        let variable: String = match self.stream.consume() {
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected identifier!")),
+        };
+        let span: Span = self.stream.previous_span();
 
+        /* Consume `in`. */
         match self.stream.consume() {
-            Some(Token::EndOfStatement) => {},
-            _ => panic!("Expected \";\"!"),
+            Some(Token::In) => {},
+            _ => return Err(self.error_at_consumed("Expected \"in\"!")),
+        }
+
+        let iterable: ExprId = self.parse_expression()?;
+        let statements: Vec<Statement> = self.parse_function_body(false)?;
+
+        let statement = Statement::For {
+            variable,
+            iterable,
+            statements,
+            span,
         };
 
-        Statement::Expression {
-            expression: expression,
+        Ok(statement)
+    }
+
+    fn parse_expression_statement(&mut self) -> Result<Statement, Diagnostic> {
+        let expression: ExprId = self.parse_expression()?;
+
+        if self.tail && self.stream.match_token(TokenKind::RightCurlyBracket) {
+            return Ok(Statement::Return {
+                expression,
+                implicit: true,
+            });
         }
+
+        self.parse_end_of_statement()?;
+
+        Ok(Statement::Expression {
+            expression,
+        })
     }
 
-    fn parse_expression(&mut self) -> Expression {
-        let mut expression_left: Expression;
+    fn parse_expression(&mut self) -> Result<ExprId, Diagnostic> {
+        self.parse_operation(1)
+    }
 
-        expression_left = self.parse_assignment_operand();
+    /// Parse an expression whose infix operators all bind at least as
+    /// tight as `min_precedence`.
+    fn parse_operation(&mut self, min_precedence: u8) -> Result<ExprId, Diagnostic> {
+        let mut expression_left: ExprId;
 
-        while let Some(token) = self.stream.peek() {
-            match token {
-                Token::Assign => {
-                    let expression_right: Expression;
+        expression_left = self.parse_unary()?;
 
-                    self.stream.consume();
+        /* A cast takes a type, not an expression, to its right. */
+        while self.stream.match_token(TokenKind::As) && CAST_PRECEDENCE >= min_precedence {
+            self.stream.consume();
+            let span: Span = self.stream.previous_span();
+            let r#type: Type = self.parse_type()?;
+
+            expression_left = self.alloc(Expression::Cast {
+                operand: expression_left,
+                r#type,
+                span,
+            })?;
+        }
 
-                    expression_right = self.parse_assignment_operand();
+        /* Whether `expression_left` is a comparison parsed right here,
+         * rather than one in parentheses. */
+        let mut comparison = false;
 
-                    expression_left = Expression::BinaryOperation {
-                        operator: BinaryOperator::Assign,
-                        operand_left: Box::new(expression_left),
-                        operand_right: Box::new(expression_right),
+        loop {
+            let (operator, precedence, associativity) = match self.stream.peek() {
+                Some(Token::DotDot | Token::DotDotEqual) if RANGE_PRECEDENCE >= min_precedence => {
+                    if let Expression::Range { .. } = self.expressions[expression_left.index()] {
+                        return Err(self.error_at_next("Ranges cannot be chained!"));
                     }
+
+                    expression_left = self.parse_range(Some(expression_left))?;
+                    continue;
                 },
-                _ => break,
+                Some(token) => match infix_operator(token) {
+                    Some(operator) => operator,
+                    None => break,
+                },
+                None => break,
+            };
+
+            if precedence < min_precedence {
+                break;
+            }
+
+            if comparison && operator.is_comparison() {
+                return Err(self.error_at_next("Comparisons cannot be chained!")
+                    .with_help("add parentheses to compare the result of the first comparison"));
             }
+
+            self.stream.consume();
+            let span: Span = self.stream.previous_span();
+
+            /* The right operand of a left-associative operator stops at
+             * the next operator of the same precedence. */
+            let expression_right: ExprId = match associativity {
+                Associativity::Left => self.parse_operation(precedence + 1)?,
+                Associativity::Right => self.parse_operation(precedence)?,
+            };
+
+            expression_left = self.alloc(Expression::BinaryOperation {
+                operator,
+                operand_left: expression_left,
+                operand_right: expression_right,
+                span,
+            })?;
+            comparison = operator.is_comparison();
         }
 
-        expression_left
+        Ok(expression_left)
     }
 
-    /// Parse assignment operand in assignment like `expr_1 = expr_2`.
-    fn parse_assignment_operand(&mut self) -> Expression {
-        let mut expression_left: Expression;
+    /// Parse a range from `start`, at the `..` or `..=`.
+    fn parse_range(
+        &mut self,
+        start: Option<ExprId>
+    ) -> Result<ExprId, Diagnostic> {
+        let inclusive = self.stream.match_token(TokenKind::DotDotEqual);
 
-        expression_left = self.parse_comparison_operand();
+        /* Consume `..` or `..=`. */
+        self.stream.consume();
 
-        while let Some(token) = self.stream.peek() {
-            match token {
-                Token::Equal |
-                Token::NotEqual => {
-                    let operator = match self.stream.consume() {
-                        Some(Token::Equal) => BinaryOperator::Equal,
-                        Some(Token::NotEqual) => BinaryOperator::NotEqual,
-                        _ => panic!(),
-                    };
-                    let expression_right = self.parse_comparison_operand();
+        let end: Option<ExprId> = match self.stream.peek() {
+            None |
+            Some(Token::RightRoundBracket | Token::RightSquareBracket | Token::LeftCurlyBracket |
+                 Token::RightCurlyBracket | Token::Comma | Token::EndOfStatement) if !inclusive => None,
+            _ => Some(self.parse_operation(RANGE_PRECEDENCE + 1)?),
+        };
 
-                    expression_left = Expression::BinaryOperation {
-                        operator,
-                        operand_left: Box::new(expression_left),
-                        operand_right: Box::new(expression_right),
-                    }
+        self.alloc(Expression::Range {
+            start,
+            end,
+            inclusive,
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<ExprId, Diagnostic> {
+        self.nested(|parser| {
+            let operator: UnaryOperator = match parser.stream.peek() {
+                Some(Token::DotDot | Token::DotDotEqual) => return parser.parse_range(None),
+                Some(token) => match prefix_operator(token) {
+                    Some(operator) => operator,
+                    None => return parser.parse_postfix(),
                 },
-                _ => break,
-            }
-        }
+                None => return parser.parse_postfix(),
+            };
+
+            parser.stream.consume();
+
+            let operand: ExprId = parser.parse_operation(PREFIX_PRECEDENCE)?;
 
-        expression_left
+            parser.alloc(Expression::UnaryOperation {
+                operator,
+                operand,
+            })
+        })
     }
 
-    /// Parse comparison operand in comparisons like
-    /// `expr_1 == expr_2` or `expr_1 != expr_2`.
-    fn parse_comparison_operand(&mut self) -> Expression {
-        let mut expression_left: Expression;
+    /// Parse a factor along with the methods called on it.
+    fn parse_postfix(&mut self) -> Result<ExprId, Diagnostic> {
+        let mut expression: ExprId;
 
-        expression_left = self.parse_term();
+        expression = self.parse_factor()?;
 
-        while let Some(token) = self.stream.peek() {
-            match token {
-                Token::Add |
-                Token::Minus => {
-                    let operator = match self.stream.consume() {
-                        Some(Token::Add) => BinaryOperator::Addition,
-                        Some(Token::Minus) => BinaryOperator::Subtraction,
-                        _ => panic!(),
-                    };
-                    let expression_right = self.parse_term();
+        loop {
+            match self.stream.peek() {
+                Some(Token::Dot) => {},
+                Some(Token::LeftSquareBracket) => {
+                    expression = self.parse_index(expression)?;
+                    continue;
+                },
+                Some(Token::QuestionMark) => {
+                    self.stream.consume();
 
-                    expression_left = Expression::BinaryOperation {
-                        operator,
-                        operand_left: Box::new(expression_left),
-                        operand_right: Box::new(expression_right),
-                    }
+                    expression = self.alloc(Expression::Try {
+                        operand: expression,
+                        span: self.stream.previous_span(),
+                    })?;
+                    continue;
                 },
                 _ => break,
             }
-        }
 
-        expression_left
-    }
+            /* Consume `.`. */
+            self.stream.consume();
 
-    fn parse_term(&mut self) -> Expression {
-        let mut expression_left: Expression;
+            let method: String = match self.stream.consume() {
+                Some(Token::Identifier(id)) => id.into_owned(),
+                _ => return Err(self.error_at_consumed("Expected method name!")),
+            };
+            let span: Span = self.stream.previous_span();
 
-        expression_left = self.parse_factor();
+            if !self.stream.match_token(TokenKind::LeftRoundBracket) {
+                return Err(self.error_at_next("Expected \"(\"!"));
+            }
 
-        while let Some(token) = self.stream.peek() {
-            match token {
-                Token::Times |
-                Token::Divide => {
-                    let operator = match self.stream.consume() {
-                        Some(Token::Times) => BinaryOperator::Multiplication,
-                        Some(Token::Divide) => BinaryOperator::Division,
-                        _ => panic!(),
-                    };
-                    let expression_right = self.parse_factor();
+            let arguments: Vec<Argument> = self.parse_function_call_arguments()?;
 
-                    expression_left = Expression::BinaryOperation {
-                        operator,
-                        operand_left: Box::new(expression_left),
-                        operand_right: Box::new(expression_right),
-                    }
-                },
-                _ => break,
-            }
+            expression = self.alloc(Expression::MethodCall {
+                receiver: expression,
+                method,
+                arguments,
+                span,
+            })?;
         }
 
-        expression_left
+        Ok(expression)
     }
 
-    fn parse_factor(&mut self) -> Expression {
-        let expression: Expression;
-
-        expression = match self.stream.peek() {
+    fn parse_factor(&mut self) -> Result<ExprId, Diagnostic> {
+        let expression: ExprId = match self.stream.peek() {
             Some(Token::Identifier(_)) =>
-                self.parse_identifier_or_function_call(),
+                self.parse_identifier_or_function_call()?,
             Some(Token::Number(_)) =>
-                self.parse_number(),
+                self.parse_number()?,
             Some(Token::String(_)) =>
-                self.parse_string(),
+                self.parse_string()?,
+            Some(Token::Nil) => {
+                self.stream.consume();
+
+                self.alloc(Expression::Nil)?
+            },
             Some(Token::LeftRoundBracket) =>
-                self.parse_grouped_expression(),
-            _ => panic!("Expected expression!"),
+                self.parse_grouped_expression()?,
+            Some(Token::LeftSquareBracket) =>
+                self.parse_array()?,
+            Some(Token::LeftCurlyBracket) =>
+                self.parse_map()?,
+            Some(Token::Match) =>
+                self.parse_match()?,
+            _ => return Err(self.error_at_next("Expected expression!")),
         };
 
-        expression
+        Ok(expression)
     }
 
     fn parse_identifier_or_function_call(
         &mut self
-    ) -> Expression {
-        let expression: Expression;
+    ) -> Result<ExprId, Diagnostic> {
         let identifier = match self.stream.consume() {
-            Some(Token::Identifier(id)) => id,
-            _ => panic!("Expected identifier!"),
+            Some(Token::Identifier(id)) => id.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected identifier!")),
         };
+        let span = self.stream.previous_span();
 
-        expression = match self.stream.peek() {
+        let expression: Expression = match self.stream.peek() {
             Some(Token::LeftRoundBracket) => {
-                let arguments = self.parse_function_call_arguments();
+                let arguments = self.parse_function_call_arguments()?;
 
                 Expression::FunctionCall {
                     callee_name: identifier,
-                    arguments: arguments,
+                    arguments,
+                    span,
                 }
             },
-            _ => Expression::Identifier(identifier),
+            _ => Expression::Identifier {
+                name: identifier,
+                span,
+            },
+        };
+
+        self.alloc(expression)
+    }
+
+    fn parse_function_call_arguments(
+        &mut self
+    ) -> Result<Vec<Argument>, Diagnostic> {
+        let mut arguments: Vec<Argument> = Vec::new();
+
+        /* Consume `(`. */
+        self.stream.consume();
+
+        /* A comma may follow the last argument. */
+        while !self.stream.match_token(TokenKind::RightRoundBracket) {
+            let argument = self.parse_function_call_argument()?;
+
+            if argument.name.is_none() &&
+               arguments.last().is_some_and(|last| last.name.is_some()) {
+                return Err(Diagnostic::error("Expected named argument after named argument!")
+                    .with_span(argument.span));
+            }
+
+            arguments.push(argument);
+
+            match self.stream.peek() {
+                Some(Token::Comma) => {
+
+                    /* Consume `,`. */
+                    self.stream.consume();
+                },
+                Some(Token::RightRoundBracket) => break,
+                _ => return Err(self.error_at_next("Expected \",\" or \")\"!")),
+            }
+        }
+
+        /* Consume `)`. */
+        self.stream.consume();
+
+        Ok(arguments)
+    }
+
+    /// Parse an argument, named if it starts with a name and `=`.
+    fn parse_function_call_argument(
+        &mut self
+    ) -> Result<Argument, Diagnostic> {
+        let span = self.stream.span();
+
+        let name: Option<String> = match (self.stream.peek(), self.stream.peek_second()) {
+            (Some(Token::Identifier(_)), Some(Token::Assign)) => {
+                let name = match self.stream.consume() {
+                    Some(Token::Identifier(id)) => id.into_owned(),
+                    _ => return Err(self.error_at_consumed("Expected identifier!")),
+                };
+
+                /* Consume `=`. */
+                self.stream.consume();
+
+                Some(name)
+            },
+            _ => None,
+        };
+
+        Ok(Argument {
+            name,
+            value: self.parse_expression()?,
+            span,
+        })
+    }
+
+    fn parse_number(
+        &mut self
+    ) -> Result<ExprId, Diagnostic> {
+        let number = match self.stream.consume() {
+            Some(Token::Number(num)) => num,
+            _ => return Err(self.error_at_consumed("Expected number!")),
+        };
+
+        self.alloc(Expression::Number(number))
+    }
+
+    fn parse_string(
+        &mut self
+    ) -> Result<ExprId, Diagnostic> {
+        let string = match self.stream.consume() {
+            Some(Token::String(str)) => str.into_owned(),
+            _ => return Err(self.error_at_consumed("Expected string!")),
         };
 
-        expression
+        self.alloc(Expression::String(string))
+    }
+
+    /// Parse the index `[index]` of `target`, which slices it if the
+    /// index is a range.
+    fn parse_index(
+        &mut self,
+        target: ExprId
+    ) -> Result<ExprId, Diagnostic> {
+        /* Consume `[`. */
+        self.stream.consume();
+        let span: Span = self.stream.previous_span();
+        let index: ExprId = self.parse_expression()?;
+
+        match self.stream.consume() {
+            Some(Token::RightSquareBracket) => {},
+            _ => return Err(self.error_at_consumed("Expected \"]\"!")),
+        }
+
+        self.alloc(Expression::Index {
+            target,
+            index,
+            span,
+        })
+    }
+
+    fn parse_array(
+        &mut self
+    ) -> Result<ExprId, Diagnostic> {
+        let mut elements: Vec<ExprId> = Vec::new();
+
+        /* Consume `[`. */
+        self.stream.consume();
+
+        /* A comma may follow the last element. */
+        while !self.stream.match_token(TokenKind::RightSquareBracket) {
+            elements.push(self.parse_expression()?);
+
+            match self.stream.peek() {
+                Some(Token::Comma) => {
+                    self.stream.consume();
+                },
+                Some(Token::RightSquareBracket) => break,
+                _ => return Err(self.error_at_next("Expected \",\" or \"]\"!")),
+            }
+        }
+
+        /* Consume `]`. */
+        self.stream.consume();
+
+        self.alloc(Expression::Array {
+            elements,
+        })
+    }
+
+    fn parse_map(
+        &mut self
+    ) -> Result<ExprId, Diagnostic> {
+        let mut entries: Vec<(ExprId, ExprId)> = Vec::new();
+
+        /* Consume `{`. */
+        self.stream.consume();
+
+        /* A comma may follow the last entry. */
+        while !self.stream.match_token(TokenKind::RightCurlyBracket) {
+            let key = self.parse_expression()?;
+
+            match self.stream.consume() {
+                Some(Token::VariableTypeIndicator) => {},
+                _ => return Err(self.error_at_consumed("Expected \":\"!")),
+            }
+
+            entries.push((key, self.parse_expression()?));
+
+            match self.stream.peek() {
+                Some(Token::Comma) => {
+                    self.stream.consume();
+                },
+                Some(Token::RightCurlyBracket) => break,
+                _ => return Err(self.error_at_next("Expected \",\" or \"}\"!")),
+            }
+        }
+
+        /* Consume `}`. */
+        self.stream.consume();
+
+        self.alloc(Expression::Map {
+            entries,
+        })
     }
 
-    fn parse_function_call_arguments(
+    fn parse_match(
         &mut self
-    ) -> Vec<Expression> {
-        let mut arguments: Vec<Expression> = Vec::new();
+    ) -> Result<ExprId, Diagnostic> {
+        let mut arms: Vec<MatchArm> = Vec::new();
 
-        /* Consume `(`. */
+        /* Consume `match`. */
         self.stream.consume();
+        let span: Span = self.stream.previous_span();
+        let scrutinee: ExprId = self.parse_expression()?;
 
-        match self.stream.peek() {
-            Some(Token::RightRoundBracket) => {
+        match self.stream.consume() {
+            Some(Token::LeftCurlyBracket) => {},
+            _ => return Err(self.error_at_consumed("Expected \"{\"!")),
+        }
 
-                /* Consume `)`. */
-                self.stream.consume();
-            },
-            _ => {
-                let mut expression: Expression;
+        /* A comma may follow the last arm. */
+        while !self.stream.match_token(TokenKind::RightCurlyBracket) {
+            let span = self.stream.span();
+            let pattern = self.parse_pattern()?;
 
-                loop {
-                    expression = self.parse_expression();
-                    arguments.push(expression);
+            match self.stream.consume() {
+                Some(Token::MatchArmIndicator) => {},
+                _ => return Err(self.error_at_consumed("Expected \"=>\"!")),
+            }
 
-                    match self.stream.peek() {
-                        Some(Token::Comma) => {
+            arms.push(MatchArm {
+                pattern,
+                value: self.parse_expression()?,
+                span,
+            });
 
-                            /* Consume `,`. */
-                            self.stream.consume();
+            match self.stream.peek() {
+                Some(Token::Comma) => {
+                    self.stream.consume();
+                },
+                Some(Token::RightCurlyBracket) => break,
+                _ => return Err(self.error_at_next("Expected \",\" or \"}\"!")),
+            }
+        }
 
-                            continue;
-                        },
-                        Some(Token::RightRoundBracket) => {
+        /* Consume `}`. */
+        self.stream.consume();
 
-                            /* Consume `)`. */
-                            self.stream.consume();
+        self.alloc(Expression::Match {
+            scrutinee,
+            arms,
+            span,
+        })
+    }
+
+    fn parse_pattern(
+        &mut self
+    ) -> Result<Pattern, Diagnostic> {
+        self.nested(|parser| {
+            let pattern: Pattern = match parser.stream.consume() {
+                Some(Token::Identifier(id)) if id == "_" => Pattern::Wildcard,
+                Some(Token::Identifier(id)) => {
+                    let name = id.into_owned();
+                    let span = parser.stream.previous_span();
+
+                    if !parser.stream.match_token(TokenKind::LeftRoundBracket) {
+                        return Ok(Pattern::Identifier {
+                            name,
+                            span,
+                        });
+                    }
 
-                            break;
+                    let mut fields: Vec<Pattern> = Vec::new();
+
+                    /* Consume `(`. */
+                    parser.stream.consume();
+
+                    while !parser.stream.match_token(TokenKind::RightRoundBracket) {
+                        fields.push(parser.parse_pattern()?);
+
+                        match parser.stream.peek() {
+                            Some(Token::Comma) => {
+                                parser.stream.consume();
+                            },
+                            Some(Token::RightRoundBracket) => break,
+                            _ => return Err(parser.error_at_next("Expected \",\" or \")\"!")),
                         }
-                        _ => panic!("Expected \",\" or \")\"!"),
                     }
-                }
-            },
-        }
 
-        arguments
-    }
+                    /* Consume `)`. */
+                    parser.stream.consume();
 
-    fn parse_number(
-        &mut self
-    ) -> Expression {
-        let number = match self.stream.consume() {
-            Some(Token::Number(num)) => num,
-            _ => panic!("Expected number!"),
-        };
+                    Pattern::Variant {
+                        name,
+                        fields,
+                        span,
+                    }
+                },
+                Some(Token::LeftSquareBracket) => {
+                    let mut elements: Vec<Pattern> = Vec::new();
 
-        Expression::Number(number)
-    }
+                    while !parser.stream.match_token(TokenKind::RightSquareBracket) {
+                        elements.push(parser.parse_pattern()?);
 
-    fn parse_string(
-        &mut self
-    ) -> Expression {
-        let string = match self.stream.consume() {
-            Some(Token::String(str)) => str,
-            _ => panic!("Expected string!"),
-        };
+                        match parser.stream.peek() {
+                            Some(Token::Comma) => {
+                                parser.stream.consume();
+                            },
+                            Some(Token::RightSquareBracket) => break,
+                            _ => return Err(parser.error_at_next("Expected \",\" or \"]\"!")),
+                        }
+                    }
+
+                    /* Consume `]`. */
+                    parser.stream.consume();
+
+                    Pattern::Array(elements)
+                },
+                Some(Token::Number(num)) => Pattern::Number(num),
+                Some(Token::Minus) => match parser.stream.consume() {
+                    Some(Token::Number(num)) => Pattern::Number(-num),
+                    _ => return Err(parser.error_at_consumed("Expected number!")),
+                },
+                Some(Token::String(str)) => Pattern::String(str.into_owned()),
+                Some(Token::Nil) => Pattern::Nil,
+                _ => return Err(parser.error_at_consumed("Expected pattern!")),
+            };
 
-        Expression::String(string)
+            Ok(pattern)
+        })
     }
 
     fn parse_grouped_expression(
         &mut self
-    ) -> Expression {
-        let expression: Expression;
-
+    ) -> Result<ExprId, Diagnostic> {
         self.stream.consume();
 
-        expression = self.parse_expression();
+        /* `()` is the unit value rather than a group. */
+        if self.stream.match_token(TokenKind::RightRoundBracket) {
+            self.stream.consume();
+
+            return self.alloc(Expression::Unit);
+        }
+
+        let expression: ExprId = self.parse_expression()?;
 
         match self.stream.consume() {
             Some(Token::RightRoundBracket) => {},
-            _ => panic!("Expected \")\"!"),
+            _ => return Err(self.error_at_consumed("Expected \")\"!")),
         }
 
-        expression
+        Ok(expression)
     }
 }
 
@@ -710,11 +3318,11 @@ mod tests {
             let stream: Stream;
             let mut parser: Parser;
 
-            tokenizer.scan($text);
+            tokenizer.scan($text).unwrap();
             stream = tokenizer.extract();
             parser = Parser::new(stream);
 
-            parser.parse_program()
+            parser.parse_program().unwrap()
         }};
     }
 
@@ -724,181 +3332,262 @@ mod tests {
 
         program = scan_and_parse_program!("let var_1;");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::VariableDefinition {
                     identifier: String::from("var_1"),
                     r#type: None,
                     value: None,
+                    span: Span::new(4, 9),
                 },
             ],
+            expressions: Arc::new(vec![]),
         });
 
         program = scan_and_parse_program!("let var_2 = 47;");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::VariableDefinition {
                     identifier: String::from("var_2"),
                     r#type: None,
-                    value: Some(Expression::Number(47)),
+                    value: Some(ExprId(0)),
+                    span: Span::new(4, 9),
                 },
             ],
+            expressions: Arc::new(vec![
+                Expression::Number(47),
+            ]),
         });
 
         program = scan_and_parse_program!("let str_1 = \"Hello, world!\\r\\n\";");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::VariableDefinition {
                     identifier: String::from("str_1"),
                     r#type: None,
-                    value: Some(Expression::String(
-                        String::from("Hello, world!\\r\\n"))),
+                    value: Some(ExprId(0)),
+                    span: Span::new(4, 9),
                 },
             ],
+            expressions: Arc::new(vec![
+                Expression::String(String::from("Hello, world!\\r\\n")),
+            ]),
         });
 
         program = scan_and_parse_program!("let var_3: int;");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::VariableDefinition {
                     identifier: String::from("var_3"),
-                    r#type: Some(String::from("int")),
+                    r#type: Some(Type::Named(String::from("int"))),
                     value: None,
+                    span: Span::new(4, 9),
                 },
             ],
+            expressions: Arc::new(vec![]),
         });
 
         program = scan_and_parse_program!("let var_4: int = 23;");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::VariableDefinition {
                     identifier: String::from("var_4"),
-                    r#type: Some(String::from("int")),
-                    value: Some(Expression::Number(23)),
+                    r#type: Some(Type::Named(String::from("int"))),
+                    value: Some(ExprId(0)),
+                    span: Span::new(4, 9),
                 },
             ],
+            expressions: Arc::new(vec![
+                Expression::Number(23),
+            ]),
         });
 
         program = scan_and_parse_program!("let var_5: int = var_1 + var_2;");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::VariableDefinition {
                     identifier: String::from("var_5"),
-                    r#type: Some(String::from("int")),
-                    value: Some(Expression::BinaryOperation {
-                        operator: BinaryOperator::Addition,
-                        operand_left: Box::new(
-                            Expression::Identifier(String::from("var_1"))),
-                        operand_right: Box::new(
-                            Expression::Identifier(String::from("var_2"))),
-                    }),
+                    r#type: Some(Type::Named(String::from("int"))),
+                    value: Some(ExprId(2)),
+                    span: Span::new(4, 9),
                 },
             ],
+            expressions: Arc::new(vec![
+                Expression::Identifier {
+                    name: String::from("var_1"),
+                    span: Span::new(17, 22),
+                },
+                Expression::Identifier {
+                    name: String::from("var_2"),
+                    span: Span::new(25, 30),
+                },
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Addition,
+                    operand_left: ExprId(0),
+                    operand_right: ExprId(1),
+                    span: Span::new(23, 24),
+                },
+            ]),
         });
 
         program = scan_and_parse_program!("let var_6: int = var_3 * var_4 - var_5;");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::VariableDefinition {
                     identifier: String::from("var_6"),
-                    r#type: Some(String::from("int")),
-                    value: Some(Expression::BinaryOperation {
-                        operator: BinaryOperator::Subtraction,
-                        operand_left: Box::new(Expression::BinaryOperation {
-                            operator: BinaryOperator::Multiplication,
-                            operand_left: Box::new(
-                                Expression::Identifier(String::from("var_3"))),
-                            operand_right: Box::new(
-                                Expression::Identifier(String::from("var_4"))),
-                        }),
-                        operand_right: Box::new(
-                            Expression::Identifier(String::from("var_5"))),
-                    }),
+                    r#type: Some(Type::Named(String::from("int"))),
+                    value: Some(ExprId(4)),
+                    span: Span::new(4, 9),
                 },
             ],
+            expressions: Arc::new(vec![
+                Expression::Identifier {
+                    name: String::from("var_3"),
+                    span: Span::new(17, 22),
+                },
+                Expression::Identifier {
+                    name: String::from("var_4"),
+                    span: Span::new(25, 30),
+                },
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Multiplication,
+                    operand_left: ExprId(0),
+                    operand_right: ExprId(1),
+                    span: Span::new(23, 24),
+                },
+                Expression::Identifier {
+                    name: String::from("var_5"),
+                    span: Span::new(33, 38),
+                },
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Subtraction,
+                    operand_left: ExprId(2),
+                    operand_right: ExprId(3),
+                    span: Span::new(31, 32),
+                },
+            ]),
         });
 
         program = scan_and_parse_program!("let var_7: int = var_3 * (var_4 - var_5);");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::VariableDefinition {
                     identifier: String::from("var_7"),
-                    r#type: Some(String::from("int")),
-                    value: Some(Expression::BinaryOperation {
-                        operator: BinaryOperator::Multiplication,
-                        operand_left: Box::new(
-                            Expression::Identifier(String::from("var_3"))),
-                        operand_right: Box::new(Expression::BinaryOperation {
-                            operator: BinaryOperator::Subtraction,
-                            operand_left: Box::new(
-                                Expression::Identifier(String::from("var_4"))),
-                            operand_right: Box::new(
-                                Expression::Identifier(String::from("var_5"))),
-                        }),
-                    }),
+                    r#type: Some(Type::Named(String::from("int"))),
+                    value: Some(ExprId(4)),
+                    span: Span::new(4, 9),
                 },
             ],
+            expressions: Arc::new(vec![
+                Expression::Identifier {
+                    name: String::from("var_3"),
+                    span: Span::new(17, 22),
+                },
+                Expression::Identifier {
+                    name: String::from("var_4"),
+                    span: Span::new(26, 31),
+                },
+                Expression::Identifier {
+                    name: String::from("var_5"),
+                    span: Span::new(34, 39),
+                },
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Subtraction,
+                    operand_left: ExprId(1),
+                    operand_right: ExprId(2),
+                    span: Span::new(32, 33),
+                },
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Multiplication,
+                    operand_left: ExprId(0),
+                    operand_right: ExprId(3),
+                    span: Span::new(23, 24),
+                },
+            ]),
         });
     }
 
     #[test]
     fn expression_assignment() {
-        let program: Program;
-
-        program = scan_and_parse_program!("value = (factor + 9) / 17;");
+        let program: Program = scan_and_parse_program!("value = (factor + 9) / 17;");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::Expression {
-                    expression: Expression::BinaryOperation {
-                        operator: BinaryOperator::Assign,
-                        operand_left: Box::new(Expression::Identifier(
-                            String::from("value")
-                        )),
-                        operand_right: Box::new(Expression::BinaryOperation {
-                            operator: BinaryOperator::Division,
-                            operand_left: Box::new(Expression::BinaryOperation {
-                                operator: BinaryOperator::Addition,
-                                operand_left: Box::new(Expression::Identifier(
-                                    String::from("factor")
-                                )),
-                                operand_right: Box::new(Expression::Number(9)),
-                            }),
-                            operand_right: Box::new(Expression::Number(17)),
-                        }),
-                    },
+                    expression: ExprId(6),
                 },
             ],
+            expressions: Arc::new(vec![
+                Expression::Identifier {
+                    name: String::from("value"),
+                    span: Span::new(0, 5),
+                },
+                Expression::Identifier {
+                    name: String::from("factor"),
+                    span: Span::new(9, 15),
+                },
+                Expression::Number(9),
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Addition,
+                    operand_left: ExprId(1),
+                    operand_right: ExprId(2),
+                    span: Span::new(16, 17),
+                },
+                Expression::Number(17),
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Division,
+                    operand_left: ExprId(3),
+                    operand_right: ExprId(4),
+                    span: Span::new(21, 22),
+                },
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Assign,
+                    operand_left: ExprId(0),
+                    operand_right: ExprId(5),
+                    span: Span::new(6, 7),
+                },
+            ]),
         });
     }
 
     #[test]
     fn block() {
-        let program: Program;
-
-        program = scan_and_parse_program!("let value = 17; { value = 45; { value = 33; } {} }");
+        let program: Program = scan_and_parse_program!("let value = 17; { value = 45; { value = 33; } {} }");
         assert_eq!(program, Program {
+            attributes: vec![],
+            scoped_attributes: vec![],
             statements: vec![
                 Statement::VariableDefinition {
                     identifier: String::from("value"),
                     r#type: None,
-                    value: Some(Expression::Number(17)),
+                    value: Some(ExprId(0)),
+                    span: Span::new(4, 9),
                 },
                 Statement::Block {
                     statements: vec![
                         Statement::Expression {
-                            expression: Expression::BinaryOperation {
-                                operator: BinaryOperator::Assign,
-                                operand_left: Box::new(Expression::Identifier(String::from("value"))),
-                                operand_right: Box::new(Expression::Number(45)),
-                            },
+                            expression: ExprId(3),
                         },
                         Statement::Block {
                             statements: vec![
                                 Statement::Expression {
-                                    expression: Expression::BinaryOperation {
-                                        operator: BinaryOperator::Assign,
-                                        operand_left: Box::new(Expression::Identifier(String::from("value"))),
-                                        operand_right: Box::new(Expression::Number(33)),
-                                    },
+                                    expression: ExprId(6),
                                 },
                             ],
                         },
@@ -908,6 +3597,834 @@ mod tests {
                     ],
                 },
             ],
+            expressions: Arc::new(vec![
+                Expression::Number(17),
+                Expression::Identifier {
+                    name: String::from("value"),
+                    span: Span::new(18, 23),
+                },
+                Expression::Number(45),
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Assign,
+                    operand_left: ExprId(1),
+                    operand_right: ExprId(2),
+                    span: Span::new(24, 25),
+                },
+                Expression::Identifier {
+                    name: String::from("value"),
+                    span: Span::new(32, 37),
+                },
+                Expression::Number(33),
+                Expression::BinaryOperation {
+                    operator: BinaryOperator::Assign,
+                    operand_left: ExprId(4),
+                    operand_right: ExprId(5),
+                    span: Span::new(38, 39),
+                },
+            ]),
+        });
+    }
+
+    #[test]
+    fn operators() {
+        let program = scan_and_parse_program!("a = b = -c * 2 - 1;");
+
+        /* `=` groups to the right, `-` in front of `c` binds tightest. */
+        assert_eq!(program.expressions[3..], [
+            Expression::UnaryOperation {
+                operator: UnaryOperator::Negation,
+                operand: ExprId(2),
+            },
+            Expression::Number(2),
+            Expression::BinaryOperation {
+                operator: BinaryOperator::Multiplication,
+                operand_left: ExprId(3),
+                operand_right: ExprId(4),
+                span: Span::new(11, 12),
+            },
+            Expression::Number(1),
+            Expression::BinaryOperation {
+                operator: BinaryOperator::Subtraction,
+                operand_left: ExprId(5),
+                operand_right: ExprId(6),
+                span: Span::new(15, 16),
+            },
+            Expression::BinaryOperation {
+                operator: BinaryOperator::Assign,
+                operand_left: ExprId(1),
+                operand_right: ExprId(7),
+                span: Span::new(6, 7),
+            },
+            Expression::BinaryOperation {
+                operator: BinaryOperator::Assign,
+                operand_left: ExprId(0),
+                operand_right: ExprId(8),
+                span: Span::new(2, 3),
+            },
+        ]);
+
+        /* Comparisons only chain in parentheses. */
+        scan_and_parse_program!("a = (b == c) != (d == e);");
+
+        for text in ["a = b == c != d;", "a = b != c + 1 == d;"] {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(text).unwrap();
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, "Comparisons cannot be chained!", "{}", text);
+        }
+    }
+
+    #[test]
+    fn append_programs() {
+        let mut program = scan_and_parse_program!("let a = 1 + 2;");
+
+        program.append(scan_and_parse_program!("a = 3;"));
+        assert_eq!(program.statements[1], Statement::Expression {
+            expression: ExprId(5),
+        });
+        assert_eq!(program.expression(ExprId(5)), &Expression::BinaryOperation {
+            operator: BinaryOperator::Assign,
+            operand_left: ExprId(3),
+            operand_right: ExprId(4),
+            span: Span::new(2, 3),
         });
     }
+    #[test]
+    fn serde_round_trip() {
+        let program: Program = scan_and_parse_program!("func add_num(a: int, b) -> int { return a + b; } let sum = add_num(1, \"2\");");
+        let json: String = serde_json::to_string(&program).unwrap();
+        assert_eq!(serde_json::from_str::<Program>(&json).unwrap(), program);
+    }
+    #[test]
+    fn error_location() {
+        let mut tokenizer = Tokenizer::new();
+        let text = "let var_1 = 1;\nlet = 2;";
+
+        tokenizer.scan(text).unwrap();
+        let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+        assert_eq!(diagnostics.render("main.fang", text),
+                   "main.fang:2:5: error: Expected identifier!\n");
+    }
+
+    #[test]
+    fn missing_semicolons() {
+        let mut tokenizer = Tokenizer::new();
+        let text = "let a = 1 // One.\nlet b = a + 2\n{ a = b }";
+
+        /* Missing at the end of a line, parsing goes on to the next
+         * one. Elsewhere, parsing stops. */
+        tokenizer.scan(text).unwrap();
+        let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+        assert_eq!(diagnostics.render("main.fang", text), "\
+main.fang:1:10: error: Expected \";\"!
+main.fang:1:10: help: add `;` after this expression
+main.fang:2:14: error: Expected \";\"!
+main.fang:2:14: help: add `;` after this expression
+main.fang:3:9: error: Expected \";\"!
+");
+    }
+
+    #[test]
+    fn implicit_return() {
+        let program = scan_and_parse_program!("func double(x: int) -> int { let y = x * 2; y }");
+
+        assert!(matches!(&program.statements[0], Statement::FunctionDefinition {
+            statements,
+            ..
+        } if matches!(&statements[..], [
+            Statement::VariableDefinition { .. },
+            Statement::Return {
+                implicit: true,
+                ..
+            },
+        ])));
+
+        /* Only a function body returns its last expression. */
+        for text in [
+            "func f() { { 1 } }",
+            "for x in xs { x }",
+            "{ 1 }",
+        ] {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(text).unwrap();
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, "Expected \";\"!", "{}", text);
+        }
+    }
+
+    #[test]
+    fn unit() {
+        let program = scan_and_parse_program!("func f() -> () { return (); } let a: ()? = ( );");
+
+        assert!(matches!(&program.statements[0], Statement::FunctionDefinition {
+            return_type: Some(Type::Unit),
+            ..
+        }));
+        assert!(matches!(&program.statements[1], Statement::VariableDefinition {
+            r#type: Some(Type::Nullable(r#type)),
+            value: Some(value),
+            ..
+        } if **r#type == Type::Unit && program.expression(*value) == &Expression::Unit));
+    }
+
+    #[test]
+    fn optional_semicolons() {
+        let options = ParseOptions {
+            optional_semicolons: true,
+            ..ParseOptions::default()
+        };
+        let program = parse_source_with("let a = 1\nlet b = a\n    + 2\n{ a = b }\nfunc f() {\n    a\n}\nf()", options).unwrap();
+
+        assert_eq!(program.statements.len(), 5);
+        assert!(matches!(&program.statements[1], Statement::VariableDefinition {
+            value: Some(value),
+            ..
+        } if matches!(program.expression(*value), Expression::BinaryOperation { .. })));
+        assert!(matches!(&program.statements[3], Statement::FunctionDefinition {
+            statements,
+            ..
+        } if matches!(statements[0], Statement::Return {
+            implicit: true,
+            ..
+        })));
+
+        /* Only a line break ends a statement within a line. */
+        assert_eq!(parse_source_with("let a = 1 let b = 2", options).unwrap_err().iter().next().unwrap().message,
+                   "Expected \";\"!");
+    }
+
+    #[test]
+    fn editions() {
+        let text = "let a = 1\na = 2";
+        let options = ParseOptions {
+            edition: Edition::E2026,
+            ..ParseOptions::default()
+        };
+
+        assert!(parse_source(text).is_err());
+        assert_eq!(parse_source_with(text, options).unwrap().statements.len(), 2);
+
+        /* The first line of a file overrides the edition it's parsed
+         * with. */
+        assert_eq!(parse_source(&format!("// edition: 2026\n{}", text)).unwrap().statements.len(), 2);
+        assert!(parse_source_with(&format!("// edition: 2025\n{}", text), options).is_err());
+        assert_eq!(parse_source("// edition: 2030\n").unwrap_err().render("main.fang", "// edition: 2030\n"), "\
+main.fang:1:1: error: Unknown edition \"2030\"!
+main.fang:1:1: help: editions are 2025 and 2026
+");
+    }
+
+    #[test]
+    fn attributes() {
+        let program = scan_and_parse_program!("#![no_std_runtime]\n#![allow(unreachable_arms)]\nlet a = 1;");
+
+        assert_eq!(program.attributes, vec![
+            Attribute {
+                name: String::from("no_std_runtime"),
+                arguments: vec![],
+                span: Span::new(0, 18),
+            },
+            Attribute {
+                name: String::from("allow"),
+                arguments: vec![String::from("unreachable_arms")],
+                span: Span::new(19, 46),
+            },
+        ]);
+        assert!(program.has_attribute("no_std_runtime"));
+        assert!(program.allows("unreachable_arms"));
+        assert_eq!(program.statements.len(), 1);
+
+        let errors = [
+            ("#![inline]", "Unknown attribute \"inline\"!"),
+            ("#![allow]", "Wrong arguments for attribute \"allow\"!"),
+            ("#![no_std_runtime(all)]", "Wrong arguments for attribute \"no_std_runtime\"!"),
+            ("#![allow(unreachable_arms]", "Expected \",\" or \")\"!"),
+            ("#!allow", "Expected \"[\"!"),
+            ("let a = 1; #![no_std_runtime]", "Attributes must come before any statement!"),
+            ("#[no_prelude] let a = 1;", "Unknown attribute \"no_prelude\"!"),
+            ("#![test]", "Unknown attribute \"test\"!"),
+            ("#[test(slow)] func f() {}", "Wrong arguments for attribute \"test\"!"),
+            ("#[test] let a = 1;", "Only functions can be tests!"),
+        ];
+
+        for (text, message) in errors {
+            let mut tokenizer = Tokenizer::new();
+
+            let _ = tokenizer.scan(text);
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{}", text);
+        }
+    }
+
+    #[test]
+    fn statement_attributes() {
+        let program = scan_and_parse_program!("#[allow(warnings)] #[deny(unreachable_arms)] func f() { a; }");
+
+        assert!(matches!(&program.statements[0], Statement::Attributed {
+            attributes,
+            statement,
+            span,
+        } if attributes.len() == 2 &&
+             attributes[1].arguments == ["unreachable_arms"] &&
+             matches!(statement.as_ref(), Statement::FunctionDefinition { .. }) &&
+             *span == Span::new(45, 60)));
+        assert!(matches!(program.statements[0].unattributed(), Statement::FunctionDefinition { .. }));
+    }
+
+    #[test]
+    fn conditionals() {
+        let program = scan_and_parse_program!("#if DEBUG { a; } #else { b; } #if TRACE {}");
+
+        assert!(matches!(&program.statements[0], Statement::Conditional {
+            symbol,
+            statements,
+            otherwise,
+            span,
+        } if symbol == "DEBUG" && statements.len() == 1 && otherwise.len() == 1 && *span == Span::new(4, 9)));
+        assert!(matches!(&program.statements[1], Statement::Conditional {
+            statements,
+            otherwise,
+            ..
+        } if statements.is_empty() && otherwise.is_empty()));
+
+        let errors = [
+            ("#ifdef DEBUG {}", "Expected \"if\"!"),
+            ("#if {}", "Expected identifier!"),
+            ("#if DEBUG a;", "Expected \"{\"!"),
+            ("#if DEBUG {} #else b;", "Expected \"{\"!"),
+            ("#if DEBUG {} #elif {}", "Expected \"if\"!"),
+        ];
+
+        for (text, message) in errors {
+            let mut tokenizer = Tokenizer::new();
+
+            let _ = tokenizer.scan(text);
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{}", text);
+        }
+    }
+
+    #[test]
+    fn trailing_commas() {
+        let lists = [
+            ("func f() {} f();", 0),
+            ("func f(a) {} f(1);", 1),
+            ("func f(a,) {} f(1,);", 1),
+            ("func f(a: int, b: int,) {} f(1, 2,);", 2),
+        ];
+
+        for (text, count) in lists {
+            let program = scan_and_parse_program!(text);
+
+            assert!(matches!(&program.statements[0], Statement::FunctionDefinition {
+                parameters,
+                ..
+            } if parameters.len() == count), "{}", text);
+            assert!(matches!(program.expressions.last(), Some(Expression::FunctionCall {
+                arguments,
+                ..
+            }) if arguments.len() == count), "{}", text);
+        }
+
+        /* A lone comma is no list. */
+        for (text, message) in [("func f(,) {}", "Expected parameters or \")\"!"), ("f(,);", "Expected expression!")] {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(text).unwrap();
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, message);
+        }
+    }
+
+    #[test]
+    fn named_arguments() {
+        let program = scan_and_parse_program!("draw(3, y = 7, color = \"red\");");
+        let mut tokenizer = Tokenizer::new();
+
+        assert_eq!(program.expressions.last(), Some(&Expression::FunctionCall {
+            callee_name: String::from("draw"),
+            arguments: vec![
+                Argument {
+                    name: None,
+                    value: ExprId(0),
+                    span: Span::new(5, 6),
+                },
+                Argument {
+                    name: Some(String::from("y")),
+                    value: ExprId(1),
+                    span: Span::new(8, 9),
+                },
+                Argument {
+                    name: Some(String::from("color")),
+                    value: ExprId(2),
+                    span: Span::new(15, 20),
+                },
+            ],
+            span: Span::new(0, 4),
+        }));
+
+        tokenizer.scan("draw(x = 3, 7);").unwrap();
+        let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+        assert_eq!(diagnostics.iter().next().unwrap(),
+                   &Diagnostic::error("Expected named argument after named argument!")
+                       .with_span(Span::new(12, 13)));
+    }
+
+    #[test]
+    fn overload_signatures() {
+        let mut tokenizer = Tokenizer::new();
+        let text = "func f(a) {} func f(a: int) {} func f(b) {}";
+
+        tokenizer.scan(text).unwrap();
+        let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+        assert_eq!(diagnostics.render("main.fang", text),
+                   "main.fang:1:37: error: Function \"f\" is already defined with the same parameter types!\n");
+    }
+
+    #[test]
+    fn implementation() {
+        let program = scan_and_parse_program!("impl int { func double(self) { return self * 2; } } -a.double(1);");
+        let mut tokenizer = Tokenizer::new();
+
+        assert!(matches!(&program.statements[0], Statement::Implementation {
+            type_name,
+            functions,
+            ..
+        } if type_name == "int" && functions.len() == 1));
+
+        /* The method call binds tighter than `-`. */
+        assert_eq!(program.expressions[3..], [
+            Expression::Identifier {
+                name: String::from("a"),
+                span: Span::new(53, 54),
+            },
+            Expression::Number(1),
+            Expression::MethodCall {
+                receiver: ExprId(3),
+                method: String::from("double"),
+                arguments: vec![
+                    Argument {
+                        name: None,
+                        value: ExprId(4),
+                        span: Span::new(62, 63),
+                    },
+                ],
+                span: Span::new(55, 61),
+            },
+            Expression::UnaryOperation {
+                operator: UnaryOperator::Negation,
+                operand: ExprId(5),
+            },
+        ]);
+
+        tokenizer.scan("impl int { func double(value) {} }").unwrap();
+        let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+        assert_eq!(diagnostics.render("main.fang", "impl int { func double(value) {} }"),
+                   "main.fang:1:17: error: Expected \"self\" as the first parameter of a method!\n");
+    }
+
+    #[test]
+    fn traits() {
+        let text = "\
+trait Shape { func area(self) -> int; func scale(self, by: int); }
+trait Shape {}
+impl Shape for int { func area(self) {} func scale(self, by: int) {} func extra(self) {} }
+impl Shape for string { func scale(self, by: int) {} }
+impl Drawable for int {}
+";
+        let mut tokenizer = Tokenizer::new();
+        let program = scan_and_parse_program!("trait Shape { func area(self) -> int; } impl Shape for int { func area(self) -> int { return 1; } }");
+
+        assert!(matches!(&program.statements[..], [
+            Statement::Trait {
+                name,
+                methods,
+                ..
+            },
+            Statement::Implementation {
+                type_name,
+                trait_name: Some(trait_name),
+                ..
+            },
+        ] if name == "Shape" && methods.len() == 1 && type_name == "int" && trait_name == "Shape"));
+
+        tokenizer.scan(text).unwrap();
+        let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+        assert_eq!(diagnostics.render("main.fang", text), "\
+main.fang:2:7: error: Trait \"Shape\" is already defined!
+main.fang:3:27: error: Method \"area\" does not match its signature in trait \"Shape\"!
+main.fang:3:75: error: Method \"extra\" is not a member of trait \"Shape\"!
+main.fang:4:16: error: Implementation of \"Shape\" for \"string\" is missing method \"area\"!
+main.fang:5:19: error: Unknown trait \"Drawable\"!
+");
+    }
+
+    #[test]
+    fn ranges() {
+        let program = scan_and_parse_program!("a == 0..n + 1; ..=2; b = 1..;");
+
+        assert!(matches!(program.expression(ExprId(program.expressions.len() as u32 - 1)), Expression::BinaryOperation {
+            operator: BinaryOperator::Assign,
+            operand_right,
+            ..
+        } if matches!(program.expression(*operand_right), Expression::Range {
+            start: Some(_),
+            end: None,
+            inclusive: false,
+        })));
+        assert!(matches!(&program.statements[..], [
+            Statement::Expression {
+                expression: first,
+            },
+            Statement::Expression {
+                expression: second,
+            },
+            _,
+        ] if matches!(program.expression(*first), Expression::BinaryOperation {
+            operator: BinaryOperator::Equal,
+            operand_right,
+            ..
+        } if matches!(program.expression(*operand_right), Expression::Range {
+            end: Some(end),
+            ..
+        } if matches!(program.expression(*end), Expression::BinaryOperation { .. })))
+            && matches!(program.expression(*second), Expression::Range {
+                start: None,
+                end: Some(_),
+                inclusive: true,
+            })));
+
+        for (text, message) in [
+            ("a = 0..1..2;", "Ranges cannot be chained!"),
+            ("a = 0..=;", "Expected expression!"),
+        ] {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(text).unwrap();
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{}", text);
+        }
+    }
+
+    #[test]
+    fn loops() {
+        let program = scan_and_parse_program!("for i in 0.. { total = total + i; }");
+
+        assert!(matches!(&program.statements[0], Statement::For {
+            variable,
+            iterable,
+            statements,
+            span,
+        } if variable == "i"
+            && *span == Span::new(4, 5)
+            && matches!(program.expression(*iterable), Expression::Range {
+                end: None,
+                ..
+            })
+            && statements.len() == 1));
+
+        for (text, message) in [
+            ("for 1 in a {}", "Expected identifier!"),
+            ("for i a {}", "Expected \"in\"!"),
+            ("for i in a;", "Expected \"{\"!"),
+        ] {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(text).unwrap();
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{}", text);
+        }
+    }
+
+    #[test]
+    fn enums() {
+        let program = scan_and_parse_program!("enum Shape { Circle(int), Rect(int, int,), Empty, } a = match s { Circle(r) => r, Rect(_, -1) => 0, other => 1 };");
+
+        assert!(matches!(&program.statements[0], Statement::Enum {
+            name,
+            variants,
+            ..
+        } if name == "Shape"
+            && variants.iter().map(|variant| variant.fields.len()).collect::<Vec<usize>>() == [1, 2, 0]
+            && variants[2].span == Span::new(43, 48)));
+        assert!(matches!(program.expressions.iter().rev().nth(1), Some(Expression::Match {
+            arms,
+            span,
+            ..
+        }) if *span == Span::new(56, 61) && matches!(&arms[..], [
+            MatchArm {
+                pattern: Pattern::Variant {
+                    name,
+                    ..
+                },
+                ..
+            },
+            MatchArm {
+                pattern: Pattern::Variant {
+                    fields,
+                    ..
+                },
+                ..
+            },
+            MatchArm {
+                pattern: Pattern::Identifier { .. },
+                ..
+            },
+        ] if name == "Circle" && fields == &[Pattern::Wildcard, Pattern::Number(-1)])));
+
+        for (text, message) in [
+            ("enum E { A, A }", "Variant \"A\" is already defined!"),
+            ("enum E { A } enum F { B, A }", "Variant \"A\" is already defined!"),
+            ("func A() {} enum E { A }", "Variant \"A\" is already defined!"),
+            ("enum E { A } func A() {}", "Function \"A\" is already defined!"),
+            ("enum E { A } let A = 3;", "Variable \"A\" is already defined!"),
+            ("func f() { enum E { A } const A = 3; }", "Constant \"A\" is already defined!"),
+            ("enum E { A B }", "Expected \",\" or \"}\"!"),
+            ("a = match b { 1 2 };", "Expected \"=>\"!"),
+            ("a = match b { + => 1 };", "Expected pattern!"),
+        ] {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(text).unwrap();
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{}", text);
+        }
+
+        assert_eq!(parse_source("enum E { A }\nlet A = 3;").unwrap_err().render("main.fang", "enum E { A }\nlet A = 3;"), "\
+main.fang:2:5: error: Variable \"A\" is already defined!
+main.fang:2:5: help: \"A\" is a variant of an enum
+");
+    }
+
+    #[test]
+    fn nesting() {
+        let deep = |open: &str, inner: &str, close: &str, depth: usize|
+            format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth));
+
+        assert!(parse_source(&format!("a = {};", deep("(", "1", ")", NESTING_LIMIT - 2))).is_ok());
+        assert!(parse_source(&format!("a = 1{};", " + 1".repeat(NESTING_LIMIT - 2))).is_ok());
+
+        for text in [
+            format!("a = {};", deep("(", "1", ")", 100_000)),
+            format!("a = {};", deep("[", "1", "]", 100_000)),
+            format!("a = {}1;", "-".repeat(100_000)),
+            deep("{", "", "}", 100_000),
+            format!("let a: {} = b;", deep("[", "int", "]", 100_000)),
+            format!("let {} = b;", deep("[", "a", "]", 100_000)),
+            format!("a = 1{};", " + 1".repeat(5000)),
+            format!("a = 1{};", " as int".repeat(5000)),
+            format!("a = b{};", "[0]".repeat(5000)),
+            format!("a = b{};", "?".repeat(5000)),
+            format!("a = b{};", ".f()".repeat(5000)),
+        ] {
+            let diagnostics = parse_source(&text).unwrap_err();
+
+            assert_eq!(diagnostics.iter().next().unwrap().message, "Nesting too deep!");
+        }
+
+        let options = ParseOptions {
+            nesting_limit: 3,
+            ..ParseOptions::default()
+        };
+
+        assert!(parse_source_with("a = (1);", options).is_ok());
+        assert_eq!(parse_source_with("a = ((1));", options).unwrap_err().render("main.fang", "a = ((1));"), "\
+main.fang:1:7: error: Nesting too deep!
+main.fang:1:7: help: code may nest 3 levels deep
+");
+    }
+
+    #[test]
+    fn limits() {
+        let text = "let a = \"four\"; match a { \"four\" => a + 1, _ => 2 };";
+        let limit = |limits: Limits| ParseOptions {
+            limits,
+            ..ParseOptions::default()
+        };
+        let errors = [
+            (Limits { max_tokens: Some(18), ..Limits::default() }, "Token limit exceeded!"),
+            (Limits { max_nodes: Some(2), ..Limits::default() }, "Node limit exceeded!"),
+            (Limits { max_string_length: Some(3), ..Limits::default() }, "String length limit exceeded!"),
+            (Limits { time_budget: Some(Duration::ZERO), ..Limits::default() }, "Time limit exceeded!"),
+        ];
+
+        assert_eq!(parse_source_with(text, limit(Limits {
+            max_tokens: Some(19),
+            max_nodes: Some(6),
+            max_string_length: Some(4),
+            time_budget: Some(Duration::from_secs(60)),
+        })).map(|_| ()), Ok(()));
+
+        for (limits, message) in errors {
+            let diagnostics = parse_source_with(text, limit(limits)).unwrap_err();
+
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{:?}", limits);
+        }
+
+        assert_eq!(parse_source_with("a = \"long\";", limit(Limits {
+            max_string_length: Some(3),
+            ..Limits::default()
+        })).unwrap_err().render("main.fang", "a = \"long\";"), "\
+main.fang:1:5: error: String length limit exceeded!
+main.fang:1:5: help: string literals may hold 3 bytes
+");
+    }
+
+    #[test]
+    fn shift_spans() {
+        let text = "\
+#![allow(unreachable_arms)]
+enum Shape { Circle(int), Empty }
+trait Area { func area(self, scale: int) -> int; }
+impl Area for Shape { func area(self, scale: int) -> int { match self { Circle(r) => r * scale, _ => 0 } } }
+let [first, Circle(r)] = shapes;
+const SIZE = 4;
+for shape in shapes { print(shape.area(scale = 2)?, len(shapes[0] as string)); }
+macro twice(x) { x; x; }
+#if DEBUG { let a = -r; } #else { { var b; } }
+";
+        let spans = |program: &Program| -> Vec<(usize, usize)> {
+            let dump = format!("{:?}", program);
+
+            dump.match_indices("Span { start: ").map(|(index, _)| {
+                let numbers: Vec<usize> = dump[index..].split(|char: char| !char.is_ascii_digit())
+                    .filter_map(|number| number.parse().ok())
+                    .take(2)
+                    .collect();
+
+                (numbers[0], numbers[1])
+            }).collect()
+        };
+        let program = parse_source(text).unwrap();
+        let mut shifted = program.clone();
+
+        shifted.shift_spans(1000);
+
+        /* Every span moves, however deep in the program it is. */
+        assert_eq!(spans(&shifted), spans(&program).iter().map(|(start, end)| (start + 1000, end + 1000)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn destructuring() {
+        let program = scan_and_parse_program!("let [x, Some(y)] = pair; let Circle(r) = shape; let a = b;");
+
+        assert!(matches!(&program.statements[0], Statement::Destructuring {
+            pattern: Pattern::Array(elements),
+            span,
+            ..
+        } if *span == Span::new(4, 5) && matches!(&elements[..], [
+            Pattern::Identifier { .. },
+            Pattern::Variant { .. },
+        ])));
+        assert!(matches!(&program.statements[1], Statement::Destructuring {
+            pattern: Pattern::Variant {
+                name,
+                ..
+            },
+            ..
+        } if name == "Circle"));
+        assert!(matches!(&program.statements[2], Statement::VariableDefinition { .. }));
+
+        for (text, message) in [
+            ("let [a, b];", "Expected \"=\"!"),
+            ("let [a b] = c;", "Expected \",\" or \"]\"!"),
+        ] {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(text).unwrap();
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{}", text);
+        }
+    }
+
+    #[test]
+    fn maps() {
+        let program = scan_and_parse_program!("let ages: map[string, int?] = {\"alex\": 30, \"kim\": nil,}; let map = {};");
+
+        assert!(matches!(&program.statements[0], Statement::VariableDefinition {
+            r#type: Some(Type::Map {
+                key,
+                value,
+            }),
+            value: Some(map),
+            ..
+        } if **key == Type::Named(String::from("string"))
+            && matches!(**value, Type::Nullable(_))
+            && matches!(program.expression(*map), Expression::Map {
+                entries,
+            } if entries.len() == 2)));
+        assert!(matches!(&program.statements[1], Statement::VariableDefinition {
+            identifier,
+            value: Some(map),
+            ..
+        } if identifier == "map" && program.expression(*map) == &Expression::Map {
+            entries: Vec::new(),
+        }));
+
+        for (text, message) in [
+            ("a = {1 2};", "Expected \":\"!"),
+            ("a = {1: 2 3: 4};", "Expected \",\" or \"}\"!"),
+            ("let a: map[string int];", "Expected \",\"!"),
+        ] {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(text).unwrap();
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{}", text);
+        }
+    }
+
+    #[test]
+    fn arrays() {
+        let mut program = scan_and_parse_program!("let buf: [int?; 4]; buf = [1, 2,][1];");
+
+        assert!(matches!(&program.statements[0], Statement::VariableDefinition {
+            r#type: Some(Type::Array {
+                element,
+                size,
+            }),
+            ..
+        } if **element == Type::Nullable(Box::new(Type::Named(String::from("int"))))
+            && program.expression(*size) == &Expression::Number(4)));
+        assert!(matches!(program.expressions.last(), Some(Expression::BinaryOperation {
+            operand_right,
+            ..
+        }) if matches!(program.expression(*operand_right), Expression::Index {
+            target,
+            ..
+        } if matches!(program.expression(*target), Expression::Array {
+            elements,
+        } if elements.len() == 2))));
+
+        program = scan_and_parse_program!("func sum(xs: [int]) {} sum(a[1..]);");
+
+        assert!(matches!(&program.statements[0], Statement::FunctionDefinition {
+            parameters,
+            ..
+        } if parameters[0].r#type == Some(Type::Slice(Box::new(Type::Named(String::from("int")))))));
+        assert!(matches!(program.expressions.iter().rev().nth(1), Some(Expression::Index {
+            index,
+            ..
+        }) if matches!(program.expression(*index), Expression::Range {
+            start: Some(_),
+            end: None,
+            inclusive: false,
+        })));
+
+        for (text, message) in [
+            ("let a: [int = b;", "Expected \";\" or \"]\"!"),
+            ("let a: [int; 2 = b;", "Expected \"]\"!"),
+            ("a = [1 2];", "Expected \",\" or \"]\"!"),
+            ("a = b[1;", "Expected \"]\"!"),
+            ("a = b[1..;", "Expected \"]\"!"),
+        ] {
+            let mut tokenizer = Tokenizer::new();
+
+            tokenizer.scan(text).unwrap();
+            let diagnostics: Diagnostics = Parser::new(tokenizer.extract()).parse_program().unwrap_err();
+            assert_eq!(diagnostics.iter().next().unwrap().message, message, "{}", text);
+        }
+    }
 }
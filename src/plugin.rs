@@ -0,0 +1,33 @@
+use crate::diagnostic::Diagnostics;
+use crate::lint::LintRegistry;
+use crate::parser::Program;
+
+/// Extension of the compiler a crate adds to a
+/// [`Frontend`](crate::frontend::Frontend) with
+/// [`add_plugin`](crate::frontend::Frontend::add_plugin), so that trying
+/// out a pass doesn't take forking the compiler.
+///
+/// Every method does nothing by default, so a plugin only implements
+/// the passes it adds. Files are compiled in parallel, hence the plugin
+/// must be shareable across threads.
+pub trait Plugin: Sync {
+
+    /// Name of the plugin, for the messages about it.
+    fn name(&self) -> &str;
+
+    /// Add the plugin's lints to `registry`, whose levels are then set
+    /// like those of the built-in lints.
+    fn register_lints(&self, _registry: &mut LintRegistry) {}
+
+    /// Transform the program once its files are merged and checked,
+    /// failing with diagnostics spanning any of the files.
+    fn transform(&self, _program: &mut Program) -> Result<(), Diagnostics> {
+        Ok(())
+    }
+
+    /// Artifact in the format named `format` for `program`, `None` if
+    /// the plugin doesn't emit that format.
+    fn emit(&self, _format: &str, _program: &Program) -> Option<String> {
+        None
+    }
+}
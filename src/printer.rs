@@ -0,0 +1,962 @@
+use crate::parser::{Argument, Associativity, Attribute, BinaryOperator, ExprId, Expression, Parameter, Pattern, Program, Statement, Type, Variant, CAST_PRECEDENCE, PREFIX_PRECEDENCE, RANGE_PRECEDENCE};
+
+/// Width of one indentation level.
+const INDENT: &str = "    ";
+
+/// Print the program back as canonically formatted source code.
+pub fn format_program(program: &Program) -> String {
+    let mut printer = Printer::new(program);
+
+    printer.print_attributes(&program.attributes);
+    printer.print_statements(&program.statements);
+
+    printer.text
+}
+
+/// Print an expression of the program with as few parentheses as its
+/// structure allows.
+pub fn format_expression(program: &Program, expression: ExprId) -> String {
+    let mut text = String::new();
+
+    print_expression(&mut text, &program.expressions, expression);
+
+    text
+}
+
+/// Print a type as written in source code, where `expressions` is the
+/// arena of the program holding it.
+pub fn format_type(expressions: &[Expression], r#type: &Type) -> String {
+    let mut text = String::new();
+
+    print_type(&mut text, expressions, r#type);
+
+    text
+}
+
+/// Print a parameter as written in a function definition.
+pub fn format_parameter(expressions: &[Expression], parameter: &Parameter) -> String {
+    match &parameter.r#type {
+        Some(r#type) => format!("{}: {}", parameter.name, format_type(expressions, r#type)),
+        None => parameter.name.to_owned(),
+    }
+}
+
+/// Print an enum variant as written in its declaration.
+pub fn format_variant(expressions: &[Expression], variant: &Variant) -> String {
+    let fields: Vec<String> = variant.fields.iter()
+        .map(|field| format_type(expressions, field))
+        .collect();
+
+    if fields.is_empty() {
+        variant.name.to_owned()
+    } else {
+        format!("{}({})", variant.name, fields.join(", "))
+    }
+}
+
+/// Print the declaration of a variable, constant, function or enum
+/// without its value or body, `None` for other statements.
+pub fn format_signature(expressions: &[Expression], statement: &Statement) -> Option<String> {
+    match statement {
+        Statement::VariableDefinition {
+            identifier,
+            r#type: Some(r#type),
+            ..
+        } => Some(format!("let {}: {}", identifier, format_type(expressions, r#type))),
+        Statement::VariableDefinition {
+            identifier,
+            ..
+        } => Some(format!("let {}", identifier)),
+        Statement::ConstantDefinition {
+            identifier,
+            r#type: Some(r#type),
+            ..
+        } => Some(format!("const {}: {}", identifier, format_type(expressions, r#type))),
+        Statement::ConstantDefinition {
+            identifier,
+            ..
+        } => Some(format!("const {}", identifier)),
+        Statement::FunctionDefinition {
+            callee_name,
+            parameters,
+            return_type,
+            ..
+        } => {
+            let parameters: Vec<String> = parameters.iter()
+                .map(|parameter| format_parameter(expressions, parameter))
+                .collect();
+            let mut signature = format!("func {}({})", callee_name, parameters.join(", "));
+
+            if let Some(return_type) = return_type {
+                signature.push_str(" -> ");
+                signature.push_str(&format_type(expressions, return_type));
+            }
+
+            Some(signature)
+        },
+        Statement::Enum {
+            name,
+            ..
+        } => Some(format!("enum {}", name)),
+        _ => None,
+    }
+}
+
+fn print_expression(text: &mut String, expressions: &[Expression], expression: ExprId) {
+    match &expressions[expression.index()] {
+        Expression::Identifier {
+            name,
+            ..
+        } => text.push_str(name),
+        Expression::Number(num) => text.push_str(&num.to_string()),
+        Expression::String(str) => {
+            text.push('"');
+            text.push_str(str);
+            text.push('"');
+        },
+        Expression::Nil => text.push_str("nil"),
+        Expression::Unit => text.push_str("()"),
+        Expression::UnaryOperation {
+            operator,
+            operand,
+        } => {
+            text.push_str(operator.symbol());
+            print_operand(text, expressions, *operand, PREFIX_PRECEDENCE);
+        },
+        Expression::BinaryOperation {
+            operator,
+            operand_left,
+            operand_right,
+            ..
+        } => {
+            let level = operator.precedence();
+
+            /* The operand on the side the operator associates to may
+             * hold operators of the same precedence unparenthesized,
+             * unless they are comparisons, which don't chain. */
+            let (level_left, level_right) = match operator.associativity() {
+                _ if operator.is_comparison() => (level + 1, level + 1),
+                Associativity::Left => (level, level + 1),
+                Associativity::Right => (level + 1, level),
+            };
+
+            print_closed(text, |text| print_operand(text, expressions, *operand_left, level_left));
+            text.push(' ');
+            text.push_str(operator.symbol());
+            text.push(' ');
+            print_operand(text, expressions, *operand_right, level_right);
+        },
+        Expression::FunctionCall {
+            callee_name,
+            arguments,
+            ..
+        } => {
+            text.push_str(callee_name);
+            print_arguments(text, expressions, arguments);
+        },
+        Expression::Cast {
+            operand,
+            r#type,
+            ..
+        } => {
+            print_operand(text, expressions, *operand, CAST_PRECEDENCE);
+            text.push_str(" as ");
+            print_type(text, expressions, r#type);
+        },
+        Expression::Try {
+            operand,
+            ..
+        } => {
+            print_operand(text, expressions, *operand, PREFIX_PRECEDENCE + 1);
+            text.push('?');
+        },
+        Expression::MethodCall {
+            receiver,
+            method,
+            arguments,
+            ..
+        } => {
+            print_operand(text, expressions, *receiver, PREFIX_PRECEDENCE + 1);
+            text.push('.');
+            text.push_str(method);
+            print_arguments(text, expressions, arguments);
+        },
+        Expression::Array {
+            elements,
+        } => {
+            text.push('[');
+
+            for (index, element) in elements.iter().enumerate() {
+                if index > 0 {
+                    text.push_str(", ");
+                }
+
+                print_expression(text, expressions, *element);
+            }
+
+            text.push(']');
+        },
+        Expression::Map {
+            entries,
+        } => {
+            text.push('{');
+
+            for (index, (key, value)) in entries.iter().enumerate() {
+                if index > 0 {
+                    text.push_str(", ");
+                }
+
+                print_closed(text, |text| print_expression(text, expressions, *key));
+                text.push_str(": ");
+                print_expression(text, expressions, *value);
+            }
+
+            text.push('}');
+        },
+        Expression::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            if let Some(start) = start {
+                print_operand(text, expressions, *start, RANGE_PRECEDENCE + 1);
+            }
+
+            text.push_str(if *inclusive { "..=" } else { ".." });
+
+            if let Some(end) = end {
+                print_unbraced(text, |text| print_operand(text, expressions, *end, RANGE_PRECEDENCE + 1));
+            }
+        },
+        Expression::Index {
+            target,
+            index,
+            ..
+        } => {
+            print_operand(text, expressions, *target, PREFIX_PRECEDENCE + 1);
+            text.push('[');
+            print_expression(text, expressions, *index);
+            text.push(']');
+        },
+        Expression::Match {
+            scrutinee,
+            arms,
+            ..
+        } => {
+            text.push_str("match ");
+            print_expression(text, expressions, *scrutinee);
+
+            if arms.is_empty() {
+                text.push_str(" {}");
+                return;
+            }
+
+            text.push_str(" { ");
+
+            for (index, arm) in arms.iter().enumerate() {
+                if index > 0 {
+                    text.push_str(", ");
+                }
+
+                print_pattern(text, &arm.pattern);
+                text.push_str(" => ");
+                print_expression(text, expressions, arm.value);
+            }
+
+            text.push_str(" }");
+        },
+    }
+}
+
+/// Print a pattern as written in source code.
+pub fn format_pattern(pattern: &Pattern) -> String {
+    let mut text = String::new();
+
+    print_pattern(&mut text, pattern);
+
+    text
+}
+
+fn print_pattern(text: &mut String, pattern: &Pattern) {
+    match pattern {
+        Pattern::Wildcard => text.push('_'),
+        Pattern::Identifier {
+            name,
+            ..
+        } => text.push_str(name),
+        Pattern::Variant {
+            name,
+            fields,
+            ..
+        } => {
+            text.push_str(name);
+            text.push('(');
+
+            for (index, field) in fields.iter().enumerate() {
+                if index > 0 {
+                    text.push_str(", ");
+                }
+
+                print_pattern(text, field);
+            }
+
+            text.push(')');
+        },
+        Pattern::Array(elements) => {
+            text.push('[');
+
+            for (index, element) in elements.iter().enumerate() {
+                if index > 0 {
+                    text.push_str(", ");
+                }
+
+                print_pattern(text, element);
+            }
+
+            text.push(']');
+        },
+        Pattern::Number(num) => text.push_str(&num.to_string()),
+        Pattern::String(str) => {
+            text.push('"');
+            text.push_str(str);
+            text.push('"');
+        },
+        Pattern::Nil => text.push_str("nil"),
+    }
+}
+
+/// Print the arguments of a call with their brackets.
+fn print_arguments(text: &mut String, expressions: &[Expression], arguments: &[Argument]) {
+    text.push('(');
+
+    for (index, argument) in arguments.iter().enumerate() {
+        if index > 0 {
+            text.push_str(", ");
+        }
+
+        match &argument.name {
+            Some(name) => {
+                text.push_str(name);
+                text.push_str(" = ");
+                print_expression(text, expressions, argument.value);
+            },
+
+            /* An unnamed assignment would read as a named argument. */
+            None => print_operand(text, expressions, argument.value, BinaryOperator::Assign.precedence() + 1),
+        }
+    }
+
+    text.push(')');
+}
+
+/// Print an operand, parenthesized if it binds looser than `level`.
+/// Print what `print` prints where a `{` would start a block, like at
+/// the start of a statement or after `..`, in parentheses if it starts
+/// with a map.
+fn print_unbraced(text: &mut String, print: impl FnOnce(&mut String)) {
+    let mut printed = String::new();
+
+    print(&mut printed);
+
+    if printed.starts_with('{') {
+        text.push('(');
+        text.push_str(&printed);
+        text.push(')');
+    } else {
+        text.push_str(&printed);
+    }
+}
+
+/// Print what `print` prints before a token that can't end a range,
+/// like an operator or `:`, in parentheses if it ends with a range
+/// without an end.
+fn print_closed(text: &mut String, print: impl FnOnce(&mut String)) {
+    let mut printed = String::new();
+
+    print(&mut printed);
+
+    if printed.ends_with("..") {
+        text.push('(');
+        text.push_str(&printed);
+        text.push(')');
+    } else {
+        text.push_str(&printed);
+    }
+}
+
+fn print_operand(text: &mut String, expressions: &[Expression], operand: ExprId, level: u8) {
+    match &expressions[operand.index()] {
+        Expression::BinaryOperation {
+            operator,
+            ..
+        } if operator.precedence() < level => {
+            text.push('(');
+            print_expression(text, expressions, operand);
+            text.push(')');
+        },
+        Expression::UnaryOperation { .. } if PREFIX_PRECEDENCE < level => {
+            text.push('(');
+            print_expression(text, expressions, operand);
+            text.push(')');
+        },
+        Expression::Range { .. } if RANGE_PRECEDENCE < level => {
+            text.push('(');
+            print_expression(text, expressions, operand);
+            text.push(')');
+        },
+        Expression::Cast { .. } if CAST_PRECEDENCE < level => {
+            text.push('(');
+            print_expression(text, expressions, operand);
+            text.push(')');
+        },
+        _ => print_expression(text, expressions, operand),
+    }
+}
+
+fn print_type(text: &mut String, expressions: &[Expression], r#type: &Type) {
+    match r#type {
+        Type::Named(name) => text.push_str(name),
+        Type::Unit => text.push_str("()"),
+        Type::Nullable(r#type) => {
+            print_type(text, expressions, r#type);
+            text.push('?');
+        },
+        Type::Array {
+            element,
+            size,
+        } => {
+            text.push('[');
+            print_type(text, expressions, element);
+            text.push_str("; ");
+            print_expression(text, expressions, *size);
+            text.push(']');
+        },
+        Type::Slice(element) => {
+            text.push('[');
+            print_type(text, expressions, element);
+            text.push(']');
+        },
+        Type::Map {
+            key,
+            value,
+        } => {
+            text.push_str("map[");
+            print_type(text, expressions, key);
+            text.push_str(", ");
+            print_type(text, expressions, value);
+            text.push(']');
+        },
+    }
+}
+
+fn print_parameter(text: &mut String, expressions: &[Expression], parameter: &Parameter) {
+    text.push_str(&parameter.name);
+
+    if let Some(r#type) = &parameter.r#type {
+        text.push_str(": ");
+        print_type(text, expressions, r#type);
+    }
+}
+
+/// Print `func`, the name, the parameters and the return type of a
+/// function.
+fn print_signature(
+    text: &mut String,
+    expressions: &[Expression],
+    name: &str,
+    parameters: &[Parameter],
+    return_type: &Option<Type>
+) {
+    text.push_str("func ");
+    text.push_str(name);
+    text.push('(');
+
+    for (index, parameter) in parameters.iter().enumerate() {
+        if index > 0 {
+            text.push_str(", ");
+        }
+
+        print_parameter(text, expressions, parameter);
+    }
+
+    text.push(')');
+
+    if let Some(return_type) = return_type {
+        text.push_str(" -> ");
+        print_type(text, expressions, return_type);
+    }
+}
+
+struct Printer<'a> {
+    program: &'a Program,
+    text: String,
+    depth: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn new(program: &'a Program) -> Printer<'a> {
+        Printer {
+            program,
+            text: String::new(),
+            depth: 0,
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.text.push_str(INDENT);
+        }
+    }
+
+    /// Print the attributes one per line, set apart from the
+    /// statements by a blank line.
+    fn print_attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            self.print_attribute("#![", attribute);
+        }
+
+        if !attributes.is_empty() && !self.program.statements.is_empty() {
+            self.text.push('\n');
+        }
+    }
+
+    /// Print the attribute after `opening` and end the line.
+    fn print_attribute(&mut self, opening: &str, attribute: &Attribute) {
+        self.text.push_str(opening);
+        self.text.push_str(&attribute.name);
+
+        if !attribute.arguments.is_empty() {
+            self.text.push('(');
+            self.text.push_str(&attribute.arguments.join(", "));
+            self.text.push(')');
+        }
+
+        self.text.push_str("]\n");
+    }
+
+    fn print_statements(&mut self, statements: &[Statement]) {
+        for (index, statement) in statements.iter().enumerate() {
+
+            /* Surround function definitions, implementation blocks,
+             * traits and enums with blank lines. */
+            if index > 0 {
+                let previous = &statements[index - 1];
+                let spaced = |statement: &Statement| matches!(statement.unattributed(),
+                    Statement::FunctionDefinition { .. } |
+                    Statement::MacroDefinition { .. } |
+                    Statement::Implementation { .. } |
+                    Statement::Trait { .. } |
+                    Statement::Enum { .. });
+
+                if spaced(statement) || spaced(previous) {
+                    self.text.push('\n');
+                }
+            }
+
+            self.print_statement(statement);
+        }
+    }
+
+    /// Print `{`, the statements, and `}` without a trailing newline.
+    fn print_block(&mut self, statements: &[Statement]) {
+        if statements.is_empty() {
+            self.text.push_str("{}");
+            return;
+        }
+
+        self.text.push_str("{\n");
+        self.depth += 1;
+        self.print_statements(statements);
+        self.depth -= 1;
+        self.indent();
+        self.text.push('}');
+    }
+
+    fn print_statement(&mut self, statement: &Statement) {
+        self.indent();
+
+        match statement {
+            Statement::VariableDefinition {
+                identifier,
+                r#type,
+                value,
+                ..
+            } => {
+                self.text.push_str("let ");
+                self.text.push_str(identifier);
+
+                if let Some(r#type) = r#type {
+                    self.text.push_str(": ");
+                    print_type(&mut self.text, &self.program.expressions, r#type);
+                }
+
+                if let Some(value) = value {
+                    self.text.push_str(" = ");
+                    print_expression(&mut self.text, &self.program.expressions, *value);
+                }
+
+                self.text.push(';');
+            },
+            Statement::Destructuring {
+                pattern,
+                value,
+                ..
+            } => {
+                self.text.push_str("let ");
+                print_pattern(&mut self.text, pattern);
+                self.text.push_str(" = ");
+                print_expression(&mut self.text, &self.program.expressions, *value);
+                self.text.push(';');
+            },
+            Statement::ConstantDefinition {
+                identifier,
+                r#type,
+                value,
+                ..
+            } => {
+                self.text.push_str("const ");
+                self.text.push_str(identifier);
+
+                if let Some(r#type) = r#type {
+                    self.text.push_str(": ");
+                    print_type(&mut self.text, &self.program.expressions, r#type);
+                }
+
+                self.text.push_str(" = ");
+                print_expression(&mut self.text, &self.program.expressions, *value);
+                self.text.push(';');
+            },
+            Statement::FunctionDefinition {
+                callee_name,
+                parameters,
+                return_type,
+                statements,
+                ..
+            } => {
+                print_signature(&mut self.text, &self.program.expressions, callee_name, parameters, return_type);
+                self.text.push(' ');
+                self.print_block(statements);
+            },
+            Statement::Return {
+                expression,
+                implicit: true,
+            } => print_unbraced(&mut self.text, |text| print_expression(text, &self.program.expressions, *expression)),
+            Statement::Return {
+                expression,
+                ..
+            } => {
+                self.text.push_str("return ");
+                print_expression(&mut self.text, &self.program.expressions, *expression);
+                self.text.push(';');
+            },
+            Statement::Expression {
+                expression,
+            } => {
+                print_unbraced(&mut self.text, |text| print_expression(text, &self.program.expressions, *expression));
+                self.text.push(';');
+            },
+            Statement::Block {
+                statements,
+            } => self.print_block(statements),
+            Statement::MacroDefinition {
+                name,
+                parameters,
+                statements,
+                ..
+            } => {
+                self.text.push_str("macro ");
+                self.text.push_str(name);
+                self.text.push('(');
+                self.text.push_str(&parameters.join(", "));
+                self.text.push_str(") ");
+                self.print_block(statements);
+            },
+            Statement::Conditional {
+                symbol,
+                statements,
+                otherwise,
+                ..
+            } => {
+                self.text.push_str("#if ");
+                self.text.push_str(symbol);
+                self.text.push(' ');
+                self.print_block(statements);
+
+                if !otherwise.is_empty() {
+                    self.text.push_str(" #else ");
+                    self.print_block(otherwise);
+                }
+            },
+            Statement::Attributed {
+                attributes,
+                statement,
+                ..
+            } => {
+                for (index, attribute) in attributes.iter().enumerate() {
+                    if index > 0 {
+                        self.indent();
+                    }
+
+                    self.print_attribute("#[", attribute);
+                }
+
+                self.print_statement(statement);
+
+                /* The statement ended the line already. */
+                return;
+            },
+            Statement::For {
+                variable,
+                iterable,
+                statements,
+                ..
+            } => {
+                self.text.push_str("for ");
+                self.text.push_str(variable);
+                self.text.push_str(" in ");
+                print_expression(&mut self.text, &self.program.expressions, *iterable);
+                self.text.push(' ');
+                self.print_block(statements);
+            },
+            Statement::Implementation {
+                type_name,
+                trait_name,
+                functions,
+                ..
+            } => {
+                self.text.push_str("impl ");
+
+                if let Some(trait_name) = trait_name {
+                    self.text.push_str(trait_name);
+                    self.text.push_str(" for ");
+                }
+
+                self.text.push_str(type_name);
+                self.text.push(' ');
+                self.print_block(functions);
+            },
+            Statement::Trait {
+                name,
+                methods,
+                ..
+            } => {
+                self.text.push_str("trait ");
+                self.text.push_str(name);
+
+                if methods.is_empty() {
+                    self.text.push_str(" {}");
+                } else {
+                    self.text.push_str(" {\n");
+                    self.depth += 1;
+
+                    for method in methods {
+                        self.indent();
+                        print_signature(&mut self.text, &self.program.expressions, &method.name, &method.parameters, &method.return_type);
+                        self.text.push_str(";\n");
+                    }
+
+                    self.depth -= 1;
+                    self.indent();
+                    self.text.push('}');
+                }
+            },
+            Statement::Enum {
+                name,
+                variants,
+                ..
+            } => {
+                self.text.push_str("enum ");
+                self.text.push_str(name);
+
+                if variants.is_empty() {
+                    self.text.push_str(" {}");
+                } else {
+                    self.text.push_str(" {\n");
+                    self.depth += 1;
+
+                    for variant in variants {
+                        self.indent();
+                        self.text.push_str(&variant.name);
+
+                        if !variant.fields.is_empty() {
+                            let fields: Vec<String> = variant.fields.iter()
+                                .map(|field| format_type(&self.program.expressions, field))
+                                .collect();
+
+                            self.text.push('(');
+                            self.text.push_str(&fields.join(", "));
+                            self.text.push(')');
+                        }
+
+                        self.text.push_str(",\n");
+                    }
+
+                    self.depth -= 1;
+                    self.indent();
+                    self.text.push('}');
+                }
+            },
+        }
+
+        self.text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_str;
+    use crate::parser::parse_source;
+    use super::*;
+
+    macro_rules! format_source {
+        ($text:expr) => {{
+            format_program(compile_str($text).unwrap().program())
+        }};
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(format_source!("let a:int=1;let b ; let c:int ?=nil; const D :int=2*3; let e : [ [int;2] ; D]; let f:map [string,[int]]={ \"a\" :[1] , \"b\":[ ] , }; func add_num(a:int,b)->int{return a+b;} {} { b = add_num(a, 2); } for x in e[0]{b=b+x;}"), "\
+let a: int = 1;
+let b;
+let c: int? = nil;
+const D: int = 2 * 3;
+let e: [[int; 2]; D];
+let f: map[string, [int]] = {\"a\": [1], \"b\": []};
+
+func add_num(a: int, b) -> int {
+    return a + b;
+}
+
+{}
+{
+    b = add_num(a, 2);
+}
+for x in e[0] {
+    b = b + x;
+}
+");
+        assert_eq!(format_source!("func log(x)->( ){return ( );}"), "\
+func log(x) -> () {
+    return ();
+}
+");
+        assert_eq!(format_source!("func double(x)->int{let y=x*2; y}"), "\
+func double(x) -> int {
+    let y = x * 2;
+    y
+}
+");
+    }
+
+    #[test]
+    fn implementation() {
+        assert_eq!(format_source!("let a = 1; impl int { func double(self)->int{return self*2;} func half(self) {} } a.double();"), "\
+let a = 1;
+
+impl int {
+    func double(self) -> int {
+        return self * 2;
+    }
+
+    func half(self) {}
+}
+
+a.double();
+");
+    }
+
+    #[test]
+    fn traits() {
+        assert_eq!(format_source!("trait Shape{func area(self)->int;func scale(self,by:int);} trait Empty {} impl Shape for int{func area(self)->int{return self;} func scale(self,by:int){}}"), "\
+trait Shape {
+    func area(self) -> int;
+    func scale(self, by: int);
+}
+
+trait Empty {}
+
+impl Shape for int {
+    func area(self) -> int {
+        return self;
+    }
+
+    func scale(self, by: int) {}
+}
+");
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(format_source!("enum Shape{Circle(int),Rect(int,int)} enum Empty{} area=match s{Circle(r)=>r*r,Rect(w,_)=>w,\"x\"=>-1,nil=>0,};"), "\
+enum Shape {
+    Circle(int),
+    Rect(int, int),
+}
+
+enum Empty {}
+
+area = match s { Circle(r) => r * r, Rect(w, _) => w, \"x\" => -1, nil => 0 };
+");
+        assert_eq!(format_source!("let[a,Circle( r ),_]=shapes;"), "let [a, Circle(r), _] = shapes;\n");
+    }
+
+    #[test]
+    fn attributes() {
+        assert_eq!(format_source!("#![no_std_runtime] #![ allow( unreachable_arms ,unreachable_arms)] let a=1;"), "\
+#![no_std_runtime]
+#![allow(unreachable_arms, unreachable_arms)]
+
+let a = 1;
+");
+        assert_eq!(format_source!("#![no_std_runtime]"), "#![no_std_runtime]\n");
+        assert_eq!(format_program(&parse_source("let a=1; # [ allow(unreachable_arms) ]#[deny( warnings )] func f(){ #[warn(unreachable_arms)] a; }").unwrap()), "\
+let a = 1;
+
+#[allow(unreachable_arms)]
+#[deny(warnings)]
+func f() {
+    #[warn(unreachable_arms)]
+    a;
+}
+");
+    }
+
+    #[test]
+    fn conditionals() {
+
+        /* Formatting keeps every branch, so the source is parsed
+         * without dropping the branches not taken. */
+        assert_eq!(format_program(&parse_source("#if DEBUG{print(1);}#else{ } # if  DEBUG {} #else {print(2);}").unwrap()), "\
+#if DEBUG {
+    print(1);
+}
+#if DEBUG {} #else {
+    print(2);
+}
+");
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(format_source!("value = (factor + 9) / 17;"), "value = (factor + 9) / 17;\n");
+        assert_eq!(format_source!("value = ((a * b) + c);"), "value = a * b + c;\n");
+        assert_eq!(format_source!("value = a - (b - c);"), "value = a - (b - c);\n");
+        assert_eq!(format_source!("check = (a == b) == (c != d);"), "check = (a == b) == (c != d);\n");
+        assert_eq!(format_source!("a = (b = c);"), "a = b = c;\n");
+        assert_eq!(format_source!("(a = b) = c;"), "(a = b) = c;\n");
+        assert_eq!(format_source!("value = -(a + b) * -c;"), "value = -(a + b) * -c;\n");
+        assert_eq!(format_source!("draw((x = 3), y = (y = 7));"), "draw((x = 3), y = y = 7);\n");
+        assert_eq!(format_source!("value = (-a).abs() + -b.abs();"), "value = (-a).abs() + -b.abs();\n");
+        assert_eq!(format_source!("value = (-a)? + -b.abs()? + (a + b)?;"), "value = (-a)? + -b.abs()? + (a + b)?;\n");
+        assert_eq!(format_source!("value = (-a as int) * (b as int as string) - -(c as int) + (d + e) as int;"), "value = -a as int * b as int as string - -(c as int) + (d + e) as int;\n");
+        assert_eq!(format_source!("value = (a as int).abs() + (a as int)?;"), "value = (a as int).abs() + (a as int)?;\n");
+        assert_eq!(format_source!("value = (-a)[0] + -b[1][c + 1] + [1,2,][0];"), "value = (-a)[0] + -b[1][c + 1] + [1, 2][0];\n");
+        assert_eq!(format_source!("value = a[1 .. n+1][..2][1..][..] as [ int ];"), "value = a[1..n + 1][..2][1..][..] as [int];\n");
+        assert_eq!(format_source!("value = (0..=n) == (a == (b..)) + (..2);"), "value = 0..=n == (a == b..) + (..2);\n");
+
+        /* Open ranges end before few tokens, and `{` may start a block. */
+        assert_eq!(format_source!("(a == (b..)) = {(c..): 1};"), "(a == b..) = {(c..): 1};\n");
+        assert_eq!(format_source!("({\"k\": 1})[\"k\"]; value = a..({});"), "({\"k\": 1}[\"k\"]);\nvalue = a..({});\n");
+    }
+}
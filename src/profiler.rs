@@ -0,0 +1,187 @@
+use crate::interpreter::{Hooks, RuntimeError};
+use crate::span::Span;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Time spent in one Fang function over a run.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Profile {
+    pub calls: usize,
+
+    /// Time from entering the function to leaving it, counting the
+    /// calls it made but only the outermost of recursive calls.
+    pub total: Duration,
+
+    /// Time spent in the function's own body, leaving out the calls it
+    /// made.
+    pub own: Duration,
+}
+
+/// Call of a function being timed.
+struct Call {
+    function: String,
+    start: Instant,
+
+    /// Time spent in the calls it made so far.
+    callees: Duration,
+}
+
+/// Hooks timing every call of a Fang function.
+///
+/// Besides the time of each function, the profiler records the time
+/// spent in each chain of calls, which [`Profiler::folded`] writes out
+/// for flame graph tools.
+pub struct Profiler {
+    calls: Vec<Call>,
+    profiles: HashMap<String, Profile>,
+
+    /// Own time of the calls at the end of each chain of calls, keyed by
+    /// the names of the functions in the chain joined by `;`.
+    stacks: HashMap<String, Duration>,
+}
+
+impl Default for Profiler {
+    fn default() -> Profiler {
+        Profiler::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            calls: Vec::new(),
+            profiles: HashMap::new(),
+            stacks: HashMap::new(),
+        }
+    }
+
+    /// Profiles of the functions called, by decreasing own time.
+    pub fn profiles(&self) -> Vec<(&str, Profile)> {
+        let mut profiles: Vec<(&str, Profile)> = self.profiles.iter()
+            .map(|(name, profile)| (name.as_str(), *profile))
+            .collect();
+
+        profiles.sort_by(|(name, profile), (other_name, other)| {
+            other.own.cmp(&profile.own).then(name.cmp(other_name))
+        });
+        profiles
+    }
+
+    /// Table of the profiles of the functions called, by decreasing own
+    /// time.
+    pub fn report(&self) -> String {
+        let profiles = self.profiles();
+        let width = profiles.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("function".len());
+        let mut text = format!("{:<width$}  {:>8}  {:>12}  {:>12}\n", "function", "calls", "total", "self");
+
+        for (name, profile) in profiles {
+            text.push_str(&format!("{:<width$}  {:>8}  {:>12}  {:>12}\n",
+                                   name, profile.calls, format_duration(profile.total), format_duration(profile.own)));
+        }
+
+        text
+    }
+
+    /// Chains of calls in the folded format of flame graph tools, one
+    /// per line with the microseconds spent at its end.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<(&String, &Duration)> = self.stacks.iter().collect();
+        let mut text = String::new();
+
+        stacks.sort();
+
+        for (stack, time) in stacks {
+            text.push_str(&format!("{} {}\n", stack, time.as_micros()));
+        }
+
+        text
+    }
+}
+
+/// Duration in milliseconds with three decimals.
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+impl Hooks for Profiler {
+    fn enter_function(&mut self, name: &str, _: Span) -> Result<(), RuntimeError> {
+        self.calls.push(Call {
+            function: name.to_owned(),
+            start: Instant::now(),
+            callees: Duration::ZERO,
+        });
+
+        Ok(())
+    }
+
+    fn exit_function(&mut self, _: &str) {
+        let call = match self.calls.pop() {
+            Some(call) => call,
+            None => return,
+        };
+        let elapsed = call.start.elapsed();
+        let own = elapsed.saturating_sub(call.callees);
+        let names: Vec<&str> = self.calls.iter()
+            .map(|call| call.function.as_str())
+            .chain([call.function.as_str()])
+            .collect();
+        let recursive = self.calls.iter().any(|caller| caller.function == call.function);
+        let profile = self.profiles.entry(call.function.to_owned()).or_default();
+
+        profile.calls += 1;
+        profile.own += own;
+
+        if !recursive {
+            profile.total += elapsed;
+        }
+
+        *self.stacks.entry(names.join(";")).or_default() += own;
+
+        if let Some(caller) = self.calls.last_mut() {
+            caller.callees += elapsed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_str;
+    use crate::interpreter::Env;
+    use super::*;
+
+    #[test]
+    fn calls_and_stacks() {
+        let program = compile_str("
+            func leaf(value) {
+                return value + 1;
+            }
+            func branch(value) {
+                let left = leaf(value);
+                let right = leaf(left);
+                return right;
+            }
+            func tail(value) {
+                return branch(value);
+            }
+            branch(1);
+            tail(2);
+        ").unwrap();
+        let mut profiler = Profiler::new();
+
+        program.eval_with_hooks(&mut Env::new(), &mut profiler).unwrap();
+
+        let calls: HashMap<&str, usize> = profiler.profiles().into_iter().map(|(name, profile)| (name, profile.calls)).collect();
+        assert_eq!(calls, HashMap::from([("leaf", 4), ("branch", 2), ("tail", 1)]));
+
+        for (_, profile) in profiler.profiles() {
+            assert!(profile.own <= profile.total);
+        }
+
+        /* The function of a tail call runs in place of its caller. */
+        let folded = profiler.folded();
+
+        let stacks: Vec<&str> = folded.lines().map(|line| line.rsplit_once(' ').unwrap().0).collect();
+        assert_eq!(stacks, ["branch", "branch;leaf", "tail"]);
+        assert!(profiler.report().starts_with("function  "));
+    }
+}
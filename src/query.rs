@@ -0,0 +1,378 @@
+use crate::diagnostic::{Diagnostics, LintLevels};
+use crate::frontend::{merge_programs, split_diagnostics};
+use crate::parser::{parse_source_with, ParseOptions, Program};
+use crate::resolver::{resolve, SymbolIndex};
+use crate::span::{FileId, SourceFile, SourceMap};
+use crate::{compile_program, CompiledProgram};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Version of the inputs of a [`Database`], bumped by every change of
+/// the text of a file.
+pub type Revision = u64;
+
+/// Text of a file with the revision it last changed at.
+struct Input {
+    text: Rc<String>,
+    changed_at: Revision,
+}
+
+/// Value a query computed for a file.
+struct Memo<T> {
+    value: Rc<T>,
+
+    /// Revision the value last became different at. Queries computed
+    /// from it stay valid as long as it doesn't change.
+    changed_at: Revision,
+
+    /// Latest revision the value was found to be up to date at.
+    verified_at: Revision,
+}
+
+/// Result of the `compile` query.
+pub type Compiled = Result<CompiledProgram, Diagnostics>;
+
+/// Result of the `compile_unit` query.
+#[derive(PartialEq, Debug)]
+pub struct CompiledUnit {
+
+    /// Program of all files, `None` if any has errors.
+    pub program: Option<CompiledProgram>,
+
+    /// Diagnostics of each file that reported any, in the order of the
+    /// files, with their spans within the text of their file.
+    pub diagnostics: Vec<(String, Diagnostics)>,
+}
+
+/// Memoized passes of the frontend over a set of files, so that after
+/// an edit only the passes over what changed run again.
+///
+/// Each file goes through three queries, each computed from the one
+/// before: `parse` lexes and parses its text, `compile` runs the checks
+/// of [`compile_program`], and `symbols` resolves its names. A query
+/// runs again only if what it was computed from changed since the
+/// last time it was asked for, and when it then gives the same value
+/// as before, the queries computed from it are kept too. An edit that
+/// moves no token, like one within a trailing comment, thus parses
+/// again but doesn't check or resolve again.
+///
+/// Files compiled together, as the frontend does, go through
+/// `compile_unit` instead, which merges their parsed programs and
+/// checks them as one, so that a macro, constant or enum of one file
+/// serves the others. There is no type checker yet to make a query of.
+pub struct Database {
+    revision: Revision,
+    options: ParseOptions,
+    symbols: Vec<String>,
+    lint_levels: LintLevels,
+    inputs: HashMap<String, Input>,
+    parsed: HashMap<String, Memo<Result<Program, Diagnostics>>>,
+    compiled: HashMap<String, Memo<Compiled>>,
+
+    /// Units by their files, one per line.
+    units: HashMap<String, Memo<CompiledUnit>>,
+    indexes: HashMap<String, Memo<SymbolIndex>>,
+
+    /// Number of times a query ran rather than reusing its memo.
+    executions: usize,
+}
+
+/// Value of the query of `file` memoized in `memos`, computing it
+/// again if what it's computed from changed after `input_changed_at`
+/// since it was last verified. Returns the value with the revision it
+/// last changed at.
+fn fetch<T: PartialEq>(
+    memos: &mut HashMap<String, Memo<T>>,
+    file: &str,
+    revision: Revision,
+    input_changed_at: Revision,
+    executions: &mut usize,
+    compute: impl FnOnce() -> T
+) -> (Rc<T>, Revision) {
+    if let Some(memo) = memos.get_mut(file) {
+        if input_changed_at <= memo.verified_at {
+            memo.verified_at = revision;
+
+            return (memo.value.clone(), memo.changed_at);
+        }
+    }
+
+    *executions += 1;
+    let value: Rc<T> = Rc::new(compute());
+
+    /* A value equal to the previous one doesn't invalidate the queries
+     * computed from it. */
+    let changed_at: Revision = match memos.get(file) {
+        Some(memo) if memo.value == value => memo.changed_at,
+        _ => revision,
+    };
+
+    memos.insert(file.to_owned(), Memo {
+        value: value.clone(),
+        changed_at,
+        verified_at: revision,
+    });
+
+    (value, changed_at)
+}
+
+impl Database {
+
+    /// Database parsing files with `options`, keeping the blocks of
+    /// conditional compilation for `symbols` and reporting the lints at
+    /// `lint_levels`.
+    pub fn new(options: ParseOptions, symbols: Vec<String>, lint_levels: LintLevels) -> Database {
+        Database {
+            revision: 0,
+            options,
+            symbols,
+            lint_levels,
+            inputs: HashMap::new(),
+            parsed: HashMap::new(),
+            compiled: HashMap::new(),
+            units: HashMap::new(),
+            indexes: HashMap::new(),
+            executions: 0,
+        }
+    }
+
+    /// Current revision of the inputs.
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Number of times a query ran rather than reusing its memo.
+    pub fn executions(&self) -> usize {
+        self.executions
+    }
+
+    /// Set the text of `file`, starting a new revision if it changed.
+    pub fn set_text(&mut self, file: &str, text: &str) {
+        if let Some(input) = self.inputs.get(file) {
+            if input.text.as_str() == text {
+                return;
+            }
+        }
+
+        self.revision += 1;
+        self.inputs.insert(file.to_owned(), Input {
+            text: Rc::new(text.to_owned()),
+            changed_at: self.revision,
+        });
+    }
+
+    /// Forget `file` and everything computed from it.
+    pub fn remove(&mut self, file: &str) {
+        if self.inputs.remove(file).is_some() {
+            self.revision += 1;
+        }
+
+        self.parsed.remove(file);
+        self.compiled.remove(file);
+        self.indexes.remove(file);
+        self.units.retain(|files, _| !files.lines().any(|unit_file| unit_file == file));
+    }
+
+    /// Text of `file`, if it was set.
+    pub fn text(&self, file: &str) -> Option<Rc<String>> {
+        self.inputs.get(file).map(|input| input.text.clone())
+    }
+
+    fn input(&self, file: &str) -> &Input {
+        match self.inputs.get(file) {
+            Some(input) => input,
+            None => panic!("No text was set for {}!", file),
+        }
+    }
+
+    fn fetch_parse(&mut self, file: &str) -> (Rc<Result<Program, Diagnostics>>, Revision) {
+        let input = self.input(file);
+        let (text, changed_at) = (input.text.clone(), input.changed_at);
+        let options = self.options;
+
+        fetch(&mut self.parsed, file, self.revision, changed_at, &mut self.executions, || {
+            parse_source_with(&text, options)
+        })
+    }
+
+    fn fetch_compile(&mut self, file: &str) -> (Rc<Compiled>, Revision) {
+        let (parsed, changed_at) = self.fetch_parse(file);
+        let symbols = &self.symbols;
+        let lint_levels = &self.lint_levels;
+
+        fetch(&mut self.compiled, file, self.revision, changed_at, &mut self.executions, || {
+            match parsed.as_ref() {
+                Ok(program) => compile_program(program.clone(), symbols, lint_levels),
+                Err(diagnostics) => Err(diagnostics.clone()),
+            }
+        })
+    }
+
+    /// Program parsed from the text of `file`.
+    ///
+    /// # Panics
+    ///
+    /// If no text was set for `file`, as for every query.
+    pub fn parse(&mut self, file: &str) -> Rc<Result<Program, Diagnostics>> {
+        self.fetch_parse(file).0
+    }
+
+    /// Program of `file` checked as by [`compile_program`].
+    pub fn compile(&mut self, file: &str) -> Rc<Compiled> {
+        self.fetch_compile(file).0
+    }
+
+    /// Program of `files` compiled as one unit, as by the frontend, their
+    /// statements following the order of `files`.
+    pub fn compile_unit(&mut self, files: &[String]) -> Rc<CompiledUnit> {
+        let mut parsed: Vec<Rc<Result<Program, Diagnostics>>> = Vec::new();
+        let mut changed_at: Revision = 0;
+
+        for file in files {
+            let (program, program_changed_at) = self.fetch_parse(file);
+
+            parsed.push(program);
+            changed_at = changed_at.max(program_changed_at);
+        }
+
+        let inputs = &self.inputs;
+        let symbols = &self.symbols;
+        let lint_levels = &self.lint_levels;
+
+        fetch(&mut self.units, &files.join("\n"), self.revision, changed_at, &mut self.executions, || {
+            let mut sources = SourceMap::new();
+            let ids: Vec<FileId> = files.iter()
+                .map(|file| sources.add(SourceFile::new(file.as_str(), inputs[file].text.as_str())))
+                .collect();
+            let mut programs: Vec<(FileId, Program)> = Vec::new();
+            let mut unit = CompiledUnit {
+                program: None,
+                diagnostics: Vec::new(),
+            };
+
+            for ((file, id), program) in files.iter().zip(&ids).zip(&parsed) {
+                match program.as_ref() {
+                    Ok(program) => programs.push((*id, program.clone())),
+                    Err(diagnostics) => unit.diagnostics.push((file.to_owned(), diagnostics.clone())),
+                }
+            }
+
+            if !unit.diagnostics.is_empty() {
+                return unit;
+            }
+
+            let diagnostics = match compile_program(merge_programs(&sources, programs), symbols, lint_levels) {
+                Ok(program) => {
+                    let warnings = program.warnings().clone();
+
+                    unit.program = Some(program);
+                    warnings
+                },
+                Err(diagnostics) => diagnostics,
+            };
+
+            unit.diagnostics = split_diagnostics(&sources, &ids, &diagnostics).into_iter()
+                .map(|(id, diagnostics)| (files[id.0 as usize].to_owned(), diagnostics))
+                .collect();
+            unit
+        }).0
+    }
+
+    /// Symbols of the program of `file`, empty if it has errors.
+    pub fn symbols(&mut self, file: &str) -> Rc<SymbolIndex> {
+        let (compiled, changed_at) = self.fetch_compile(file);
+
+        fetch(&mut self.indexes, file, self.revision, changed_at, &mut self.executions, || {
+            match compiled.as_ref() {
+                Ok(program) => resolve(program.program()),
+                Err(_) => SymbolIndex::default(),
+            }
+        }).0
+    }
+
+    /// Errors of `file` if it doesn't compile, else its warnings.
+    pub fn diagnostics(&mut self, file: &str) -> Diagnostics {
+        match self.compile(file).as_ref() {
+            Ok(program) => program.warnings().clone(),
+            Err(diagnostics) => diagnostics.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Database {
+        Database::new(ParseOptions::default(), Vec::new(), LintLevels::new())
+    }
+
+    #[test]
+    fn memoized_queries() {
+        let mut database = database();
+
+        database.set_text("main.fang", "let a = 1; print(a);");
+        assert!(database.compile("main.fang").is_ok());
+        database.symbols("main.fang");
+        database.symbols("main.fang");
+        assert_eq!(database.executions(), 3);
+
+        /* Nothing changed. */
+        database.set_text("main.fang", "let a = 1; print(a);");
+        database.symbols("main.fang");
+        assert_eq!(database.revision(), 1);
+        assert_eq!(database.executions(), 3);
+
+        /* Every query depends on the text. */
+        database.set_text("main.fang", "let b = 1; print(b);");
+        database.symbols("main.fang");
+        assert_eq!(database.executions(), 6);
+
+        /* A trailing comment moves no token, so the program is the same. */
+        database.set_text("main.fang", "let b = 1; print(b); // done");
+        database.symbols("main.fang");
+        assert_eq!(database.executions(), 7);
+    }
+
+    #[test]
+    fn files_are_independent() {
+        let mut database = database();
+
+        database.set_text("a.fang", "let a = 1;");
+        database.set_text("b.fang", "let b = ;");
+        database.symbols("a.fang");
+        database.symbols("b.fang");
+        assert_eq!(database.executions(), 6);
+        assert!(database.diagnostics("b.fang").has_errors());
+
+        database.set_text("b.fang", "let b = 2;");
+        database.symbols("a.fang");
+        database.symbols("b.fang");
+        assert_eq!(database.executions(), 9);
+        assert!(database.diagnostics("b.fang").is_empty());
+
+        database.remove("b.fang");
+        assert_eq!(database.text("b.fang"), None);
+        assert_eq!(database.text("a.fang").as_deref().map(String::as_str), Some("let a = 1;"));
+    }
+
+    #[test]
+    fn units_share_definitions() {
+        let mut database = database();
+        let files = [String::from("m1.fang"), String::from("m2.fang")];
+
+        database.set_text("m1.fang", "macro twice(x) { x; x; }");
+        database.set_text("m2.fang", "twice(print(1));");
+        assert_eq!(database.compile_unit(&files).program.as_ref().map(|program| program.program().statements.len()),
+                   Some(2));
+        assert_eq!(database.executions(), 3);
+
+        database.compile_unit(&files);
+        assert_eq!(database.executions(), 3);
+
+        database.set_text("m2.fang", "twice(print(1)");
+        assert_eq!(database.compile_unit(&files).diagnostics.iter().map(|(file, _)| file.as_str()).collect::<Vec<&str>>(),
+                   ["m2.fang"]);
+        assert_eq!(database.executions(), 5);
+    }
+}
@@ -0,0 +1,67 @@
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Pseudorandom generator behind the builtin `rand_int`, giving the same
+/// numbers for the same seed on every platform.
+///
+/// It's SplitMix64, fast and good enough for games and tests, but not
+/// for anything that must be unpredictable.
+pub struct Rng {
+    state: Cell<u64>,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng {
+            state: Cell::new(seed),
+        }
+    }
+
+    /// Generator seeded with the time of the system, so its numbers
+    /// differ from run to run.
+    pub fn from_time() -> Rng {
+        Rng::new(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_nanos() as u64))
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        let state = self.state.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut mixed = state;
+
+        self.state.set(state);
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        mixed ^ (mixed >> 31)
+    }
+
+    /// Number from `low` up to but not including `high`, like the range
+    /// `low..high`, or `None` if the range is empty.
+    pub fn int_in(&self, low: isize, high: isize) -> Option<isize> {
+        if low >= high {
+            return None;
+        }
+
+        let span = high.abs_diff(low) as u128;
+
+        /* Scaling keeps the bias below one in 2^64 / span. */
+        Some(low.wrapping_add(((self.next_u64() as u128 * span) >> 64) as isize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_numbers() {
+        let first = Rng::new(42);
+        let second = Rng::new(42);
+        let numbers: Vec<isize> = (0..100).map(|_| first.int_in(-3, 4).unwrap()).collect();
+
+        assert_eq!(numbers, (0..100).map(|_| second.int_in(-3, 4).unwrap()).collect::<Vec<isize>>());
+        assert!(numbers.iter().all(|number| (-3..4).contains(number)));
+        assert!((-3..4).all(|number| numbers.contains(&number)));
+
+        assert_eq!(first.int_in(1, 1), None);
+        assert!(first.int_in(isize::MIN, isize::MAX).is_some());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::compile_str;
+use crate::interpreter::{Env, Value};
+use crate::lexer::KEYWORDS;
+use crate::resolver::resolve;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::collections::BTreeSet;
+use std::env;
+use std::path::PathBuf;
+
+/// Name used for the REPL input in diagnostics.
+const INPUT_NAME: &str = "<repl>";
+
+/// File in the home directory keeping the input of past sessions.
+const HISTORY_NAME: &str = ".fang_history";
+
+/// Read-eval-print loop evaluating input in one persistent environment.
+pub struct Repl {
+    env: Env,
+
+    /// Input of a statement spanning several lines.
+    buffer: String,
+
+    /// Names of the global symbols defined so far.
+    names: BTreeSet<String>,
+}
+
+/// Helper of the line editor completing keywords and names in scope.
+struct Completion {
+    names: Vec<String>,
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .rfind(|char: char| !(char.is_alphanumeric() || char == '_'))
+            .map_or(0, |index| index + 1);
+        let prefix = &line[start..pos];
+
+        Ok((start, self.names.iter().filter(|name| name.starts_with(prefix)).cloned().collect()))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+/// Path of the history file, in the user's home directory.
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_NAME))
+}
+
+/// Whether the text leaves a `{` unclosed, ignoring string literals.
+fn is_incomplete(text: &str) -> bool {
+    let mut depth: isize = 0;
+    let mut in_string = false;
+
+    for byte in text.bytes() {
+        match byte {
+            b'"' => in_string = !in_string,
+            b'{' if !in_string => depth += 1,
+            b'}' if !in_string => depth -= 1,
+            _ => {},
+        }
+    }
+
+    in_string || depth > 0
+}
+
+impl Repl {
+    pub fn new(env: Env) -> Repl {
+        Repl {
+            env,
+            buffer: String::new(),
+            names: BTreeSet::new(),
+        }
+    }
+
+    /// Keywords and names of the global symbols defined so far, sorted,
+    /// that start with `prefix`.
+    pub fn completions(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = KEYWORDS.iter()
+            .map(|keyword| keyword.to_string())
+            .chain(self.names.iter().cloned())
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Feed one line of input.
+    ///
+    /// Returns `None` while the input is an incomplete statement,
+    /// otherwise the value it evaluated to or the rendered errors.
+    /// A missing `;` after the last statement is tolerated.
+    pub fn feed(&mut self, line: &str) -> Option<Result<Value, String>> {
+        let mut source: String;
+
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+
+        if is_incomplete(&self.buffer) {
+            return None;
+        }
+
+        source = std::mem::take(&mut self.buffer);
+
+        if !source.trim_end().ends_with([';', '}']) {
+            source = format!("{};\n", source.trim_end());
+        }
+
+        let program = match compile_str(&source) {
+            Ok(program) => program,
+            Err(diagnostics) => return Some(Err(diagnostics.render(INPUT_NAME, &source))),
+        };
+
+        eprint!("{}", program.warnings().render(INPUT_NAME, &source));
+
+        let result = program.eval(&mut self.env).map_err(|err| err.render(INPUT_NAME, &source));
+
+        /* Symbols local to functions aren't globals of the environment. */
+        for symbol in resolve(program.program()).symbols() {
+            if self.env.get(&symbol.name).is_some() {
+                self.names.insert(symbol.name.to_owned());
+            }
+        }
+
+        Some(result)
+    }
+
+    /// Run the loop on the standard input until it's closed, with line
+    /// editing, completion and the history of past sessions if it's a
+    /// terminal.
+    pub fn run(&mut self) {
+        let mut editor: Editor<Completion, FileHistory> = match Editor::new() {
+            Ok(editor) => editor,
+            Err(err) => {
+                eprintln!("Failed to start the line editor: {}", err);
+                return;
+            },
+        };
+        let history = history_path();
+
+        if let Some(path) = &history {
+            let _ = editor.load_history(path);
+        }
+
+        loop {
+            editor.set_helper(Some(Completion {
+                names: self.completions(""),
+            }));
+
+            let line = match editor.readline(if self.buffer.is_empty() { "> " } else { "... " }) {
+                Ok(line) => line,
+
+                /* Ctrl-C drops the statement being typed. */
+                Err(ReadlineError::Interrupted) => {
+                    self.buffer.clear();
+                    continue;
+                },
+                Err(_) => break,
+            };
+
+            if !line.trim().is_empty() {
+                let _ = editor.add_history_entry(line.as_str());
+            }
+
+            match self.feed(&line) {
+                None |
+                Some(Ok(Value::Unit)) => {},
+                Some(Ok(value)) => println!("{}", value),
+                Some(Err(text)) => eprint!("{}", text),
+            }
+        }
+
+        if let Some(path) = &history {
+            if let Err(err) = editor.save_history(path) {
+                eprintln!("Failed to save the history to \"{}\": {}", path.display(), err);
+            }
+        }
+
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_line_input() {
+        let mut repl = Repl::new(Env::new());
+
+        assert_eq!(repl.feed("func add_num(a, b) {"), None);
+        assert_eq!(repl.feed("    return a + b;"), None);
+        assert_eq!(repl.feed("}"), Some(Ok(Value::Unit)));
+        assert_eq!(repl.feed("let total = add_num(40, 7);"), Some(Ok(Value::Unit)));
+        assert_eq!(repl.feed("total"), Some(Ok(Value::Int(47))));
+        assert_eq!(repl.feed("let = 3;"),
+                   Some(Err(String::from("<repl>:1:5: error: Expected identifier!\n"))));
+    }
+
+    #[test]
+    fn completions() {
+        let mut repl = Repl::new(Env::with_builtins());
+
+        assert_eq!(repl.feed("func measure(length) { let local = length; return local; }"), Some(Ok(Value::Unit)));
+        assert_eq!(repl.feed("let meters = measure(3);"), Some(Ok(Value::Unit)));
+        assert_eq!(repl.completions("m"), ["macro", "match", "measure", "meters"]);
+        assert_eq!(repl.completions("l"), ["len", "let"]);
+    }
+}
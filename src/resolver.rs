@@ -0,0 +1,507 @@
+use crate::interpreter::PRELUDE;
+use crate::parser::{ExprId, Expression, Parameter, Pattern, Program, Statement, Type};
+use crate::span::Span;
+use std::collections::HashMap;
+
+/// Index of a symbol in its `SymbolIndex`.
+pub type SymbolId = usize;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SymbolKind {
+    Function,
+    Constant,
+    Variable,
+    Parameter,
+    Variant,
+
+    /// Builtin function of the prelude, defined outside the source.
+    Builtin,
+}
+
+/// Something a name can refer to.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+
+    /// Span of the name in the definition.
+    pub span: Span,
+}
+
+/// Symbols defined by a program and every resolved use of them.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SymbolIndex {
+    symbols: Vec<Symbol>,
+
+    /// Span of each use, with the symbol it refers to, in source order
+    /// within each scope.
+    uses: Vec<(Span, SymbolId)>,
+}
+
+impl SymbolIndex {
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id]
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// Symbol named at `offset`, by its definition or by a use, along
+    /// with the span of that name.
+    pub fn symbol_at(&self, offset: usize) -> Option<(SymbolId, Span)> {
+        let contains = |span: &Span| span.start <= offset && offset <= span.end;
+
+        if let Some(id) = self.symbols.iter().position(|symbol| symbol.kind != SymbolKind::Builtin && contains(&symbol.span)) {
+            return Some((id, self.symbols[id].span));
+        }
+
+        self.uses.iter()
+            .find(|(span, _)| contains(span))
+            .map(|(span, id)| (*id, *span))
+    }
+
+    /// Spans of every use of the symbol, excluding its definition.
+    pub fn references(&self, id: SymbolId) -> Vec<Span> {
+        let mut spans: Vec<Span> = self.uses.iter()
+            .filter(|(_, symbol)| *symbol == id)
+            .map(|(span, _)| *span)
+            .collect();
+
+        spans.sort_by_key(|span| span.start);
+
+        spans
+    }
+}
+
+type Scope = HashMap<String, SymbolId>;
+
+/// Name resolution following the scoping rules of the interpreter.
+///
+/// Function and constant definitions are hoisted to the start of their
+/// statement list and variables are visible from their definition on. Function
+/// bodies see their own parameters and locals, then the top-level
+/// definitions, but not the locals of enclosing functions.
+struct Resolver<'a> {
+    program: &'a Program,
+    index: SymbolIndex,
+    scopes: Vec<Scope>,
+
+    /// Function bodies left to resolve once the top level is known.
+    functions: Vec<(&'a [Parameter], &'a [Statement])>,
+}
+
+/// Resolve every name used in the program to its definition, or to the
+/// prelude unless the program opts out of it.
+///
+/// Names without a definition, such as globals the host defines, are
+/// left out.
+pub fn resolve(program: &Program) -> SymbolIndex {
+    let mut resolver = Resolver {
+        program,
+        index: SymbolIndex::default(),
+        scopes: vec![Scope::new()],
+        functions: Vec::new(),
+    };
+
+    if program.uses_prelude() {
+        for name in PRELUDE {
+            resolver.define(name, SymbolKind::Builtin, Span::default());
+        }
+    }
+
+    resolver.resolve_statements(&program.statements);
+    let globals: Scope = resolver.scopes.pop().unwrap();
+
+    while let Some((parameters, statements)) = resolver.functions.pop() {
+        resolver.scopes = vec![globals.clone(), Scope::new()];
+
+        for parameter in parameters {
+            resolver.define(&parameter.name, SymbolKind::Parameter, parameter.span);
+        }
+
+        resolver.resolve_statements(statements);
+    }
+
+    resolver.index
+}
+
+impl<'a> Resolver<'a> {
+    fn define(&mut self, name: &str, kind: SymbolKind, span: Span) {
+        let id = self.index.symbols.len();
+
+        self.index.symbols.push(Symbol {
+            name: name.to_owned(),
+            kind,
+            span,
+        });
+        self.scopes.last_mut().unwrap().insert(name.to_owned(), id);
+    }
+
+    fn use_name(&mut self, name: &str, span: Span) {
+        let id = self.scopes.iter().rev().find_map(|scope| scope.get(name));
+
+        if let Some(&id) = id {
+            self.index.uses.push((span, id));
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &'a [Statement]) {
+        for statement in statements.iter().map(Statement::unattributed) {
+            if let Statement::FunctionDefinition {
+                callee_name,
+                span,
+                ..
+            } = statement {
+                self.define(callee_name, SymbolKind::Function, *span);
+            }
+
+            if let Statement::Enum {
+                variants,
+                ..
+            } = statement {
+                for variant in variants {
+                    self.define(&variant.name, SymbolKind::Variant, variant.span);
+                }
+            }
+
+            if let Statement::ConstantDefinition {
+                identifier,
+                span,
+                ..
+            } = statement {
+                self.define(identifier, SymbolKind::Constant, *span);
+            }
+        }
+
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &'a Statement) {
+        match statement {
+            Statement::VariableDefinition {
+                identifier,
+                r#type,
+                value,
+                span,
+            } => {
+                if let Some(r#type) = r#type {
+                    self.resolve_type(r#type);
+                }
+
+                if let Some(value) = value {
+                    self.resolve_expression(*value);
+                }
+
+                self.define(identifier, SymbolKind::Variable, *span);
+            },
+            Statement::Destructuring {
+                pattern,
+                value,
+                ..
+            } => {
+                self.resolve_expression(*value);
+                self.resolve_pattern(pattern);
+            },
+            Statement::ConstantDefinition {
+                r#type,
+                value,
+                ..
+            } => {
+                if let Some(r#type) = r#type {
+                    self.resolve_type(r#type);
+                }
+
+                self.resolve_expression(*value);
+            },
+            Statement::FunctionDefinition {
+                parameters,
+                statements,
+                ..
+            } => self.functions.push((parameters, statements)),
+            Statement::Return {
+                expression,
+                ..
+            } |
+            Statement::Expression {
+                expression,
+            } => self.resolve_expression(*expression),
+            Statement::Block {
+                statements,
+            } => {
+                self.scopes.push(Scope::new());
+                self.resolve_statements(statements);
+                self.scopes.pop();
+            },
+            Statement::For {
+                variable,
+                iterable,
+                statements,
+                span,
+            } => {
+                self.resolve_expression(*iterable);
+
+                self.scopes.push(Scope::new());
+                self.define(variable, SymbolKind::Variable, *span);
+                self.resolve_statements(statements);
+                self.scopes.pop();
+            },
+
+            /* Methods are looked up on their receiver, not by name. */
+            Statement::Implementation {
+                functions,
+                ..
+            } => {
+                for function in functions {
+                    self.resolve_statement(function);
+                }
+            },
+            Statement::Trait { .. } => {},
+
+            /* Names in a macro body only mean something once it's
+             * expanded. */
+            Statement::MacroDefinition { .. } => {},
+
+            /* Only formatting leaves both branches in place, each
+             * resolved as if it were kept. */
+            Statement::Conditional {
+                statements,
+                otherwise,
+                ..
+            } => {
+                self.resolve_statements(statements);
+                self.resolve_statements(otherwise);
+            },
+            Statement::Attributed {
+                statement,
+                ..
+            } => self.resolve_statement(statement),
+            Statement::Enum {
+                variants,
+                ..
+            } => {
+                for variant in variants {
+                    for field in &variant.fields {
+                        self.resolve_type(field);
+                    }
+                }
+            },
+        }
+    }
+
+    /// Resolve the variants `pattern` names and define the variables
+    /// it binds.
+    fn resolve_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Identifier {
+                name,
+                span,
+            } => {
+                let id = self.scopes.iter().rev().find_map(|scope| scope.get(name));
+
+                match id {
+                    Some(&id) if self.index.symbols[id].kind == SymbolKind::Variant =>
+                        self.index.uses.push((*span, id)),
+                    _ => self.define(name, SymbolKind::Variable, *span),
+                }
+            },
+            Pattern::Variant {
+                name,
+                fields,
+                span,
+            } => {
+                self.use_name(name, *span);
+
+                for field in fields {
+                    self.resolve_pattern(field);
+                }
+            },
+            Pattern::Array(elements) => {
+                for element in elements {
+                    self.resolve_pattern(element);
+                }
+            },
+            Pattern::Wildcard |
+            Pattern::Number(_) |
+            Pattern::String(_) |
+            Pattern::Nil => {},
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: ExprId) {
+        match self.program.expression(expression) {
+            Expression::Identifier {
+                name,
+                span,
+            } => self.use_name(name, *span),
+            Expression::Number(_) |
+            Expression::String(_) |
+            Expression::Nil |
+            Expression::Unit => {},
+            Expression::UnaryOperation {
+                operand,
+                ..
+            } |
+            Expression::Try {
+                operand,
+                ..
+            } => self.resolve_expression(*operand),
+            Expression::Cast {
+                operand,
+                r#type,
+                ..
+            } => {
+                self.resolve_expression(*operand);
+                self.resolve_type(r#type);
+            },
+            Expression::Array {
+                elements,
+            } => {
+                for element in elements {
+                    self.resolve_expression(*element);
+                }
+            },
+            Expression::Map {
+                entries,
+            } => {
+                for (key, value) in entries {
+                    self.resolve_expression(*key);
+                    self.resolve_expression(*value);
+                }
+            },
+            Expression::Range {
+                start,
+                end,
+                ..
+            } => {
+                for bound in [start, end].into_iter().flatten() {
+                    self.resolve_expression(*bound);
+                }
+            },
+            Expression::Index {
+                target,
+                index,
+                ..
+            } => {
+                self.resolve_expression(*target);
+                self.resolve_expression(*index);
+            },
+            Expression::BinaryOperation {
+                operand_left,
+                operand_right,
+                ..
+            } => {
+                self.resolve_expression(*operand_left);
+                self.resolve_expression(*operand_right);
+            },
+            Expression::FunctionCall {
+                callee_name,
+                arguments,
+                span,
+            } => {
+                self.use_name(callee_name, *span);
+
+                for argument in arguments {
+                    self.resolve_expression(argument.value);
+                }
+            },
+            Expression::MethodCall {
+                receiver,
+                arguments,
+                ..
+            } => {
+                self.resolve_expression(*receiver);
+
+                for argument in arguments {
+                    self.resolve_expression(argument.value);
+                }
+            },
+            Expression::Match {
+                scrutinee,
+                arms,
+                ..
+            } => {
+                self.resolve_expression(*scrutinee);
+
+                for arm in arms {
+                    self.scopes.push(Scope::new());
+                    self.resolve_pattern(&arm.pattern);
+                    self.resolve_expression(arm.value);
+                    self.scopes.pop();
+                }
+            },
+        }
+    }
+
+    /// Resolve the names used by the sizes of array types.
+    fn resolve_type(&mut self, r#type: &Type) {
+        match r#type {
+            Type::Named(_) |
+            Type::Unit => {},
+            Type::Nullable(r#type) |
+            Type::Slice(r#type) => self.resolve_type(r#type),
+            Type::Map {
+                key,
+                value,
+            } => {
+                self.resolve_type(key);
+                self.resolve_type(value);
+            },
+            Type::Array {
+                element,
+                size,
+            } => {
+                self.resolve_type(element);
+                self.resolve_expression(*size);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_str;
+    use super::*;
+
+    #[test]
+    fn scopes() {
+        let text = "let a = 1; func f(a) { return a + b; } let b = f(a); { let a = 2; a = f(b); }";
+        let index = resolve(compile_str(text).unwrap().program());
+        let span_of = |needle: &str| {
+            let start = text.find(needle).unwrap();
+
+            Span::new(start, start + 1)
+        };
+        let name_at = |offset: usize| {
+            let (id, _) = index.symbol_at(offset).unwrap();
+
+            (index.symbol(id).kind, index.symbol(id).span)
+        };
+
+        /* `a` in `f` is the parameter, `b` is the later global. */
+        assert_eq!(name_at(text.find("a + b").unwrap()), (SymbolKind::Parameter, span_of("a) {")));
+        assert_eq!(name_at(text.find("b; }").unwrap()), (SymbolKind::Variable, span_of("b = f")));
+
+        /* The block's `a` shadows the global one. */
+        assert_eq!(name_at(text.find("a = f(b)").unwrap()), (SymbolKind::Variable, span_of("a = 2")));
+        assert_eq!(index.references(index.symbol_at(4).unwrap().0), vec![span_of("a); {")]);
+        assert_eq!(index.references(index.symbol_at(16).unwrap().0).len(), 2);
+    }
+
+    #[test]
+    fn prelude() {
+        let text = "func f() { print(1); } print(2);";
+        let index = resolve(compile_str(text).unwrap().program());
+        let (id, _) = index.symbol_at(text.find("print(2)").unwrap()).unwrap();
+
+        assert_eq!(index.symbol(id).kind, SymbolKind::Builtin);
+        assert_eq!(index.references(id).len(), 2);
+        assert_eq!(index.symbol_at(5).map(|(id, _)| index.symbol(id).kind), Some(SymbolKind::Function));
+
+        let index = resolve(compile_str("#![no_prelude]\nprint(2);").unwrap().program());
+
+        assert_eq!(index.symbol_at(16), None);
+    }
+}
@@ -0,0 +1,259 @@
+use serde::{Serialize, Deserialize};
+
+/// Byte range `start..end` of a piece of source code.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span {
+            start,
+            end,
+        }
+    }
+
+    /// Smallest span covering both `self` and `other`.
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// One-based line and column of the span's start in `source`, as
+    /// [`SourceFile::location`] finds them.
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        SourceFile::new("", source).location(self.start)
+    }
+
+    /// Span moved `offset` bytes further.
+    pub fn shift(&mut self, offset: usize) {
+        self.start += offset;
+        self.end += offset;
+    }
+}
+
+/// Source file, locating the byte offsets of its text for the people
+/// reading diagnostics and for the editors speaking the language
+/// server protocol alike.
+///
+/// Lines end at `\n`, a `\r` before it belonging to the terminator, so
+/// a file has the same lines and columns with CRLF line endings as with
+/// LF ones.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+
+    /// Path of the file, or whatever names it in diagnostics.
+    pub name: String,
+    pub text: String,
+
+    /// Offset of the file's text among the files of its
+    /// [`SourceMap`], zero for a file of its own.
+    pub base: usize,
+
+    /// Offset of the first byte of each line.
+    line_starts: Vec<usize>,
+
+    /// Width of a tab in the columns of [`SourceFile::location`].
+    tab_width: usize,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, text: impl Into<String>) -> SourceFile {
+        let text: String = text.into();
+        let mut line_starts: Vec<usize> = vec![0];
+
+        line_starts.extend(text.match_indices('\n').map(|(index, _)| index + 1));
+
+        SourceFile {
+            name: name.into(),
+            text,
+            base: 0,
+            line_starts,
+            tab_width: 1,
+        }
+    }
+
+    /// File whose columns have a tab reach the next multiple of
+    /// `tab_width`, the way the source is displayed, rather than count
+    /// as one like any other character.
+    pub fn with_tab_width(mut self, tab_width: usize) -> SourceFile {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    /// Zero-based line of the byte `offset` of the text and the text
+    /// of that line up to it.
+    fn line(&self, offset: usize) -> (usize, &str) {
+        let mut offset = offset.min(self.text.len());
+
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+
+        (line, &self.text[self.line_starts[line]..offset])
+    }
+
+    /// One-based line and column of the byte `offset` of the text,
+    /// columns counting characters rather than bytes.
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        let (line, before) = self.line(offset);
+        let mut column = 0;
+
+        for char in before.chars() {
+            column = match char {
+                '\t' => (column / self.tab_width + 1) * self.tab_width,
+                _ => column + 1,
+            };
+        }
+
+        (line + 1, column + 1)
+    }
+
+    /// Zero-based line and column of the byte `offset` of the text,
+    /// columns counting UTF-16 code units as the language server
+    /// protocol does.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let (line, before) = self.line(offset);
+
+        (line, before.encode_utf16().count())
+    }
+
+    /// Byte offset in the text of the zero-based `line` and UTF-16
+    /// `column`, the end of the line if it's shorter, or of the text if
+    /// it has fewer lines.
+    pub fn offset(&self, line: usize, column: usize) -> usize {
+        let start = match self.line_starts.get(line) {
+            Some(start) => *start,
+            None => return self.text.len(),
+        };
+        let end = self.line_starts.get(line + 1).map_or(self.text.len(), |next| next - 1);
+        let text = &self.text[start..end];
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let mut units = 0;
+
+        for (index, char) in text.char_indices() {
+            if units >= column {
+                return start + index;
+            }
+
+            units += char.len_utf16();
+        }
+
+        start + text.len()
+    }
+}
+
+/// Index of a file in a [`SourceMap`].
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct FileId(pub u32);
+
+/// Source files compiled together, laid out one after the other so
+/// that a byte offset among all of them tells both the file and the
+/// place within it.
+///
+/// Each file is parsed on its own, with spans starting from zero, and
+/// its program is then moved to the file's base by
+/// [`Program::shift_spans`](crate::parser::Program::shift_spans).
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap {
+            files: Vec::new(),
+        }
+    }
+
+    /// Take in `file`, placing it after the files taken in so far.
+    ///
+    /// A byte separates each file from the next, so even an empty file
+    /// has an offset of its own.
+    pub fn add(&mut self, mut file: SourceFile) -> FileId {
+        file.base = match self.files.last() {
+            Some(last) => last.base + last.text.len() + 1,
+            None => 0,
+        };
+        self.files.push(file);
+
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// File the byte `offset` among all files falls in, with the
+    /// offset within its text, `None` if there are no files.
+    pub fn find(&self, offset: usize) -> Option<(&SourceFile, usize)> {
+        let index = self.files.partition_point(|file| file.base <= offset).checked_sub(1)?;
+        let file = &self.files[index];
+
+        Some((file, offset - file.base))
+    }
+
+    /// Name of the file the byte `offset` among all files falls in, and
+    /// the one-based line and column of the offset within that file.
+    pub fn location(&self, offset: usize) -> Option<(&str, usize, usize)> {
+        let (file, offset) = self.find(offset)?;
+        let (line, column) = file.location(offset);
+
+        Some((&file.name, line, column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_source;
+    use super::*;
+
+    #[test]
+    fn source_file() {
+        let text = "let a = 1;\r\n\t/* caf\u{e9} \u{1F600} */ let b = a;\r\nb;";
+        let file = SourceFile::new("main.fang", text);
+        let b = text.rfind('b').unwrap();
+        let semicolon = text.rfind("a;").unwrap() + 1;
+
+        assert!(parse_source(text).is_ok());
+
+        /* CRLF ends a line like LF does. */
+        assert_eq!(file.location(b), (3, 1));
+        assert_eq!(file.position(b), (2, 0));
+
+        /* Columns count characters, or UTF-16 code units for editors,
+         * and a tab reaches the next tab stop when tabs are wide. */
+        assert_eq!(file.location(semicolon), (2, 24));
+        assert_eq!(file.clone().with_tab_width(4).location(semicolon), (2, 27));
+        assert_eq!(file.position(semicolon), (1, 24));
+
+        assert_eq!(file.offset(1, 24), semicolon);
+        assert_eq!(file.offset(1, 100), semicolon + 1);
+        assert_eq!(file.offset(0, 100), text.find('\r').unwrap());
+        assert_eq!(file.offset(5, 0), text.len());
+    }
+
+    #[test]
+    fn source_map() {
+        let mut map = SourceMap::new();
+        let first = map.add(SourceFile::new("a.fang", "let a = 1;\n"));
+        let empty = map.add(SourceFile::new("empty.fang", ""));
+        let last = map.add(SourceFile::new("b.fang", "a;\nb;"));
+
+        assert_eq!((map.file(first).base, map.file(empty).base, map.file(last).base), (0, 12, 13));
+        assert_eq!(map.location(4), Some(("a.fang", 1, 5)));
+        assert_eq!(map.location(12), Some(("empty.fang", 1, 1)));
+        assert_eq!(map.location(16), Some(("b.fang", 2, 1)));
+        assert_eq!(SourceMap::new().location(0), None);
+    }
+}
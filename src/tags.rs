@@ -0,0 +1,238 @@
+use crate::parser::{Program, Statement};
+use crate::span::{SourceFile, Span};
+
+/// Kind of definition a tag points to.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TagKind {
+    Function,
+    Method,
+    Enum,
+    Variant,
+    Trait,
+    Macro,
+
+    /// Variable defined at the top level.
+    Global,
+    Constant,
+}
+
+impl TagKind {
+
+    /// Letter of the kind in a ctags file, as Universal Ctags names the
+    /// kinds of Rust definitions.
+    pub fn letter(&self) -> char {
+        match self {
+            TagKind::Function => 'f',
+            TagKind::Method => 'P',
+            TagKind::Enum => 'g',
+            TagKind::Variant => 'e',
+            TagKind::Trait => 'i',
+            TagKind::Macro => 'M',
+            TagKind::Global => 'v',
+            TagKind::Constant => 'C',
+        }
+    }
+}
+
+/// Definition of a name an editor can jump to.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Tag {
+    pub name: String,
+    pub kind: TagKind,
+
+    /// Span of the name in the definition.
+    pub span: Span,
+}
+
+/// Add the tag of `name` to `tags`.
+fn tag(tags: &mut Vec<Tag>, name: &str, kind: TagKind, span: Span) {
+    tags.push(Tag {
+        name: name.to_owned(),
+        kind,
+        span,
+    });
+}
+
+/// Tags of the definitions in the top-level `statements`, keeping those
+/// under every branch of conditional compilation.
+fn collect(statements: &[Statement], tags: &mut Vec<Tag>) {
+    for statement in statements {
+        match statement {
+            Statement::VariableDefinition {
+                identifier,
+                span,
+                ..
+            } => tag(tags, identifier, TagKind::Global, *span),
+            Statement::ConstantDefinition {
+                identifier,
+                span,
+                ..
+            } => tag(tags, identifier, TagKind::Constant, *span),
+            Statement::FunctionDefinition {
+                callee_name,
+                span,
+                ..
+            } => tag(tags, callee_name, TagKind::Function, *span),
+            Statement::Implementation {
+                functions,
+                ..
+            } => for function in functions {
+                if let Statement::FunctionDefinition { callee_name, span, .. } = function {
+                    tag(tags, callee_name, TagKind::Method, *span);
+                }
+            },
+            Statement::Trait {
+                name,
+                span,
+                ..
+            } => tag(tags, name, TagKind::Trait, *span),
+            Statement::Enum {
+                name,
+                variants,
+                span,
+            } => {
+                tag(tags, name, TagKind::Enum, *span);
+
+                for variant in variants {
+                    tag(tags, &variant.name, TagKind::Variant, variant.span);
+                }
+            },
+            Statement::MacroDefinition {
+                name,
+                span,
+                ..
+            } => tag(tags, name, TagKind::Macro, *span),
+            Statement::Conditional {
+                statements,
+                otherwise,
+                ..
+            } => {
+                collect(statements, tags);
+                collect(otherwise, tags);
+            },
+            Statement::Attributed {
+                statement,
+                ..
+            } => collect(std::slice::from_ref(statement.as_ref()), tags),
+            _ => {},
+        }
+    }
+}
+
+/// Tags of the functions, enums, traits, macros and globals a program
+/// defines, in source order.
+///
+/// The program must be as parsed, its spans within its own file and
+/// its conditional compilation not yet resolved.
+pub fn collect_tags(program: &Program) -> Vec<Tag> {
+    let mut tags = Vec::new();
+
+    collect(&program.statements, &mut tags);
+    tags
+}
+
+/// Line of `file` holding the byte `offset`, without its terminator,
+/// with its one-based number and the offset it starts at.
+fn line_at(file: &SourceFile, offset: usize) -> (usize, usize, &str) {
+    let (line, _) = file.location(offset);
+    let start = file.offset(line - 1, 0);
+    let text = &file.text[start..];
+    let text = &text[..text.find('\n').unwrap_or(text.len())];
+
+    (line, start, text.strip_suffix('\r').unwrap_or(text))
+}
+
+/// Tags file in the extended ctags format vi and its relatives read,
+/// sorted by name, each tag found by a search for its line.
+pub fn ctags(files: &[(SourceFile, Vec<Tag>)]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut text = String::from("!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+                                 !_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n");
+
+    for (file, tags) in files {
+        for tag in tags {
+            let (line, _, source) = line_at(file, tag.span.start);
+            let pattern = source.replace('\\', "\\\\").replace('/', "\\/");
+
+            lines.push(format!("{}\t{}\t/^{}$/;\"\t{}\tline:{}\n",
+                               tag.name, file.name, pattern, tag.kind.letter(), line));
+        }
+    }
+
+    lines.sort();
+
+    for line in lines {
+        text.push_str(&line);
+    }
+
+    text
+}
+
+/// Tags file in the etags format Emacs reads, a section per file with
+/// its tags in source order.
+pub fn etags(files: &[(SourceFile, Vec<Tag>)]) -> String {
+    let mut text = String::new();
+
+    for (file, tags) in files {
+        let mut section = String::new();
+
+        for tag in tags {
+            let (line, start, source) = line_at(file, tag.span.start);
+            let end = (tag.span.end - start).min(source.len());
+
+            section.push_str(&format!("{}\x7f{}\x01{},{}\n", &source[..end], tag.name, line, start));
+        }
+
+        text.push_str(&format!("\x0c\n{},{}\n{}", file.name, section.len(), section));
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_source;
+    use super::*;
+
+    #[test]
+    fn tags_files() {
+        let source = "\
+const SIZE = 4;
+let total = 0;
+func add(a, b) {
+    let sum = a + b;
+    return sum;
+}
+enum Shape {
+    Circle(int),
+    Empty,
+}
+#if DEBUG {
+    func trace(value) {}
+}
+impl int {
+    func double(self) -> int {
+        return self * 2;
+    }
+}
+";
+        let program = parse_source(source).unwrap();
+        let files = [(SourceFile::new("src/main.fang", source), collect_tags(&program))];
+        let names: Vec<(&str, TagKind)> = files[0].1.iter().map(|tag| (tag.name.as_str(), tag.kind)).collect();
+
+        assert_eq!(names, [
+            ("SIZE", TagKind::Constant),
+            ("total", TagKind::Global),
+            ("add", TagKind::Function),
+            ("Shape", TagKind::Enum),
+            ("Circle", TagKind::Variant),
+            ("Empty", TagKind::Variant),
+            ("trace", TagKind::Function),
+            ("double", TagKind::Method),
+        ]);
+        assert_eq!(ctags(&files).lines().nth(2), Some("Circle\tsrc/main.fang\t/^    Circle(int),$/;\"\te\tline:8"));
+        assert!(ctags(&files).contains("\ntotal\tsrc/main.fang\t/^let total = 0;$/;\"\tv\tline:2\n"));
+        assert!(etags(&files).starts_with("\x0c\nsrc/main.fang,"));
+        assert!(etags(&files).contains("\nfunc add\x7fadd\x013,31\n"));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::interpreter::Value;
+use crate::parser::{Expression, Program};
+use crate::resolver::{resolve, SymbolKind};
+
+/// Builtins taking a format string followed by a value for each of its
+/// placeholders.
+pub const FORMATTING_BUILTINS: [&str; 2] = ["format", "printf"];
+
+/// How a placeholder writes its value.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Style {
+
+    /// `{}`, the value as `print` writes it.
+    Display,
+
+    /// `{:?}`, the value as written in source code, quoting strings
+    /// even within arrays, maps and results.
+    Debug,
+}
+
+/// Piece of a format string.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Piece {
+    Text(String),
+    Placeholder(Style),
+}
+
+/// Split a format string into its text and placeholders, `{{` and `}}`
+/// standing for literal braces.
+pub fn parse_template(template: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces: Vec<Piece> = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            },
+            '{' => {
+                let mut spec = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(char) => spec.push(char),
+                        None => return Err(String::from("Unclosed \"{\" in format string!")),
+                    }
+                }
+
+                let style = match spec.as_str() {
+                    "" => Style::Display,
+                    ":?" => Style::Debug,
+                    _ => return Err(format!("Unknown placeholder \"{{{}}}\" in format string!", spec)),
+                };
+
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+
+                pieces.push(Piece::Placeholder(style));
+            },
+            '}' => return Err(String::from("Unmatched \"}\" in format string, write \"}}\" for a brace!")),
+            _ => text.push(char),
+        }
+    }
+
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+
+    Ok(pieces)
+}
+
+/// Number of placeholders of the parsed format string.
+pub fn count_placeholders(pieces: &[Piece]) -> usize {
+    pieces.iter().filter(|piece| matches!(piece, Piece::Placeholder(_))).count()
+}
+
+/// Value as written in source code.
+fn debug(value: &Value) -> String {
+    match value {
+        Value::String(str) => format!("{:?}", str),
+        Value::Ok(value) => format!("ok({})", debug(value)),
+        Value::Err(error) => format!("err({})", debug(error)),
+        Value::Array(_) |
+        Value::Slice { .. } => {
+            let elements: Vec<String> = value.elements().unwrap().iter().map(debug).collect();
+
+            format!("[{}]", elements.join(", "))
+        },
+        Value::Map(entries) => {
+            let entries: Vec<String> = entries.iter()
+                .map(|(key, value)| format!("{}: {}", debug(key), debug(value)))
+                .collect();
+
+            format!("{{{}}}", entries.join(", "))
+        },
+        _ => value.to_string(),
+    }
+}
+
+/// Format string filled in with `values`, one per placeholder in order.
+pub fn format_values(template: &str, values: &[Value]) -> Result<String, String> {
+    let pieces = parse_template(template)?;
+    let mut values = values.iter();
+    let mut text = String::new();
+
+    if count_placeholders(&pieces) != values.len() {
+        return Err(format!("The format string has {} placeholder(s) but {} value(s) were given!",
+                           count_placeholders(&pieces), values.len()));
+    }
+
+    for piece in &pieces {
+        match piece {
+            Piece::Text(str) => text.push_str(str),
+            Piece::Placeholder(Style::Display) => text.push_str(&values.next().unwrap().to_string()),
+            Piece::Placeholder(Style::Debug) => text.push_str(&debug(values.next().unwrap())),
+        }
+    }
+
+    Ok(text)
+}
+
+/// Check that every call of a formatting builtin whose format string is
+/// a literal passes it a value for each of its placeholders.
+///
+/// Calls of functions shadowing the builtins aren't checked, nor are
+/// format strings built at run time, which the builtins check then.
+pub fn check_formats(program: &Program) -> Result<(), Diagnostics> {
+    let index = resolve(program);
+    let mut diagnostics = Diagnostics::new();
+
+    for expression in program.expressions.iter() {
+        let (name, arguments, span) = match expression {
+            Expression::FunctionCall {
+                callee_name,
+                arguments,
+                span,
+            } if FORMATTING_BUILTINS.contains(&callee_name.as_str()) => (callee_name, arguments, span),
+            _ => continue,
+        };
+
+        if !index.symbol_at(span.start).is_some_and(|(id, _)| index.symbol(id).kind == SymbolKind::Builtin) {
+            continue;
+        }
+
+        let template = match arguments.first().map(|argument| program.expression(argument.value)) {
+            Some(Expression::String(template)) => template,
+            _ => continue,
+        };
+
+        match parse_template(template) {
+            Ok(pieces) if count_placeholders(&pieces) != arguments.len() - 1 => {
+                diagnostics.push(Diagnostic::error(format!(
+                    "\"{}\" is given {} value(s) for the {} placeholder(s) of its format string!",
+                    name, arguments.len() - 1, count_placeholders(&pieces)))
+                    .with_span(*span));
+            },
+            Ok(_) => {},
+            Err(message) => diagnostics.push(Diagnostic::error(message).with_span(arguments[0].span)),
+        }
+    }
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_source;
+    use super::*;
+
+    #[test]
+    fn templates() {
+        assert_eq!(parse_template("x = {}, {{y}} = {:?}"), Ok(vec![
+            Piece::Text(String::from("x = ")),
+            Piece::Placeholder(Style::Display),
+            Piece::Text(String::from(", {y} = ")),
+            Piece::Placeholder(Style::Debug),
+        ]));
+        assert!(parse_template("{").is_err());
+        assert!(parse_template("}").is_err());
+        assert!(parse_template("{:x}").is_err());
+
+        assert_eq!(format_values("{} and {:?}", &[Value::String(String::from("a")), Value::String(String::from("b"))]),
+                   Ok(String::from("a and \"b\"")));
+        assert!(format_values("{}", &[]).is_err());
+    }
+
+    #[test]
+    fn placeholder_counts() {
+        let check = |source: &str| check_formats(&parse_source(source).unwrap()).map_err(|diagnostics| {
+            diagnostics.iter().map(|diagnostic| diagnostic.message.to_owned()).collect::<Vec<String>>()
+        });
+
+        assert_eq!(check("let x = 1; printf(\"x = {}\", x); format(\"{{}}\");"), Ok(()));
+        assert_eq!(check("let x = 1; let text = \"{}\"; format(text);"), Ok(()));
+        assert_eq!(check("func format(a, b) { return a; } format(\"{}\", 1);"), Ok(()));
+        assert_eq!(check("format(\"{} {}\", 1);"), Err(vec![
+            String::from("\"format\" is given 1 value(s) for the 2 placeholder(s) of its format string!"),
+        ]));
+        assert_eq!(check("printf(\"{\");"), Err(vec![String::from("Unclosed \"{\" in format string!")]));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::interpreter::{Env, Hooks, Interpreter, RuntimeError};
+use crate::parser::{ExprId, Expression, Program, Statement};
+use crate::span::Span;
+use std::sync::Arc;
+
+/// Function marked `#[test]`, which passes if calling it without
+/// arguments returns rather than fails.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Test {
+    pub name: String,
+
+    /// Span of the function name.
+    pub span: Span,
+}
+
+/// Tests of the configured `program`, the top-level functions whose
+/// attributes include `#[test]`, in source order.
+pub fn collect_tests(program: &Program) -> Vec<Test> {
+    let is_test = |span: Span| program.scoped_attributes.iter().any(|(scope, attributes)| {
+        scope.start <= span.start &&
+        span.end <= scope.end &&
+        attributes.iter().any(|attribute| attribute.name == "test")
+    });
+
+    program.statements.iter()
+        .filter_map(|statement| match statement {
+            Statement::FunctionDefinition {
+                callee_name,
+                span,
+                ..
+            } if is_test(*span) => Some(Test {
+                name: callee_name.to_owned(),
+                span: *span,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Program defining the functions, types, constants and globals
+/// `program` defines at its top level, then calling `test`.
+///
+/// The other top-level statements don't run, so a test doesn't do what
+/// the program does when it runs.
+fn test_program(program: &Program, test: &Test) -> Program {
+    let mut tested = program.clone();
+    let call = ExprId(tested.expressions.len() as u32);
+
+    tested.statements.retain(|statement| matches!(statement,
+        Statement::VariableDefinition { .. } |
+        Statement::Destructuring { .. } |
+        Statement::ConstantDefinition { .. } |
+        Statement::FunctionDefinition { .. } |
+        Statement::Implementation { .. } |
+        Statement::Trait { .. } |
+        Statement::Enum { .. }));
+    Arc::make_mut(&mut tested.expressions).push(Expression::FunctionCall {
+        callee_name: test.name.to_owned(),
+        arguments: Vec::new(),
+        span: test.span,
+    });
+    tested.statements.push(Statement::Expression {
+        expression: call,
+    });
+
+    tested
+}
+
+/// Run `test` of `program` in `env`, which should be a fresh one for
+/// every test, so that no test sees what another one left behind.
+pub fn run_test(program: &Program, test: &Test, env: &mut Env) -> Result<(), RuntimeError> {
+    Interpreter::new(env).run(&test_program(program, test)).map(|_| ())
+}
+
+/// Run `test` like [`run_test`], calling `hooks` as it runs, to measure
+/// what the tests cover.
+pub fn run_test_with_hooks(program: &Program, test: &Test, env: &mut Env, hooks: &mut dyn Hooks) -> Result<(), RuntimeError> {
+    Interpreter::new(env).with_hooks(hooks).run(&test_program(program, test)).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_str;
+    use super::*;
+
+    #[test]
+    fn tests_run_alone() {
+        let program = compile_str("\
+let base = 40;
+print(\"not in tests\");
+#[test]
+func adds() {
+    assert(base + 2 == 42);
+}
+func helper() {}
+#[allow(unreachable_arms)]
+#[test]
+func fails() {
+    assert(base == 0, \"base is not 0\");
+}
+").unwrap();
+        let program = program.program();
+        let tests = collect_tests(program);
+
+        assert_eq!(tests.iter().map(|test| test.name.as_str()).collect::<Vec<&str>>(), ["adds", "fails"]);
+        assert_eq!(run_test(program, &tests[0], &mut Env::with_builtins()), Ok(()));
+        assert_eq!(run_test(program, &tests[1], &mut Env::with_builtins()).unwrap_err().message,
+                   "Assertion failed: base is not 0");
+    }
+}
@@ -0,0 +1,598 @@
+use crate::parser::{ExprId, Expression, Parameter, Pattern, Program, Statement, Type};
+use std::mem;
+use std::sync::Arc;
+
+/// Pass over the syntax tree of a program, called on every statement,
+/// expression, pattern and type in source order.
+///
+/// Each method walks into the children of what it's called on by
+/// default. A pass overrides the methods for what it looks at, calling
+/// the matching `walk_` function unless it means to skip the
+/// children, so it never has to match every kind of node itself.
+///
+/// Expressions are visited by id, since they live in the arena of
+/// `program`, which every method is given to look them up in.
+pub trait Visitor<'ast> {
+    fn visit_statement(&mut self, program: &'ast Program, statement: &'ast Statement) {
+        walk_statement(self, program, statement);
+    }
+
+    fn visit_expression(&mut self, program: &'ast Program, id: ExprId) {
+        walk_expression(self, program, id);
+    }
+
+    fn visit_pattern(&mut self, program: &'ast Program, pattern: &'ast Pattern) {
+        walk_pattern(self, program, pattern);
+    }
+
+    fn visit_type(&mut self, program: &'ast Program, r#type: &'ast Type) {
+        walk_type(self, program, r#type);
+    }
+}
+
+/// Visit the statements of `program`.
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program) {
+    for statement in &program.statements {
+        visitor.visit_statement(program, statement);
+    }
+}
+
+/// Visit the types of a function signature.
+fn walk_signature<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    program: &'ast Program,
+    parameters: &'ast [Parameter],
+    return_type: &'ast Option<Type>
+) {
+    for r#type in parameters.iter().filter_map(|parameter| parameter.r#type.as_ref()).chain(return_type) {
+        visitor.visit_type(program, r#type);
+    }
+}
+
+/// Visit what `statement` holds: its types, patterns, expressions and
+/// the statements nested in it.
+pub fn walk_statement<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program, statement: &'ast Statement) {
+    match statement {
+        Statement::VariableDefinition {
+            r#type,
+            value,
+            ..
+        } => {
+            if let Some(r#type) = r#type {
+                visitor.visit_type(program, r#type);
+            }
+
+            if let Some(value) = value {
+                visitor.visit_expression(program, *value);
+            }
+        },
+        Statement::ConstantDefinition {
+            r#type,
+            value,
+            ..
+        } => {
+            if let Some(r#type) = r#type {
+                visitor.visit_type(program, r#type);
+            }
+
+            visitor.visit_expression(program, *value);
+        },
+        Statement::Destructuring {
+            pattern,
+            value,
+            ..
+        } => {
+            visitor.visit_pattern(program, pattern);
+            visitor.visit_expression(program, *value);
+        },
+        Statement::FunctionDefinition {
+            parameters,
+            return_type,
+            statements,
+            ..
+        } => {
+            walk_signature(visitor, program, parameters, return_type);
+
+            for statement in statements {
+                visitor.visit_statement(program, statement);
+            }
+        },
+        Statement::Return {
+            expression,
+            ..
+        } |
+        Statement::Expression {
+            expression,
+        } => visitor.visit_expression(program, *expression),
+        Statement::For {
+            iterable,
+            statements,
+            ..
+        } => {
+            visitor.visit_expression(program, *iterable);
+
+            for statement in statements {
+                visitor.visit_statement(program, statement);
+            }
+        },
+        Statement::Block {
+            statements,
+        } |
+        Statement::Implementation {
+            functions: statements,
+            ..
+        } |
+        Statement::MacroDefinition {
+            statements,
+            ..
+        } => {
+            for statement in statements {
+                visitor.visit_statement(program, statement);
+            }
+        },
+        Statement::Conditional {
+            statements,
+            otherwise,
+            ..
+        } => {
+            for statement in statements.iter().chain(otherwise) {
+                visitor.visit_statement(program, statement);
+            }
+        },
+        Statement::Attributed {
+            statement,
+            ..
+        } => visitor.visit_statement(program, statement),
+        Statement::Trait {
+            methods,
+            ..
+        } => {
+            for method in methods {
+                walk_signature(visitor, program, &method.parameters, &method.return_type);
+            }
+        },
+        Statement::Enum {
+            variants,
+            ..
+        } => {
+            for r#type in variants.iter().flat_map(|variant| &variant.fields) {
+                visitor.visit_type(program, r#type);
+            }
+        },
+    }
+}
+
+/// Visit the expressions, patterns and types the expression `id`
+/// holds.
+pub fn walk_expression<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program, id: ExprId) {
+    match program.expression(id) {
+        Expression::Identifier {
+            ..
+        } |
+        Expression::Number(_) |
+        Expression::String(_) |
+        Expression::Nil |
+        Expression::Unit => {},
+        Expression::UnaryOperation {
+            operand,
+            ..
+        } |
+        Expression::Try {
+            operand,
+            ..
+        } => visitor.visit_expression(program, *operand),
+        Expression::BinaryOperation {
+            operand_left,
+            operand_right,
+            ..
+        } => {
+            visitor.visit_expression(program, *operand_left);
+            visitor.visit_expression(program, *operand_right);
+        },
+        Expression::FunctionCall {
+            arguments,
+            ..
+        } => {
+            for argument in arguments {
+                visitor.visit_expression(program, argument.value);
+            }
+        },
+        Expression::Cast {
+            operand,
+            r#type,
+            ..
+        } => {
+            visitor.visit_expression(program, *operand);
+            visitor.visit_type(program, r#type);
+        },
+        Expression::Array {
+            elements,
+        } => {
+            for element in elements {
+                visitor.visit_expression(program, *element);
+            }
+        },
+        Expression::Map {
+            entries,
+        } => {
+            for (key, value) in entries {
+                visitor.visit_expression(program, *key);
+                visitor.visit_expression(program, *value);
+            }
+        },
+        Expression::Range {
+            start,
+            end,
+            ..
+        } => {
+            for bound in [start, end].into_iter().flatten() {
+                visitor.visit_expression(program, *bound);
+            }
+        },
+        Expression::Index {
+            target,
+            index,
+            ..
+        } => {
+            visitor.visit_expression(program, *target);
+            visitor.visit_expression(program, *index);
+        },
+        Expression::MethodCall {
+            receiver,
+            arguments,
+            ..
+        } => {
+            visitor.visit_expression(program, *receiver);
+
+            for argument in arguments {
+                visitor.visit_expression(program, argument.value);
+            }
+        },
+        Expression::Match {
+            scrutinee,
+            arms,
+            ..
+        } => {
+            visitor.visit_expression(program, *scrutinee);
+
+            for arm in arms {
+                visitor.visit_pattern(program, &arm.pattern);
+                visitor.visit_expression(program, arm.value);
+            }
+        },
+    }
+}
+
+/// Visit the patterns nested in `pattern`.
+pub fn walk_pattern<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program, pattern: &'ast Pattern) {
+    match pattern {
+        Pattern::Variant {
+            fields: patterns,
+            ..
+        } |
+        Pattern::Array(patterns) => {
+            for pattern in patterns {
+                visitor.visit_pattern(program, pattern);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Visit the types nested in `r#type`, and the size expression of an
+/// array type.
+pub fn walk_type<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program, r#type: &'ast Type) {
+    match r#type {
+        Type::Named(_) |
+        Type::Unit => {},
+        Type::Nullable(r#type) |
+        Type::Slice(r#type) => visitor.visit_type(program, r#type),
+        Type::Array {
+            element,
+            size,
+        } => {
+            visitor.visit_type(program, element);
+            visitor.visit_expression(program, *size);
+        },
+        Type::Map {
+            key,
+            value,
+        } => {
+            visitor.visit_type(program, key);
+            visitor.visit_type(program, value);
+        },
+    }
+}
+
+/// Pass rewriting the syntax tree of a program, like the desugaring of
+/// a construct or the expansion of macros.
+///
+/// A statement is folded into the statements taking its place, none
+/// to drop it or several to splice them in, and an expression into
+/// the id of the expression taking its place. By default, each method
+/// folds the children of what it's called on and keeps it, so a pass
+/// only overrides the methods for what it rewrites.
+///
+/// Expressions live in the arena of the program, which every method
+/// is given to look them up in and to push new expressions to.
+pub trait Folder {
+    fn fold_statement(&mut self, expressions: &mut Vec<Expression>, mut statement: Statement) -> Vec<Statement> {
+        walk_statement_mut(self, expressions, &mut statement);
+
+        vec![statement]
+    }
+
+    fn fold_expression(&mut self, expressions: &mut Vec<Expression>, id: ExprId) -> ExprId {
+        walk_expression_mut(self, expressions, id);
+
+        id
+    }
+}
+
+/// Fold the statements of `program`.
+pub fn fold_program<F: Folder + ?Sized>(folder: &mut F, program: &mut Program) {
+    program.statements = fold_statements(folder, Arc::make_mut(&mut program.expressions), mem::take(&mut program.statements));
+}
+
+/// Fold each of `statements` into the statements taking its place.
+pub fn fold_statements<F: Folder + ?Sized>(
+    folder: &mut F,
+    expressions: &mut Vec<Expression>,
+    statements: Vec<Statement>
+) -> Vec<Statement> {
+    let mut folded: Vec<Statement> = Vec::with_capacity(statements.len());
+
+    for statement in statements {
+        folded.extend(folder.fold_statement(expressions, statement));
+    }
+
+    folded
+}
+
+/// Fold the expressions `statement` holds and the statements nested
+/// in it.
+pub fn walk_statement_mut<F: Folder + ?Sized>(folder: &mut F, expressions: &mut Vec<Expression>, statement: &mut Statement) {
+    let mut fold = |id: &mut ExprId| *id = folder.fold_expression(expressions, *id);
+
+    match statement {
+        Statement::VariableDefinition {
+            r#type,
+            value,
+            ..
+        } => {
+            if let Some(r#type) = r#type {
+                r#type.visit_ids(&mut fold);
+            }
+
+            if let Some(value) = value {
+                fold(value);
+            }
+        },
+        Statement::ConstantDefinition {
+            r#type,
+            value,
+            ..
+        } => {
+            if let Some(r#type) = r#type {
+                r#type.visit_ids(&mut fold);
+            }
+
+            fold(value);
+        },
+        Statement::Return {
+            expression,
+            ..
+        } |
+        Statement::Expression {
+            expression,
+        } |
+        Statement::Destructuring {
+            value: expression,
+            ..
+        } => fold(expression),
+        Statement::Trait {
+            methods,
+            ..
+        } => {
+            for method in methods {
+                fold_signature(&mut method.parameters, &mut method.return_type, &mut fold);
+            }
+        },
+        Statement::Enum {
+            variants,
+            ..
+        } => {
+            for field in variants.iter_mut().flat_map(|variant| &mut variant.fields) {
+                field.visit_ids(&mut fold);
+            }
+        },
+        Statement::FunctionDefinition {
+            parameters,
+            return_type,
+            statements,
+            ..
+        } => {
+            fold_signature(parameters, return_type, &mut fold);
+            *statements = fold_statements(folder, expressions, mem::take(statements));
+        },
+        Statement::For {
+            iterable,
+            statements,
+            ..
+        } => {
+            fold(iterable);
+            *statements = fold_statements(folder, expressions, mem::take(statements));
+        },
+        Statement::Block {
+            statements,
+        } |
+        Statement::Implementation {
+            functions: statements,
+            ..
+        } |
+        Statement::MacroDefinition {
+            statements,
+            ..
+        } => *statements = fold_statements(folder, expressions, mem::take(statements)),
+        Statement::Conditional {
+            statements,
+            otherwise,
+            ..
+        } => {
+            *statements = fold_statements(folder, expressions, mem::take(statements));
+            *otherwise = fold_statements(folder, expressions, mem::take(otherwise));
+        },
+
+        /* The attributes apply to one statement, so it's walked in
+         * place rather than folded into any number of them. */
+        Statement::Attributed {
+            statement,
+            ..
+        } => walk_statement_mut(folder, expressions, statement),
+    }
+}
+
+/// Fold the size expressions of the types of a function signature.
+fn fold_signature(parameters: &mut [Parameter], return_type: &mut Option<Type>, fold: &mut dyn FnMut(&mut ExprId)) {
+    for r#type in parameters.iter_mut().filter_map(|parameter| parameter.r#type.as_mut()).chain(return_type) {
+        r#type.visit_ids(fold);
+    }
+}
+
+/// Fold the expressions the expression `id` holds, replacing their ids
+/// in it.
+pub fn walk_expression_mut<F: Folder + ?Sized>(folder: &mut F, expressions: &mut Vec<Expression>, id: ExprId) {
+    /* The expression is moved out of the arena while its children are
+     * folded, which may push to the arena. */
+    let mut expression = mem::replace(&mut expressions[id.index()], Expression::Unit);
+
+    expression.visit_ids(&mut |child| *child = folder.fold_expression(expressions, *child));
+    expressions[id.index()] = expression;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_source, BinaryOperator};
+    use crate::printer::format_program;
+    use super::*;
+
+    /// Names of the identifiers, patterns and named types met, in the
+    /// order they're visited.
+    #[derive(Default)]
+    struct Names<'ast> {
+        names: Vec<&'ast str>,
+    }
+
+    impl<'ast> Visitor<'ast> for Names<'ast> {
+        fn visit_expression(&mut self, program: &'ast Program, id: ExprId) {
+            if let Expression::Identifier {
+                name,
+                ..
+            } = program.expression(id) {
+                self.names.push(name);
+            }
+
+            walk_expression(self, program, id);
+        }
+
+        fn visit_pattern(&mut self, program: &'ast Program, pattern: &'ast Pattern) {
+            if let Pattern::Identifier {
+                name,
+                ..
+            } = pattern {
+                self.names.push(name);
+            }
+
+            walk_pattern(self, program, pattern);
+        }
+
+        fn visit_type(&mut self, program: &'ast Program, r#type: &'ast Type) {
+            if let Type::Named(name) = r#type {
+                self.names.push(name);
+            }
+
+            walk_type(self, program, r#type);
+        }
+    }
+
+    #[test]
+    fn source_order() {
+        let program = parse_source("\
+enum Shape { Circle(int) }
+func f(a: [string; N], b) -> bool? {
+    let [c, Circle(d)] = a[b..];
+    for e in g(h as int) { match e { i => j }; }
+    k.l(m = {n: o});
+}
+").unwrap();
+        let mut visitor = Names::default();
+
+        walk_program(&mut visitor, &program);
+
+        assert_eq!(visitor.names, vec![
+            "int", "string", "N", "bool", "c", "d", "a", "b", "h", "int", "e", "i", "j", "k", "n", "o",
+        ]);
+    }
+
+    /// Adds up the sums of numbers and splices blocks into the
+    /// enclosing statements.
+    struct Flatten;
+
+    impl Folder for Flatten {
+        fn fold_statement(&mut self, expressions: &mut Vec<Expression>, mut statement: Statement) -> Vec<Statement> {
+            match statement {
+                Statement::Block {
+                    statements,
+                } => fold_statements(self, expressions, statements),
+                _ => {
+                    walk_statement_mut(self, expressions, &mut statement);
+
+                    vec![statement]
+                },
+            }
+        }
+
+        fn fold_expression(&mut self, expressions: &mut Vec<Expression>, id: ExprId) -> ExprId {
+            walk_expression_mut(self, expressions, id);
+
+            if let Expression::BinaryOperation {
+                operator: BinaryOperator::Addition,
+                operand_left,
+                operand_right,
+                ..
+            } = &expressions[id.index()] {
+                if let (Expression::Number(left), Expression::Number(right)) =
+                    (&expressions[operand_left.index()], &expressions[operand_right.index()]) {
+                    expressions.push(Expression::Number(left + right));
+
+                    return ExprId(expressions.len() as u32 - 1);
+                }
+            }
+
+            id
+        }
+    }
+
+    #[test]
+    fn folding() {
+        let mut program = parse_source("\
+let a = 1 + 2 * 3;
+{ let b = (1 + 2) + 3; { print(b + 4); } }
+func f(c: [int; 2 + 2]) { for d in c { { d; } } }
+").unwrap();
+
+        fold_program(&mut Flatten, &mut program);
+
+        assert_eq!(format_program(&program), "\
+let a = 1 + 2 * 3;
+let b = 6;
+print(b + 4);
+
+func f(c: [int; 4]) {
+    for d in c {
+        d;
+    }
+}
+");
+    }
+}
@@ -0,0 +1,206 @@
+//! Snapshot tests running `yuan` over the fixtures in `tests/fixtures`.
+//!
+//! Each `name.fang` is passed to the command its first line names in a
+//! `// yuan: ...` comment, or to `check` if it names none. What the
+//! command prints is compared against `name.stdout` and `name.stderr`,
+//! a missing file standing for no output. Run the tests with `BLESS=1`
+//! to write the output into those files instead, then review the diff.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Comment on the first line of a fixture naming the command to run.
+const COMMAND_PREFIX: &str = "// yuan:";
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures")
+}
+
+/// Standard output and error of `yuan` run over the fixture `name`
+/// from the fixtures directory, so the paths it prints are stable.
+fn run_fixture(dir: &Path, name: &str) -> (String, String) {
+    let text = fs::read_to_string(dir.join(name)).unwrap();
+    let command = text.lines()
+        .next()
+        .and_then(|line| line.strip_prefix(COMMAND_PREFIX))
+        .unwrap_or("check");
+    let output = Command::new(env!("CARGO_BIN_EXE_yuan"))
+        .args(command.split_whitespace())
+        .arg(name)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+
+    (String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+/// Compare `actual` against the snapshot at `path`, or write it there
+/// if `bless` is set, returning a description of any difference.
+fn compare(path: &Path, actual: &str, bless: bool) -> Option<String> {
+    let expected = fs::read_to_string(path).unwrap_or_default();
+
+    if bless {
+        if actual.is_empty() {
+            let _ = fs::remove_file(path);
+        } else {
+            fs::write(path, actual).unwrap();
+        }
+
+        return None;
+    }
+
+    if expected == actual {
+        return None;
+    }
+
+    Some(format!("{}:\n--- expected\n{}--- actual\n{}", path.display(), expected, actual))
+}
+
+#[test]
+fn snapshots() {
+    let dir = fixtures_dir();
+    let bless = std::env::var_os("BLESS").is_some();
+    let mut names: Vec<String> = Vec::new();
+    let mut failures: Vec<String> = Vec::new();
+
+    for entry in fs::read_dir(&dir).unwrap() {
+        let name = entry.unwrap().file_name().to_string_lossy().into_owned();
+
+        if name.ends_with(".fang") {
+            names.push(name);
+        }
+    }
+
+    names.sort();
+    assert!(!names.is_empty(), "no fixtures in {}", dir.display());
+
+    for name in names {
+        let (stdout, stderr) = run_fixture(&dir, &name);
+        let stem = name.trim_end_matches(".fang");
+
+        failures.extend(compare(&dir.join(format!("{}.stdout", stem)), &stdout, bless));
+        failures.extend(compare(&dir.join(format!("{}.stderr", stem)), &stderr, bless));
+    }
+
+    assert!(failures.is_empty(), "{} snapshots differ, run with BLESS=1 to update them\n\n{}",
+        failures.len(), failures.join("\n"));
+}
+
+#[test]
+fn exit_codes() {
+    let dir = fixtures_dir();
+    let code = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_yuan"))
+        .args(args)
+        .current_dir(&dir)
+        .output()
+        .unwrap()
+        .status
+        .code();
+
+    assert_eq!(code(&["run", "hello.fang"]), Some(0));
+    assert_eq!(code(&["check", "parse_error.fang"]), Some(1));
+    assert_eq!(code(&["run", "runtime_error.fang"]), Some(1));
+    assert_eq!(code(&["check", "missing.fang"]), Some(1));
+    assert_eq!(code(&["new", "/"]), Some(2));
+    assert_eq!(code(&["build", "--emit", "nothing", "hello.fang"]), Some(2));
+}
+
+#[test]
+fn long_chains() {
+    let dir = std::env::temp_dir().join(format!("fang-long-chains-{}", std::process::id()));
+    let check = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_yuan"))
+        .arg("check")
+        .args(args)
+        .arg("chain.fang")
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("chain.fang"), format!("let a = 1{};", " + 1".repeat(10_000))).unwrap();
+
+    /* Past the default limit the chain is an error rather than a crash,
+     * and under a limit it fits in, the checks get the stack it takes. */
+    let output = check(&[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("error: Nesting too deep!"));
+    assert_eq!(check(&["--nesting-limit", "20000"]).status.code(), Some(0));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn doc_pages() {
+    let dir = std::env::temp_dir().join(format!("fang-doc-pages-{}", std::process::id()));
+    let doc = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_yuan"))
+        .arg("doc")
+        .args(args)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+
+    for package in ["one", "two"] {
+        fs::create_dir_all(dir.join(package)).unwrap();
+        fs::write(dir.join(package).join("main.fang"), format!("/// Of {}.\nfunc {}() {{}}\n", package, package)).unwrap();
+    }
+
+    /* Files sharing a name get a page each, named by their path. */
+    assert_eq!(doc(&["-o", "out", "one/main.fang", "two/main.fang"]).status.code(), Some(0));
+    assert!(fs::read_to_string(dir.join("out/one/main.md")).unwrap().contains("Of one."));
+    assert!(fs::read_to_string(dir.join("out/two/main.md")).unwrap().contains("Of two."));
+    assert_eq!(fs::read_to_string(dir.join("out/index.md")).unwrap(), "\
+# Modules
+
+- [one/main](one/main.md)
+- [two/main](two/main.md)
+");
+
+    let output = doc(&["-o", "out", "one/main.fang", "./one/main.fang"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(),
+               "Files \"one/main.fang\" and \"./one/main.fang\" would both be documented in \"one/main.md\".\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_coverage() {
+    let dir = std::env::temp_dir().join(format!("fang-test-coverage-{}", std::process::id()));
+
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.fang"), "\
+func double(value) {
+    return value * 2;
+}
+
+func unused() {
+    print(1);
+}
+
+#[test]
+func doubles() {
+    assert(double(2) == 4);
+}
+").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_yuan"))
+        .args(["test", "--coverage", "out.lcov", "main.fang"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "main.fang: 2/3 lines covered (66.7%)\n");
+    assert_eq!(fs::read_to_string(dir.join("out.lcov")).unwrap(), "\
+SF:main.fang
+DA:2,1
+DA:6,0
+DA:11,1
+LH:2
+LF:3
+end_of_record
+");
+
+    fs::remove_dir_all(&dir).unwrap();
+}